@@ -31,9 +31,12 @@ fn measure_all_character_widths() -> String {
         if ch.is_ascii_control() {
             continue; // Skip control characters
         }
-        output.push_str(&format!("    ('{}', {:.2}),\n",
-            if ch == '\'' { "\\'" } else if ch == '\\' { "\\\\" } else { &ch.to_string() },
-            width));
+        let escaped = match ch {
+            '\'' => "\\'".to_string(),
+            '\\' => "\\\\".to_string(),
+            _ => ch.to_string(),
+        };
+        output.push_str(&format!("    ('{}', {:.2}),\n", escaped, width));
     }
 
     output.push_str("];\n\n");
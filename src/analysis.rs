@@ -0,0 +1,444 @@
+// Harmonic and rhythmic analysis
+// Suggests the chord symbol that fits a set of simultaneous or recent melodic pitches,
+// checks the voice leading between two voices for parallel fifths and octaves, and
+// counts the notes of a line the way a method book does ("1 e & a 2 e & a")
+
+use crate::models::PitchCode;
+use crate::parse::model::{directive, BeatElement, ContentElement, ContentLine, Note, Source, Stave, StaveLine};
+use crate::renderers::note_names::NoteNames;
+use crate::renderers::transposition::pitchcode_to_semitones;
+use crate::rhythm::measures::MeasureCounter;
+use fraction::Fraction;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Directive keys for the meter (e.g. `Time: 3/4`); `TimeSignature` is an alias
+pub const TIME_DIRECTIVE: &str = "Time";
+pub const TIME_SIGNATURE_DIRECTIVE: &str = "TimeSignature";
+
+/// Directive printing a counting line under the notes (`CountLine: on`)
+pub const COUNT_LINE_DIRECTIVE: &str = "CountLine";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChordQuality {
+    Major,
+    Minor,
+    Diminished,
+    Augmented,
+    Dominant7,
+    Major7,
+    Minor7,
+    HalfDiminished7,
+    Diminished7,
+}
+
+impl ChordQuality {
+    /// Semitones above the root, root included
+    fn intervals(self) -> &'static [u8] {
+        match self {
+            ChordQuality::Major => &[0, 4, 7],
+            ChordQuality::Minor => &[0, 3, 7],
+            ChordQuality::Diminished => &[0, 3, 6],
+            ChordQuality::Augmented => &[0, 4, 8],
+            ChordQuality::Dominant7 => &[0, 4, 7, 10],
+            ChordQuality::Major7 => &[0, 4, 7, 11],
+            ChordQuality::Minor7 => &[0, 3, 7, 10],
+            ChordQuality::HalfDiminished7 => &[0, 3, 6, 10],
+            ChordQuality::Diminished7 => &[0, 3, 6, 9],
+        }
+    }
+
+    /// Suffix written after the root in a chord symbol
+    pub fn suffix(self) -> &'static str {
+        match self {
+            ChordQuality::Major => "",
+            ChordQuality::Minor => "m",
+            ChordQuality::Diminished => "dim",
+            ChordQuality::Augmented => "aug",
+            ChordQuality::Dominant7 => "7",
+            ChordQuality::Major7 => "maj7",
+            ChordQuality::Minor7 => "m7",
+            ChordQuality::HalfDiminished7 => "m7b5",
+            ChordQuality::Diminished7 => "dim7",
+        }
+    }
+
+    const ALL: [ChordQuality; 9] = [
+        ChordQuality::Major,
+        ChordQuality::Minor,
+        ChordQuality::Diminished,
+        ChordQuality::Augmented,
+        ChordQuality::Dominant7,
+        ChordQuality::Major7,
+        ChordQuality::Minor7,
+        ChordQuality::HalfDiminished7,
+        ChordQuality::Diminished7,
+    ];
+}
+
+/// A chord root (spelled as it appeared in the notes) and quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChordSymbol {
+    pub root: PitchCode,
+    pub quality: ChordQuality,
+}
+
+impl fmt::Display for ChordSymbol {
+    /// Chord symbol with the root named in C, e.g. "Dm7" for root N2 of a minor seventh
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let root = NoteNames::Letter.label(self.root).unwrap_or_default();
+        write!(f, "{}{}", root, self.quality.suffix())
+    }
+}
+
+/// Bit set of the pitch classes sounding in `notes`
+fn pitch_class_set(notes: &[PitchCode]) -> u16 {
+    notes.iter().fold(0, |set, &note| set | 1 << pitchcode_to_semitones(note).rem_euclid(12))
+}
+
+/// The triad or seventh chord whose tones are exactly the pitch classes of `notes`.
+/// Octaves and repeated notes don't matter; any note may be the root.
+/// Returns `None` when no chord matches or more than one does (augmented and
+/// diminished seventh chords are symmetric, so their root can't be told).
+pub fn detect_chord(notes: &[PitchCode]) -> Option<ChordSymbol> {
+    let set = pitch_class_set(notes);
+    let mut matches: Vec<ChordSymbol> = Vec::new();
+    for &root in notes {
+        let root_class = pitchcode_to_semitones(root).rem_euclid(12);
+        for quality in ChordQuality::ALL {
+            let chord_set = quality.intervals().iter()
+                .fold(0u16, |s, &interval| s | 1 << ((root_class + interval as i8) % 12));
+            let same_root = |c: &ChordSymbol| pitchcode_to_semitones(c.root).rem_euclid(12) == root_class;
+            if chord_set == set && !matches.iter().any(|c| same_root(c) && c.quality == quality) {
+                matches.push(ChordSymbol { root, quality });
+            }
+        }
+    }
+    match matches.as_slice() {
+        [chord] => Some(*chord),
+        _ => None,
+    }
+}
+
+/// Perfect consonance two voices must not move into in parallel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParallelInterval {
+    Fifths,
+    /// Octaves, compound octaves and unisons
+    Octaves,
+}
+
+/// Parallel motion found by `check_voice_leading`, located where the voices
+/// arrive on the second interval (measure and beat counted from 1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoiceLeadingIssue {
+    pub interval: ParallelInterval,
+    pub measure: usize,
+    pub beat: usize,
+}
+
+impl fmt::Display for VoiceLeadingIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let interval = match self.interval {
+            ParallelInterval::Fifths => "fifths",
+            ParallelInterval::Octaves => "octaves",
+        };
+        write!(f, "Parallel {} at measure {}, beat {}", interval, self.measure, self.beat)
+    }
+}
+
+/// A note or rest starting in a voice; `time` is counted in beats from the start of the line
+struct Onset {
+    time: Fraction,
+    measure: usize,
+    beat: usize,
+    semitones: Option<i16>,
+}
+
+/// Note and rest onsets of a voice in order. Dashes extend the sounding note,
+/// breath marks take no time.
+fn voice_onsets(voice: &ContentLine) -> Vec<Onset> {
+    let mut onsets = Vec::new();
+    let mut measures = MeasureCounter::default();
+    let (mut beat_index, mut beat_in_measure, mut current_measure) = (0u64, 0, 0);
+    for element in &voice.elements {
+        match element {
+            ContentElement::Beat(beat) => {
+                let measure = measures.beat();
+                beat_in_measure = if measure == current_measure { beat_in_measure + 1 } else { 1 };
+                current_measure = measure;
+                let slots: Vec<&BeatElement> = beat.elements.iter()
+                    .filter(|e| !matches!(e, BeatElement::BreathMark(_)))
+                    .collect();
+                for (slot, element) in slots.iter().enumerate() {
+                    let semitones = match element {
+                        BeatElement::Note(note) => Some(Some(pitchcode_to_semitones(note.pitch_code) as i16 + 12 * note.octave as i16)),
+                        BeatElement::Rest(_) => Some(None),
+                        BeatElement::Dash(dash) if dash.numerator.is_some() && slot == 0 && !beat.tied_to_previous.unwrap_or(false) => Some(None),
+                        _ => None,
+                    };
+                    if let Some(semitones) = semitones {
+                        let time = Fraction::from(beat_index) + Fraction::new(slot as u64, slots.len() as u64);
+                        onsets.push(Onset { time, measure, beat: beat_in_measure, semitones });
+                    }
+                }
+                beat_index += 1;
+            }
+            ContentElement::Barline(_) => measures.barline(),
+            _ => {}
+        }
+    }
+    onsets
+}
+
+/// Pitch sounding in a voice at `time`, if any
+fn sounding_at(onsets: &[Onset], time: Fraction) -> Option<i16> {
+    onsets.iter().take_while(|onset| onset.time <= time).last().and_then(|onset| onset.semitones)
+}
+
+/// Flag parallel perfect fifths and octaves between two voices whose beats line up
+/// one to one. Both voices have to move in the same direction from one perfect
+/// interval to another of the same kind; contrary and oblique motion pass, and a
+/// rest in either voice starts the check over.
+pub fn check_voice_leading(voice_a: &ContentLine, voice_b: &ContentLine) -> Vec<VoiceLeadingIssue> {
+    let (onsets_a, onsets_b) = (voice_onsets(voice_a), voice_onsets(voice_b));
+    let mut moments: Vec<&Onset> = onsets_a.iter().chain(&onsets_b).collect();
+    moments.sort_by_key(|onset| onset.time);
+    moments.dedup_by(|later, earlier| later.time == earlier.time);
+
+    let mut issues = Vec::new();
+    let mut previous: Option<(i16, i16)> = None;
+    for moment in moments {
+        let current = sounding_at(&onsets_a, moment.time).zip(sounding_at(&onsets_b, moment.time));
+        if let (Some((a0, b0)), Some((a1, b1))) = (previous, current) {
+            let similar_motion = a1 != a0 && b1 != b0 && (a1 > a0) == (b1 > b0);
+            let interval = |a: i16, b: i16| match (a - b).abs() % 12 {
+                7 => Some(ParallelInterval::Fifths),
+                0 => Some(ParallelInterval::Octaves),
+                _ => None,
+            };
+            if let (true, Some(before), Some(after)) = (similar_motion, interval(a0, b0), interval(a1, b1)) {
+                if before == after {
+                    issues.push(VoiceLeadingIssue { interval: after, measure: moment.measure, beat: moment.beat });
+                }
+            }
+        }
+        previous = current;
+    }
+    issues
+}
+
+/// Meter from the `Time` directive (e.g. "3/4"); 4/4 when absent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeSignature {
+    pub beats: usize,
+    pub unit: usize,
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        TimeSignature { beats: 4, unit: 4 }
+    }
+}
+
+impl TimeSignature {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (beats, unit) = value.trim().split_once('/')?;
+        let beats = beats.trim().parse().ok().filter(|&b| b > 0)?;
+        let unit = unit.trim().parse().ok().filter(|&u| u > 0)?;
+        Some(TimeSignature { beats, unit })
+    }
+
+    pub fn from_directives(directives: &HashMap<String, String>) -> Self {
+        Self::declared(directives).unwrap_or_default()
+    }
+
+    /// Meter change written in a content line after a barline, e.g. "(3/4)"
+    pub fn from_inline(token: &str) -> Option<Self> {
+        token.strip_prefix('(')?.strip_suffix(')').and_then(Self::parse)
+    }
+
+    /// Length of a measure in whole notes (3/4 for 3/4 time)
+    pub fn measure_length(&self) -> Fraction {
+        Fraction::new(self.beats as u64, self.unit as u64)
+    }
+
+    /// The `Time` (or `TimeSignature`) directive, if the document has a valid one
+    pub fn declared(directives: &HashMap<String, String>) -> Option<Self> {
+        directive(directives, TIME_DIRECTIVE)
+            .or_else(|| directive(directives, TIME_SIGNATURE_DIRECTIVE))
+            .and_then(Self::parse)
+    }
+
+    /// The meter to count in when the `CountLine` directive is on ("on", "true" or "yes")
+    pub fn for_count_line(directives: &HashMap<String, String>) -> Option<Self> {
+        let enabled = directive(directives, COUNT_LINE_DIRECTIVE)
+            .is_some_and(|value| matches!(value.to_lowercase().as_str(), "on" | "true" | "yes"));
+        enabled.then(|| Self::from_directives(directives))
+    }
+}
+
+/// Syllable for a note starting `slot` subdivisions into a beat of `slots`: the beat
+/// number on the beat, "e & a" for sixteenths and "trip let" for triplets. Finer
+/// positions are left uncounted (an empty syllable).
+fn count_syllable(beat_number: usize, slot: usize, slots: usize) -> String {
+    let position = Fraction::new(slot as u64, slots as u64);
+    let syllable = if position == Fraction::from(0) {
+        return beat_number.to_string();
+    } else if position == Fraction::new(1u64, 2u64) {
+        "&"
+    } else if position == Fraction::new(1u64, 4u64) {
+        "e"
+    } else if position == Fraction::new(3u64, 4u64) {
+        "a"
+    } else if position == Fraction::new(1u64, 3u64) {
+        "trip"
+    } else if position == Fraction::new(2u64, 3u64) {
+        "let"
+    } else {
+        ""
+    };
+    syllable.to_string()
+}
+
+/// Counting syllable of every note of a line in order. Beats are numbered from each
+/// barline, wrapping after the time signature's beat count when barlines are missing.
+pub fn count_line(line: &ContentLine, time_signature: TimeSignature) -> Vec<(&Note, String)> {
+    let mut counts = Vec::new();
+    let mut measures = MeasureCounter::default();
+    let (mut beat_in_measure, mut current_measure) = (0, 0);
+    for element in &line.elements {
+        match element {
+            ContentElement::Beat(beat) => {
+                let measure = measures.beat();
+                beat_in_measure = if measure == current_measure { beat_in_measure + 1 } else { 1 };
+                current_measure = measure;
+                let beat_number = (beat_in_measure - 1) % time_signature.beats + 1;
+                let slots: Vec<&BeatElement> = beat.elements.iter()
+                    .filter(|e| !matches!(e, BeatElement::BreathMark(_)))
+                    .collect();
+                for (slot, element) in slots.iter().enumerate() {
+                    if let BeatElement::Note(note) = element {
+                        counts.push((note, count_syllable(beat_number, slot, slots.len())));
+                    }
+                }
+            }
+            ContentElement::Barline(_) => measures.barline(),
+            _ => {}
+        }
+    }
+    counts
+}
+
+/// Counting syllables ("1 e & a 2 ...") for the notes of every content line, located
+/// at each note's source
+pub fn count_syllables<'a>(staves: impl IntoIterator<Item = &'a Stave>, time_signature: TimeSignature) -> Vec<(Source, String)> {
+    staves.into_iter()
+        .flat_map(|stave| stave.lines.iter())
+        .filter_map(|line| match line {
+            StaveLine::ContentLine(content_line) => Some(content_line),
+            _ => None,
+        })
+        .flat_map(|content_line| count_line(content_line, time_signature))
+        .filter_map(|(note, syllable)| note.source.map(|source| (source, syllable)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use PitchCode::*;
+
+    #[test]
+    fn test_detect_major_triad_on_tonic() {
+        let chord = detect_chord(&[N1, N3, N5]).unwrap();
+        assert_eq!(chord, ChordSymbol { root: N1, quality: ChordQuality::Major });
+        assert_eq!(chord.to_string(), "C");
+    }
+
+    #[test]
+    fn test_detect_chord_inversions_and_sevenths() {
+        // First inversion, doubled root
+        assert_eq!(detect_chord(&[N3, N5, N1, N1]).unwrap().to_string(), "C");
+        assert_eq!(detect_chord(&[N5, N7, N2, N4]).unwrap().to_string(), "G7");
+        assert_eq!(detect_chord(&[N2, N4, N6, N1]).unwrap().to_string(), "Dm7");
+        assert_eq!(detect_chord(&[N7b, N2, N4]).unwrap().to_string(), "Bb");
+    }
+
+    #[test]
+    fn test_detect_chord_ambiguous_or_unknown() {
+        // Augmented triads are symmetric
+        assert_eq!(detect_chord(&[N1, N3, N5s]), None);
+        assert_eq!(detect_chord(&[N1, N2, N3]), None);
+        assert_eq!(detect_chord(&[N1, N5]), None);
+        assert_eq!(detect_chord(&[]), None);
+    }
+
+    fn voice(text: &str) -> ContentLine {
+        let mut line = crate::document::line_parser::content_line_parser::unused_parse_content_line(
+            text, 0, crate::parse::model::NotationSystem::Number, 0,
+        ).unwrap();
+        crate::rhythm::analyzer::analyze_content_line_rhythm(&mut line.elements).unwrap();
+        line
+    }
+
+    #[test]
+    fn test_parallel_fifths_flagged_at_each_motion() {
+        let issues = check_voice_leading(&voice("5 6 | 5 6"), &voice("1 2 | 1 2"));
+        let locations: Vec<(usize, usize)> = issues.iter().map(|i| (i.measure, i.beat)).collect();
+        assert_eq!(locations, vec![(1, 2), (2, 1), (2, 2)]);
+        assert!(issues.iter().all(|i| i.interval == ParallelInterval::Fifths));
+        assert_eq!(issues[0].to_string(), "Parallel fifths at measure 1, beat 2");
+    }
+
+    #[test]
+    fn test_contrary_and_oblique_motion_pass() {
+        // Fifth to octave to fifth in contrary motion, then a held lower voice
+        assert!(check_voice_leading(&voice("5 1 5 6"), &voice("1 1 1 1")).is_empty());
+        assert!(check_voice_leading(&voice("5 6 7 1"), &voice("1 7 6 5")).is_empty());
+        // A rest between the fifths breaks the parallel; the breath mark keeps the dash
+        // after the barline from holding the note over
+        assert!(check_voice_leading(&voice("5' | - 6"), &voice("1' | - 2")).is_empty());
+    }
+
+    #[test]
+    fn test_parallel_unisons_count_as_octaves() {
+        let issues = check_voice_leading(&voice("1 2-3"), &voice("1 2-3"));
+        assert_eq!(issues.len(), 2);
+        assert!(issues.iter().all(|i| i.interval == ParallelInterval::Octaves));
+        assert_eq!((issues[1].measure, issues[1].beat), (1, 2));
+    }
+
+    #[test]
+    fn test_count_eighths_in_four_four() {
+        let line = voice("12 34 56 71");
+        let stave = Stave {
+            id: uuid::Uuid::new_v4(),
+            value: None,
+            notation_system: crate::parse::model::NotationSystem::Number,
+            line: 0,
+            column: 0,
+            index_in_line: 0,
+            index_in_doc: 0,
+            clef: None,
+            grand_staff: None,
+            lines: vec![StaveLine::ContentLine(line)],
+        };
+        let counts = count_syllables([&stave], TimeSignature::parse("4/4").unwrap());
+        let syllables: Vec<&str> = counts.iter().map(|(_, syllable)| syllable.as_str()).collect();
+        assert_eq!(syllables, vec!["1", "&", "2", "&", "3", "&", "4", "&"]);
+        let columns: Vec<usize> = counts.iter().map(|(source, _)| source.column).collect();
+        assert_eq!(columns, vec![1, 2, 4, 5, 7, 8, 10, 11]);
+    }
+
+    fn counted(text: &str, time: &str) -> Vec<String> {
+        count_line(&voice(text), TimeSignature::parse(time).unwrap()).into_iter().map(|(_, syllable)| syllable).collect()
+    }
+
+    #[test]
+    fn test_count_sixteenths_triplets_and_barlines() {
+        assert_eq!(counted("1-23 | 1 234 | 12--", "3/4"), vec!["1", "&", "a", "1", "2", "trip", "let", "1", "e"]);
+        // Without barlines beats wrap at the time signature
+        assert_eq!(counted("1 2 3 4", "3/4"), vec!["1", "2", "3", "1"]);
+    }
+}
@@ -0,0 +1,94 @@
+// Divisi spans within a single voice
+// `div.` splits the line into two pitch streams sharing one rhythm; `unis.` rejoins them
+
+use crate::parse::model::{ContentLine, ContentElement, BeatElement, DivisiPitch};
+
+pub const DIVISI_MARKER: &str = "div.";
+pub const UNISON_MARKER: &str = "unis.";
+
+/// Whether the line opens a divisi span
+pub fn has_divisi(line: &ContentLine) -> bool {
+    line.elements.iter().any(|element| matches!(element, ContentElement::UnknownToken(token) if token.token_value == DIVISI_MARKER))
+}
+
+/// Attach the notes of `second_stream` to the notes of `line` that fall between
+/// `div.` and `unis.` markers, pairing them in order.
+/// A `div.` without a matching `unis.` lasts to the end of the line.
+pub fn apply_divisi(line: &mut ContentLine, second_stream: &ContentLine) -> Result<(), String> {
+    let second_pitches: Vec<DivisiPitch> = second_stream.elements.iter()
+        .filter_map(|element| match element {
+            ContentElement::Beat(beat) => Some(beat),
+            _ => None,
+        })
+        .flat_map(|beat| beat.elements.iter())
+        .filter_map(|element| match element {
+            BeatElement::Note(note) => Some(DivisiPitch {
+                value: note.value.clone(),
+                pitch_code: note.pitch_code,
+                octave: note.octave,
+            }),
+            _ => None,
+        })
+        .collect();
+
+    let mut in_divisi = false;
+    let mut divisi_notes = Vec::new();
+    for element in &mut line.elements {
+        match element {
+            ContentElement::UnknownToken(token) if token.token_value == DIVISI_MARKER => in_divisi = true,
+            ContentElement::UnknownToken(token) if token.token_value == UNISON_MARKER => in_divisi = false,
+            ContentElement::Beat(beat) if in_divisi => {
+                for beat_element in &mut beat.elements {
+                    if let BeatElement::Note(note) = beat_element {
+                        divisi_notes.push(note);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if divisi_notes.len() != second_pitches.len() {
+        return Err(format!(
+            "Divisi span has {} notes but the second stream has {}",
+            divisi_notes.len(),
+            second_pitches.len()
+        ));
+    }
+    for (note, pitch) in divisi_notes.into_iter().zip(second_pitches) {
+        note.divisi = Some(pitch);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::line_parser::content_line_parser::unused_parse_content_line;
+    use crate::parse::model::{NotationSystem, PitchCode};
+
+    #[test]
+    fn test_divisi_pairs_notes_until_unison() {
+        let mut line = unused_parse_content_line("1 div. 3 4 unis. 5", 0, NotationSystem::Number, 0).unwrap();
+        let second = unused_parse_content_line("1 2", 1, NotationSystem::Number, 19).unwrap();
+        apply_divisi(&mut line, &second).unwrap();
+
+        let divisi: Vec<Option<PitchCode>> = line.elements.iter()
+            .filter_map(|e| match e {
+                ContentElement::Beat(beat) => match &beat.elements[0] {
+                    BeatElement::Note(note) => Some(note.divisi.as_ref().map(|d| d.pitch_code)),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        assert_eq!(divisi, vec![None, Some(PitchCode::N1), Some(PitchCode::N2), None]);
+    }
+
+    #[test]
+    fn test_divisi_stream_length_mismatch() {
+        let mut line = unused_parse_content_line("div. 3 4", 0, NotationSystem::Number, 0).unwrap();
+        let second = unused_parse_content_line("1", 1, NotationSystem::Number, 9).unwrap();
+        assert!(apply_divisi(&mut line, &second).is_err());
+    }
+}
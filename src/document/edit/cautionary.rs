@@ -0,0 +1,47 @@
+use crate::parse::Document;
+use crate::models::core::StaveLine;
+use crate::models::elements::{BeatElement, ContentElement};
+use uuid::Uuid;
+
+/// Mark (or unmark) the accidentals of specific notes as cautionary.
+/// Targets may be Note UUIDs or Beat UUIDs (every note of the beat).
+pub fn apply_cautionary_edit(
+    document: &mut Document,
+    target_uuids: &[String],
+    cautionary: bool,
+) -> Result<(), String> {
+    let target_uuids: Vec<Uuid> = target_uuids
+        .iter()
+        .map(|s| s.parse::<Uuid>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Invalid UUID format: {}", e))?;
+
+    let mut modified_count = 0;
+    for element in &mut document.elements {
+        if let crate::models::core::DocumentElement::Stave(stave) = element {
+            for line in &mut stave.lines {
+                if let StaveLine::ContentLine(content_line) = line {
+                    for content_element in &mut content_line.elements {
+                        if let ContentElement::Beat(beat) = content_element {
+                            let whole_beat = target_uuids.contains(&beat.id);
+                            for beat_element in &mut beat.elements {
+                                if let BeatElement::Note(note) = beat_element {
+                                    if whole_beat || target_uuids.contains(&note.id) {
+                                        note.cautionary = cautionary;
+                                        modified_count += 1;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if modified_count == 0 {
+        return Err(format!("No notes found with the provided UUIDs. Searched for {} UUIDs in document.", target_uuids.len()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use crate::parse::Document;
+use crate::models::notation::PitchCode;
+use crate::models::SpanEdge;
+use super::execute_edit;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// The values of a note that edits change
+#[derive(Debug, Clone, PartialEq)]
+struct NoteValues {
+    pitch_code: PitchCode,
+    octave: i8,
+    value: Option<String>,
+    cautionary: bool,
+    slur: Option<SpanEdge>,
+    phrase: Option<SpanEdge>,
+}
+
+/// The notes one edit changed, with their values before and after it
+#[derive(Debug, Clone)]
+struct EditRecord {
+    before: Vec<(Uuid, NoteValues)>,
+    after: Vec<(Uuid, NoteValues)>,
+}
+
+/// A document edited through `execute_edit`, keeping the previous values of the notes
+/// each edit changed (by UUID) so the edits can be undone and redone
+#[derive(Debug, Clone)]
+pub struct EditHistory {
+    document: Document,
+    undo_stack: Vec<EditRecord>,
+    redo_stack: Vec<EditRecord>,
+}
+
+impl EditHistory {
+    pub fn new(document: Document) -> Self {
+        Self { document, undo_stack: Vec::new(), redo_stack: Vec::new() }
+    }
+
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    /// Apply an edit as `execute_edit` does. An edit that changes notes clears the redo
+    /// history; one that fails leaves the document as it was.
+    pub fn apply(&mut self, edit_type: &str, target_uuids: &[String], params: &serde_json::Value) -> Result<(), String> {
+        let before = note_values(&self.document);
+        if let Err(error) = execute_edit(&mut self.document, edit_type, target_uuids, params) {
+            restore(&mut self.document, &before);
+            return Err(error);
+        }
+        let after = note_values(&self.document);
+        let (before, after): (Vec<_>, Vec<_>) = after.into_iter()
+            .filter_map(|(id, values)| {
+                let previous = before.get(&id)?;
+                (*previous != values).then(|| ((id, previous.clone()), (id, values)))
+            })
+            .unzip();
+        if !after.is_empty() {
+            self.undo_stack.push(EditRecord { before, after });
+            self.redo_stack.clear();
+        }
+        Ok(())
+    }
+
+    /// Undo the last edit; `false` when there is none
+    pub fn undo(&mut self) -> bool {
+        let Some(record) = self.undo_stack.pop() else { return false };
+        restore(&mut self.document, &record.before.iter().cloned().collect());
+        self.redo_stack.push(record);
+        true
+    }
+
+    /// Redo the last undone edit; `false` when there is none
+    pub fn redo(&mut self) -> bool {
+        let Some(record) = self.redo_stack.pop() else { return false };
+        restore(&mut self.document, &record.after.iter().cloned().collect());
+        self.undo_stack.push(record);
+        true
+    }
+}
+
+fn note_values(document: &Document) -> HashMap<Uuid, NoteValues> {
+    document.notes()
+        .map(|note| (note.id, NoteValues {
+            pitch_code: note.pitch_code,
+            octave: note.octave,
+            value: note.value.clone(),
+            cautionary: note.cautionary,
+            slur: note.slur,
+            phrase: note.phrase,
+        }))
+        .collect()
+}
+
+fn restore(document: &mut Document, values: &HashMap<Uuid, NoteValues>) {
+    for note in document.notes_mut() {
+        if let Some(values) = values.get(&note.id) {
+            note.pitch_code = values.pitch_code;
+            note.octave = values.octave;
+            note.value = values.value.clone();
+            note.cautionary = values.cautionary;
+            note.slur = values.slur;
+            note.phrase = values.phrase;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(document: &Document) -> serde_json::Value {
+        serde_json::to_value(document).unwrap()
+    }
+
+    #[test]
+    fn test_undo_and_redo_octave_edits() {
+        let document = crate::pipeline::document_from_notation("| 1 2 3 |\n").unwrap();
+        let original = snapshot(&document);
+        let ids: Vec<String> = document.notes().map(|note| note.id.to_string()).collect();
+        let mut history = EditHistory::new(document);
+
+        history.apply("set_octave", &ids[..1], &serde_json::json!({ "octave_type": "higher" })).unwrap();
+        history.apply("set_octave", &ids[1..], &serde_json::json!({ "octave_type": "lowest" })).unwrap();
+        let edited = snapshot(history.document());
+        let octaves: Vec<i8> = history.document().notes().map(|note| note.octave).collect();
+        assert_eq!(octaves, vec![1, -2, -2]);
+
+        assert!(history.undo());
+        assert!(history.undo());
+        assert!(!history.undo());
+        assert_eq!(snapshot(history.document()), original);
+
+        assert!(history.redo());
+        assert!(history.redo());
+        assert!(!history.redo());
+        assert_eq!(snapshot(history.document()), edited);
+    }
+
+    #[test]
+    fn test_undo_and_redo_slur_edits() {
+        let document = crate::pipeline::document_from_notation("| 1 2 3 |\n").unwrap();
+        let ids: Vec<String> = document.notes().map(|note| note.id.to_string()).collect();
+        let mut history = EditHistory::new(document);
+        let slurs = |history: &EditHistory| history.document().notes().map(|note| note.slur).collect::<Vec<_>>();
+
+        history.apply("apply_slur", &ids, &serde_json::json!({})).unwrap();
+        let slurred = slurs(&history);
+        assert_eq!(slurred, vec![Some(SpanEdge::Start), None, Some(SpanEdge::End)]);
+        history.apply("remove_slur", &ids, &serde_json::json!({})).unwrap();
+        assert_eq!(slurs(&history), vec![None, None, None]);
+
+        assert!(history.undo());
+        assert_eq!(slurs(&history), slurred);
+        assert!(history.undo());
+        assert_eq!(slurs(&history), vec![None, None, None]);
+        assert!(history.redo());
+        assert_eq!(slurs(&history), slurred);
+    }
+
+    #[test]
+    fn test_failed_edit_is_not_recorded() {
+        let document = crate::pipeline::document_from_notation("| 1 2 |\n").unwrap();
+        let ids: Vec<String> = document.notes().map(|note| note.id.to_string()).collect();
+        let mut history = EditHistory::new(document);
+        history.apply("transpose", &ids, &serde_json::json!({ "semitones": 2 })).unwrap();
+        assert!(history.apply("transpose", &ids, &serde_json::json!({})).is_err());
+        assert!(history.undo());
+        let pitches: Vec<PitchCode> = history.document().notes().map(|note| note.pitch_code).collect();
+        assert_eq!(pitches, vec![PitchCode::N1, PitchCode::N2]);
+        assert!(!history.undo());
+    }
+}
@@ -4,6 +4,10 @@ use serde_json;
 pub mod octave;
 pub mod text;
 pub mod structural;
+pub mod cautionary;
+pub mod transpose;
+pub mod history;
+pub mod slur;
 
 /// Execute an edit operation on a document
 /// This is the main entry point for all document edit operations
@@ -22,10 +26,24 @@ pub fn execute_edit(
 
             octave::apply_octave_edit(document, target_uuids, octave_type)
         }
-        "apply_slur" => {
-            // Future: slur::apply_slur_edit(document, target_uuids)
-            Ok(())
+        "set_cautionary" => {
+            let cautionary = params
+                .get("cautionary")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true);
+
+            cautionary::apply_cautionary_edit(document, target_uuids, cautionary)
+        }
+        "transpose" => {
+            let semitones = params
+                .get("semitones")
+                .and_then(|v| v.as_i64())
+                .ok_or("transpose requires an integer 'semitones' parameter")?;
+
+            transpose::apply_transpose_edit(document, target_uuids, semitones as i32)
         }
+        "apply_slur" => slur::apply_slur_edit(document, target_uuids),
+        "remove_slur" => slur::remove_slur_edit(document, target_uuids),
         _ => Err(format!("Unknown edit type: {}", edit_type)),
     }
 }
\ No newline at end of file
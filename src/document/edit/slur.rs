@@ -0,0 +1,169 @@
+use crate::parse::Document;
+use crate::models::core::{DocumentElement, StaveLine};
+use crate::models::elements::{BeatElement, ContentElement, Note, SpanEdge};
+use uuid::Uuid;
+
+/// Slur the targeted notes: the first starts the slur, the last ends it and any slur
+/// marks on the notes between are cleared, as a run of underscores over them would do.
+/// Targets may be Note UUIDs or Beat UUIDs (every note of the beat) and must be two or
+/// more notes next to each other on one stave.
+pub fn apply_slur_edit(document: &mut Document, target_uuids: &[String]) -> Result<(), String> {
+    let target_uuids = parse_uuids(target_uuids)?;
+    let mut staves = stave_notes(document);
+    let mut selections = staves.iter()
+        .map(|notes| targeted_indices(notes, &target_uuids))
+        .enumerate()
+        .filter(|(_, targeted)| !targeted.is_empty());
+    let Some((stave_index, targeted)) = selections.next() else {
+        return Err(format!("No notes found with the provided UUIDs. Searched for {} UUIDs in document.", target_uuids.len()));
+    };
+    if selections.next().is_some() {
+        return Err("A slur can't cross from one stave to another".to_string());
+    }
+    let (first, last) = (targeted[0], targeted[targeted.len() - 1]);
+    if last - first + 1 != targeted.len() {
+        return Err("Slurred notes must be next to each other".to_string());
+    }
+    if targeted.len() < 2 {
+        return Err("A slur needs at least two notes".to_string());
+    }
+
+    for (index, (_, note)) in staves[stave_index].iter_mut().enumerate().take(last + 1).skip(first) {
+        note.slur = match index {
+            i if i == first => Some(SpanEdge::Start),
+            i if i == last => Some(SpanEdge::End),
+            _ => None,
+        };
+    }
+
+    Ok(())
+}
+
+/// Remove every slur that starts, ends or passes over one of the targeted notes
+pub fn remove_slur_edit(document: &mut Document, target_uuids: &[String]) -> Result<(), String> {
+    let target_uuids = parse_uuids(target_uuids)?;
+    let mut removed_count = 0;
+    for mut notes in stave_notes(document) {
+        let targeted = targeted_indices(&notes, &target_uuids);
+        let mut start = None;
+        for index in 0..notes.len() {
+            match notes[index].1.slur {
+                Some(SpanEdge::Start) => start = Some(index),
+                Some(SpanEdge::End) => {
+                    let first = start.take().unwrap_or(index);
+                    if targeted.iter().any(|i| (first..=index).contains(i)) {
+                        notes[first].1.slur = None;
+                        notes[index].1.slur = None;
+                        removed_count += 1;
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+
+    if removed_count == 0 {
+        return Err("No slur found on the selected notes".to_string());
+    }
+
+    Ok(())
+}
+
+fn parse_uuids(target_uuids: &[String]) -> Result<Vec<Uuid>, String> {
+    target_uuids
+        .iter()
+        .map(|s| s.parse::<Uuid>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Invalid UUID format: {}", e))
+}
+
+/// The notes of each stave in order, each with the UUID of its beat
+fn stave_notes(document: &mut Document) -> Vec<Vec<(Uuid, &mut Note)>> {
+    document.elements.iter_mut()
+        .filter_map(|element| match element {
+            DocumentElement::Stave(stave) => Some(stave),
+            _ => None,
+        })
+        .map(|stave| {
+            stave.lines.iter_mut()
+                .filter_map(|line| match line {
+                    StaveLine::ContentLine(content_line) => Some(content_line),
+                    _ => None,
+                })
+                .flat_map(|content_line| &mut content_line.elements)
+                .filter_map(|element| match element {
+                    ContentElement::Beat(beat) => Some(beat),
+                    _ => None,
+                })
+                .flat_map(|beat| {
+                    let beat_id = beat.id;
+                    beat.elements.iter_mut().filter_map(move |element| match element {
+                        BeatElement::Note(note) => Some((beat_id, note)),
+                        _ => None,
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Positions of the notes selected by their own UUID or their beat's
+fn targeted_indices(notes: &[(Uuid, &mut Note)], target_uuids: &[Uuid]) -> Vec<usize> {
+    notes.iter()
+        .enumerate()
+        .filter(|(_, (beat_id, note))| target_uuids.contains(beat_id) || target_uuids.contains(&note.id))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::edit::execute_edit;
+    use crate::pipeline::document_from_notation;
+    use crate::renderers::lilypond::renderer::convert_document_to_lilypond_src;
+
+    fn note_ids(document: &Document) -> Vec<String> {
+        document.notes().map(|note| note.id.to_string()).collect()
+    }
+
+    fn slurs(document: &Document) -> Vec<Option<SpanEdge>> {
+        document.notes().map(|note| note.slur).collect()
+    }
+
+    #[test]
+    fn test_slur_three_selected_notes() {
+        let mut document = document_from_notation("|1 2 3 4|\n").unwrap();
+        let ids = note_ids(&document);
+        execute_edit(&mut document, "apply_slur", &ids[0..3], &serde_json::json!({})).unwrap();
+        assert_eq!(slurs(&document), vec![Some(SpanEdge::Start), None, Some(SpanEdge::End), None]);
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("c4( d4 e4) f4"), "{}", lilypond);
+
+        execute_edit(&mut document, "remove_slur", &ids[1..2], &serde_json::json!({})).unwrap();
+        assert_eq!(slurs(&document), vec![None; 4]);
+        assert!(remove_slur_edit(&mut document, &ids).is_err());
+    }
+
+    #[test]
+    fn test_slur_targets_a_whole_beat() {
+        let mut document = document_from_notation("|123 4|\n").unwrap();
+        let DocumentElement::Stave(stave) = &document.elements[0] else { panic!("expected a stave") };
+        let StaveLine::ContentLine(line) = &stave.lines[0] else { panic!("expected a content line") };
+        let ContentElement::Beat(beat) = &line.elements[1] else { panic!("expected a beat") };
+        let beat_id = beat.id.to_string();
+        apply_slur_edit(&mut document, &[beat_id]).unwrap();
+        assert_eq!(slurs(&document), vec![Some(SpanEdge::Start), None, Some(SpanEdge::End), None]);
+    }
+
+    #[test]
+    fn test_slur_rejects_gaps_and_stave_crossings() {
+        let mut document = document_from_notation("|1 2 3|\n\n|4 5|\n").unwrap();
+        let ids = note_ids(&document);
+        assert!(apply_slur_edit(&mut document, &[ids[0].clone(), ids[2].clone()]).unwrap_err().contains("next to each other"));
+        assert!(apply_slur_edit(&mut document, &ids[2..4]).unwrap_err().contains("stave"));
+        assert!(apply_slur_edit(&mut document, &ids[0..1]).unwrap_err().contains("two notes"));
+        assert!(apply_slur_edit(&mut document, &[Uuid::new_v4().to_string()]).is_err());
+        assert_eq!(slurs(&document), vec![None; 5]);
+    }
+}
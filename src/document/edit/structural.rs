@@ -158,6 +158,7 @@ fn parse_whitespace(ch: char, position: usize) -> Result<crate::models::ContentE
     Ok(crate::models::ContentElement::Whitespace(crate::models::Whitespace {
         id: uuid::Uuid::new_v4(),
         value: Some(ch.to_string()),
+        source: None,
     }))
 }
 
@@ -166,17 +167,18 @@ fn parse_single_barline(ch: char, position: usize) -> Result<crate::models::Cont
     Ok(crate::models::ContentElement::Barline(Barline::Single(SingleBarline {
         id: uuid::Uuid::new_v4(),
         value: Some(ch.to_string()),
+        source: None,
     })))
 }
 
 fn parse_note(notation_system: &NotationSystem, ch: char, position: usize) -> Result<crate::models::ContentElement, String> {
     // Use pitch_systems to parse the note
     let degree_opt = match notation_system {
-        NotationSystem::Number => crate::models::pitch_systems::number::lookup(&ch.to_string()),
+        NotationSystem::Number | NotationSystem::Jianpu => crate::models::pitch_systems::number::lookup(&ch.to_string()),
         NotationSystem::Western => None, // TODO: implement western note parsing
         NotationSystem::Sargam => None,   // TODO: implement sargam parsing
         NotationSystem::Bhatkhande => None, // TODO: implement bhatkhande parsing
-        NotationSystem::Tabla => None,    // TODO: implement tabla parsing
+        NotationSystem::Tabla | NotationSystem::Scat => None,    // TODO: implement tabla parsing
     };
 
     if let Some(degree) = degree_opt {
@@ -191,8 +193,21 @@ fn parse_note(notation_system: &NotationSystem, ch: char, position: usize) -> Re
                 octave: 0,
                 value: Some(ch.to_string()),
                 denominator: None,
+                divisi: None,
+                vibrato: false,
+                bend: None,
+                cautionary: false,
+                tie: None,
+                grace_notes: Vec::new(),
+                lyrics: Vec::new(),
+                microtone: None,
+                articulations: Vec::new(),
+                ornament: None,
+                slur: None,
+                phrase: None,
                 numerator: None,
                 notation_system: notation_system.clone(),
+                source: None,
             })],
             value: Some(ch.to_string()),
             tied_to_previous: None,
@@ -200,6 +215,12 @@ fn parse_note(notation_system: &NotationSystem, ch: char, position: usize) -> Re
             divisions: None,
             is_tuplet: None,
             tuplet_ratio: None,
+            repeat_count: None,
+            chord: None,
+            dynamic: None,
+            feather: None,
+            explicit_tuplet: None,
+            source: None,
         }))
     } else {
         parse_unknown(ch, position)
@@ -215,6 +236,7 @@ fn parse_dash(ch: char, position: usize) -> Result<crate::models::ContentElement
                 value: Some(ch.to_string()),
             denominator: None,
             numerator: None,
+            source: None,
         })],
         value: Some(ch.to_string()),
         tied_to_previous: None,
@@ -222,6 +244,12 @@ fn parse_dash(ch: char, position: usize) -> Result<crate::models::ContentElement
         divisions: None,
         is_tuplet: None,
         tuplet_ratio: None,
+        repeat_count: None,
+        chord: None,
+        dynamic: None,
+        feather: None,
+        explicit_tuplet: None,
+        source: None,
     }))
 }
 
@@ -236,6 +264,7 @@ fn parse_unknown(ch: char, position: usize) -> Result<crate::models::ContentElem
         id: uuid::Uuid::new_v4(),
         value: Some(ch.to_string()),
         token_value: ch.to_string(),
+        source: None,
     }))
 }
 
@@ -0,0 +1,133 @@
+use crate::parse::Document;
+use crate::models::core::StaveLine;
+use crate::models::elements::{BeatElement, ContentElement};
+use crate::models::pitch_systems::pitchcode_to_string;
+use crate::renderers::transposition::{transpose_by_interval_steps, transpose_by_semitones, Interval};
+use uuid::Uuid;
+
+/// Transpose specific notes by a number of semitones, moving them into the next
+/// octave up or down when they pass the top or bottom of the scale.
+/// Targets may be Note UUIDs or Beat UUIDs (every note of the beat).
+pub fn apply_transpose_edit(
+    document: &mut Document,
+    target_uuids: &[String],
+    semitones: i32,
+) -> Result<(), String> {
+    let target_uuids: Vec<Uuid> = target_uuids
+        .iter()
+        .map(|s| s.parse::<Uuid>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Invalid UUID format: {}", e))?;
+
+    let mut modified_count = 0;
+    for element in &mut document.elements {
+        if let crate::models::core::DocumentElement::Stave(stave) = element {
+            for line in &mut stave.lines {
+                if let StaveLine::ContentLine(content_line) = line {
+                    for content_element in &mut content_line.elements {
+                        if let ContentElement::Beat(beat) = content_element {
+                            let whole_beat = target_uuids.contains(&beat.id);
+                            for beat_element in &mut beat.elements {
+                                if let BeatElement::Note(note) = beat_element {
+                                    if whole_beat || target_uuids.contains(&note.id) {
+                                        let (pitch_code, octave) = transpose_by_semitones(note.pitch_code, note.octave, semitones);
+                                        note.pitch_code = pitch_code;
+                                        note.octave = octave;
+                                        // Keep the raw pitch string in step; systems without a spelling keep the old one
+                                        if let Some(value) = pitchcode_to_string(pitch_code, note.notation_system.into()) {
+                                            note.value = Some(value);
+                                        }
+                                        modified_count += 1;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if modified_count == 0 {
+        return Err(format!("No notes found with the provided UUIDs. Searched for {} UUIDs in document.", target_uuids.len()));
+    }
+
+    Ok(())
+}
+
+/// Transpose every note of the document by a named interval such as "M3" or "-P4",
+/// spelling accidentals by scale steps. Grace notes and divisi pitches move too.
+pub fn transpose_by_interval(document: &mut Document, interval: &str) -> Result<(), String> {
+    let interval = Interval::parse(interval)?;
+    for note in document.notes_mut() {
+        (note.pitch_code, note.octave) = transpose_by_interval_steps(note.pitch_code, note.octave, interval);
+        if let Some(value) = pitchcode_to_string(note.pitch_code, note.notation_system.into()) {
+            note.value = Some(value);
+        }
+        for grace in &mut note.grace_notes {
+            (grace.pitch_code, grace.octave) = transpose_by_interval_steps(grace.pitch_code, grace.octave, interval);
+            grace.value = pitchcode_to_string(grace.pitch_code, note.notation_system.into()).or(grace.value.take());
+        }
+        if let Some(divisi) = &mut note.divisi {
+            (divisi.pitch_code, divisi.octave) = transpose_by_interval_steps(divisi.pitch_code, divisi.octave, interval);
+            divisi.value = pitchcode_to_string(divisi.pitch_code, note.notation_system.into()).or(divisi.value.take());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::edit::execute_edit;
+    use crate::parse::model::PitchCode;
+
+    fn document(notation: &str) -> Document {
+        crate::pipeline::document_from_notation(notation).unwrap()
+    }
+
+    fn transpose_all(text: &str, semitones: i64) -> Vec<(PitchCode, i8, Option<String>)> {
+        let mut document = document(text);
+        let targets: Vec<String> = document.notes().map(|note| note.id.to_string()).collect();
+        execute_edit(&mut document, "transpose", &targets, &serde_json::json!({ "semitones": semitones })).unwrap();
+        document.notes().map(|note| (note.pitch_code, note.octave, note.value.clone())).collect()
+    }
+
+    #[test]
+    fn test_transpose_up_a_whole_tone() {
+        assert_eq!(transpose_all("|1|\n", 2), vec![(PitchCode::N2, 0, Some("2".to_string()))]);
+    }
+
+    #[test]
+    fn test_transpose_wraps_into_next_octave() {
+        assert_eq!(transpose_all("|7|\n", 1), vec![(PitchCode::N1, 1, Some("1".to_string()))]);
+        assert_eq!(transpose_all("|1|\n", -1), vec![(PitchCode::N7, -1, Some("7".to_string()))]);
+    }
+
+    #[test]
+    fn test_transpose_only_targeted_notes() {
+        let mut document = document("|1 3|\n");
+        let first = document.notes().next().unwrap().id.to_string();
+        apply_transpose_edit(&mut document, &[first], 5).unwrap();
+        let pitches: Vec<PitchCode> = document.notes().map(|note| note.pitch_code).collect();
+        assert_eq!(pitches, vec![PitchCode::N4, PitchCode::N3]);
+
+        assert!(execute_edit(&mut document, "transpose", &[], &serde_json::json!({})).is_err());
+    }
+
+    #[test]
+    fn test_transpose_by_interval() {
+        let by_interval = |text: &str, interval: &str| {
+            let mut document = document(text);
+            transpose_by_interval(&mut document, interval).unwrap();
+            document.notes().map(|note| (note.pitch_code, note.octave, note.value.clone())).collect::<Vec<_>>()
+        };
+        assert_eq!(by_interval("|1|\n", "M3"), vec![(PitchCode::N3, 0, Some("3".to_string()))]);
+        assert_eq!(by_interval("|1|\n", "P5"), vec![(PitchCode::N5, 0, Some("5".to_string()))]);
+        assert_eq!(by_interval("|1|\n", "-P5"), vec![(PitchCode::N4, -1, Some("4".to_string()))]);
+        assert_eq!(by_interval("|1|\n", "m3"), vec![(PitchCode::N3b, 0, Some("3b".to_string()))]);
+
+        let error = transpose_by_interval(&mut document("|1|\n"), "up a third").unwrap_err();
+        assert!(error.contains("Unknown interval 'up a third'"), "{}", error);
+    }
+}
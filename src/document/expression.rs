@@ -0,0 +1,69 @@
+// Expressive playback markers from an annotation line above the notes
+// `~~~` marks a vibrato span (a lone `~` is a mordent), `^N` / `^-N` bends the note beneath by N semitones
+
+use crate::parse::model::ContentLine;
+
+/// Mark vibrato spans and pitch bends on the notes beneath an upper annotation line,
+/// matching by column like chord alignment
+pub fn apply_expression_markers(content_line: &mut ContentLine, upper_line: &str) {
+    let chars: Vec<char> = upper_line.chars().collect();
+    let mut vibrato_columns = Vec::new();
+    let mut bends = Vec::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '~' => {
+                let run = chars[i..].iter().take_while(|&&c| c == '~').count();
+                if run > 1 {
+                    vibrato_columns.extend(i + 1..=i + run);
+                }
+                i += run;
+                continue;
+            }
+            '^' => {
+                let mut j = i + 1;
+                if chars.get(j) == Some(&'-') {
+                    j += 1;
+                }
+                while chars.get(j).is_some_and(|c| c.is_ascii_digit()) {
+                    j += 1;
+                }
+                let amount: String = chars[i + 1..j].iter().collect();
+                if let Ok(semitones) = amount.parse::<i8>() {
+                    bends.push((i + 1, semitones));
+                }
+                i = j;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    for note in content_line.notes_mut() {
+        let Some(source) = note.source else { continue };
+        let columns = source.column..source.column + source.length;
+        if vibrato_columns.iter().any(|c| columns.contains(c)) {
+            note.vibrato = true;
+        }
+        if let Some((_, semitones)) = bends.iter().find(|(c, _)| columns.contains(c)) {
+            note.bend = Some(*semitones);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::line_parser::content_line_parser::unused_parse_content_line;
+    use crate::parse::model::NotationSystem;
+
+    #[test]
+    fn test_vibrato_span_and_bend() {
+        let mut line = unused_parse_content_line("1 2 3 4", 1, NotationSystem::Number, 8).unwrap();
+        apply_expression_markers(&mut line, "  ~~~ ^-1");
+        let marks: Vec<(bool, Option<i8>)> = line.notes_mut().map(|n| (n.vibrato, n.bend)).collect();
+        assert_eq!(marks, vec![(false, None), (true, None), (true, None), (false, Some(-1))]);
+    }
+}
@@ -0,0 +1,103 @@
+// Grace notes from an annotation line above the notes
+// Pitches written just before a note (e.g. above the barline opening its measure)
+// lead into it without taking time from its beat
+
+use crate::document::line_parser::content_line_parser::unused_parse_content_line;
+use crate::parse::model::{BeatElement, ContentElement, ContentLine, GraceNote, NotationSystem};
+
+/// Columns a grace group may end before the note it leads into, leaving room for
+/// the barline and space in front of the first note of a measure
+const MAX_GAP: usize = 2;
+
+/// Attach each group of pitches in an upper annotation line to the note starting
+/// right after it, matching by column. Words that are not pitches of the line's
+/// notation system (slurs, vibrato, bends) and ornament tokens such as `tr` are ignored.
+pub fn apply_grace_notes(content_line: &mut ContentLine, upper_line: &str, notation_system: NotationSystem) {
+    let groups = grace_groups(upper_line, notation_system);
+    if groups.is_empty() {
+        return;
+    }
+    let notes = content_line.elements.iter_mut()
+        .filter_map(|element| match element {
+            ContentElement::Beat(beat) => Some(beat),
+            _ => None,
+        })
+        .flat_map(|beat| beat.elements.iter_mut())
+        .filter_map(|element| match element {
+            BeatElement::Note(note) => Some(note),
+            _ => None,
+        });
+    let mut groups = groups.into_iter().peekable();
+    for note in notes {
+        let Some(source) = note.source else { continue };
+        // Groups ending too far before this note have no note to lead into
+        while groups.peek().is_some_and(|(end, _)| end + MAX_GAP < source.column) {
+            groups.next();
+        }
+        if groups.peek().is_some_and(|(end, _)| *end <= source.column) {
+            if let Some((_, grace_notes)) = groups.next() {
+                note.grace_notes = grace_notes;
+            }
+        }
+    }
+}
+
+/// Words of the upper line made only of notes, with the column just past each word
+fn grace_groups(upper_line: &str, notation_system: NotationSystem) -> Vec<(usize, Vec<GraceNote>)> {
+    let mut groups = Vec::new();
+    let mut column = 1;
+    for word in upper_line.split(' ') {
+        let length = word.chars().count();
+        if let Some(grace_notes) = notes_of(word, notation_system) {
+            groups.push((column + length, grace_notes));
+        }
+        column += length + 1;
+    }
+    groups
+}
+
+fn notes_of(word: &str, notation_system: NotationSystem) -> Option<Vec<GraceNote>> {
+    if word.is_empty() || crate::document::ornaments::is_ornament_token(word) {
+        return None;
+    }
+    let line = unused_parse_content_line(word, 0, notation_system, 0).ok()?;
+    let mut grace_notes = Vec::new();
+    for element in &line.elements {
+        let ContentElement::Beat(beat) = element else { return None };
+        for beat_element in &beat.elements {
+            let BeatElement::Note(note) = beat_element else { return None };
+            grace_notes.push(GraceNote {
+                value: note.value.clone(),
+                pitch_code: note.pitch_code,
+                octave: note.octave,
+            });
+        }
+    }
+    (!grace_notes.is_empty()).then_some(grace_notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::model::PitchCode;
+
+    #[test]
+    fn test_grace_group_before_barline_leads_into_downbeat() {
+        let mut line = unused_parse_content_line("| 1 2 3 4 | 5 6 7 1 |", 1, NotationSystem::Number, 0).unwrap();
+        apply_grace_notes(&mut line, "   ~     43", NotationSystem::Number);
+        let graced: Vec<(Option<String>, Vec<PitchCode>)> = line.elements.iter()
+            .filter_map(|element| match element {
+                ContentElement::Beat(beat) => Some(beat),
+                _ => None,
+            })
+            .flat_map(|beat| beat.elements.iter())
+            .filter_map(|element| match element {
+                BeatElement::Note(note) if !note.grace_notes.is_empty() => {
+                    Some((note.value.clone(), note.grace_notes.iter().map(|g| g.pitch_code).collect()))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(graced, vec![(Some("5".to_string()), vec![PitchCode::N4, PitchCode::N3])]);
+    }
+}
@@ -0,0 +1,290 @@
+// Incremental reparsing for the live editor
+// Staves are separated by blank lines, and a stave the edit didn't touch parses the same
+// way again, so only the lines between the last untouched stave before the edit and the
+// first one after it are parsed. The untouched staves are copied over with their ids,
+// those after the edit moved to their new line and character positions.
+
+use crate::parse::line_classifier::{classify_line_types, LineType};
+use crate::parse::model::{Barline, BeatElement, ContentElement, Document, DocumentElement, Source, Stave, StaveLine, GRAND_STAFF_CLOSE, GRAND_STAFF_OPEN};
+use crate::pipeline::{add_notation_lines, document_from_notation};
+use std::ops::Range;
+
+/// Parse `new_input`, the text of `old` after replacing some of it so that the new text is
+/// at byte range `edit_range` of `new_input`. Staves outside the edit are taken from `old`;
+/// when that can't be done safely (the header, grand staff braces or the notation system
+/// detected for the document changed, or `edit_range` doesn't describe the change) the
+/// whole document is parsed again, as `pipeline::document_from_notation` does.
+pub fn parse_document_incremental(old: &Document, new_input: &str, edit_range: Range<usize>) -> Result<Document, String> {
+    match reparse_edited_staves(old, new_input, edit_range) {
+        Some(document) => document,
+        None => document_from_notation(new_input),
+    }
+}
+
+/// The reparsed document, or None when only a full parse gives the right result
+fn reparse_edited_staves(old: &Document, new_input: &str, edit_range: Range<usize>) -> Option<Result<Document, String>> {
+    let old_input = old.value.as_deref()?;
+    let old_end = (edit_range.end + old_input.len()).checked_sub(new_input.len())?;
+    if edit_range.start > edit_range.end.min(old_end)
+        || new_input.get(..edit_range.start)? != old_input.get(..edit_range.start)?
+        || new_input.get(edit_range.end..)? != old_input.get(old_end..)?
+    {
+        return None;
+    }
+    let braced = |input: &str| input.lines().any(|line| line.trim() == GRAND_STAFF_OPEN || line.trim() == GRAND_STAFF_CLOSE);
+    if braced(old_input) || braced(new_input) {
+        return None;
+    }
+
+    // Lines from the first changed one to the last, in old and new line numbers (0-based)
+    let first_changed = new_input[..edit_range.start].matches('\n').count();
+    let new_last_changed = new_input[..edit_range.end].matches('\n').count();
+    let old_last_changed = old_input[..old_end].matches('\n').count();
+
+    let old_classified = classify_line_types(old_input);
+    let new_classified = classify_line_types(new_input);
+    let header = header_lines(&old_classified)?;
+    if header != header_lines(&new_classified)? || first_changed < header {
+        return None;
+    }
+    let ranges = element_line_ranges(old, header);
+    if ranges.last().map_or(header, |range| range.end) != old_classified.len() {
+        return None;
+    }
+
+    // Keep the elements up to a blank line wholly before the edit, and the staves after
+    // a blank line wholly after it; everything between is parsed again
+    let new_lines: Vec<&str> = new_input.lines().collect();
+    let blank = |index: usize| matches!(old.elements[index], DocumentElement::BlankLines(_));
+    let kept_before = (1..=old.elements.len()).rev()
+        .find(|&index| {
+            let end = ranges[index - 1].end;
+            blank(index - 1) && (end < first_changed || (end == first_changed && new_lines.get(end).is_none_or(|line| !line.trim().is_empty())))
+        })
+        .unwrap_or(0);
+    let kept_after = (kept_before.max(1)..old.elements.len())
+        .find(|&index| matches!(old.elements[index], DocumentElement::Stave(_)) && blank(index - 1) && ranges[index].start > old_last_changed)
+        .unwrap_or(old.elements.len());
+    let reparse_start = if kept_before > 0 { ranges[kept_before - 1].end } else { header };
+    let reparse_end = match ranges.get(kept_after) {
+        Some(range) => (range.start + new_last_changed).checked_sub(old_last_changed)?,
+        None => new_classified.len(),
+    };
+
+    // Kept lines must read the same in the new text: the notation system detected for the
+    // document and the role of each line can change with lines far from them
+    let kept_after_start = ranges.get(kept_after).map_or(old_classified.len(), |range| range.start);
+    if old_classified[..reparse_start] != new_classified[..reparse_start]
+        || old_classified[kept_after_start..] != new_classified[reparse_end..]
+    {
+        return None;
+    }
+
+    let mut middle = Document {
+        value: Some(new_input.to_string()),
+        title: old.title.clone(),
+        directives: old.directives.clone(),
+        ..Default::default()
+    };
+    let line_start = new_lines[..reparse_start].iter().map(|line| line.chars().count() + 1).sum();
+    let mut pending = match add_notation_lines(&mut middle, new_input, &new_classified, reparse_start..reparse_end, line_start) {
+        Ok(pending) => pending,
+        Err(error) => return Some(Err(error)),
+    };
+    // The staves after the edit must still start after a blank line with nothing waiting
+    // for a content line, or the reparsed lines would run into them
+    if kept_after < old.elements.len() && (!pending.is_empty() || !matches!(middle.elements.last(), Some(DocumentElement::BlankLines(_)))) {
+        return None;
+    }
+    if let Err(error) = crate::rhythm::analyzer::analyze_rhythm_into_document(&mut middle) {
+        return Some(Err(error));
+    }
+
+    let line_shift = new_last_changed as isize - old_last_changed as isize;
+    let char_shift = new_input.chars().count() as isize - old_input.chars().count() as isize;
+    let mut elements = old.elements[..kept_before].to_vec();
+    elements.append(&mut middle.elements);
+    elements.extend(old.elements[kept_after..].iter().cloned().map(|mut element| {
+        shift_element(&mut element, line_shift, char_shift);
+        element
+    }));
+    // Lines after the last stave stay with it
+    if let Some(DocumentElement::Stave(stave)) = elements.iter_mut().rev().find(|e| matches!(e, DocumentElement::Stave(_))) {
+        stave.lines.append(&mut pending);
+    }
+
+    Some(Ok(Document {
+        value: Some(new_input.to_string()),
+        title: old.title.clone(),
+        directives: old.directives.clone(),
+        elements,
+        ..Default::default()
+    }))
+}
+
+/// Number of title and directive lines at the top, or None when there are more further down
+fn header_lines(classified: &[(LineType, &str)]) -> Option<usize> {
+    let is_header = |line_type: &LineType| matches!(line_type, LineType::Title | LineType::Directive);
+    let header = classified.iter().take_while(|(line_type, _)| is_header(line_type)).count();
+    if classified[header..].iter().any(|(line_type, _)| is_header(line_type)) {
+        return None;
+    }
+    Some(header)
+}
+
+/// Lines (0-based, end exclusive) of the source each document element was parsed from.
+/// Elements hold every line after the header in order: one per stave line, and one per
+/// newline of a run of blank lines.
+fn element_line_ranges(document: &Document, header: usize) -> Vec<Range<usize>> {
+    let mut start = header;
+    document.elements.iter()
+        .map(|element| {
+            let count = match element {
+                DocumentElement::Stave(stave) => stave.lines.len(),
+                DocumentElement::BlankLines(blank_lines) => blank_lines.value.as_deref().unwrap_or("\n").matches('\n').count(),
+            };
+            start += count;
+            start - count..start
+        })
+        .collect()
+}
+
+/// Move an element kept from the old document to where its text is in the new one
+fn shift_element(element: &mut DocumentElement, lines: isize, chars: isize) {
+    let shift = |value: &mut usize, by: isize| *value = value.saturating_add_signed(by);
+    match element {
+        DocumentElement::Stave(stave) => shift_stave(stave, lines, chars),
+        DocumentElement::BlankLines(blank_lines) => {
+            shift(&mut blank_lines.line, lines);
+            shift(&mut blank_lines.index_in_doc, chars);
+        }
+    }
+}
+
+fn shift_stave(stave: &mut Stave, lines: isize, chars: isize) {
+    let shift = |value: &mut usize, by: isize| *value = value.saturating_add_signed(by);
+    let shift_source = |source: &mut Option<Source>| {
+        if let Some(source) = source {
+            shift(&mut source.line, lines);
+            shift(&mut source.char_index, chars);
+        }
+    };
+    shift(&mut stave.line, lines);
+    shift(&mut stave.index_in_doc, chars);
+    for line in &mut stave.lines {
+        match line {
+            StaveLine::BlankLines(blank_lines) => {
+                shift(&mut blank_lines.line, lines);
+                shift(&mut blank_lines.index_in_doc, chars);
+            }
+            StaveLine::ContentLine(content_line) => {
+                for element in &mut content_line.elements {
+                    match element {
+                        ContentElement::Beat(beat) => {
+                            shift_source(&mut beat.source);
+                            for beat_element in &mut beat.elements {
+                                shift_source(match beat_element {
+                                    BeatElement::Note(note) => &mut note.source,
+                                    BeatElement::Dash(dash) => &mut dash.source,
+                                    BeatElement::BreathMark(breath) => &mut breath.source,
+                                    BeatElement::Rest(rest) => &mut rest.source,
+                                });
+                            }
+                        }
+                        ContentElement::Barline(barline) => shift_source(match barline {
+                            Barline::Single(b) => &mut b.source,
+                            Barline::Double(b) => &mut b.source,
+                            Barline::Final(b) => &mut b.source,
+                            Barline::RepeatStart(b) => &mut b.source,
+                            Barline::RepeatEnd(b) => &mut b.source,
+                            Barline::RepeatBoth(b) => &mut b.source,
+                        }),
+                        ContentElement::Whitespace(whitespace) => shift_source(&mut whitespace.source),
+                        ContentElement::UnknownToken(token) => shift_source(&mut token.source),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn stave_note_ids(document: &Document) -> Vec<Vec<Uuid>> {
+        document.elements.iter()
+            .filter_map(|element| match element {
+                DocumentElement::Stave(stave) => Some(stave),
+                _ => None,
+            })
+            .map(|stave| stave.lines.iter()
+                .filter_map(|line| match line {
+                    StaveLine::ContentLine(content_line) => Some(content_line),
+                    _ => None,
+                })
+                .flat_map(|content_line| &content_line.elements)
+                .filter_map(|element| match element {
+                    ContentElement::Beat(beat) => Some(beat),
+                    _ => None,
+                })
+                .flat_map(|beat| &beat.elements)
+                .filter_map(|element| match element {
+                    BeatElement::Note(note) => Some(note.id),
+                    _ => None,
+                })
+                .collect())
+            .collect()
+    }
+
+    /// Apply an edit replacing `old_text` with `new_text` and check the incremental result
+    /// against a full parse of the new text
+    fn edit(input: &str, old_text: &str, new_text: &str) -> (Document, Document) {
+        let old = document_from_notation(input).unwrap();
+        let start = input.find(old_text).unwrap();
+        let new_input = format!("{}{}{}", &input[..start], new_text, &input[start + old_text.len()..]);
+        let document = parse_document_incremental(&old, &new_input, start..start + new_text.len()).unwrap();
+
+        let full = document_from_notation(&new_input).unwrap();
+        assert_eq!(document.to_plain_text(), full.to_plain_text());
+        let sounding = |document: &Document| document.notes()
+            .map(|note| (note.pitch_code, note.octave, note.numerator, note.denominator, note.source))
+            .collect::<Vec<_>>();
+        assert_eq!(sounding(&document), sounding(&full));
+        (old, document)
+    }
+
+    #[test]
+    fn test_editing_second_stave_keeps_first_stave_ids() {
+        let (old, document) = edit("Title: Song\nKey: D\n\n|1 2 3 4|\n\n|5 6 7 1|\nla la la la\n", "6", "6 5");
+        let (old_ids, ids) = (stave_note_ids(&old), stave_note_ids(&document));
+        assert_eq!(ids[0], old_ids[0]);
+        assert_eq!(ids[1].len(), 5);
+        assert!(ids[1].iter().all(|id| !old_ids[1].contains(id)));
+    }
+
+    #[test]
+    fn test_staves_after_the_edit_move_with_their_text() {
+        let (old, document) = edit("|1 2|\n\n|3 4|\n\n\n|5 6|\n", "|3 4|\n", "|3 4 3|\n.\n|1|\n");
+        let (old_ids, ids) = (stave_note_ids(&old), stave_note_ids(&document));
+        assert_eq!(ids.len(), 4);
+        assert_eq!(ids[0], old_ids[0]);
+        assert_eq!(ids[3], old_ids[2]);
+        let DocumentElement::Stave(stave) = document.elements.last().unwrap() else { panic!("expected a stave") };
+        assert_eq!(stave.line, 8);
+    }
+
+    #[test]
+    fn test_falls_back_to_full_parse() {
+        // A changed header can change how every stave reads
+        let (old, document) = edit("Key: D\n\n|1 2|\n\n|3 4|\n", "D", "Eb");
+        assert!(stave_note_ids(&document).iter().flatten().all(|id| !stave_note_ids(&old).concat().contains(id)));
+
+        // An edit range that doesn't match the text is not trusted
+        let old = document_from_notation("|1 2|\n\n|3 4|\n").unwrap();
+        let document = parse_document_incremental(&old, "|1 2|\n\n|3 5|\n", 0..0).unwrap();
+        assert_eq!(document.to_plain_text(), "|1 2|\n\n|3 5|\n");
+    }
+}
@@ -0,0 +1,70 @@
+// Articulation line parser
+// A line marked "@articulation" such as ".   >   -" puts a staccato, accent or
+// tenuto on the note at each mark's column. The marker keeps a staccato dot
+// from being read as an octave dot in an upper or lower line.
+
+use crate::parse::model::{Articulation, BeatElement, ContentElement, ContentLine};
+
+/// Articulations of an articulation line, with the 1-based column of each.
+/// Other characters are ignored.
+pub fn parse_articulation_line(input: &str) -> Vec<(usize, Articulation)> {
+    input.chars()
+        .enumerate()
+        .filter_map(|(column, ch)| Articulation::from_symbol(ch).map(|articulation| (column + 1, articulation)))
+        .collect()
+}
+
+/// Attach marks from an articulation line to the notes at their columns.
+/// A mark belongs to the note at its column or, between notes, to the nearest note to its right.
+pub fn attach_articulations(content_line: &mut ContentLine, articulation_line: &str) {
+    for (column, articulation) in parse_articulation_line(articulation_line) {
+        let target = content_line.elements.iter_mut()
+            .filter_map(|element| match element {
+                ContentElement::Beat(beat) => Some(beat.elements.iter_mut()),
+                _ => None,
+            })
+            .flatten()
+            .find_map(|element| match element {
+                BeatElement::Note(note) => match note.source {
+                    Some(source) if source.column + source.length > column => Some(note),
+                    _ => None,
+                },
+                _ => None,
+            });
+        if let Some(note) = target {
+            note.articulations.push(articulation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::model::NotationSystem;
+
+    #[test]
+    fn test_articulations_align_to_notes() {
+        let mut line = crate::document::line_parser::content_line_parser::unused_parse_content_line(
+            "1 23 4 5", 0, NotationSystem::Number, 0,
+        ).unwrap();
+        attach_articulations(&mut line, ".  >   -");
+        let marks: Vec<Vec<Articulation>> = line.elements.iter()
+            .filter_map(|e| match e {
+                ContentElement::Beat(beat) => Some(&beat.elements),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|e| match e {
+                BeatElement::Note(note) => Some(note.articulations.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(marks, vec![
+            vec![Articulation::Staccato],
+            vec![],
+            vec![Articulation::Accent],
+            vec![],
+            vec![Articulation::Tenuto],
+        ]);
+    }
+}
@@ -1,7 +1,7 @@
-use crate::parse::model::{ContentLine, ContentElement, NotationSystem, Barline, SingleBarline, DoubleBarline, FinalBarline, RepeatStartBarline, RepeatEndBarline, RepeatBothBarline};
-use crate::parse::beat::parse_beat;
-use crate::parse::pitch::is_pitch_start;
-use crate::parse::ParseError;
+use crate::parse::model::{ContentLine, ContentElement, BeatElement, HasPosition, NotationSystem, Barline, SingleBarline, DoubleBarline, FinalBarline, RepeatStartBarline, RepeatEndBarline, RepeatBothBarline};
+use crate::parse::beat::{parse_beat, parse_tuplet_beat};
+use crate::parse::pitch::{is_pitch_start, is_rest_start, ScientificPitch};
+use crate::parse::{ParseError, ParseErrorKind, source_span};
 use std::str::CharIndices;
 use std::iter::Peekable;
 
@@ -15,6 +15,17 @@ fn index_in_line_from_pos(input: &str, pos: usize, _line_num: usize) -> usize {
     input[..pos].chars().rev().take_while(|&c| c != '\n').count()
 }
 
+/// How strictly the content line parser treats text it can't interpret
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Drop line numbers and reject malformed barlines
+    #[default]
+    Standard,
+    /// Keep every character: line numbers and malformed barlines become unknown tokens,
+    /// so `to_source` reproduces the input exactly
+    Lossless,
+}
+
 /// Parse content line according to grammar:
 /// content_line = line_number? non-beat-element* beat (non-beat-element | beat)* newline
 /// non-beat-element = barline | whitespace
@@ -23,6 +34,30 @@ pub fn unused_parse_content_line(
     line_num: usize,
     notation_system: NotationSystem,
     line_start_doc_index: usize,
+) -> Result<ContentLine, ParseError> {
+    parse_content_line_with_mode(input, line_num, notation_system, line_start_doc_index, ParseMode::Standard)
+}
+
+/// Parse a content line, choosing whether uninterpretable text is kept (see `ParseMode`)
+pub fn parse_content_line_with_mode(
+    input: &str,
+    line_num: usize,
+    notation_system: NotationSystem,
+    line_start_doc_index: usize,
+    mode: ParseMode,
+) -> Result<ContentLine, ParseError> {
+    parse_content_line_with_options(input, line_num, notation_system, line_start_doc_index, mode, ScientificPitch::default())
+}
+
+/// Parse a content line, also choosing whether Western pitches carry octave digits
+/// (see `ScientificPitch`)
+pub fn parse_content_line_with_options(
+    input: &str,
+    line_num: usize,
+    notation_system: NotationSystem,
+    line_start_doc_index: usize,
+    mode: ParseMode,
+    scientific_pitch: ScientificPitch,
 ) -> Result<ContentLine, ParseError> {
     let mut elements = Vec::new();
     let mut chars = input.char_indices().peekable();
@@ -44,7 +79,17 @@ pub fn unused_parse_content_line(
         }
     }
 
-    if has_dot_after_digits {
+    if has_dot_after_digits && mode == ParseMode::Lossless {
+        // Keep the line number as written; the spaces after it are parsed as whitespace
+        let mut token = String::new();
+        while let Some((_, ch)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+            token.push(ch);
+        }
+        if let Some((_, dot)) = chars.next_if(|(_, c)| *c == '.') {
+            token.push(dot);
+        }
+        elements.push(unknown_token(input, 0, token, line_num, line_start_doc_index));
+    } else if has_dot_after_digits {
         // Skip digits
         while chars.peek().map_or(false, |(_, c)| c.is_ascii_digit()) {
             chars.next();
@@ -69,15 +114,30 @@ pub fn unused_parse_content_line(
 
             '|' | ':' => {
                 // Parse barline using tokenization
-                let barline = parse_barline(
+                let before_barline = chars.clone();
+                match parse_barline(
                     &mut chars,
                     ch,
                     pos,
                     line_num,
                     input,
                     line_start_doc_index,
-                )?;
-                elements.push(ContentElement::Barline(barline));
+                ) {
+                    Ok(barline) => elements.push(ContentElement::Barline(barline)),
+                    Err(_) if mode == ParseMode::Lossless => {
+                        // Keep the malformed barline text up to the next space or barline
+                        chars = before_barline;
+                        let mut token = String::new();
+                        if let Some((_, first)) = chars.next() {
+                            token.push(first);
+                        }
+                        while let Some((_, ch)) = chars.next_if(|(_, c)| !matches!(c, ' ' | '|' | '\n')) {
+                            token.push(ch);
+                        }
+                        elements.push(unknown_token(input, pos, token, line_num, line_start_doc_index));
+                    }
+                    Err(error) => return Err(error),
+                }
             }
 
             ' ' => {
@@ -94,6 +154,7 @@ pub fn unused_parse_content_line(
                 elements.push(ContentElement::Whitespace(crate::parse::model::Whitespace {
                     id: uuid::Uuid::new_v4(),
                     value: Some(whitespace_content),
+                    source: Some(source_span(input, start_pos, start_pos + space_count, line_num, line_start_doc_index)),
                 }));
             }
 
@@ -102,6 +163,7 @@ pub fn unused_parse_content_line(
                 let beat = parse_beat(
                     &mut chars,
                     notation_system,
+                    scientific_pitch,
                     line_num,
                     input,
                     line_start_doc_index,
@@ -110,11 +172,69 @@ pub fn unused_parse_content_line(
                 elements.push(ContentElement::Beat(beat));
             }
 
-            ch if is_pitch_start(ch, notation_system) => {
+            '(' if meter_change_ahead(&chars) => {
+                // Meter change after a barline ("|(3/4)"), kept as a token like a breath mark
+                let mut token = String::new();
+                for (_, ch) in chars.by_ref() {
+                    token.push(ch);
+                    if ch == ')' {
+                        break;
+                    }
+                }
+                elements.push(unknown_token(input, pos, token, line_num, line_start_doc_index));
+            }
+
+            '(' => {
+                // Beat opening with grace notes before its first pitch ("(2)1")
+                let before_grace = chars.clone();
+                match parse_beat(&mut chars, notation_system, scientific_pitch, line_num, input, line_start_doc_index) {
+                    Ok(beat) => elements.push(ContentElement::Beat(beat)),
+                    Err(_) if mode == ParseMode::Lossless => {
+                        // Keep the malformed grace notes up to the next space or barline
+                        chars = before_grace;
+                        let mut token = String::new();
+                        while let Some((_, ch)) = chars.next_if(|(_, c)| !matches!(c, ' ' | '|' | '\n')) {
+                            token.push(ch);
+                        }
+                        elements.push(unknown_token(input, pos, token, line_num, line_start_doc_index));
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+
+            '[' => {
+                // Beat with an explicit tuplet ratio ("[3:2 1 2 3]")
+                let before_tuplet = chars.clone();
+                match parse_tuplet_beat(&mut chars, notation_system, scientific_pitch, line_num, input, line_start_doc_index) {
+                    Ok(beat) => elements.push(ContentElement::Beat(beat)),
+                    Err(_) if mode == ParseMode::Lossless => {
+                        // Keep the malformed tuplet text up to the next space or barline
+                        chars = before_tuplet;
+                        let mut token = String::new();
+                        while let Some((_, ch)) = chars.next_if(|(_, c)| !matches!(c, ' ' | '|' | '\n')) {
+                            token.push(ch);
+                        }
+                        elements.push(unknown_token(input, pos, token, line_num, line_start_doc_index));
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+
+            'R' if multi_measure_rest_ahead(&chars) => {
+                // Multi-measure rest ("R*8"), never a Sargam Re repeated
+                let mut token = String::new();
+                while let Some((_, ch)) = chars.next_if(|(_, c)| !matches!(c, ' ' | '|' | '\n')) {
+                    token.push(ch);
+                }
+                elements.push(unknown_token(input, pos, token, line_num, line_start_doc_index));
+            }
+
+            ch if is_pitch_start(ch, notation_system) || is_rest_start(ch, notation_system) => {
                 // Parse beat
                 let beat = parse_beat(
                     &mut chars,
                     notation_system,
+                    scientific_pitch,
                     line_num,
                     input,
                     line_start_doc_index,
@@ -139,13 +259,7 @@ pub fn unused_parse_content_line(
 
                 if !token.is_empty() {
                     // Create an UnknownToken element - like whitespace, it separates beats
-                    let unknown_token = crate::parse::model::UnknownToken {
-                        id: uuid::Uuid::new_v4(),
-                        value: Some(token.clone()),
-                            token_value: token,
-                    };
-
-                    elements.push(ContentElement::UnknownToken(unknown_token));
+                    elements.push(unknown_token(input, start_pos, token, line_num, line_start_doc_index));
                 }
             }
         }
@@ -158,9 +272,67 @@ pub fn unused_parse_content_line(
     })
 }
 
+/// Whether the text at `chars` is a parenthesized time signature ("(3/4)") rather than grace notes
+fn meter_change_ahead(chars: &Peekable<CharIndices>) -> bool {
+    let mut token = String::new();
+    for (_, ch) in chars.clone() {
+        token.push(ch);
+        if ch == ')' || matches!(ch, ' ' | '|' | '\n') {
+            break;
+        }
+    }
+    crate::analysis::TimeSignature::from_inline(&token).is_some()
+}
+
+/// Whether the text at `chars` is a multi-measure rest token ("R*8")
+fn multi_measure_rest_ahead(chars: &Peekable<CharIndices>) -> bool {
+    let token: String = chars.clone().map(|(_, ch)| ch).take_while(|ch| !matches!(ch, ' ' | '|' | '\n')).collect();
+    crate::rhythm::measures::multi_measure_rest_count(&token).is_some()
+}
+
+/// Unknown token for the text starting at byte offset `start_pos`
+fn unknown_token(input: &str, start_pos: usize, token: String, line_num: usize, line_start_doc_index: usize) -> ContentElement {
+    ContentElement::UnknownToken(crate::parse::model::UnknownToken {
+        id: uuid::Uuid::new_v4(),
+        value: Some(token.clone()),
+        source: Some(source_span(input, start_pos, start_pos + token.len(), line_num, line_start_doc_index)),
+        token_value: token,
+    })
+}
+
+/// Source text of a parsed content line, rebuilt from its elements.
+/// Lines parsed in `ParseMode::Lossless` come back exactly as written.
+pub fn to_source(content_line: &ContentLine) -> String {
+    content_line.elements.iter().map(element_to_source).collect()
+}
+
+/// Source text of one element of a content line
+pub fn element_to_source(element: &ContentElement) -> String {
+    match element {
+        ContentElement::Beat(beat) if beat.explicit_tuplet.is_some() => beat.value.clone().unwrap_or_default(),
+        ContentElement::Beat(beat) => beat.elements.iter()
+            .filter_map(|e| match e {
+                BeatElement::Note(note) => note.value.clone().map(|value| {
+                    let grace_notes: String = note.grace_notes.iter().filter_map(|grace| grace.value.clone()).collect();
+                    let grace_group = if grace_notes.is_empty() { String::new() } else { format!("({})", grace_notes) };
+                    grace_group + &value + &note.tie.map(String::from).unwrap_or_default()
+                }),
+                other => other.value().cloned(),
+            })
+            .collect::<String>(),
+        ContentElement::Barline(barline) => barline.value().cloned().unwrap_or_default(),
+        ContentElement::Whitespace(whitespace) => whitespace.value.clone().unwrap_or_default(),
+        ContentElement::UnknownToken(token) => token.token_value.clone(),
+    }
+}
+
 /// Parse barline using recursive descent tokenization
-/// Grammar: barline = '|' ( '|' | ':' | '.' | ':|' )? | ':' '|' ( ':' )?
-fn parse_barline(
+/// Grammar: barline = ( '|' ( '|' | ':' | '.' | ':|' )? | ':' '|' ( ':' )? ) ending?
+/// ending = digit+ '.'
+///
+/// An ending number opens a first, second, ... ending bracket after the barline
+/// (e.g. "|1." or ":|2."); it is kept in the barline's value.
+pub(crate) fn parse_barline(
     chars: &mut Peekable<CharIndices>,
     first_char: char,
     start_pos: usize,
@@ -219,25 +391,262 @@ fn parse_barline(
             }
         } else {
             return Err(ParseError {
+                kind: ParseErrorKind::MissingBarline,
                 message: "Expected '|' after ':' in barline".to_string(),
                 line: line_num,
                 column: column_from_pos(input, start_pos + 1),
-            });
+                suggestions: Vec::new(),
+            }.with_suggestion("did you forget a barline? A repeat is closed with ':|' and opened with '|:'"));
+        }
+    }
+
+    let barline_type = barline_str.clone();
+    if barline_type != "|." {
+        let mut ahead = chars.clone();
+        let mut digits = String::new();
+        while let Some((_, ch)) = ahead.next_if(|(_, c)| c.is_ascii_digit()) {
+            digits.push(ch);
+        }
+        if !digits.is_empty() && ahead.next_if(|(_, c)| *c == '.').is_some() {
+            *chars = ahead;
+            barline_str.push_str(&digits);
+            barline_str.push('.');
         }
     }
 
     let value = Some(barline_str.clone());
+    let id = uuid::Uuid::new_v4();
+    let source = Some(source_span(input, start_pos, start_pos + barline_str.len(), line_num, line_start_doc_index));
 
     // Create specific barline object based on pattern
-    let barline = match barline_str.as_str() {
+    let barline = match barline_type.as_str() {
+        "|" => Barline::Single(SingleBarline { id, value, source }),
+        "||" => Barline::Double(DoubleBarline { id, value, source }),
+        "|." => Barline::Final(FinalBarline { id, value, source }),
+        "|:" => Barline::RepeatStart(RepeatStartBarline { id, value, source }),
+        ":|" => Barline::RepeatEnd(RepeatEndBarline { id, value, source }),
+        ":|:" | "|:|" => Barline::RepeatBoth(RepeatBothBarline { id, value, source }),
         _ => {
             return Err(ParseError {
+                kind: ParseErrorKind::InvalidBarline,
                 message: format!("Invalid barline pattern: {}", barline_str),
                 line: line_num,
                 column: column_from_pos(input, start_pos),
+                suggestions: Vec::new(),
             });
         }
     };
 
     Ok(barline)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::model::{BeatElement, Source};
+
+    #[test]
+    fn test_explicit_tuplet_ratio_beats() {
+        let mut line = unused_parse_content_line("[3:2 1 2 3 4] 5", 0, NotationSystem::Number, 0).unwrap();
+        crate::rhythm::analyzer::analyze_content_line_rhythm(&mut line.elements).unwrap();
+        let ContentElement::Beat(beat) = &line.elements[0] else { panic!("{:?}", line.elements[0]) };
+        assert_eq!(beat.elements.len(), 4);
+        assert_eq!((beat.is_tuplet, beat.tuplet_ratio), (Some(true), Some((3, 2))));
+        assert!(matches!(line.elements[2], ContentElement::Beat(_)));
+
+        let error = |text: &str| unused_parse_content_line(text, 1, NotationSystem::Number, 0).unwrap_err().message;
+        assert_eq!(error("[3:0 1 2 3]"), "Tuplet ratio can't contain zero");
+        assert_eq!(error("[3:2 1 [3:2 1 2 3]]"), "Tuplets can't be nested");
+        assert_eq!(error("[3:2 1 2 3 | 4"), "Unclosed tuplet, expected ']'");
+        assert_eq!(error("[3 1 2 3]"), "Expected ':' in tuplet ratio");
+
+        let text = "[3:2 1 2 3] [3:0 1]";
+        let lossless = parse_content_line_with_mode(text, 0, NotationSystem::Number, 0, ParseMode::Lossless).unwrap();
+        assert_eq!(to_source(&lossless), text);
+    }
+
+    #[test]
+    fn test_inline_grace_notes() {
+        let mut line = unused_parse_content_line("(2)1 3(54)2", 0, NotationSystem::Number, 0).unwrap();
+        crate::rhythm::analyzer::analyze_content_line_rhythm(&mut line.elements).unwrap();
+        let beats: Vec<&crate::parse::model::Beat> = line.elements.iter().filter_map(|e| match e {
+            ContentElement::Beat(beat) => Some(beat),
+            _ => None,
+        }).collect();
+        assert_eq!(beats.len(), 2);
+
+        // Grace notes take no subdivision of their beat
+        assert_eq!(beats[0].elements.len(), 1);
+        assert_eq!(beats[0].divisions, Some(1));
+        let BeatElement::Note(note) = &beats[0].elements[0] else { panic!("{:?}", beats[0].elements[0]) };
+        assert_eq!(note.pitch_code, crate::parse::model::PitchCode::N1);
+        assert_eq!(note.grace_notes.iter().map(|g| g.pitch_code).collect::<Vec<_>>(), vec![crate::parse::model::PitchCode::N2]);
+        assert_eq!(beats[1].divisions, Some(2));
+        let BeatElement::Note(note) = &beats[1].elements[1] else { panic!("{:?}", beats[1].elements[1]) };
+        assert_eq!(note.grace_notes.len(), 2);
+
+        let error = |text: &str| unused_parse_content_line(text, 1, NotationSystem::Number, 0).unwrap_err().message;
+        assert_eq!(error("(2 1"), "Unclosed grace notes, expected ')'");
+        assert_eq!(error("()1"), "Grace notes in parentheses can't be empty");
+        assert_eq!(error("(2) 1"), "Grace notes must be followed by the note they lead into");
+
+        let text = "(2)1 3(54)2 (2";
+        let lossless = parse_content_line_with_mode(text, 0, NotationSystem::Number, 0, ParseMode::Lossless).unwrap();
+        assert_eq!(to_source(&lossless), text);
+    }
+
+    #[test]
+    fn test_meter_change_after_barline() {
+        let text = "|1 2 3 4|(3/4) 5 6 7|";
+        let line = unused_parse_content_line(text, 0, NotationSystem::Number, 0).unwrap();
+        let changes: Vec<_> = line.elements.iter().filter_map(crate::rhythm::measures::meter_change).collect();
+        assert_eq!(changes, vec![crate::analysis::TimeSignature { beats: 3, unit: 4 }]);
+        assert_eq!(line.elements.iter().filter(|e| matches!(e, ContentElement::Beat(_))).count(), 7);
+        assert_eq!(to_source(&line), text);
+
+        // Grace notes in parentheses are still grace notes
+        let line = unused_parse_content_line("(3)4", 0, NotationSystem::Number, 0).unwrap();
+        assert!(line.elements.iter().all(|e| crate::rhythm::measures::meter_change(e).is_none()));
+    }
+
+    #[test]
+    fn test_scientific_pitch_octave_digits() {
+        let on = ScientificPitch(true);
+        let notes = |text: &str, system: NotationSystem, scientific_pitch: ScientificPitch| {
+            let line = parse_content_line_with_options(text, 0, system, 0, ParseMode::Standard, scientific_pitch).unwrap();
+            line.elements.iter().filter_map(|e| match e {
+                ContentElement::Beat(beat) => Some(beat),
+                _ => None,
+            }).flat_map(|beat| &beat.elements).filter_map(|e| match e {
+                BeatElement::Note(note) => Some((note.value.clone().unwrap(), note.octave)),
+                _ => None,
+            }).collect::<Vec<_>>()
+        };
+        let owned = |expected: &[(&str, i8)]| expected.iter().map(|(v, o)| (v.to_string(), *o)).collect::<Vec<_>>();
+
+        assert_eq!(notes("C4 C5 C3 | Bb3G5", NotationSystem::Western, on), owned(&[("C4", 0), ("C5", 1), ("C3", -1), ("Bb3", -1), ("G5", 1)]));
+        assert_eq!(notes("[3:2 E4 F4 G4] (D5)C5", NotationSystem::Western, on), owned(&[("E4", 0), ("F4", 0), ("G4", 0), ("C5", 1)]));
+        // Off by default, and never for number notation
+        assert_eq!(notes("C5", NotationSystem::Western, ScientificPitch::default()), owned(&[("C", 0)]));
+        assert_eq!(notes("1 4 5", NotationSystem::Number, on), owned(&[("1", 0), ("4", 0), ("5", 0)]));
+
+        let line = parse_content_line_with_options("C5 D4", 0, NotationSystem::Western, 0, ParseMode::Standard, on).unwrap();
+        assert_eq!(to_source(&line), "C5 D4");
+    }
+
+    #[test]
+    fn test_quarter_tone_accidentals() {
+        let line = unused_parse_content_line("1+ 1# 1#+ 3𝄳 2", 0, NotationSystem::Number, 0).unwrap();
+        let notes: Vec<(String, crate::parse::model::PitchCode, Option<i8>)> = line.elements.iter()
+            .filter_map(|e| match e {
+                ContentElement::Beat(beat) => Some(beat),
+                _ => None,
+            })
+            .flat_map(|beat| &beat.elements)
+            .filter_map(|e| match e {
+                BeatElement::Note(note) => Some((note.value.clone().unwrap(), note.pitch_code, note.microtone)),
+                _ => None,
+            })
+            .collect();
+        use crate::parse::model::PitchCode::{N1, N1s, N2, N3};
+        assert_eq!(notes, vec![
+            ("1+".to_string(), N1, Some(1)),
+            ("1#".to_string(), N1s, None),
+            ("1#+".to_string(), N1s, Some(1)),
+            ("3𝄳".to_string(), N3, Some(-1)),
+            ("2".to_string(), N2, None),
+        ]);
+        assert_eq!(to_source(&line), "1+ 1# 1#+ 3𝄳 2");
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let kind = |text: &str, system: NotationSystem| unused_parse_content_line(text, 1, system, 0).unwrap_err().kind;
+        assert_eq!(kind("1 2 :3 4", NotationSystem::Number), ParseErrorKind::MissingBarline);
+        assert_eq!(kind("(2 1", NotationSystem::Number), ParseErrorKind::UnterminatedGroup);
+        assert_eq!(kind("[3:2 1 2 3 | 4", NotationSystem::Number), ParseErrorKind::UnterminatedGroup);
+        assert_eq!(kind("[3:2 1 [3:2 1 2 3]]", NotationSystem::Number), ParseErrorKind::InvalidGroup);
+        assert_eq!(kind("[3:2 1 x]", NotationSystem::Number), ParseErrorKind::UnexpectedToken);
+        assert_eq!(kind("C c", NotationSystem::Western), ParseErrorKind::InvalidPitch);
+        assert_eq!(ParseErrorKind::MissingBarline.code(), "missing_barline");
+
+        // The message stays as it was
+        let error = unused_parse_content_line("1 2 :3 4", 1, NotationSystem::Number, 0).unwrap_err();
+        assert_eq!(error.to_string(), "Parse error at line 1, column 6: Expected '|' after ':' in barline");
+    }
+
+    #[test]
+    fn test_missing_barline_pretty_error() {
+        let source = "Title: Scale\n1 2 :3 4";
+        let error = unused_parse_content_line("1 2 :3 4", 2, NotationSystem::Number, 13).unwrap_err();
+        assert_eq!((error.line, error.column), (2, 6));
+        assert_eq!(error.render_pretty(source), [
+            "error: Expected '|' after ':' in barline",
+            "  --> line 2, column 6",
+            "  |",
+            "2 | 1 2 :3 4",
+            "  |      ^",
+            "  = help: did you forget a barline? A repeat is closed with ':|' and opened with '|:'",
+            "",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn test_elements_carry_source_locations() {
+        let line = unused_parse_content_line("1-2 || 3", 2, NotationSystem::Number, 10).unwrap();
+        let sources: Vec<Source> = line.elements.iter().map(|e| *e.source().unwrap()).collect();
+
+        assert_eq!(sources[0], Source { line: 2, column: 1, char_index: 10, length: 3 }); // beat "1-2"
+        assert_eq!(sources[1], Source { line: 2, column: 4, char_index: 13, length: 1 }); // whitespace
+        assert_eq!(sources[2], Source { line: 2, column: 5, char_index: 14, length: 2 }); // "||"
+        assert_eq!(sources[4], Source { line: 2, column: 8, char_index: 17, length: 1 }); // beat "3"
+
+        if let ContentElement::Beat(beat) = &line.elements[0] {
+            let columns: Vec<usize> = beat.elements.iter().map(|e| e.source().unwrap().column).collect();
+            assert_eq!(columns, vec![1, 2, 3]);
+            assert!(matches!(beat.elements[1], BeatElement::Dash(_)));
+        } else {
+            panic!("expected a beat");
+        }
+    }
+
+    #[test]
+    fn test_barline_types() {
+        let line = unused_parse_content_line("|: 1 :| 2 |.", 0, NotationSystem::Number, 0).unwrap();
+        let barlines: Vec<&str> = line.elements.iter()
+            .filter(|e| matches!(e, ContentElement::Barline(_)))
+            .map(|e| e.type_name())
+            .collect();
+        assert_eq!(barlines, vec!["RepeatStartBarline", "RepeatEndBarline", "FinalBarline"]);
+    }
+
+    #[test]
+    fn test_barline_endings() {
+        let text = "|: 1 |1. 2 :|2. 3 |.";
+        let line = unused_parse_content_line(text, 0, NotationSystem::Number, 0).unwrap();
+        let barlines: Vec<(&str, Option<usize>)> = line.elements.iter()
+            .filter_map(|e| match e {
+                ContentElement::Barline(barline) => Some((e.type_name(), barline.ending())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(barlines, vec![
+            ("RepeatStartBarline", None),
+            ("SingleBarline", Some(1)),
+            ("RepeatEndBarline", Some(2)),
+            ("FinalBarline", None),
+        ]);
+        assert_eq!(to_source(&line), text);
+    }
+
+    #[test]
+    fn test_lossless_round_trip() {
+        for input in ["12. 1-2 | 3", "1 ♯ 2\t@x é|3", "1 ||: 2 :| 3", "1~|1_ 2"] {
+            let line = parse_content_line_with_mode(input, 0, NotationSystem::Number, 0, ParseMode::Lossless).unwrap();
+            assert_eq!(to_source(&line), input);
+        }
+        // Standard mode drops the line number and rejects the malformed barline
+        let line = unused_parse_content_line("12. 1-2 | 3", 0, NotationSystem::Number, 0).unwrap();
+        assert_eq!(to_source(&line), "1-2 | 3");
+        assert!(unused_parse_content_line("1 ||: 2 :| 3", 0, NotationSystem::Number, 0).is_err());
+    }
+}
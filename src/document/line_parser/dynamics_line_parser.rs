@@ -0,0 +1,83 @@
+// Dynamics line parser
+// A line under the notes such as "p  <     f" marks the beat under each mark
+// with a dynamic level, or with the start of a crescendo ("<" or "cresc") or
+// decrescendo (">" or "dim"). Letters here are never read as pitches.
+
+use crate::parse::model::{ContentElement, ContentLine};
+
+/// Dynamic levels from softest to loudest
+pub const DYNAMIC_LEVELS: &[&str] = &["ppp", "pp", "p", "mp", "mf", "f", "ff", "fff"];
+
+pub const CRESCENDO: &str = "<";
+pub const DECRESCENDO: &str = ">";
+
+/// Crescendo and diminuendo written out as words rather than hairpins
+pub const CRESCENDO_TEXT: &str = "cresc";
+pub const DIMINUENDO_TEXT: &str = "dim";
+
+/// Whether a mark starts a gradual change in loudness, as a hairpin or in words
+pub fn is_hairpin(mark: &str) -> bool {
+    [CRESCENDO, DECRESCENDO, CRESCENDO_TEXT, DIMINUENDO_TEXT].contains(&mark)
+}
+
+/// Whether a mark is a dynamic level or a hairpin start
+pub fn is_dynamic_mark(mark: &str) -> bool {
+    DYNAMIC_LEVELS.contains(&mark) || is_hairpin(mark)
+}
+
+/// Dynamic marks of a dynamics line, with the 1-based column of each.
+/// Unrecognized words are ignored.
+pub fn parse_dynamics_line(input: &str) -> Vec<(usize, String)> {
+    let mut marks = Vec::new();
+    let mut word: Option<(usize, String)> = None;
+    for (column, ch) in input.chars().chain(std::iter::once(' ')).enumerate() {
+        if ch.is_whitespace() {
+            if let Some((start, mark)) = word.take() {
+                if is_dynamic_mark(&mark) {
+                    marks.push((start, mark));
+                }
+            }
+        } else {
+            word.get_or_insert_with(|| (column + 1, String::new())).1.push(ch);
+        }
+    }
+    marks
+}
+
+/// Attach marks from a dynamics line to the beats above them by column.
+/// A mark belongs to the beat over its first character or, between beats, to the nearest beat to its right.
+pub fn attach_dynamics(content_line: &mut ContentLine, dynamics_line: &str) {
+    for (column, mark) in parse_dynamics_line(dynamics_line) {
+        let target = content_line.elements.iter_mut().find_map(|element| match element {
+            ContentElement::Beat(beat) => match beat.source {
+                Some(source) if source.column + source.length > column => Some(beat),
+                _ => None,
+            },
+            _ => None,
+        });
+        if let Some(beat) = target {
+            beat.dynamic = Some(mark);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::model::NotationSystem;
+
+    #[test]
+    fn test_dynamics_align_to_beats() {
+        let mut line = crate::document::line_parser::content_line_parser::unused_parse_content_line(
+            "1 2 3 4", 0, NotationSystem::Number, 0,
+        ).unwrap();
+        attach_dynamics(&mut line, "p < cresc f");
+        let marks: Vec<Option<String>> = line.elements.iter().filter_map(|e| match e {
+            ContentElement::Beat(beat) => Some(beat.dynamic.clone()),
+            _ => None,
+        }).collect();
+        assert_eq!(marks, vec![Some("p".to_string()), Some("<".to_string()), Some("cresc".to_string()), None]);
+        assert_eq!(parse_dynamics_line("mf  >  pp"), vec![(1, "mf".to_string()), (5, ">".to_string()), (8, "pp".to_string())]);
+        assert_eq!(parse_dynamics_line("dim piano"), vec![(1, "dim".to_string())]);
+    }
+}
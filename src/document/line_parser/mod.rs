@@ -1 +1,5 @@
-pub mod content_line_parser;
\ No newline at end of file
+pub mod content_line_parser;
+pub mod scat_line_parser;
+pub mod slash_line_parser;
+pub mod dynamics_line_parser;
+pub mod articulation_line_parser;
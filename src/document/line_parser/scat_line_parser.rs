@@ -0,0 +1,191 @@
+use crate::parse::model::{ContentLine, ContentElement, Beat, BeatElement, Note, Dash, NotationSystem, PitchCode};
+use crate::parse::{ParseError, source_span};
+use super::content_line_parser::parse_barline;
+use std::str::CharIndices;
+use std::iter::Peekable;
+
+/// Parse a scat (vocal percussion) content line:
+/// scat_line = (barline | whitespace | beat)*
+/// beat = (syllable | dash) (syllable | dash | '.')*
+/// syllable = letter+
+///
+/// Syllables carry rhythm only. Each becomes an unpitched note (N1, scat notation
+/// system) whose value is the syllable text, so renderers can print it beneath the note.
+/// A '.' separates syllables inside one beat without taking any time ("ta.ka").
+pub fn parse_scat_content_line(
+    input: &str,
+    line_num: usize,
+    line_start_doc_index: usize,
+) -> Result<ContentLine, ParseError> {
+    let mut elements = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(pos, ch)) = chars.peek() {
+        match ch {
+            '\n' => break,
+
+            '|' | ':' => {
+                let barline = parse_barline(&mut chars, ch, pos, line_num, input, line_start_doc_index)?;
+                elements.push(ContentElement::Barline(barline));
+            }
+
+            ' ' => {
+                let mut space_count = 0;
+                while chars.peek().is_some_and(|(_, c)| *c == ' ') {
+                    chars.next();
+                    space_count += 1;
+                }
+                elements.push(ContentElement::Whitespace(crate::parse::model::Whitespace {
+                    id: uuid::Uuid::new_v4(),
+                    value: Some(" ".repeat(space_count)),
+                    source: Some(source_span(input, pos, pos + space_count, line_num, line_start_doc_index)),
+                }));
+            }
+
+            ch if ch == '-' || ch.is_alphabetic() => {
+                let beat = parse_scat_beat(&mut chars, line_num, input, line_start_doc_index);
+                elements.push(ContentElement::Beat(beat));
+            }
+
+            _ => {
+                // Anything else separates beats, as in the pitched parser
+                let mut token = String::new();
+                while let Some(&(_, ch)) = chars.peek() {
+                    if ch == ' ' || ch == '|' || ch == '\n' {
+                        break;
+                    }
+                    token.push(ch);
+                    chars.next();
+                }
+                elements.push(ContentElement::UnknownToken(crate::parse::model::UnknownToken {
+                    id: uuid::Uuid::new_v4(),
+                    value: Some(token.clone()),
+                    source: Some(source_span(input, pos, pos + token.len(), line_num, line_start_doc_index)),
+                    token_value: token,
+                }));
+            }
+        }
+    }
+
+    Ok(ContentLine {
+        id: uuid::Uuid::new_v4(),
+        elements,
+        value: Some(input.to_string()),
+    })
+}
+
+fn parse_scat_beat(
+    chars: &mut Peekable<CharIndices>,
+    line_num: usize,
+    input: &str,
+    line_start_doc_index: usize,
+) -> Beat {
+    let mut elements = Vec::new();
+    let beat_start_pos = chars.peek().map(|(pos, _)| *pos).unwrap_or(0);
+
+    while let Some(&(pos, ch)) = chars.peek() {
+        if ch == '-' {
+            chars.next();
+            elements.push(BeatElement::Dash(Dash {
+                id: uuid::Uuid::new_v4(),
+                value: Some("-".to_string()),
+                numerator: None,
+                denominator: None,
+                source: Some(source_span(input, pos, pos + 1, line_num, line_start_doc_index)),
+            }));
+        } else if ch.is_alphabetic() {
+            let mut syllable = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if !c.is_alphabetic() {
+                    break;
+                }
+                syllable.push(c);
+                chars.next();
+            }
+            elements.push(BeatElement::Note(Note {
+                id: uuid::Uuid::new_v4(),
+                source: Some(source_span(input, pos, pos + syllable.len(), line_num, line_start_doc_index)),
+                value: Some(syllable),
+                pitch_code: PitchCode::N1, // Unpitched - only the rhythm matters
+                octave: 0,
+                notation_system: NotationSystem::Scat,
+                numerator: None, // Will be populated by rhythm analysis
+                denominator: None, // Will be populated by rhythm analysis
+                divisi: None,
+                vibrato: false,
+                bend: None,
+                cautionary: false,
+                tie: None,
+                grace_notes: Vec::new(),
+                lyrics: Vec::new(),
+                microtone: None,
+                articulations: Vec::new(),
+                ornament: None,
+                slur: None,
+                phrase: None,
+            }));
+        } else if ch == '.' && !elements.is_empty() {
+            chars.next();
+        } else {
+            break;
+        }
+    }
+
+    let beat_end_pos = chars.peek().map(|(pos, _)| *pos).unwrap_or(input.len());
+
+    Beat {
+        id: uuid::Uuid::new_v4(),
+        source: Some(source_span(input, beat_start_pos, beat_end_pos, line_num, line_start_doc_index)),
+        elements,
+        value: None,
+        divisions: None,        // Will be populated by rhythm analysis
+        total_duration: None,   // Will be populated by rhythm analysis
+        is_tuplet: None,        // Will be populated by rhythm analysis
+        tuplet_ratio: None,     // Will be populated by rhythm analysis
+        tied_to_previous: None, // Will be populated by rhythm analysis
+        repeat_count: None,
+        chord: None,
+        dynamic: None,
+        feather: None,
+        explicit_tuplet: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn syllables(line: &ContentLine) -> Vec<Vec<String>> {
+        line.elements.iter().filter_map(|e| match e {
+            ContentElement::Beat(beat) => Some(beat.elements.iter().filter_map(|be| match be {
+                BeatElement::Note(note) => note.value.clone(),
+                _ => None,
+            }).collect()),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn test_scat_syllables_are_unpitched_notes() {
+        let line = parse_scat_content_line("ta ka di mi", 0, 0).unwrap();
+        assert_eq!(syllables(&line), vec![vec!["ta"], vec!["ka"], vec!["di"], vec!["mi"]]);
+        if let ContentElement::Beat(beat) = &line.elements[0] {
+            if let BeatElement::Note(note) = &beat.elements[0] {
+                assert_eq!(note.notation_system, NotationSystem::Scat);
+                assert_eq!(note.source.unwrap().length, 2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_scat_beat_subdivisions_and_barlines() {
+        let line = parse_scat_content_line("| ta.ka-di mi |", 0, 0).unwrap();
+        assert_eq!(syllables(&line), vec![vec!["ta", "ka", "di"], vec!["mi"]]);
+        if let ContentElement::Beat(beat) = &line.elements[2] {
+            assert_eq!(beat.elements.len(), 4); // ta ka - di
+        } else {
+            panic!("expected a beat");
+        }
+        assert!(matches!(line.elements.last(), Some(ContentElement::Barline(_))));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::parse::model::{ContentLine, ContentElement, Beat, BeatElement, Note, Dash, NotationSystem, PitchCode};
+use crate::parse::{ParseError, source_span};
+use super::content_line_parser::parse_barline;
+
+/// Value of the unpitched note produced for each rhythm slash
+pub const SLASH: &str = "/";
+
+/// Whether a line is a fake-book rhythm line: slashes, with only dashes, barlines and
+/// spaces between them ("/ / / /", "| /-/ / |")
+pub fn is_slash_line(line: &str) -> bool {
+    line.contains('/') && line.chars().all(|ch| matches!(ch, '/' | '-' | '|' | ':' | ' '))
+}
+
+/// Parse a fake-book rhythm line:
+/// slash_line = (barline | whitespace | beat)*
+/// beat = '/' ('/' | '-')*
+///
+/// Every slash is an unpitched note on the middle staff line (B, N7) whose value is "/",
+/// so renderers can draw it with a slash notehead.
+pub fn parse_slash_content_line(
+    input: &str,
+    line_num: usize,
+    line_start_doc_index: usize,
+) -> Result<ContentLine, ParseError> {
+    let mut elements = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(pos, ch)) = chars.peek() {
+        match ch {
+            '\n' => break,
+
+            '|' | ':' => {
+                let barline = parse_barline(&mut chars, ch, pos, line_num, input, line_start_doc_index)?;
+                elements.push(ContentElement::Barline(barline));
+            }
+
+            ' ' => {
+                let mut space_count = 0;
+                while chars.peek().is_some_and(|(_, c)| *c == ' ') {
+                    chars.next();
+                    space_count += 1;
+                }
+                elements.push(ContentElement::Whitespace(crate::parse::model::Whitespace {
+                    id: uuid::Uuid::new_v4(),
+                    value: Some(" ".repeat(space_count)),
+                    source: Some(source_span(input, pos, pos + space_count, line_num, line_start_doc_index)),
+                }));
+            }
+
+            '/' => {
+                let mut beat_elements = Vec::new();
+                while let Some(&(pos, ch)) = chars.peek() {
+                    let source = Some(source_span(input, pos, pos + 1, line_num, line_start_doc_index));
+                    match ch {
+                        '/' => beat_elements.push(BeatElement::Note(Note {
+                            id: uuid::Uuid::new_v4(),
+                            value: Some(SLASH.to_string()),
+                            pitch_code: PitchCode::N7, // Middle line of the treble staff
+                            octave: 0,
+                            notation_system: NotationSystem::Number,
+                            numerator: None, // Will be populated by rhythm analysis
+                            denominator: None, // Will be populated by rhythm analysis
+                            divisi: None,
+                            vibrato: false,
+                            bend: None,
+                            cautionary: false,
+                            tie: None,
+                            grace_notes: Vec::new(),
+                            lyrics: Vec::new(),
+                            microtone: None,
+                            articulations: Vec::new(),
+                            ornament: None,
+                            slur: None,
+                            phrase: None,
+                            source,
+                        })),
+                        '-' => beat_elements.push(BeatElement::Dash(Dash {
+                            id: uuid::Uuid::new_v4(),
+                            value: Some("-".to_string()),
+                            numerator: None,
+                            denominator: None,
+                            source,
+                        })),
+                        _ => break,
+                    }
+                    chars.next();
+                }
+                let beat_end_pos = chars.peek().map(|(pos, _)| *pos).unwrap_or(input.len());
+
+                elements.push(ContentElement::Beat(Beat {
+                    id: uuid::Uuid::new_v4(),
+                    source: Some(source_span(input, pos, beat_end_pos, line_num, line_start_doc_index)),
+                    elements: beat_elements,
+                    value: None,
+                    divisions: None,        // Will be populated by rhythm analysis
+                    total_duration: None,   // Will be populated by rhythm analysis
+                    is_tuplet: None,        // Will be populated by rhythm analysis
+                    tuplet_ratio: None,     // Will be populated by rhythm analysis
+                    tied_to_previous: None, // Will be populated by rhythm analysis
+                    repeat_count: None,
+                    chord: None,            // Filled in by attach_chords
+                    dynamic: None,
+                    feather: None,
+                    explicit_tuplet: None,
+                }));
+            }
+
+            _ => {
+                let mut token = String::new();
+                while let Some(&(_, ch)) = chars.peek() {
+                    if ch == ' ' || ch == '|' || ch == '\n' {
+                        break;
+                    }
+                    token.push(ch);
+                    chars.next();
+                }
+                elements.push(ContentElement::UnknownToken(crate::parse::model::UnknownToken {
+                    id: uuid::Uuid::new_v4(),
+                    value: Some(token.clone()),
+                    source: Some(source_span(input, pos, pos + token.len(), line_num, line_start_doc_index)),
+                    token_value: token,
+                }));
+            }
+        }
+    }
+
+    Ok(ContentLine {
+        id: uuid::Uuid::new_v4(),
+        elements,
+        value: Some(input.to_string()),
+    })
+}
+
+/// Chord symbols of an upper line such as "[C] [F]", with the 1-based column of each '['
+pub fn parse_chord_line(input: &str) -> Vec<(usize, String)> {
+    let mut chords = Vec::new();
+    let mut open: Option<(usize, String)> = None;
+    for (column, ch) in input.chars().enumerate() {
+        match (ch, open.as_mut()) {
+            ('[', _) => open = Some((column + 1, String::new())),
+            (']', Some(_)) => {
+                let (start, symbol) = open.take().unwrap();
+                if !symbol.trim().is_empty() {
+                    chords.push((start, symbol.trim().to_string()));
+                }
+            }
+            (_, Some((_, symbol))) => symbol.push(ch),
+            _ => {}
+        }
+    }
+    chords
+}
+
+/// Attach chords from an upper line to the beats beneath them by column.
+/// A chord belongs to the beat under its '[' or, between beats, to the nearest beat to its right.
+pub fn attach_chords(content_line: &mut ContentLine, chord_line: &str) {
+    for (column, symbol) in parse_chord_line(chord_line) {
+        let target = content_line.elements.iter_mut().find_map(|element| match element {
+            ContentElement::Beat(beat) => match beat.source {
+                Some(source) if source.column + source.length > column => Some(beat),
+                _ => None,
+            },
+            _ => None,
+        });
+        if let Some(beat) = target {
+            beat.chord = Some(symbol);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chords_align_to_slash_beats() {
+        let mut line = parse_slash_content_line("/ / / /", 1, 8).unwrap();
+        attach_chords(&mut line, "[C] [F]");
+        let chords: Vec<Option<String>> = line.elements.iter().filter_map(|e| match e {
+            ContentElement::Beat(beat) => Some(beat.chord.clone()),
+            _ => None,
+        }).collect();
+        assert_eq!(chords, vec![Some("C".to_string()), None, Some("F".to_string()), None]);
+    }
+
+    #[test]
+    fn test_parse_chord_line_columns() {
+        assert_eq!(parse_chord_line("  [Bb7]  [Ebmaj7]"), vec![(3, "Bb7".to_string()), (10, "Ebmaj7".to_string())]);
+    }
+}
@@ -0,0 +1,117 @@
+// Lyrics lines under the notes
+// Each lyrics line under a content line is one verse. Its syllables go to the notes
+// in order, skipping the notes a syllable is held over: notes tied from the previous
+// one and the notes after the first under a slur (a melisma)
+
+use crate::document::overlay::underscore_runs;
+use crate::parse::model::{BeatElement, ContentElement, ContentLine, LyricsLine, Syllable};
+
+/// Syllables of a lyrics line. Hyphenated words are split after each hyphen, which
+/// stays on the syllable before it ("hap-py" is "hap-" and "py").
+pub fn parse_lyrics_line(input: &str) -> Vec<String> {
+    input.split_whitespace()
+        .flat_map(|word| word.split_inclusive('-'))
+        .filter(|syllable| *syllable != "-")
+        .map(str::to_string)
+        .collect()
+}
+
+/// The lyrics line model kept on the stave, for editors drawing it back
+pub fn lyrics_line(input: &str) -> LyricsLine {
+    LyricsLine {
+        id: uuid::Uuid::new_v4(),
+        value: Some(input.to_string()),
+        syllables: parse_lyrics_line(input).into_iter()
+            .map(|content| Syllable { id: uuid::Uuid::new_v4(), value: Some(content.clone()), content })
+            .collect(),
+    }
+}
+
+/// Give the syllables of `lyrics_line` to the notes of `content_line` as verse `verse`
+/// (0-based). Slurs are the runs of underscores in `upper_lines`, matched by column.
+pub fn apply_lyrics(content_line: &mut ContentLine, verse: usize, lyrics_line: &str, upper_lines: &[&str]) {
+    let slurs: Vec<(usize, usize)> = upper_lines.iter().flat_map(|line| underscore_runs(line)).collect();
+    let mut syllables = parse_lyrics_line(lyrics_line).into_iter();
+    let mut tied_from_previous = false;
+    let mut slurs_started: Vec<(usize, usize)> = Vec::new();
+
+    let notes = content_line.elements.iter_mut()
+        .filter_map(|element| match element {
+            ContentElement::Beat(beat) => Some(beat),
+            _ => None,
+        })
+        .flat_map(|beat| beat.elements.iter_mut())
+        .filter_map(|element| match element {
+            BeatElement::Note(note) => Some(note),
+            _ => None,
+        });
+    for note in notes {
+        let under_slur = note.source
+            .and_then(|source| slurs.iter().find(|(first, last)| (*first..=*last).contains(&source.column)).copied());
+        let melisma = match under_slur {
+            Some(slur) if slurs_started.contains(&slur) => true,
+            Some(slur) => {
+                slurs_started.push(slur);
+                false
+            }
+            None => false,
+        };
+        let held = tied_from_previous || melisma;
+        tied_from_previous = note.tie.is_some();
+        if held {
+            continue;
+        }
+        let Some(syllable) = syllables.next() else { break };
+        if note.lyrics.len() <= verse {
+            note.lyrics.resize(verse + 1, String::new());
+        }
+        note.lyrics[verse] = syllable;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::line_parser::content_line_parser::unused_parse_content_line;
+    use crate::parse::model::NotationSystem;
+
+    fn lyrics_of(line: &ContentLine) -> Vec<Vec<String>> {
+        line.elements.iter()
+            .filter_map(|element| match element {
+                ContentElement::Beat(beat) => Some(beat),
+                _ => None,
+            })
+            .flat_map(|beat| beat.elements.iter())
+            .filter_map(|element| match element {
+                BeatElement::Note(note) => Some(note.lyrics.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_two_verses() {
+        let mut line = unused_parse_content_line("1 2 3", 1, NotationSystem::Number, 0).unwrap();
+        apply_lyrics(&mut line, 0, "hap-py day", &[]);
+        apply_lyrics(&mut line, 1, "good night", &[]);
+        assert_eq!(lyrics_of(&line), vec![
+            vec!["hap-".to_string(), "good".to_string()],
+            vec!["py".to_string(), "night".to_string()],
+            vec!["day".to_string()],
+        ]);
+    }
+
+    #[test]
+    fn test_melisma_under_slur_in_every_verse() {
+        let mut line = unused_parse_content_line("1 2 3 4", 1, NotationSystem::Number, 0).unwrap();
+        let upper = ["_____"];
+        apply_lyrics(&mut line, 0, "oh say", &upper);
+        apply_lyrics(&mut line, 1, "ah me", &upper);
+        assert_eq!(lyrics_of(&line), vec![
+            vec!["oh".to_string(), "ah".to_string()],
+            vec![],
+            vec![],
+            vec!["say".to_string(), "me".to_string()],
+        ]);
+    }
+}
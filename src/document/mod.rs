@@ -2,4 +2,15 @@
 // Following Modern Rust practices
 
 pub mod edit;
-pub mod line_parser;
\ No newline at end of file
+pub mod line_parser;
+pub mod spans;
+pub mod ties;
+pub mod divisi;
+pub mod expression;
+pub mod ornaments;
+pub mod grace;
+pub mod octaves;
+pub mod lyrics;
+pub mod slurs;
+pub mod overlay;
+pub mod incremental;
@@ -0,0 +1,43 @@
+// Octave markers from the annotation lines around the notes
+// `.` and `:` above a note raise it one and two octaves, below it they lower it
+
+use crate::parse::model::ContentLine;
+
+/// Shift the octave of every note under an octave marker, matching by column like
+/// chord alignment. `direction` is 1 for an upper line and -1 for a lower line.
+pub fn apply_octave_markers(content_line: &mut ContentLine, annotation_line: &str, direction: i8) {
+    let markers: Vec<(usize, i8)> = annotation_line.chars().enumerate()
+        .filter_map(|(i, c)| match c {
+            '.' => Some((i + 1, direction)),
+            ':' => Some((i + 1, 2 * direction)),
+            _ => None,
+        })
+        .collect();
+    if markers.is_empty() {
+        return;
+    }
+
+    for note in content_line.notes_mut() {
+        let Some(source) = note.source else { continue };
+        let columns = source.column..source.column + source.length;
+        if let Some((_, shift)) = markers.iter().find(|(c, _)| columns.contains(c)) {
+            note.octave += shift;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::line_parser::content_line_parser::unused_parse_content_line;
+    use crate::parse::model::NotationSystem;
+
+    #[test]
+    fn test_upper_and_lower_octave_markers() {
+        let mut line = unused_parse_content_line("1 2 3 4", 1, NotationSystem::Number, 0).unwrap();
+        apply_octave_markers(&mut line, ".   :", 1);
+        apply_octave_markers(&mut line, "  .   .", -1);
+        let octaves: Vec<i8> = line.notes_mut().map(|n| n.octave).collect();
+        assert_eq!(octaves, vec![1, -1, 2, -1]);
+    }
+}
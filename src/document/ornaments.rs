@@ -0,0 +1,56 @@
+// Ornaments from an annotation line above the notes
+// A lone `~` is a mordent, `tr` a trill and `turn` (or the turn glyph `𝆗`) a turn
+
+use crate::parse::model::{ContentLine, Ornament};
+
+/// Attach each ornament token of an upper annotation line to the note beneath its
+/// first character, matching by column like chord alignment. Other words (slurs, vibrato spans, bends,
+/// grace notes) are left to their own passes.
+pub fn apply_ornaments(content_line: &mut ContentLine, upper_line: &str) {
+    let ornaments = ornament_tokens(upper_line);
+    if ornaments.is_empty() {
+        return;
+    }
+
+    for note in content_line.notes_mut() {
+        let Some(source) = note.source else { continue };
+        let columns = source.column..source.column + source.length;
+        if let Some((_, ornament)) = ornaments.iter().find(|(c, _)| columns.contains(c)) {
+            note.ornament = Some(*ornament);
+        }
+    }
+}
+
+/// Whether a word of an upper line is an ornament rather than grace-note pitches
+pub fn is_ornament_token(word: &str) -> bool {
+    Ornament::from_token(word).is_some()
+}
+
+/// Ornament words of the upper line with the column each one starts at
+fn ornament_tokens(upper_line: &str) -> Vec<(usize, Ornament)> {
+    let mut tokens = Vec::new();
+    let mut column = 1;
+    for word in upper_line.split(' ') {
+        let length = word.chars().count();
+        if let Some(ornament) = Ornament::from_token(word) {
+            tokens.push((column, ornament));
+        }
+        column += length + 1;
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::line_parser::content_line_parser::unused_parse_content_line;
+    use crate::parse::model::NotationSystem;
+
+    #[test]
+    fn test_ornament_tokens_over_notes() {
+        let mut line = unused_parse_content_line("1 2 3 4 5", 1, NotationSystem::Number, 8).unwrap();
+        apply_ornaments(&mut line, "~ tr  turn ~~~");
+        let ornaments: Vec<Option<Ornament>> = line.notes_mut().map(|n| n.ornament).collect();
+        assert_eq!(ornaments, vec![Some(Ornament::Mordent), Some(Ornament::Trill), None, Some(Ornament::Turn), None]);
+    }
+}
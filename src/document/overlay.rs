@@ -0,0 +1,154 @@
+// Annotation overlay export
+// Slur and beat-group spans with their CSS classes and variables, positioned in the
+// notation text, so any renderer's output can be decorated with the editor's overlays
+
+use crate::document::spans::SpanKind;
+use crate::parse::model::{BeatElement, ContentElement, ContentLine, DocumentElement, SpanEdge, StaveLine};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// An overlay span. `start` and `end` are byte offsets into the input text, taken
+/// from the same source positions the editor uses for its character indices.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OverlaySpan {
+    pub kind: SpanKind,
+    pub start: usize,
+    pub end: usize,
+    pub classes: Vec<&'static str>,
+    pub styles: BTreeMap<&'static str, String>,
+}
+
+/// Overlay spans of all content lines in `input`: a slur for every slur the document
+/// model attaches from the upper lines (as `pipeline::document_from_notation` does), and
+/// a beat group (`--lower-loop-char-count`, plus `--tuplet` for tuplets) for every
+/// subdivided beat. Input that doesn't parse has no overlays.
+pub fn overlay_spans(input: &str) -> Vec<OverlaySpan> {
+    let Ok(document) = crate::pipeline::document_from_notation(input) else { return Vec::new() };
+    // Columns count characters of the normalized text the document was read from
+    let input = document.value.as_deref().unwrap_or(input);
+    let byte_offsets: Vec<usize> = input.char_indices().map(|(i, _)| i).chain(std::iter::once(input.len())).collect();
+    let byte_offset = |char_index: usize| byte_offsets.get(char_index).copied().unwrap_or(input.len());
+
+    let mut spans = Vec::new();
+    let content_lines = document.elements.iter()
+        .filter_map(|element| match element {
+            DocumentElement::Stave(stave) => Some(stave.lines.iter()),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|line| match line {
+            StaveLine::ContentLine(content_line) => Some(content_line),
+            _ => None,
+        });
+    for content_line in content_lines {
+        spans.extend(slurs(content_line, byte_offset));
+        spans.extend(beat_groups(content_line, byte_offset));
+    }
+    spans.sort_by_key(|span| (span.start, std::cmp::Reverse(span.end)));
+    spans
+}
+
+/// First and last 1-based columns of each run of underscores
+pub fn underscore_runs(line: &str) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (i, ch) in line.chars().enumerate() {
+        if ch != '_' {
+            continue;
+        }
+        match runs.last_mut() {
+            Some((_, last)) if *last == i => *last = i + 1,
+            _ => runs.push((i + 1, i + 1)),
+        }
+    }
+    runs
+}
+
+/// A slur from the start of its first note to the end of its last note
+fn slurs(content_line: &ContentLine, byte_offset: impl Fn(usize) -> usize) -> Vec<OverlaySpan> {
+    let notes = content_line.elements.iter()
+        .filter_map(|element| match element {
+            ContentElement::Beat(beat) => Some(beat.elements.iter()),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|element| match element {
+            BeatElement::Note(note) => Some(note),
+            _ => None,
+        });
+    let mut spans = Vec::new();
+    let mut start = None;
+    for note in notes {
+        match (note.slur, note.source) {
+            (Some(SpanEdge::Start), Some(source)) => start = Some(source.char_index),
+            (Some(SpanEdge::End), Some(source)) => {
+                if let Some(start) = start.take() {
+                    spans.push(OverlaySpan {
+                        kind: SpanKind::Slur,
+                        start: byte_offset(start),
+                        end: byte_offset(source.end_char_index()),
+                        classes: vec!["slur"],
+                        styles: BTreeMap::new(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    spans
+}
+
+/// A beat group for every beat with more than one note, dash or rest
+fn beat_groups(content_line: &ContentLine, byte_offset: impl Fn(usize) -> usize) -> Vec<OverlaySpan> {
+    content_line.elements.iter().filter_map(|element| {
+        let ContentElement::Beat(beat) = element else { return None };
+        let source = beat.source?;
+        let subdivisions = beat.elements.iter().filter(|e| !matches!(e, BeatElement::BreathMark(_))).count();
+        if subdivisions < 2 {
+            return None;
+        }
+        let mut classes = vec!["beat-group"];
+        let mut styles = BTreeMap::from([("--lower-loop-char-count", source.length.to_string())]);
+        if beat.is_tuplet.unwrap_or(false) {
+            classes.push("tuplet");
+            styles.insert("--tuplet", beat.divisions.unwrap_or(subdivisions).to_string());
+        }
+        Some(OverlaySpan {
+            kind: SpanKind::BeatGroup,
+            start: byte_offset(source.char_index),
+            end: byte_offset(source.end_char_index()),
+            classes,
+            styles,
+        })
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slurred_triplet_overlay() {
+        let input = "|1 2 3 4|\n\n _____\n|5 123|";
+        let spans = overlay_spans(input);
+        assert_eq!(spans.len(), 2, "{:?}", spans);
+
+        let slur = &spans[0];
+        assert_eq!(slur.kind, SpanKind::Slur);
+        assert_eq!(slur.classes, vec!["slur"]);
+        assert_eq!(&input[slur.start..slur.end], "5 123");
+
+        let triplet = &spans[1];
+        assert_eq!(triplet.classes, vec!["beat-group", "tuplet"]);
+        assert_eq!(&input[triplet.start..triplet.end], "123");
+        assert_eq!(triplet.styles.get("--tuplet").map(String::as_str), Some("3"));
+        assert_eq!(triplet.styles.get("--lower-loop-char-count").map(String::as_str), Some("3"));
+    }
+
+    #[test]
+    fn test_slur_from_unmarked_upper_line() {
+        let input = "  ___\n| 1 2 3 |";
+        let spans = overlay_spans(input);
+        assert_eq!(spans.len(), 1, "{:?}", spans);
+        assert_eq!(&input[spans[0].start..spans[0].end], "1 2");
+    }
+}
@@ -0,0 +1,94 @@
+// Slurs and phrase marks from an upper line
+// A run of underscores slurs the notes beneath it; a "(" ... ")" pair draws a
+// phrase mark from the note under "(" to the note under ")". The two are kept
+// apart, so one phrase mark can span several slurs.
+
+use crate::document::overlay::underscore_runs;
+use crate::parse::model::{BeatElement, ContentElement, ContentLine, Note, SpanEdge};
+
+/// First and last 1-based columns of each matched "(" ")" pair
+pub fn phrase_mark_columns(line: &str) -> Vec<(usize, usize)> {
+    let mut open = Vec::new();
+    let mut pairs = Vec::new();
+    for (i, ch) in line.chars().enumerate() {
+        match ch {
+            '(' => open.push(i + 1),
+            ')' => {
+                if let Some(first) = open.pop() {
+                    pairs.push((first, i + 1));
+                }
+            }
+            _ => {}
+        }
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+/// Mark the first and last note under each slur and phrase mark of `upper_line`.
+/// Spans over fewer than two notes are ignored.
+pub fn attach_slurs(content_line: &mut ContentLine, upper_line: &str) {
+    for (first, last) in underscore_runs(upper_line) {
+        mark_span(content_line, first, last, |note| &mut note.slur);
+    }
+    for (first, last) in phrase_mark_columns(upper_line) {
+        mark_span(content_line, first, last, |note| &mut note.phrase);
+    }
+}
+
+fn mark_span(content_line: &mut ContentLine, first: usize, last: usize, edge: impl Fn(&mut Note) -> &mut Option<SpanEdge>) {
+    let mut notes: Vec<&mut Note> = content_line.elements.iter_mut()
+        .filter_map(|element| match element {
+            ContentElement::Beat(beat) => Some(beat.elements.iter_mut()),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|element| match element {
+            BeatElement::Note(note) => Some(note),
+            _ => None,
+        })
+        .filter(|note| note.source.is_some_and(|source| (first..=last).contains(&source.column)))
+        .collect();
+    if notes.len() < 2 {
+        return;
+    }
+    *edge(notes.first_mut().unwrap()) = Some(SpanEdge::Start);
+    *edge(notes.last_mut().unwrap()) = Some(SpanEdge::End);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::line_parser::content_line_parser::unused_parse_content_line;
+    use crate::parse::model::NotationSystem;
+
+    #[test]
+    fn test_phrase_mark_over_two_slurs() {
+        let mut line = unused_parse_content_line("1 2 3 4", 1, NotationSystem::Number, 0).unwrap();
+        attach_slurs(&mut line, "___ ___");
+        attach_slurs(&mut line, "(     )");
+        let marks: Vec<(Option<SpanEdge>, Option<SpanEdge>)> = line.elements.iter()
+            .filter_map(|e| match e {
+                ContentElement::Beat(beat) => Some(&beat.elements),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|e| match e {
+                BeatElement::Note(note) => Some((note.slur, note.phrase)),
+                _ => None,
+            })
+            .collect();
+        use SpanEdge::*;
+        assert_eq!(marks, vec![
+            (Some(Start), Some(Start)),
+            (Some(End), None),
+            (Some(Start), None),
+            (Some(End), Some(End)),
+        ]);
+    }
+
+    #[test]
+    fn test_phrase_mark_columns() {
+        assert_eq!(phrase_mark_columns(" (  (  ) )  )"), vec![(2, 10), (5, 8)]);
+    }
+}
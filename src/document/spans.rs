@@ -0,0 +1,189 @@
+// Span nesting validation
+// Slurs, phrasing slurs, beams, tuplets and beat groups must nest properly,
+// otherwise renderers emit unbalanced brackets (e.g. `( \( ) \)` in LilyPond)
+
+use crate::parse::model::{Document, DocumentElement, StaveLine, ContentElement, BeatElement, Source, SpanEdge};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum SpanKind {
+    Slur,
+    PhrasingSlur,
+    Beam,
+    Tuplet,
+    BeatGroup,
+}
+
+impl SpanKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpanKind::Slur => "slur",
+            SpanKind::PhrasingSlur => "phrasing slur",
+            SpanKind::Beam => "beam",
+            SpanKind::Tuplet => "tuplet",
+            SpanKind::BeatGroup => "beat group",
+        }
+    }
+}
+
+/// A span-type annotation covering a range of the source text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub kind: SpanKind,
+    pub source: Source,
+}
+
+/// An illegal crossing, reported at the start of the later span
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SpanWarning {
+    pub message: String,
+    pub source: Source,
+}
+
+/// Whether two overlapping spans of these kinds must nest.
+/// Slurs and beams are drawn independently, so they may cross each other;
+/// every other combination has to nest. Slurs cannot even nest in their own kind.
+fn must_nest(a: SpanKind, b: SpanKind) -> bool {
+    use SpanKind::*;
+    !matches!((a, b), (Slur, Beam) | (Beam, Slur) | (PhrasingSlur, Beam) | (Beam, PhrasingSlur))
+}
+
+fn may_contain_same_kind(kind: SpanKind) -> bool {
+    matches!(kind, SpanKind::Tuplet | SpanKind::BeatGroup)
+}
+
+/// Check all spans for proper nesting and return a warning for every illegal crossing
+pub fn validate_span_nesting(spans: &[Span]) -> Vec<SpanWarning> {
+    let mut sorted: Vec<&Span> = spans.iter().collect();
+    sorted.sort_by_key(|s| (s.source.char_index, std::cmp::Reverse(s.source.end_char_index())));
+
+    let mut warnings = Vec::new();
+    for (i, outer) in sorted.iter().enumerate() {
+        for inner in &sorted[i + 1..] {
+            if inner.source.char_index >= outer.source.end_char_index() {
+                break; // Sorted by start, so no later span overlaps `outer`
+            }
+            let contained = inner.source.end_char_index() <= outer.source.end_char_index();
+            let message = if outer.kind == inner.kind {
+                if contained && may_contain_same_kind(outer.kind) {
+                    continue;
+                }
+                format!("{} overlaps another {}", capitalize(inner.kind.name()), outer.kind.name())
+            } else if !contained && must_nest(outer.kind, inner.kind) {
+                format!("{} crosses the end of a {}", capitalize(inner.kind.name()), outer.kind.name())
+            } else {
+                continue;
+            };
+            warnings.push(SpanWarning { message, source: inner.source });
+        }
+    }
+    warnings
+}
+
+/// Spans implied by the analyzed document: a beam for every beat with more than one
+/// sounding note, a tuplet for every tuplet beat, and the slurs and phrasing slurs
+/// from the first to the last note they mark
+pub fn document_spans(document: &Document) -> Vec<Span> {
+    let mut spans = Vec::new();
+    for element in &document.elements {
+        if let DocumentElement::Stave(stave) = element {
+            for line in &stave.lines {
+                if let StaveLine::ContentLine(content_line) = line {
+                    // Start of the open slur and of each open phrasing slur
+                    let mut slur_start: Option<Source> = None;
+                    let mut phrase_starts: Vec<Source> = Vec::new();
+                    for content_element in &content_line.elements {
+                        if let ContentElement::Beat(beat) = content_element {
+                            for beat_element in &beat.elements {
+                                let BeatElement::Note(note) = beat_element else { continue };
+                                let Some(source) = note.source else { continue };
+                                match note.slur {
+                                    Some(SpanEdge::Start) => slur_start = Some(source),
+                                    Some(SpanEdge::End) => spans.extend(slur_start.take().map(|start| span_between(SpanKind::Slur, start, source))),
+                                    None => {}
+                                }
+                                match note.phrase {
+                                    Some(SpanEdge::Start) => phrase_starts.push(source),
+                                    Some(SpanEdge::End) => spans.extend(phrase_starts.pop().map(|start| span_between(SpanKind::PhrasingSlur, start, source))),
+                                    None => {}
+                                }
+                            }
+                            let Some(source) = beat.source else { continue };
+                            let note_count = beat.elements.iter()
+                                .filter(|e| matches!(e, BeatElement::Note(_)))
+                                .count();
+                            if note_count > 1 {
+                                spans.push(Span { kind: SpanKind::Beam, source });
+                            }
+                            if beat.is_tuplet.unwrap_or(false) {
+                                spans.push(Span { kind: SpanKind::Tuplet, source });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    spans
+}
+
+/// Span of `kind` from the start of `first` to the end of `last`
+fn span_between(kind: SpanKind, first: Source, last: Source) -> Span {
+    Span { kind, source: Source { length: last.end_char_index() - first.char_index, ..first } }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(kind: SpanKind, start: usize, end: usize) -> Span {
+        Span { kind, source: Source { line: 0, column: start + 1, char_index: start, length: end - start } }
+    }
+
+    #[test]
+    fn test_slur_crossing_phrasing_slur_warns() {
+        let spans = vec![span(SpanKind::PhrasingSlur, 0, 6), span(SpanKind::Slur, 4, 9)];
+        let warnings = validate_span_nesting(&spans);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "Slur crosses the end of a phrasing slur");
+        assert_eq!(warnings[0].source.char_index, 4);
+    }
+
+    #[test]
+    fn test_proper_nesting_passes() {
+        let spans = vec![
+            span(SpanKind::PhrasingSlur, 0, 9),
+            span(SpanKind::Slur, 0, 3),
+            span(SpanKind::Slur, 4, 9),
+            span(SpanKind::Tuplet, 4, 7),
+            span(SpanKind::Tuplet, 5, 6),
+            span(SpanKind::Beam, 1, 4), // Beams may cross slurs
+        ];
+        assert!(validate_span_nesting(&spans).is_empty());
+    }
+
+    #[test]
+    fn test_slur_crossing_phrase_mark_from_notation() {
+        let warnings = |input: &str| {
+            let document = crate::pipeline::document_from_notation(input).unwrap();
+            crate::pipeline::notation_warnings(&document).into_iter().map(|w| (w.message, w.source.column)).collect::<Vec<_>>()
+        };
+        // The slur over 2 3 starts inside the phrase mark over 1 2 and ends after it
+        assert_eq!(warnings("  ___\n(  )\n1 2 3 4\n"), vec![("Slur crosses the end of a phrasing slur".to_string(), 3)]);
+        assert!(warnings("  ___\n(    )\n1 2 3 4\n").is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_slurs_warn() {
+        let spans = vec![span(SpanKind::Slur, 0, 5), span(SpanKind::Slur, 2, 4)];
+        assert_eq!(validate_span_nesting(&spans)[0].message, "Slur overlaps another slur");
+    }
+}
@@ -0,0 +1,135 @@
+// Explicit ties
+// A tie marker after a note ("1~1", "1~|1") ties it to the next note of the line,
+// across beats and barlines; the two notes should share the same pitch
+
+use crate::parse::model::{Document, DocumentElement, StaveLine, ContentLine, ContentElement, BeatElement, Note};
+use crate::document::spans::SpanWarning;
+use std::borrow::Cow;
+
+/// Each note carrying a tie marker paired with the note it ties to.
+/// Dashes between them only extend the first note; a rest or the end of the
+/// line leaves the tie without a second note and it is dropped.
+pub fn tie_pairs(content_line: &ContentLine) -> Vec<(&Note, &Note)> {
+    let mut pairs = Vec::new();
+    let mut tied_from: Option<&Note> = None;
+    for element in sounding_elements(content_line) {
+        match element {
+            BeatElement::Note(note) => {
+                if let Some(from) = tied_from {
+                    pairs.push((from, note));
+                }
+                tied_from = note.tie.map(|_| note);
+            }
+            BeatElement::Rest(_) => tied_from = None,
+            _ => {}
+        }
+    }
+    pairs
+}
+
+/// Whether a tie from `from` to `to` holds one pitch
+fn joins_same_pitch(from: &Note, to: &Note) -> bool {
+    from.pitch_code == to.pitch_code && from.octave == to.octave
+}
+
+/// The content line with the tie markers that join different pitches removed, so
+/// renderers never tie two different notes. Borrowed when every tie is valid.
+pub fn with_matching_ties(content_line: &ContentLine) -> Cow<'_, ContentLine> {
+    let mismatched: Vec<uuid::Uuid> = tie_pairs(content_line).into_iter()
+        .filter(|(from, to)| !joins_same_pitch(from, to))
+        .map(|(from, _)| from.id)
+        .collect();
+    if mismatched.is_empty() {
+        return Cow::Borrowed(content_line);
+    }
+    let mut content_line = content_line.clone();
+    for element in &mut content_line.elements {
+        if let ContentElement::Beat(beat) = element {
+            for beat_element in &mut beat.elements {
+                if let BeatElement::Note(note) = beat_element {
+                    if mismatched.contains(&note.id) {
+                        note.tie = None;
+                    }
+                }
+            }
+        }
+    }
+    Cow::Owned(content_line)
+}
+
+/// Notes and rests of a content line in order
+fn sounding_elements(content_line: &ContentLine) -> impl Iterator<Item = &BeatElement> {
+    content_line.elements.iter()
+        .filter_map(|element| match element {
+            ContentElement::Beat(beat) => Some(beat.elements.iter()),
+            _ => None,
+        })
+        .flatten()
+        .filter(|element| matches!(element, BeatElement::Note(_) | BeatElement::Rest(_)))
+}
+
+/// Warn about ties between different pitches and ties with no note to end on,
+/// reported at the note carrying the tie marker
+pub fn validate_ties(document: &Document) -> Vec<SpanWarning> {
+    let mut warnings = Vec::new();
+    for element in &document.elements {
+        if let DocumentElement::Stave(stave) = element {
+            for line in &stave.lines {
+                if let StaveLine::ContentLine(content_line) = line {
+                    let pairs = tie_pairs(content_line);
+                    for (from, to) in &pairs {
+                        if !joins_same_pitch(from, to) {
+                            let message = format!("Tie joins different pitches ({} and {})",
+                                from.value.as_deref().unwrap_or("?"), to.value.as_deref().unwrap_or("?"));
+                            warnings.extend(from.source.map(|source| SpanWarning { message, source }));
+                        }
+                    }
+                    for element in sounding_elements(content_line) {
+                        if let BeatElement::Note(note) = element {
+                            if note.tie.is_some() && !pairs.iter().any(|(from, _)| from.id == note.id) {
+                                let message = "Tie is not followed by a note".to_string();
+                                warnings.extend(note.source.map(|source| SpanWarning { message, source }));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::document_from_notation;
+
+    fn content_line(document: &Document) -> &ContentLine {
+        match &document.elements[0] {
+            DocumentElement::Stave(stave) => match &stave.lines[0] {
+                StaveLine::ContentLine(line) => line,
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_tie_pairs_across_barlines_and_dashes() {
+        let document = document_from_notation("|1~|1 2_ -2 3~|\n").unwrap();
+        let pairs: Vec<(&str, &str)> = tie_pairs(content_line(&document)).iter()
+            .map(|(from, to)| (from.value.as_deref().unwrap(), to.value.as_deref().unwrap()))
+            .collect();
+        assert_eq!(pairs, vec![("1", "1"), ("2", "2")]);
+    }
+
+    #[test]
+    fn test_validate_ties_warns_on_mismatch_and_dangling_tie() {
+        assert!(validate_ties(&document_from_notation("|1~ 1|\n").unwrap()).is_empty());
+
+        let warnings = validate_ties(&document_from_notation("|1~ 2 3~|\n").unwrap());
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages, vec!["Tie joins different pitches (1 and 2)", "Tie is not followed by a note"]);
+        assert_eq!(warnings[0].source.char_index, 1);
+    }
+}
@@ -0,0 +1,651 @@
+/// Import of a simple LilyPond subset
+/// Handles a single voice, absolute or inside `\relative`: note names (English `cs`/`bf`
+/// or Dutch `cis`/`bes`), rests, durations, dots, ties, slurs, barlines, `\time` and `\key`.
+/// Anything else (chords, tuplets, multiple voices, markup...) is rejected with an error.
+use crate::models::core::{Document, DocumentElement, Stave, StaveLine};
+use crate::parse::model::{
+    Barline, Beat, BeatElement, ContentElement, ContentLine, Dash, DoubleBarline, FinalBarline,
+    NotationSystem, Note, PitchCode, Rest, SingleBarline, SpanEdge, Whitespace,
+};
+use std::collections::HashMap;
+
+/// Durations are counted in ticks: 1024 per whole note, so a quarter-note beat is 256
+const TICKS_PER_WHOLE: u32 = 1024;
+const TICKS_PER_BEAT: u32 = TICKS_PER_WHOLE / 4;
+
+const STEP_NAMES: [&str; 7] = ["C", "D", "E", "F", "G", "A", "B"];
+
+const PITCH_CODES: [[PitchCode; 5]; 7] = [
+    [PitchCode::N1bb, PitchCode::N1b, PitchCode::N1, PitchCode::N1s, PitchCode::N1ss],
+    [PitchCode::N2bb, PitchCode::N2b, PitchCode::N2, PitchCode::N2s, PitchCode::N2ss],
+    [PitchCode::N3bb, PitchCode::N3b, PitchCode::N3, PitchCode::N3s, PitchCode::N3ss],
+    [PitchCode::N4bb, PitchCode::N4b, PitchCode::N4, PitchCode::N4s, PitchCode::N4ss],
+    [PitchCode::N5bb, PitchCode::N5b, PitchCode::N5, PitchCode::N5s, PitchCode::N5ss],
+    [PitchCode::N6bb, PitchCode::N6b, PitchCode::N6, PitchCode::N6s, PitchCode::N6ss],
+    [PitchCode::N7bb, PitchCode::N7b, PitchCode::N7, PitchCode::N7s, PitchCode::N7ss],
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "LilyPond import error at line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Pitch as a diatonic step (0 = C .. 6 = B), alteration in semitones and octave (0 = c')
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Pitch {
+    step: i32,
+    alter: i32,
+    octave: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Event {
+    Note { pitch: Pitch, ticks: u32, tied: bool, slur: Option<SpanEdge> },
+    Rest { ticks: u32 },
+    Barline(String),
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(src: &'a str) -> Self {
+        Self { chars: src.chars().peekable(), line: 1, column: 1 }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.chars.next()?;
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(ch)
+    }
+
+    fn error(&self, message: impl Into<String>) -> ImportError {
+        ImportError { message: message.into(), line: self.line, column: self.column }
+    }
+
+    /// Skip whitespace and `%` line comments
+    fn skip_blank(&mut self) {
+        while let Some(ch) = self.peek() {
+            if ch.is_whitespace() {
+                self.next();
+            } else if ch == '%' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.next();
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let mut taken = String::new();
+        while let Some(ch) = self.peek().filter(|c| predicate(*c)) {
+            taken.push(ch);
+            self.next();
+        }
+        taken
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ImportError> {
+        self.skip_blank();
+        match self.next() {
+            Some(ch) if ch == expected => Ok(()),
+            Some(ch) => Err(self.error(format!("Expected '{}' but found '{}'", expected, ch))),
+            None => Err(self.error(format!("Expected '{}' but the input ended", expected))),
+        }
+    }
+
+    fn quoted_string(&mut self) -> Result<String, ImportError> {
+        self.expect('"')?;
+        let text = self.take_while(|c| c != '"');
+        self.expect('"')?;
+        Ok(text)
+    }
+
+    /// Note name with its alteration: English (`cs`, `bf`, `css`) or Dutch (`cis`, `bes`,
+    /// `es`, `as`). The Dutch flats of e and a make `es` and `as` flats, not English sharps.
+    fn pitch_name(&mut self) -> Result<(i32, i32), ImportError> {
+        let letter = self.next().ok_or_else(|| self.error("Expected a note name"))?;
+        let step = "cdefgab".find(letter).ok_or_else(|| self.error(format!("Unknown note name '{}'", letter)))? as i32;
+        let suffix = self.take_while(|c| c.is_ascii_lowercase());
+        let mut rest = suffix.as_str();
+        let mut alter: i32 = 0;
+        // Dutch drops the `e` of `es` after e and a (`es`, `eses`, `as`, `ases`);
+        // the spelled-out `ees` and `aes` are read by the loop below
+        if matches!(letter, 'e' | 'a') {
+            if let Some(r) = rest.strip_prefix('s').filter(|r| r.is_empty() || *r == "es") {
+                alter -= 1;
+                rest = r;
+            }
+        }
+        while !rest.is_empty() {
+            rest = if let Some(r) = rest.strip_prefix("is") {
+                alter += 1;
+                r
+            } else if let Some(r) = rest.strip_prefix("es") {
+                alter -= 1;
+                r
+            } else if let Some(r) = rest.strip_prefix('s') {
+                alter += 1;
+                r
+            } else if let Some(r) = rest.strip_prefix('f') {
+                alter -= 1;
+                r
+            } else {
+                return Err(self.error(format!("Unknown note name '{}{}'", letter, suffix)));
+            };
+        }
+        if alter.abs() > 2 {
+            return Err(self.error(format!("Unsupported alteration in '{}{}'", letter, suffix)));
+        }
+        Ok((step, alter))
+    }
+
+    /// Octave marks after a note name: each `'` raises and each `,` lowers by an octave
+    fn octave_marks(&mut self) -> i32 {
+        let marks = self.take_while(|c| c == '\'' || c == ',');
+        marks.chars().map(|c| if c == '\'' { 1 } else { -1 }).sum()
+    }
+
+    /// Duration in ticks, or None when the note repeats the previous duration
+    fn duration(&mut self) -> Result<Option<u32>, ImportError> {
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        if digits.is_empty() {
+            return Ok(None);
+        }
+        let base = match digits.as_str() {
+            "1" | "2" | "4" | "8" | "16" | "32" | "64" => TICKS_PER_WHOLE / digits.parse::<u32>().unwrap(),
+            _ => return Err(self.error(format!("Unsupported duration '{}'", digits))),
+        };
+        let dots = self.take_while(|c| c == '.').len() as u32;
+        let mut ticks = base;
+        for dot in 1..=dots {
+            if base % (1 << dot) != 0 {
+                return Err(self.error("Too many dots for the duration"));
+            }
+            ticks += base >> dot;
+        }
+        Ok(Some(ticks))
+    }
+}
+
+/// Staff position of a pitch in diatonic steps, used to resolve `\relative` octaves
+fn diatonic(pitch: Pitch) -> i32 {
+    pitch.octave * 7 + pitch.step
+}
+
+/// Octave of `step` that lies closest (within a fourth) to the reference pitch
+fn relative_octave(step: i32, reference: Pitch) -> i32 {
+    let target = diatonic(reference);
+    let mut octave = reference.octave;
+    while octave * 7 + step - target > 3 {
+        octave -= 1;
+    }
+    while target - (octave * 7 + step) > 3 {
+        octave += 1;
+    }
+    octave
+}
+
+/// Import a single-voice LilyPond fragment such as `\relative c' { c4 d e f | g1 }`.
+///
+/// `\time` and `\key` become the `Time` and `Key` directives; the music becomes one
+/// stave of quarter-note beats in Western notation, with notes crossing a beat written
+/// as tied continuations. Slurs become slur marks on their first and last notes.
+pub fn import_lilypond(src: &str) -> Result<Document, ImportError> {
+    let mut lexer = Lexer::new(src);
+    let mut directives = HashMap::new();
+    let mut events = Vec::new();
+    // Reference pitch for `\relative`; None while reading absolute pitches
+    let mut reference: Option<Pitch> = None;
+    let mut duration = TICKS_PER_BEAT;
+    let mut depth = 0;
+    let mut seen_music = false;
+
+    loop {
+        lexer.skip_blank();
+        let Some(ch) = lexer.peek() else { break };
+        match ch {
+            '\\' => {
+                lexer.next();
+                let command = lexer.take_while(|c| c.is_ascii_alphabetic());
+                match command.as_str() {
+                    "version" => {
+                        lexer.skip_blank();
+                        lexer.quoted_string()?;
+                    }
+                    "relative" if !seen_music => {
+                        lexer.skip_blank();
+                        reference = Some(if lexer.peek() == Some('{') {
+                            // No start pitch: the first note is measured from f
+                            Pitch { step: 3, alter: 0, octave: -1 }
+                        } else {
+                            let (step, alter) = lexer.pitch_name()?;
+                            Pitch { step, alter, octave: -1 + lexer.octave_marks() }
+                        });
+                    }
+                    "time" => {
+                        lexer.skip_blank();
+                        let time = lexer.take_while(|c| c.is_ascii_digit() || c == '/');
+                        if !time.contains('/') {
+                            return Err(lexer.error("Expected a time signature such as 3/4"));
+                        }
+                        directives.insert("Time".to_string(), time);
+                    }
+                    "key" => {
+                        lexer.skip_blank();
+                        let (step, alter) = lexer.pitch_name()?;
+                        lexer.skip_blank();
+                        lexer.expect('\\')?;
+                        let mode = lexer.take_while(|c| c.is_ascii_alphabetic());
+                        if mode != "major" && mode != "minor" {
+                            return Err(lexer.error(format!("Unsupported key mode '\\{}'", mode)));
+                        }
+                        directives.insert("Key".to_string(), format!("{} {}", pitch_name(step, alter), mode));
+                    }
+                    "bar" => {
+                        lexer.skip_blank();
+                        let bar = lexer.quoted_string()?;
+                        if !matches!(bar.as_str(), "|" | "||" | "|.") {
+                            return Err(lexer.error(format!("Unsupported bar line \"{}\"", bar)));
+                        }
+                        events.push(Event::Barline(bar));
+                    }
+                    _ => return Err(lexer.error(format!("Unsupported LilyPond construct '\\{}'", command))),
+                }
+            }
+            '{' => {
+                lexer.next();
+                if seen_music && depth == 0 {
+                    return Err(lexer.error("Only a single voice can be imported"));
+                }
+                depth += 1;
+                seen_music = true;
+            }
+            '}' => {
+                lexer.next();
+                if depth == 0 {
+                    return Err(lexer.error("Unmatched '}'"));
+                }
+                depth -= 1;
+            }
+            '|' => {
+                lexer.next();
+                events.push(Event::Barline("|".to_string()));
+            }
+            '~' => {
+                lexer.next();
+                match events.last_mut() {
+                    Some(Event::Note { tied, .. }) => *tied = true,
+                    _ => return Err(lexer.error("A tie must follow a note")),
+                }
+            }
+            '(' | ')' => {
+                lexer.next();
+                let edge = if ch == '(' { SpanEdge::Start } else { SpanEdge::End };
+                match events.last_mut() {
+                    Some(Event::Note { slur, .. }) => *slur = Some(edge),
+                    _ => return Err(lexer.error("A slur must follow a note")),
+                }
+            }
+            'r' => {
+                lexer.next();
+                seen_music = true;
+                duration = lexer.duration()?.unwrap_or(duration);
+                events.push(Event::Rest { ticks: duration });
+            }
+            'a'..='g' => {
+                seen_music = true;
+                let (step, alter) = lexer.pitch_name()?;
+                let marks = lexer.octave_marks();
+                let octave = match reference {
+                    Some(reference) => relative_octave(step, reference) + marks,
+                    None => -1 + marks,
+                };
+                let pitch = Pitch { step, alter, octave };
+                if reference.is_some() {
+                    reference = Some(pitch);
+                }
+                duration = lexer.duration()?.unwrap_or(duration);
+                events.push(Event::Note { pitch, ticks: duration, tied: false, slur: None });
+            }
+            '<' => return Err(lexer.error("Chords are not supported")),
+            _ => return Err(lexer.error(format!("Unexpected '{}'", ch))),
+        }
+    }
+    if depth != 0 {
+        return Err(lexer.error("Missing '}'"));
+    }
+
+    let content_line = build_content_line(&merge_ties(events)).map_err(|message| ImportError {
+        message,
+        line: lexer.line,
+        column: lexer.column,
+    })?;
+
+    Ok(Document {
+        id: uuid::Uuid::new_v4(),
+        document_uuid: None,
+        value: None,
+        title: None,
+        author: None,
+        directives,
+        elements: vec![DocumentElement::Stave(Stave {
+            id: uuid::Uuid::new_v4(),
+            value: content_line.value.clone(),
+            notation_system: NotationSystem::Western,
+            line: 0,
+            column: 0,
+            index_in_line: 0,
+            index_in_doc: 0,
+            clef: None,
+            grand_staff: None,
+            lines: vec![StaveLine::ContentLine(content_line)],
+        })],
+        ui_state: Default::default(),
+        timestamp: String::new(),
+    })
+}
+
+/// Western note name such as "C", "F#" or "Bb"
+fn pitch_name(step: i32, alter: i32) -> String {
+    let accidental = match alter {
+        -2 => "bb",
+        -1 => "b",
+        1 => "#",
+        2 => "##",
+        _ => "",
+    };
+    format!("{}{}", STEP_NAMES[step as usize], accidental)
+}
+
+/// Join notes tied to the same pitch into one longer note
+fn merge_ties(events: Vec<Event>) -> Vec<Event> {
+    let mut merged: Vec<Event> = Vec::new();
+    for event in events {
+        if let (Some(Event::Note { pitch, ticks, tied, slur }), Event::Note { pitch: next, ticks: more, tied: next_tied, slur: next_slur }) = (merged.last_mut(), &event) {
+            if *tied && pitch == next {
+                *ticks += more;
+                *tied = *next_tied;
+                *slur = slur.or(*next_slur);
+                continue;
+            }
+        }
+        merged.push(event);
+    }
+    merged
+}
+
+/// Part of an event falling in one beat
+#[derive(Debug, Clone, Copy)]
+enum Segment {
+    /// Note starting here, with the slur it starts or ends
+    Note(Pitch, Option<SpanEdge>),
+    /// Note held over from the previous beat
+    Held,
+    Rest,
+}
+
+/// Lay the events out as quarter-note beats separated by whitespace and barlines
+fn build_content_line(events: &[Event]) -> Result<ContentLine, String> {
+    let mut elements = Vec::new();
+    let mut text: Vec<String> = Vec::new();
+    // (ticks into the beat, length in ticks, segment) of the beat being filled
+    let mut beat: Vec<(u32, u32, Segment)> = Vec::new();
+    let mut position = 0;
+
+    for event in events {
+        let (ticks, first, rest) = match event {
+            Event::Barline(bar) => {
+                if position % TICKS_PER_BEAT != 0 {
+                    return Err("Barline in the middle of a beat".to_string());
+                }
+                push_beat(&mut beat, &mut elements, &mut text);
+                if !elements.is_empty() {
+                    elements.push(whitespace());
+                }
+                text.push(bar.clone());
+                elements.push(ContentElement::Barline(barline(bar)));
+                continue;
+            }
+            Event::Note { pitch, ticks, slur, .. } => (*ticks, Segment::Note(*pitch, *slur), Segment::Held),
+            Event::Rest { ticks } => (*ticks, Segment::Rest, Segment::Rest),
+        };
+
+        let mut remaining = ticks;
+        let mut segment = first;
+        while remaining > 0 {
+            let offset = position % TICKS_PER_BEAT;
+            let length = remaining.min(TICKS_PER_BEAT - offset);
+            beat.push((offset, length, segment));
+            position += length;
+            remaining -= length;
+            segment = rest;
+            if position % TICKS_PER_BEAT == 0 {
+                push_beat(&mut beat, &mut elements, &mut text);
+            }
+        }
+    }
+    push_beat(&mut beat, &mut elements, &mut text);
+
+    Ok(ContentLine {
+        id: uuid::Uuid::new_v4(),
+        elements,
+        value: Some(text.join(" ")),
+    })
+}
+
+/// Close the beat being filled, padding a short final beat with a rest
+fn push_beat(beat: &mut Vec<(u32, u32, Segment)>, elements: &mut Vec<ContentElement>, text: &mut Vec<String>) {
+    if beat.is_empty() {
+        return;
+    }
+    let filled: u32 = beat.iter().map(|(_, len, _)| len).sum();
+    if filled < TICKS_PER_BEAT {
+        beat.push((filled, TICKS_PER_BEAT - filled, Segment::Rest));
+    }
+    let beat = make_beat(std::mem::take(beat));
+    if !elements.is_empty() {
+        elements.push(whitespace());
+    }
+    text.push(beat.value.clone().unwrap_or_default());
+    elements.push(ContentElement::Beat(beat));
+}
+
+/// Build an analyzed beat: each segment takes as many equal subdivisions as it lasts
+fn make_beat(segments: Vec<(u32, u32, Segment)>) -> Beat {
+    let unit = segments.iter().fold(TICKS_PER_BEAT, |g, (offset, len, _)| gcd(gcd(g, *offset), *len));
+    let dash = |numerator: Option<u32>, denominator: Option<u32>| BeatElement::Dash(Dash {
+        id: uuid::Uuid::new_v4(),
+        value: Some("-".to_string()),
+        numerator,
+        denominator,
+        source: None,
+    });
+
+    let mut elements = Vec::new();
+    let mut value = String::new();
+    for (_, len, segment) in &segments {
+        let divisor = gcd(*len, TICKS_PER_WHOLE);
+        let (numerator, denominator) = (len / divisor, TICKS_PER_WHOLE / divisor);
+        match segment {
+            Segment::Note(pitch, slur) => {
+                let name = pitch_name(pitch.step, pitch.alter);
+                value.push_str(&name);
+                elements.push(BeatElement::Note(Note {
+                    octave: pitch.octave as i8,
+                    numerator: Some(numerator),
+                    denominator: Some(denominator),
+                    slur: *slur,
+                    ..Note::new(Some(name), PITCH_CODES[pitch.step as usize][(pitch.alter + 2) as usize], NotationSystem::Western)
+                }));
+            }
+            Segment::Held => {
+                value.push('-');
+                elements.push(dash(None, None));
+            }
+            Segment::Rest => {
+                value.push('-');
+                elements.push(BeatElement::Rest(Rest {
+                    id: uuid::Uuid::new_v4(),
+                    value: Some("r".to_string()),
+                    numerator: Some(numerator),
+                    denominator: Some(denominator),
+                    source: None,
+                }));
+            }
+        }
+        for _ in 1..len / unit {
+            value.push('-');
+            elements.push(dash(None, None));
+        }
+    }
+
+    Beat {
+        id: uuid::Uuid::new_v4(),
+        value: Some(value),
+        divisions: Some((TICKS_PER_BEAT / unit) as usize),
+        is_tuplet: Some(false),
+        tuplet_ratio: None,
+        tied_to_previous: Some(matches!(segments.first(), Some((_, _, Segment::Held)))),
+        total_duration: Some(fraction::Fraction::new(1u64, 4u64)),
+        repeat_count: None,
+        source: None,
+        chord: None,
+        dynamic: None,
+        feather: None,
+        explicit_tuplet: None,
+        elements,
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn whitespace() -> ContentElement {
+    ContentElement::Whitespace(Whitespace {
+        id: uuid::Uuid::new_v4(),
+        value: Some(" ".to_string()),
+        source: None,
+    })
+}
+
+fn barline(bar: &str) -> Barline {
+    let (id, value, source) = (uuid::Uuid::new_v4(), Some(bar.to_string()), None);
+    match bar {
+        "||" => Barline::Double(DoubleBarline { id, value, source }),
+        "|." => Barline::Final(FinalBarline { id, value, source }),
+        _ => Barline::Single(SingleBarline { id, value, source }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beats(document: &Document) -> Vec<&Beat> {
+        let DocumentElement::Stave(stave) = &document.elements[0] else { panic!("expected a stave") };
+        let StaveLine::ContentLine(line) = &stave.lines[0] else { panic!("expected a content line") };
+        line.elements.iter().filter_map(|e| match e {
+            ContentElement::Beat(beat) => Some(beat),
+            _ => None,
+        }).collect()
+    }
+
+    fn notes(document: &Document) -> Vec<(PitchCode, i8, u32, u32)> {
+        beats(document).iter().flat_map(|b| b.elements.iter()).filter_map(|e| match e {
+            BeatElement::Note(n) => Some((n.pitch_code, n.octave, n.numerator.unwrap(), n.denominator.unwrap())),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn test_import_relative_melody() {
+        let document = import_lilypond("\\relative c' { c4 d e f | g1 }").unwrap();
+        assert_eq!(notes(&document), vec![
+            (PitchCode::N1, 0, 1, 4),
+            (PitchCode::N2, 0, 1, 4),
+            (PitchCode::N3, 0, 1, 4),
+            (PitchCode::N4, 0, 1, 4),
+            (PitchCode::N5, 0, 1, 4),
+        ]);
+        // The whole note is held through three tied beats
+        let beats = beats(&document);
+        assert_eq!(beats.len(), 8);
+        assert!(beats[5..].iter().all(|b| b.tied_to_previous == Some(true)));
+        let DocumentElement::Stave(stave) = &document.elements[0] else { unreachable!() };
+        assert_eq!(stave.value.as_deref(), Some("C D E F | G - - -"));
+
+        let lilypond = crate::renderers::lilypond::renderer::convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("c4 d4 e4 f4 |\n        g4~ g4~ g4~ g4"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_import_absolute_with_dots_ties_and_directives() {
+        let document = import_lilypond("\\time 3/4 \\key bf \\major { bf'8. c''16~ c''8 r8 ees'4 }").unwrap();
+        assert_eq!(document.directives.get("Time").map(String::as_str), Some("3/4"));
+        assert_eq!(document.directives.get("Key").map(String::as_str), Some("Bb major"));
+        assert_eq!(notes(&document), vec![
+            (PitchCode::N7b, 0, 3, 16),
+            (PitchCode::N1, 1, 1, 16),
+            (PitchCode::N3b, 0, 1, 4),
+        ]);
+        let beats = beats(&document);
+        assert_eq!(beats[0].divisions, Some(4));
+        assert_eq!(beats[1].tied_to_previous, Some(true));
+        assert!(matches!(beats[1].elements[..], [BeatElement::Dash(_), BeatElement::Rest(_)]));
+    }
+
+    #[test]
+    fn test_import_dutch_flats_of_e_and_a() {
+        let document = import_lilypond("{ es'4 as' ees' aes' eses' ases' bes' cis' }").unwrap();
+        let pitches: Vec<PitchCode> = notes(&document).into_iter().map(|(pitch, ..)| pitch).collect();
+        assert_eq!(pitches, vec![
+            PitchCode::N3b, PitchCode::N6b, PitchCode::N3b, PitchCode::N6b,
+            PitchCode::N3bb, PitchCode::N6bb, PitchCode::N7b, PitchCode::N1s,
+        ]);
+    }
+
+    #[test]
+    fn test_import_slurs() {
+        let document = import_lilypond("{ c'4( d' e'~ e') f' }").unwrap();
+        let slurs: Vec<Option<SpanEdge>> = beats(&document).iter().flat_map(|b| b.elements.iter()).filter_map(|e| match e {
+            BeatElement::Note(n) => Some(n.slur),
+            _ => None,
+        }).collect();
+        assert_eq!(slurs, vec![Some(SpanEdge::Start), None, Some(SpanEdge::End), None]);
+        assert!(import_lilypond("{ ( c'4 }").is_err());
+    }
+
+    #[test]
+    fn test_import_rejects_unsupported_constructs() {
+        let error = import_lilypond("{ \\tuplet 3/2 { c8 d e } }").unwrap_err();
+        assert_eq!(error.message, "Unsupported LilyPond construct '\\tuplet'");
+        assert!(import_lilypond("{ <c e g>4 }").is_err());
+        assert!(import_lilypond("{ c8 | d8 }").is_err());
+    }
+}
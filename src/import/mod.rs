@@ -1,2 +1,3 @@
 pub mod musicxml;
+pub mod lilypond;
 
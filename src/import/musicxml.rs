@@ -244,6 +244,8 @@ pub fn import_musicxml_to_document(xml: &str, opts: Option<ImportOptions>) -> an
         column: 0,
         index_in_line: 0,
         index_in_doc: 0,
+        clef: None,
+        grand_staff: None,
         lines: vec![
             StaveLine::Text(TextLine{ id: uuid::Uuid::new_v4(), value: Some(content_line) })
         ],
@@ -6,5 +6,6 @@ pub mod pipeline;
 pub mod models;
 pub mod document;
 pub mod import;
+pub mod analysis;
 
 
@@ -1,4 +1,7 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use music_text::parse::model::Document;
 
 
 #[derive(Parser)]
@@ -8,6 +11,143 @@ struct Cli {
     /// Start web server mode
     #[arg(long)]
     web: bool,
+
+    /// Port for web server mode
+    #[arg(long, default_value_t = 3000)]
+    port: u16,
+
+    /// Address to bind in web server mode
+    #[arg(long, default_value = "127.0.0.1")]
+    bind: IpAddr,
+
+    /// Directory of frontend files served at `/` in web server mode
+    #[arg(long, default_value = music_text::web::DEFAULT_STATIC_DIR)]
+    static_dir: String,
+
+    /// Enable debugging endpoints such as `/api/debug/tokens` in web server mode
+    #[arg(long)]
+    debug: bool,
+
+    /// Notation file to convert
+    #[arg(long)]
+    input: Option<PathBuf>,
+
+    /// Output format for `--input`
+    #[arg(long, value_enum, default_value_t = OutputFormat::Lilypond)]
+    to: OutputFormat,
+
+    /// File to write the converted output to instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// Notation file to check: report parse errors and warnings without rendering,
+    /// exiting with status 1 if it doesn't parse
+    #[arg(long, value_name = "FILE")]
+    validate: Option<PathBuf>,
+
+    /// Reverse-highlight annotation markers consumed by notes in `--to flattened`
+    /// instead of hiding them
+    #[arg(long)]
+    show_consumed: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Lilypond,
+    Vexflow,
+    Svg,
+    Midi,
+    Abc,
+    Musicxml,
+    /// Jianpu (numbered notation) as an HTML fragment
+    Jianpu,
+    /// Flattened spatial text view of the annotation lines, for debugging alignment
+    Flattened,
+}
+
+impl OutputFormat {
+    fn render(self, document: &Document, show_consumed: bool) -> Result<Vec<u8>, String> {
+        use music_text::parse::model::{DocumentElement, NotationSystem};
+        use music_text::renderers;
+        Ok(match self {
+            OutputFormat::Lilypond => {
+                renderers::lilypond::renderer::convert_processed_document_to_lilypond_src(document, None)?.into_bytes()
+            }
+            OutputFormat::Vexflow => {
+                let data = renderers::vexflow::VexFlowRenderer::new().render_data_from_document(document);
+                data.get("vexflow_js").and_then(|js| js.as_str()).unwrap_or_default().as_bytes().to_vec()
+            }
+            OutputFormat::Svg => renderers::svg::render_staff_svg(document).into_bytes(),
+            OutputFormat::Midi => {
+                let tempo = renderers::midi::events::tempo_from_directives(&document.directives);
+                renderers::midi::render_midi(document, tempo)
+            }
+            OutputFormat::Abc => renderers::abc::render_abc(document).into_bytes(),
+            OutputFormat::Musicxml => renderers::musicxml::render_musicxml(document).into_bytes(),
+            OutputFormat::Jianpu => renderers::jianpu::render_jianpu_html(document).into_bytes(),
+            OutputFormat::Flattened => {
+                let notation_system = document.elements.iter()
+                    .find_map(|element| match element {
+                        DocumentElement::Stave(stave) => Some(stave.notation_system),
+                        _ => None,
+                    })
+                    .unwrap_or(NotationSystem::Number);
+                let options = renderers::flattened_view::FlattenedViewOptions { show_consumed };
+                let view = renderers::flattened_view::generate_flattened_spatial_view(document.value.as_deref().unwrap_or_default(), notation_system, options);
+                format!("{}\n", view).into_bytes()
+            }
+        })
+    }
+}
+
+/// Convert a notation file to `format`, writing to `output` or stdout
+fn convert(input: &PathBuf, format: OutputFormat, output: Option<&PathBuf>, show_consumed: bool) -> Result<(), String> {
+    let text = std::fs::read_to_string(input)
+        .map_err(|e| format!("error: can't read {}: {}", input.display(), e))?;
+    let document = music_text::pipeline::document_from_notation(&text)?;
+    for warning in music_text::pipeline::notation_warnings(&document) {
+        eprintln!("warning: {}:{}:{}: {}", input.display(), warning.source.line, warning.source.column, warning.message);
+    }
+    let rendered = format.render(&document, show_consumed)?;
+    match output {
+        Some(path) => std::fs::write(path, rendered)
+            .map_err(|e| format!("error: can't write {}: {}", path.display(), e)),
+        None => {
+            use std::io::Write;
+            std::io::stdout().write_all(&rendered).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Check that a notation file parses, printing every error and warning to stderr.
+/// Returns whether it parsed.
+fn validate(input: &PathBuf) -> Result<bool, String> {
+    let text = std::fs::read_to_string(input)
+        .map_err(|e| format!("error: can't read {}: {}", input.display(), e))?;
+    let errors = music_text::pipeline::content_parse_errors(&text);
+    for error in &errors {
+        eprintln!("error[{}]: {}:{}:{}: {}", error.kind.code(), input.display(), error.line, error.column, error.message);
+        for suggestion in &error.suggestions {
+            eprintln!("  = help: {}", suggestion);
+        }
+    }
+    if !errors.is_empty() {
+        return Ok(false);
+    }
+    let document = match music_text::pipeline::document_from_notation(&text) {
+        Ok(document) => document,
+        Err(error) => {
+            eprintln!("error: {}: {}", input.display(), error);
+            return Ok(false);
+        }
+    };
+    for warning in music_text::pipeline::process_notation_directives(&text).warnings {
+        eprintln!("warning: {}: {}", input.display(), warning);
+    }
+    for warning in music_text::pipeline::notation_warnings(&document) {
+        eprintln!("warning: {}:{}:{}: {}", input.display(), warning.source.line, warning.source.column, warning.message);
+    }
+    Ok(true)
 }
 
 
@@ -17,10 +157,31 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
 
     // Web server mode
     if cli.web {
-        music_text::web::start_server().await?;
+        let addr = SocketAddr::new(cli.bind, cli.port);
+        music_text::web::start_server(addr, &cli.static_dir, cli.debug).await?;
         return Ok(());
     }
 
+    // Validation mode
+    if let Some(input) = &cli.validate {
+        match validate(input) {
+            Ok(true) => return Ok(()),
+            Ok(false) => std::process::exit(1),
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // File conversion mode
+    if let Some(input) = &cli.input {
+        if let Err(error) = convert(input, cli.to, cli.output.as_ref(), cli.show_consumed) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    }
+
     Ok(())
 }
 
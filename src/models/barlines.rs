@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use super::position::Source;
 
 // Barline models
 
@@ -9,6 +10,8 @@ pub struct SingleBarline {
     #[serde(default = "Uuid::new_v4")]
     pub id: Uuid,
     pub value: Option<String>,
+    #[serde(default)]
+    pub source: Option<Source>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +19,8 @@ pub struct DoubleBarline {
     #[serde(default = "Uuid::new_v4")]
     pub id: Uuid,
     pub value: Option<String>,
+    #[serde(default)]
+    pub source: Option<Source>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +28,8 @@ pub struct FinalBarline {
     #[serde(default = "Uuid::new_v4")]
     pub id: Uuid,
     pub value: Option<String>,
+    #[serde(default)]
+    pub source: Option<Source>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +37,8 @@ pub struct RepeatStartBarline {
     #[serde(default = "Uuid::new_v4")]
     pub id: Uuid,
     pub value: Option<String>,
+    #[serde(default)]
+    pub source: Option<Source>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +46,8 @@ pub struct RepeatEndBarline {
     #[serde(default = "Uuid::new_v4")]
     pub id: Uuid,
     pub value: Option<String>,
+    #[serde(default)]
+    pub source: Option<Source>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +55,8 @@ pub struct RepeatBothBarline {
     #[serde(default = "Uuid::new_v4")]
     pub id: Uuid,
     pub value: Option<String>,
+    #[serde(default)]
+    pub source: Option<Source>,
 }
 
 // Unified barline enum for ContentElement
@@ -55,4 +68,52 @@ pub enum Barline {
     RepeatStart(RepeatStartBarline),
     RepeatEnd(RepeatEndBarline),
     RepeatBoth(RepeatBothBarline),
-}
\ No newline at end of file
+}
+/// Kind of barline without the per-element data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BarlineType {
+    Single,
+    Double,
+    Final,
+    RepeatStart,
+    RepeatEnd,
+    RepeatBoth,
+}
+
+impl BarlineType {
+    pub const ALL: [BarlineType; 6] = [
+        BarlineType::Single,
+        BarlineType::Double,
+        BarlineType::Final,
+        BarlineType::RepeatStart,
+        BarlineType::RepeatEnd,
+        BarlineType::RepeatBoth,
+    ];
+}
+
+impl Barline {
+    /// Number of the ending bracket opened after the barline ("|1.", ":|2.")
+    pub fn ending(&self) -> Option<usize> {
+        let value = match self {
+            Barline::Single(b) => b.value.as_ref(),
+            Barline::Double(b) => b.value.as_ref(),
+            Barline::Final(b) => b.value.as_ref(),
+            Barline::RepeatStart(b) => b.value.as_ref(),
+            Barline::RepeatEnd(b) => b.value.as_ref(),
+            Barline::RepeatBoth(b) => b.value.as_ref(),
+        }?.strip_suffix('.')?;
+        let digits = value.trim_start_matches(['|', ':']);
+        digits.parse().ok()
+    }
+
+    pub fn barline_type(&self) -> BarlineType {
+        match self {
+            Barline::Single(_) => BarlineType::Single,
+            Barline::Double(_) => BarlineType::Double,
+            Barline::Final(_) => BarlineType::Final,
+            Barline::RepeatStart(_) => BarlineType::RepeatStart,
+            Barline::RepeatEnd(_) => BarlineType::RepeatEnd,
+            Barline::RepeatBoth(_) => BarlineType::RepeatBoth,
+        }
+    }
+}
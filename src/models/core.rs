@@ -40,7 +40,29 @@ pub struct Document {
     pub timestamp: String,
 }
 
+/// Directive key for the author shown in score headers
+pub const AUTHOR_DIRECTIVE: &str = "Author";
+/// Directive key for the composer shown in score headers, taking precedence over `Author`
+pub const COMPOSER_DIRECTIVE: &str = "Composer";
+/// Directive key for the key signature (e.g. `Key: D major`)
+pub const KEY_DIRECTIVE: &str = "Key";
+
+/// Trimmed value of the directive `key`, matching the key case-insensitively
+pub fn directive<'a>(directives: &'a HashMap<String, String>, key: &str) -> Option<&'a str> {
+    directives.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .map(|(_, value)| value.trim())
+}
+
 impl Document {
+    /// Composer credited in score headers: the document's author, else the `Composer`
+    /// directive, else the `Author` directive
+    pub fn composer(&self) -> Option<&str> {
+        self.author.as_deref()
+            .or_else(|| directive(&self.directives, COMPOSER_DIRECTIVE))
+            .or_else(|| directive(&self.directives, AUTHOR_DIRECTIVE))
+    }
+
     /// Get unique notation systems detected across all staves
     pub fn get_detected_notation_systems(&self) -> Vec<super::notation::NotationSystem> {
         use std::collections::HashSet;
@@ -59,6 +81,8 @@ impl Document {
             super::notation::NotationSystem::Sargam => 2,
             super::notation::NotationSystem::Bhatkhande => 3,
             super::notation::NotationSystem::Tabla => 4,
+            super::notation::NotationSystem::Jianpu => 5,
+            super::notation::NotationSystem::Scat => 6,
         });
         result
     }
@@ -86,9 +110,65 @@ pub struct Stave {
     pub column: usize,
     pub index_in_line: usize,
     pub index_in_doc: usize,
+    #[serde(default)]
+    pub clef: Option<Clef>,     // Clef from a `clef:` line before the stave or the `Clef` directive
+    #[serde(default)]
+    pub grand_staff: Option<usize>, // Index of the `{` ... `}` group braced into one grand staff with it
     pub lines: Vec<StaveLine>,  // All lines in order
 }
 
+/// Lines opening and closing a group of staves braced into one grand staff (piano system)
+pub const GRAND_STAFF_OPEN: &str = "{";
+pub const GRAND_STAFF_CLOSE: &str = "}";
+
+/// Directive key for the clef of staves without a `clef:` line of their own
+pub const CLEF_DIRECTIVE: &str = "Clef";
+
+/// Clef a stave is drawn with. Pitches stay absolute: a clef only moves where notes
+/// sit on the staff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Clef {
+    #[default]
+    Treble,
+    Bass,
+    Alto,
+    Tenor,
+}
+
+impl Clef {
+    /// Clef named `name` ("bass", "Treble", ...)
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "treble" => Some(Clef::Treble),
+            "bass" => Some(Clef::Bass),
+            "alto" => Some(Clef::Alto),
+            "tenor" => Some(Clef::Tenor),
+            _ => None,
+        }
+    }
+
+    /// Clef of a `clef: bass` line written before a stave
+    pub fn from_marker(line: &str) -> Option<Self> {
+        let (key, name) = line.split_once(':')?;
+        if key.trim().eq_ignore_ascii_case(CLEF_DIRECTIVE) { Clef::parse(name) } else { None }
+    }
+
+    /// Clef from the `Clef` directive
+    pub fn from_directives(directives: &HashMap<String, String>) -> Option<Self> {
+        directive(directives, CLEF_DIRECTIVE).and_then(Clef::parse)
+    }
+
+    /// Clef name in LilyPond's `\clef` and VexFlow's `addClef`
+    pub fn name(self) -> &'static str {
+        match self {
+            Clef::Treble => "treble",
+            Clef::Bass => "bass",
+            Clef::Alto => "alto",
+            Clef::Tenor => "tenor",
+        }
+    }
+}
+
 // Enum for different types of lines in a stave
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StaveLine {
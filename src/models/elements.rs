@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use fraction::Fraction;
 use uuid::Uuid;
+use super::position::Source;
 
 // Core music elements - the fundamental building blocks of musical notation
 
@@ -16,6 +17,99 @@ pub struct Note {
     pub notation_system: super::notation::NotationSystem, // Which notation system this note uses
     pub numerator: Option<u32>,         // Simple duration numerator
     pub denominator: Option<u32>,       // Simple duration denominator
+    #[serde(default)]
+    pub source: Option<Source>,         // Location in the source text, set by the parser
+    #[serde(default)]
+    pub divisi: Option<DivisiPitch>,    // Second pitch sounding with this note inside a div. span
+    #[serde(default)]
+    pub vibrato: bool,                  // Under a vibrato (wavy line) span
+    #[serde(default)]
+    pub bend: Option<i8>,               // Pitch bend in semitones (e.g., 1 for "^1")
+    #[serde(default)]
+    pub cautionary: bool,               // Accidental printed in parentheses as a reminder
+    #[serde(default)]
+    pub tie: Option<char>,              // Tie marker written after the note ('~' or '_'), tying it to the next note
+    #[serde(default)]
+    pub grace_notes: Vec<GraceNote>,    // Quick notes leading into this one, written in an upper line
+    #[serde(default)]
+    pub lyrics: Vec<String>,            // Syllable sung on this note in each verse; empty where a verse has none
+    #[serde(default)]
+    pub microtone: Option<i8>,          // Quarter tones added to the pitch (1 for "1+", -1 for a quarter-tone flat)
+    #[serde(default)]
+    pub articulations: Vec<Articulation>, // Marks from an articulation line over or under the note
+    #[serde(default)]
+    pub ornament: Option<Ornament>,     // Mordent, trill or turn written above the note
+    #[serde(default)]
+    pub slur: Option<SpanEdge>,         // Starts or ends a slur ("___" in an upper line)
+    #[serde(default)]
+    pub phrase: Option<SpanEdge>,       // Starts or ends a phrase mark ("(" and ")" in an upper line)
+}
+
+/// Pitch of the second divisi stream, sharing the rhythm of the note it is attached to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DivisiPitch {
+    pub value: Option<String>,
+    pub pitch_code: super::notation::PitchCode,
+    pub octave: i8,
+}
+
+/// A grace note, taking no time from the beat of the note it leads into
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraceNote {
+    pub value: Option<String>,
+    pub pitch_code: super::notation::PitchCode,
+    pub octave: i8,
+}
+
+/// How a note is attacked or held, written in an articulation line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Articulation {
+    /// "." - short and detached
+    Staccato,
+    /// ">" - stressed
+    Accent,
+    /// "-" - held for its full length
+    Tenuto,
+}
+
+impl Articulation {
+    pub fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '.' => Some(Articulation::Staccato),
+            '>' => Some(Articulation::Accent),
+            '-' => Some(Articulation::Tenuto),
+            _ => None,
+        }
+    }
+}
+
+/// Ornament written in an upper line over a note
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Ornament {
+    /// A lone "~" (a longer "~~~" run is a vibrato span)
+    Mordent,
+    /// "tr"
+    Trill,
+    /// "turn" or the turn glyph "𝆗"
+    Turn,
+}
+
+impl Ornament {
+    pub fn from_token(token: &str) -> Option<Self> {
+        match token {
+            "~" => Some(Ornament::Mordent),
+            "tr" => Some(Ornament::Trill),
+            "turn" | "\u{1D197}" => Some(Ornament::Turn),
+            _ => None,
+        }
+    }
+}
+
+/// First or last note under a slur or phrase mark
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpanEdge {
+    Start,
+    End,
 }
 
 impl Note {
@@ -33,6 +127,19 @@ impl Note {
             notation_system,
             numerator: None,                // Will be populated by rhythm analysis
             denominator: None,              // Will be populated by rhythm analysis
+            source: None,
+            divisi: None,
+            vibrato: false,
+            bend: None,
+            cautionary: false,
+            tie: None,
+            grace_notes: Vec::new(),
+            lyrics: Vec::new(),
+            microtone: None,
+            articulations: Vec::new(),
+            ornament: None,
+            slur: None,
+            phrase: None,
         }
     }
 }
@@ -45,6 +152,8 @@ pub struct Dash {
     // Duration fields populated by rhythm analyzer
     pub numerator: Option<u32>,
     pub denominator: Option<u32>,
+    #[serde(default)]
+    pub source: Option<Source>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,6 +161,8 @@ pub struct BreathMark {
     #[serde(default = "Uuid::new_v4")]
     pub id: Uuid,
     pub value: Option<String>,
+    #[serde(default)]
+    pub source: Option<Source>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,6 +173,8 @@ pub struct Rest {
     // Duration fields populated by rhythm analyzer
     pub numerator: Option<u32>,
     pub denominator: Option<u32>,
+    #[serde(default)]
+    pub source: Option<Source>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -77,6 +190,8 @@ pub struct Whitespace {
     #[serde(default = "Uuid::new_v4")]
     pub id: Uuid,
     pub value: Option<String>,
+    #[serde(default)]
+    pub source: Option<Source>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -113,15 +228,38 @@ pub struct Beat {
     pub tuplet_ratio: Option<(usize, usize)>, // Tuplet ratio (e.g., (3, 2) for triplet)
     pub tied_to_previous: Option<bool>,  // Whether this beat's first note is tied to the previous beat's last note
     pub total_duration: Option<Fraction>, // Total duration of this beat (e.g., 1/4 for quarter note beat)
+    #[serde(default)]
+    pub repeat_count: Option<usize>,     // Note repetition shorthand (e.g., 4 for "1*4"), expanded before rhythm analysis
+    #[serde(default)]
+    pub source: Option<Source>,          // Location of the whole beat in the source text
+    #[serde(default)]
+    pub chord: Option<String>,           // Chord symbol aligned above this beat (e.g., "F" from "[F]")
+    #[serde(default)]
+    pub dynamic: Option<String>,         // Dynamic mark aligned below this beat ("p", "f", or "<"/">" starting a hairpin)
+    #[serde(default)]
+    pub feather: Option<Feather>,        // Feathered beam over this beat's notes, from "<<" or ">>" above it
+    #[serde(default)]
+    pub explicit_tuplet: Option<(usize, usize)>, // Ratio written as "[3:2 1 2 3]", overriding the inferred one
     pub elements: Vec<BeatElement>,
 }
 
+/// Direction a feathered beam fans out in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Feather {
+    /// Beams spread towards the end of the group as the notes speed up ("<<")
+    Accelerando,
+    /// Beams narrow towards the end of the group as the notes slow down (">>")
+    Ritardando,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UnknownToken {
     #[serde(default = "Uuid::new_v4")]
     pub id: Uuid,
     pub value: Option<String>,
     pub token_value: String,
+    #[serde(default)]
+    pub source: Option<Source>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
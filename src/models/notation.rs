@@ -10,6 +10,8 @@ pub enum NotationSystem {
     Sargam,     // S R G M P D N (Indian classical music)
     Bhatkhande, // स रे ग म प ध नि (Devanagari script)
     Tabla,      // dha dhin ta ka taka trkt ge (tabla bols/percussion syllables)
+    Jianpu,     // 1 2 3 4 5 6 7 written as jianpu (Chinese numbered notation)
+    Scat,       // ta ka di mi (vocal percussion syllables, rhythm only)
 }
 
 // Alternative notation enum for legacy compatibility
@@ -46,8 +48,11 @@ impl From<NotationSystem> for Notation {
             NotationSystem::Western => Notation::Western,
             NotationSystem::Number => Notation::Number,
             NotationSystem::Sargam => Notation::Sargam,
-            NotationSystem::Tabla => Notation::Tabla,
+            // Scat syllables are unpitched like tabla bols
+            NotationSystem::Tabla | NotationSystem::Scat => Notation::Tabla,
             NotationSystem::Bhatkhande => Notation::Bhatkhande,
+            // Jianpu is written with the same digits as Number
+            NotationSystem::Jianpu => Notation::Number,
         }
     }
 }
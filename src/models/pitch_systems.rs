@@ -1,6 +1,7 @@
 use crate::models::{Degree, Notation, NotationSystem, PitchCode};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use std::collections::HashMap;
 
 // Import all pitch system modules
 pub mod western;
@@ -23,10 +24,10 @@ fn notation_to_system(notation: Notation) -> NotationSystem {
 fn system_to_notation(system: NotationSystem) -> Notation {
     match system {
         NotationSystem::Western => Notation::Western,
-        NotationSystem::Number => Notation::Number,
+        NotationSystem::Number | NotationSystem::Jianpu => Notation::Number,
         NotationSystem::Sargam => Notation::Sargam,
         NotationSystem::Bhatkhande => Notation::Bhatkhande,
-        NotationSystem::Tabla => Notation::Tabla,
+        NotationSystem::Tabla | NotationSystem::Scat => Notation::Tabla,
     }
 }
 
@@ -91,15 +92,19 @@ static NUMBER_RE: Lazy<Regex> = Lazy::new(|| build_regex_for_system(NotationSyst
 static WESTERN_RE: Lazy<Regex> = Lazy::new(|| build_regex_for_system(NotationSystem::Western));
 static BHATKHANDE_RE: Lazy<Regex> = Lazy::new(|| build_regex_for_system(NotationSystem::Bhatkhande));
 
-/// Build regex pattern for a specific notation system
-fn build_regex_for_system(system: NotationSystem) -> Regex {
-    let symbols = match system {
-        NotationSystem::Tabla => tabla::get_all_symbols(),
+fn symbols_for_system(system: NotationSystem) -> Vec<String> {
+    match system {
+        NotationSystem::Tabla | NotationSystem::Scat => tabla::get_all_symbols(),
         NotationSystem::Sargam => sargam::get_all_symbols(),
-        NotationSystem::Number => number::get_all_symbols(),
+        NotationSystem::Number | NotationSystem::Jianpu => number::get_all_symbols(),
         NotationSystem::Western => western::get_all_symbols(),
         NotationSystem::Bhatkhande => bhatkhande::get_all_symbols(),
-    };
+    }
+}
+
+/// Build regex pattern for a specific notation system
+fn build_regex_for_system(system: NotationSystem) -> Regex {
+    let symbols = symbols_for_system(system);
 
     // Escape special regex characters and join with alternation
     let escaped_symbols: Vec<String> = symbols.iter()
@@ -113,14 +118,64 @@ fn build_regex_for_system(system: NotationSystem) -> Regex {
 /// Get the compiled regex for a specific notation system
 pub fn get_regex_for_system(system: NotationSystem) -> &'static Regex {
     match system {
-        NotationSystem::Tabla => &*TABLA_RE,
-        NotationSystem::Sargam => &*SARGAM_RE,
-        NotationSystem::Number => &*NUMBER_RE,
-        NotationSystem::Western => &*WESTERN_RE,
-        NotationSystem::Bhatkhande => &*BHATKHANDE_RE,
+        NotationSystem::Tabla | NotationSystem::Scat => &TABLA_RE,
+        NotationSystem::Sargam => &SARGAM_RE,
+        NotationSystem::Number | NotationSystem::Jianpu => &NUMBER_RE,
+        NotationSystem::Western => &WESTERN_RE,
+        NotationSystem::Bhatkhande => &BHATKHANDE_RE,
+    }
+}
+
+/// Pitch symbols of one notation system, precomputed for matching at a parse position.
+/// Symbols are grouped by their (lowercased) first character and keep the priority
+/// order of the regex alternation, so the first one that matches wins just as with the regex.
+struct PitchTable {
+    notation: Notation,
+    candidates: HashMap<char, Vec<String>>,
+    pitches: HashMap<String, Degree>,
+}
+
+impl PitchTable {
+    fn build(system: NotationSystem) -> Self {
+        let notation = system_to_notation(system);
+        let mut candidates: HashMap<char, Vec<String>> = HashMap::new();
+        let mut pitches = HashMap::new();
+        for symbol in symbols_for_system(system) {
+            if let Some(first) = symbol.chars().next() {
+                candidates.entry(first.to_ascii_lowercase()).or_default().push(symbol.clone());
+            }
+            if let Some(degree) = lookup_pitch(&symbol, notation) {
+                pitches.insert(symbol, degree);
+            }
+        }
+        Self { notation, candidates, pitches }
     }
 }
 
+static TABLA_TABLE: Lazy<PitchTable> = Lazy::new(|| PitchTable::build(NotationSystem::Tabla));
+static SARGAM_TABLE: Lazy<PitchTable> = Lazy::new(|| PitchTable::build(NotationSystem::Sargam));
+static NUMBER_TABLE: Lazy<PitchTable> = Lazy::new(|| PitchTable::build(NotationSystem::Number));
+static WESTERN_TABLE: Lazy<PitchTable> = Lazy::new(|| PitchTable::build(NotationSystem::Western));
+static BHATKHANDE_TABLE: Lazy<PitchTable> = Lazy::new(|| PitchTable::build(NotationSystem::Bhatkhande));
+
+/// Match a pitch symbol at the start of `input` (case-insensitively, like the system regex).
+/// Returns the matched text and its pitch, which is None when the text matches a symbol
+/// only up to case and is not itself a valid pitch.
+pub fn match_pitch(input: &str, system: NotationSystem) -> Option<(&str, Option<Degree>)> {
+    let table = match system {
+        NotationSystem::Tabla | NotationSystem::Scat => &*TABLA_TABLE,
+        NotationSystem::Sargam => &*SARGAM_TABLE,
+        NotationSystem::Number | NotationSystem::Jianpu => &*NUMBER_TABLE,
+        NotationSystem::Western => &*WESTERN_TABLE,
+        NotationSystem::Bhatkhande => &*BHATKHANDE_TABLE,
+    };
+    let first = input.chars().next()?.to_ascii_lowercase();
+    let matched = table.candidates.get(&first)?.iter()
+        .find_map(|symbol| input.get(..symbol.len()).filter(|prefix| prefix.eq_ignore_ascii_case(symbol)))?;
+    let degree = table.pitches.get(matched).copied().or_else(|| lookup_pitch(matched, table.notation));
+    Some((matched, degree))
+}
+
 /// Classify input text and return best matching notation system with confidence score
 pub fn classify_notation_system(input: &str) -> (NotationSystem, f32) {
     let systems = [
@@ -154,6 +209,75 @@ pub fn classify_notation_system(input: &str) -> (NotationSystem, f32) {
     (best_system, best_score)
 }
 
+/// Every notation system that could read the input, with confidence scores summing to 1,
+/// most likely first. Each whitespace-separated chunk counts once for every system
+/// whose pitches (with dashes and barlines between them) spell it out completely,
+/// so ambiguous input such as `D E` surfaces all its readings. Ties keep the order
+/// Number, Western, Sargam, Bhatkhande, Tabla.
+/// A chunk that is only a rest token (`r`, `r-`) would read as a Sargam komal Re, so it
+/// counts only when no other chunk spells a pitch.
+pub fn rank_notation_systems(input: &str) -> Vec<(NotationSystem, f32)> {
+    let systems = [
+        NotationSystem::Number,
+        NotationSystem::Western,
+        NotationSystem::Sargam,
+        NotationSystem::Bhatkhande,
+        NotationSystem::Tabla,
+    ];
+    let count_chunks = |rests: bool| -> Vec<(NotationSystem, usize)> {
+        systems.iter()
+            .map(|&system| {
+                let count = input.split_whitespace()
+                    .filter(|chunk| rests || !is_rest_chunk(chunk))
+                    .filter(|chunk| spells_chunk(chunk, system))
+                    .count();
+                (system, count)
+            })
+            .collect()
+    };
+    let mut counts = count_chunks(false);
+    if counts.iter().all(|(_, count)| *count == 0) {
+        counts = count_chunks(true);
+    }
+    let total: usize = counts.iter().map(|(_, count)| count).sum();
+    if total == 0 {
+        return Vec::new();
+    }
+    let mut ranked: Vec<(NotationSystem, f32)> = counts.into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(system, count)| (system, count as f32 / total as f32))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked
+}
+
+/// Whether a chunk is an explicit rest token extended by dashes ("r", "r--"), barlines aside
+fn is_rest_chunk(chunk: &str) -> bool {
+    chunk.trim_matches(|c| matches!(c, '|' | ':'))
+        .strip_prefix('r')
+        .is_some_and(|dashes| dashes.chars().all(|c| c == '-'))
+}
+
+/// Whether a chunk is made only of pitches of `system`, dashes and barlines, with at least one pitch
+fn spells_chunk(chunk: &str, system: NotationSystem) -> bool {
+    let mut rest = chunk;
+    let mut pitches = 0;
+    while let Some(c) = rest.chars().next() {
+        if matches!(c, '-' | '|' | ':') {
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        match match_pitch(rest, system) {
+            Some((matched, Some(_))) => {
+                rest = &rest[matched.len()..];
+                pitches += 1;
+            }
+            _ => return false,
+        }
+    }
+    pitches > 0
+}
+
 /// Main dispatcher for pitch lookups across all notation systems
 ///
 /// This function routes symbol lookups to the appropriate notation system
@@ -235,9 +359,77 @@ mod tests {
         assert_eq!(lookup_pitch("unknown", Notation::Tabla), None);
     }
 
+    #[test]
+    fn test_rank_notation_systems() {
+        let ranked = rank_notation_systems("| S R G M |");
+        assert_eq!(ranked[0].0, NotationSystem::Sargam);
+        assert!(ranked.iter().any(|(system, _)| *system == NotationSystem::Bhatkhande));
+
+        let ranked = rank_notation_systems("1 2 3");
+        assert_eq!(ranked, vec![(NotationSystem::Number, 1.0)]);
+
+        let ranked = rank_notation_systems("C D E");
+        assert_eq!(ranked[0].0, NotationSystem::Western);
+        let total: f32 = ranked.iter().map(|(_, score)| score).sum();
+        assert!((total - 1.0).abs() < 1e-6);
+
+        assert!(rank_notation_systems("| |").is_empty());
+    }
+
     #[test]
     fn test_dispatcher_invalid() {
         assert_eq!(lookup_pitch("X", Notation::Sargam), None);
         assert_eq!(lookup_pitch("", Notation::Western), None);
     }
-}
\ No newline at end of file
+
+    /// Pitch match the parser used to make: the first regex match, taken at the parse position
+    fn regex_match(input: &str, system: NotationSystem) -> Option<(&str, Option<Degree>)> {
+        let found = get_regex_for_system(system).find(input).filter(|m| m.start() == 0)?;
+        Some((found.as_str(), lookup_pitch(found.as_str(), system_to_notation(system))))
+    }
+
+    /// A large document for each system: one line repeated, matched at every character position
+    fn pitch_match_inputs() -> Vec<(NotationSystem, String, Vec<usize>)> {
+        let systems = [
+            (NotationSystem::Number, "1 2# 3b 4## 5bb | 6-7 1'2 "),
+            (NotationSystem::Western, "C D# Eb F## Gbb | a-b c'd "),
+            (NotationSystem::Sargam, "S r R g G m M P d D n N | S## mb s# "),
+            (NotationSystem::Bhatkhande, "स र ग म प ध न | S M# "),
+            (NotationSystem::Tabla, "dha ge na tin | ka ta dhin "),
+        ];
+        systems.into_iter().map(|(system, line)| {
+            let document = line.repeat(400);
+            let positions = document.char_indices().map(|(i, _)| i).collect();
+            (system, document, positions)
+        }).collect()
+    }
+
+    #[test]
+    fn test_pitch_table_matches_regex() {
+        for (system, document, positions) in pitch_match_inputs() {
+            let from_regex: Vec<_> = positions.iter().map(|&i| regex_match(&document[i..], system)).collect();
+            let from_table: Vec<_> = positions.iter().map(|&i| match_pitch(&document[i..], system)).collect();
+            assert_eq!(from_table, from_regex, "{:?}", system);
+        }
+    }
+
+    /// Timing comparison, left out of the default run since it depends on the machine;
+    /// run with `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_pitch_table_is_faster_than_regex() {
+        for (system, document, positions) in pitch_match_inputs() {
+            let started = std::time::Instant::now();
+            let from_regex: Vec<_> = positions.iter().map(|&i| regex_match(&document[i..], system)).collect();
+            let regex_time = started.elapsed();
+
+            let started = std::time::Instant::now();
+            let from_table: Vec<_> = positions.iter().map(|&i| match_pitch(&document[i..], system)).collect();
+            let table_time = started.elapsed();
+
+            assert_eq!(from_table.len(), from_regex.len());
+            assert!(table_time < regex_time, "{:?}: table {:?}, regex {:?}", system, table_time, regex_time);
+        }
+    }
+}
+
@@ -18,6 +18,24 @@ pub struct Position {
     pub index_in_doc: usize,
 }
 
+/// Source location of a parsed element in the original text.
+/// `line` is 0-based like the parser's line numbers, `column` is 1-based within the line,
+/// `char_index` is the character offset in the document and `length` counts characters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Source {
+    pub line: usize,
+    pub column: usize,
+    pub char_index: usize,
+    pub length: usize,
+}
+
+impl Source {
+    /// Character offset just past the end of the element
+    pub fn end_char_index(&self) -> usize {
+        self.char_index + self.length
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Attributes {
     pub slur_position: SlurPosition,
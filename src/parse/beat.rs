@@ -1,33 +1,119 @@
-use crate::parse::model::{Beat, BeatElement, Note, Dash, BreathMark, NotationSystem};
-use crate::parse::pitch::{parse_pitch_with_indices, is_pitch_start};
-use crate::parse::ParseError;
+use crate::parse::model::{Beat, BeatElement, Note, Dash, Rest, BreathMark, GraceNote, NotationSystem};
+use crate::parse::pitch::{parse_pitch_with_indices, parse_microtone, parse_octave_digit, is_pitch_start, is_rest_start, ScientificPitch};
+use crate::parse::{ParseError, ParseErrorKind, source_span};
 use std::str::CharIndices;
 use std::iter::Peekable;
 
+/// Characters that tie a note to the next one when written right after it
+pub const TIE_MARKERS: [char; 2] = ['~', '_'];
+
 /// Helper function to calculate column from position in input
 fn column_from_pos(input: &str, pos: usize) -> usize {
     input[..pos].chars().rev().take_while(|&c| c != '\n').count() + 1
 }
 
+/// Consume a tie marker directly after a pitch
+fn parse_tie_marker(chars: &mut Peekable<CharIndices>) -> Option<char> {
+    chars.next_if(|(_, c)| TIE_MARKERS.contains(c)).map(|(_, c)| c)
+}
+
+/// Parse a group of grace notes in parentheses leading into the pitch right after it:
+/// grace-group = '(' pitch+ ')'
+///
+/// e.g. the "(2)" of "(2)1". Returns an empty group when there is no '(' to parse.
+fn parse_grace_group(
+    chars: &mut Peekable<CharIndices>,
+    notation_system: NotationSystem,
+    scientific_pitch: ScientificPitch,
+    line_num: usize,
+    input: &str,
+) -> Result<Vec<GraceNote>, ParseError> {
+    let error = |kind: ParseErrorKind, message: String, pos: usize| ParseError {
+        kind,
+        message,
+        line: line_num,
+        column: column_from_pos(input, pos),
+        suggestions: Vec::new(),
+    };
+    let Some((open_pos, _)) = chars.next_if(|(_, c)| *c == '(') else { return Ok(Vec::new()) };
+
+    let mut grace_notes = Vec::new();
+    loop {
+        match chars.peek() {
+            Some(&(_, ')')) => {
+                chars.next();
+                break;
+            }
+            Some(&(_, ch)) if is_pitch_start(ch, notation_system) => {
+                let (pitch_str, pitch_code) = parse_pitch_with_indices(chars, notation_system, line_num, input)?;
+                let (pitch_str, octave) = parse_octave_digit(chars, pitch_str, notation_system, scientific_pitch);
+                grace_notes.push(GraceNote { value: Some(pitch_str), pitch_code, octave });
+            }
+            Some(&(pos, ' ')) | Some(&(pos, '|')) | Some(&(pos, '\n')) => return Err(error(ParseErrorKind::UnterminatedGroup, "Unclosed grace notes, expected ')'".to_string(), pos)),
+            None => return Err(error(ParseErrorKind::UnterminatedGroup, "Unclosed grace notes, expected ')'".to_string(), input.len())),
+            Some(&(pos, ch)) => return Err(error(ParseErrorKind::UnexpectedToken, format!("Unexpected '{}' in grace notes", ch), pos)),
+        }
+    }
+    if grace_notes.is_empty() {
+        return Err(error(ParseErrorKind::InvalidGroup, "Grace notes in parentheses can't be empty".to_string(), open_pos));
+    }
+    match chars.peek() {
+        Some(&(_, ch)) if is_pitch_start(ch, notation_system) => Ok(grace_notes),
+        _ => Err(error(ParseErrorKind::InvalidGroup, "Grace notes must be followed by the note they lead into".to_string(), open_pos)),
+    }
+}
+
+/// Parse an explicit rest token ('r' or '0', see `is_rest_start`); its duration
+/// comes from the dashes after it, like a note's
+fn parse_rest(chars: &mut Peekable<CharIndices>, line_num: usize, input: &str, line_start_doc_index: usize) -> Option<BeatElement> {
+    let (pos, ch) = chars.next()?;
+    Some(BeatElement::Rest(Rest {
+        id: uuid::Uuid::new_v4(),
+        value: Some(ch.to_string()),
+        numerator: None, // Will be populated by rhythm analysis
+        denominator: None, // Will be populated by rhythm analysis
+        source: Some(source_span(input, pos, pos + ch.len_utf8(), line_num, line_start_doc_index)),
+    }))
+}
+
 /// Helper function to calculate index in line from position
 fn index_in_line_from_pos(input: &str, pos: usize, _line_num: usize) -> usize {
     input[..pos].chars().rev().take_while(|&c| c != '\n').count()
 }
 
+/// Most notes a repetition shorthand ("1*4") may stand for
+pub const MAX_REPEAT_COUNT: usize = 64;
+
 /// Parse a beat according to the grammar:
-/// beat = (pitch | dash) beat-element*
-/// beat-element = pitch | dash | breath-mark
+/// beat = (pitch | rest | dash) beat-element* | pitch '*' count
+/// beat-element = pitch | rest | dash | breath-mark
+///
+/// A rest is an explicit rest token ('r' or '0' depending on the notation system,
+/// see `is_rest_start`) lasting as long as the dashes after it extend it (e.g. "r-1").
+///
+/// A pitch may be followed by a tie marker ('~' or '_') tying it to the next note,
+/// even across beats and barlines (e.g. "1~|1"), and preceded by grace notes in
+/// parentheses (e.g. "(2)1"), which take no time from the beat.
+///
+/// `pitch '*' count` is the note repetition shorthand (e.g. "1*4"); it is
+/// recorded as `repeat_count` and expanded into separate beats before rhythm analysis.
+/// Counts above `MAX_REPEAT_COUNT` are an error.
+///
+/// With `scientific_pitch` on, a capital Western pitch may end in an octave digit
+/// (e.g. "C5"), which sets the note's octave.
 ///
 /// Returns the parsed beat
 pub fn parse_beat(
     chars: &mut Peekable<CharIndices>,
     notation_system: NotationSystem,
+    scientific_pitch: ScientificPitch,
     line_num: usize,
     input: &str,
     line_start_doc_index: usize,
 ) -> Result<Beat, ParseError> {
     let mut elements = Vec::new();
     let beat_start_pos = chars.peek().map(|(pos, _)| *pos).unwrap_or(0);
+    let mut grace_notes = parse_grace_group(chars, notation_system, scientific_pitch, line_num, input)?;
 
     // First element must be pitch or dash
     match chars.peek() {
@@ -38,39 +124,101 @@ pub fn parse_beat(
                 value: Some("-".to_string()),
                 numerator: None,
                 denominator: None,
+                source: Some(source_span(input, pos, pos + 1, line_num, line_start_doc_index)),
             }));
         }
         Some(&(pos, ch)) if is_pitch_start(ch, notation_system) => {
             let (pitch_str, pitch_code) = parse_pitch_with_indices(chars, notation_system, line_num, input)?;
+            let (pitch_str, microtone) = parse_microtone(chars, pitch_str, notation_system);
+            let (pitch_str, octave) = parse_octave_digit(chars, pitch_str, notation_system, scientific_pitch);
+            let tie = parse_tie_marker(chars);
 
             elements.push(BeatElement::Note(Note {
                 id: uuid::Uuid::new_v4(),
+                source: Some(source_span(input, pos, pos + pitch_str.len(), line_num, line_start_doc_index)),
                 value: Some(pitch_str),
                 pitch_code,
-                octave: 0, // Default octave, will be adjusted by spatial annotations
+                octave, // Scientific pitch octave digit, or 0 until adjusted by spatial annotations
                 notation_system,
                 numerator: None, // Will be populated by rhythm analysis
                 denominator: None, // Will be populated by rhythm analysis
+                divisi: None,
+                vibrato: false,
+                bend: None,
+                cautionary: false,
+                tie,
+                grace_notes: std::mem::take(&mut grace_notes),
+                lyrics: Vec::new(),
+                microtone,
+                articulations: Vec::new(),
+                ornament: None,
+                slur: None,
+                phrase: None,
             }));
         }
+        Some(&(_, ch)) if is_rest_start(ch, notation_system) => {
+            elements.extend(parse_rest(chars, line_num, input, line_start_doc_index));
+        }
         Some(&(pos, ch)) => {
             return Err(ParseError {
+                kind: ParseErrorKind::UnexpectedToken,
                 message: format!("Expected pitch or dash to start beat, found '{}'", ch),
                 line: line_num,
                 column: column_from_pos(input, pos),
+                suggestions: Vec::new(),
             });
         }
         None => {
             return Err(ParseError {
+                kind: ParseErrorKind::UnexpectedEnd,
                 message: "Unexpected end of input, expected pitch or dash to start beat".to_string(),
                 line: line_num,
                 column: 1,
+                suggestions: Vec::new(),
             });
         }
     }
 
+    // Note repetition shorthand applies to a single pitch
+    let mut repeat_count = None;
+    if matches!(elements.first(), Some(BeatElement::Note(_))) {
+        if let Some(&(star_pos, '*')) = chars.peek() {
+            chars.next();
+            let mut digits = String::new();
+            while let Some(&(_, ch)) = chars.peek() {
+                if !ch.is_ascii_digit() {
+                    break;
+                }
+                digits.push(ch);
+                chars.next();
+            }
+            match digits.parse::<usize>() {
+                Ok(count) if (1..=MAX_REPEAT_COUNT).contains(&count) => repeat_count = Some(count),
+                // Counts too long for a usize are over the limit as well
+                Ok(_) | Err(_) if !digits.trim_start_matches('0').is_empty() => {
+                    return Err(ParseError {
+                        kind: ParseErrorKind::UnexpectedToken,
+                        message: format!("Repeat count {} is more than {}", digits, MAX_REPEAT_COUNT),
+                        line: line_num,
+                        column: column_from_pos(input, star_pos + 1),
+                        suggestions: Vec::new(),
+                    });
+                }
+                _ => {
+                    return Err(ParseError {
+                        kind: ParseErrorKind::UnexpectedToken,
+                        message: format!("Expected repeat count after '*', found '{}'", digits),
+                        line: line_num,
+                        column: column_from_pos(input, star_pos + 1),
+                        suggestions: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+
     // Continue parsing beat-elements
-    loop {
+    while repeat_count.is_none() {
         match chars.peek() {
             // Beat terminators
             Some(&(_, ' ')) | Some(&(_, '|')) | Some(&(_, '\n')) | None => break,
@@ -83,6 +231,7 @@ pub fn parse_beat(
                     value: Some("-".to_string()),
                     numerator: None,
                     denominator: None,
+                    source: Some(source_span(input, pos, pos + 1, line_num, line_start_doc_index)),
                 }));
             }
 
@@ -92,31 +241,69 @@ pub fn parse_beat(
                 elements.push(BeatElement::BreathMark(BreathMark {
                     id: uuid::Uuid::new_v4(),
                     value: Some("'".to_string()),
+                    source: Some(source_span(input, pos, pos + 1, line_num, line_start_doc_index)),
                 }));
             }
 
+            // Grace notes leading into the next pitch; a malformed group ends the beat
+            // and is reported by the caller when it starts the next one
+            Some(&(_, '(')) => {
+                let mut group_chars = chars.clone();
+                match parse_grace_group(&mut group_chars, notation_system, scientific_pitch, line_num, input) {
+                    Ok(group) => {
+                        *chars = group_chars;
+                        grace_notes = group;
+                    }
+                    Err(_) => break,
+                }
+            }
+
             // Another pitch
             Some(&(pos, ch)) if is_pitch_start(ch, notation_system) => {
                 let (pitch_str, pitch_code) = parse_pitch_with_indices(chars, notation_system, line_num, input)?;
+                let (pitch_str, microtone) = parse_microtone(chars, pitch_str, notation_system);
+                let (pitch_str, octave) = parse_octave_digit(chars, pitch_str, notation_system, scientific_pitch);
+                let tie = parse_tie_marker(chars);
 
                 elements.push(BeatElement::Note(Note {
                     id: uuid::Uuid::new_v4(),
+                    source: Some(source_span(input, pos, pos + pitch_str.len(), line_num, line_start_doc_index)),
                     value: Some(pitch_str),
                         pitch_code,
-                    octave: 0,
+                    octave,
                     notation_system,
                     numerator: None, // Will be populated by rhythm analysis
-                    denominator: None // Will be populated by rhythm analysis
+                    denominator: None, // Will be populated by rhythm analysis
+                    divisi: None,
+                    vibrato: false,
+                    bend: None,
+                    cautionary: false,
+                    tie,
+                    grace_notes: std::mem::take(&mut grace_notes),
+                    lyrics: Vec::new(),
+                    microtone,
+                    articulations: Vec::new(),
+                    ornament: None,
+                    slur: None,
+                    phrase: None,
                 }));
             }
 
+            // Explicit rest
+            Some(&(_, ch)) if is_rest_start(ch, notation_system) => {
+                elements.extend(parse_rest(chars, line_num, input, line_start_doc_index));
+            }
+
             // Unknown character ends the beat
             Some(_) => break,
         }
     }
 
+    let beat_end_pos = chars.peek().map(|(pos, _)| *pos).unwrap_or(input.len());
+
     let beat = Beat {
         id: uuid::Uuid::new_v4(),
+        source: Some(source_span(input, beat_start_pos, beat_end_pos, line_num, line_start_doc_index)),
         elements,
         value: None, // Will be filled by caller if needed
         divisions: None,        // Will be populated by rhythm analysis
@@ -124,7 +311,140 @@ pub fn parse_beat(
         is_tuplet: None,        // Will be populated by rhythm analysis
         tuplet_ratio: None,     // Will be populated by rhythm analysis
         tied_to_previous: None, // Will be populated by rhythm analysis
+        repeat_count,
+        chord: None,
+        dynamic: None,
+        feather: None,
+        explicit_tuplet: None,
     };
 
     Ok(beat)
-}
\ No newline at end of file
+}
+/// Parse a beat with an explicit tuplet ratio:
+/// tuplet-beat = '[' count ':' count ' '* (beat-element ' '*)+ ']'
+///
+/// e.g. "[3:2 1 2 3]" plays three notes in the time of two. Every element inside the
+/// brackets is one subdivision of the beat; spaces between them don't end the beat.
+pub fn parse_tuplet_beat(
+    chars: &mut Peekable<CharIndices>,
+    notation_system: NotationSystem,
+    scientific_pitch: ScientificPitch,
+    line_num: usize,
+    input: &str,
+    line_start_doc_index: usize,
+) -> Result<Beat, ParseError> {
+    let beat_start_pos = chars.peek().map(|(pos, _)| *pos).unwrap_or(0);
+    let error = |kind: ParseErrorKind, message: String, pos: usize| ParseError {
+        kind,
+        message,
+        line: line_num,
+        column: column_from_pos(input, pos),
+        suggestions: Vec::new(),
+    };
+    chars.next_if(|(_, c)| *c == '[');
+
+    let mut ratio = [0usize; 2];
+    for (i, count) in ratio.iter_mut().enumerate() {
+        let pos = chars.peek().map(|(pos, _)| *pos).unwrap_or(input.len());
+        let mut digits = String::new();
+        while let Some((_, ch)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+            digits.push(ch);
+        }
+        *count = match digits.parse::<usize>() {
+            Ok(0) => return Err(error(ParseErrorKind::InvalidGroup, "Tuplet ratio can't contain zero".to_string(), pos)),
+            Ok(count) => count,
+            Err(_) => return Err(error(ParseErrorKind::InvalidGroup, format!("Expected tuplet ratio like '3:2' after '[', found '{}'", digits), pos)),
+        };
+        if i == 0 && chars.next_if(|(_, c)| *c == ':').is_none() {
+            let pos = chars.peek().map(|(pos, _)| *pos).unwrap_or(input.len());
+            return Err(error(ParseErrorKind::InvalidGroup, "Expected ':' in tuplet ratio".to_string(), pos));
+        }
+    }
+
+    let mut elements = Vec::new();
+    loop {
+        match chars.peek() {
+            Some(&(_, ' ')) => {
+                chars.next();
+            }
+            Some(&(_, ']')) => {
+                chars.next();
+                break;
+            }
+            Some(&(pos, '[')) => return Err(error(ParseErrorKind::InvalidGroup, "Tuplets can't be nested".to_string(), pos)),
+            Some(&(pos, '-')) => {
+                chars.next();
+                elements.push(BeatElement::Dash(Dash {
+                    id: uuid::Uuid::new_v4(),
+                    value: Some("-".to_string()),
+                    numerator: None,
+                    denominator: None,
+                    source: Some(source_span(input, pos, pos + 1, line_num, line_start_doc_index)),
+                }));
+            }
+            Some(&(pos, '\'')) => {
+                chars.next();
+                elements.push(BeatElement::BreathMark(BreathMark {
+                    id: uuid::Uuid::new_v4(),
+                    value: Some("'".to_string()),
+                    source: Some(source_span(input, pos, pos + 1, line_num, line_start_doc_index)),
+                }));
+            }
+            Some(&(pos, ch)) if is_pitch_start(ch, notation_system) => {
+                let (pitch_str, pitch_code) = parse_pitch_with_indices(chars, notation_system, line_num, input)?;
+                let (pitch_str, microtone) = parse_microtone(chars, pitch_str, notation_system);
+                let (pitch_str, octave) = parse_octave_digit(chars, pitch_str, notation_system, scientific_pitch);
+                let tie = parse_tie_marker(chars);
+                elements.push(BeatElement::Note(Note {
+                    id: uuid::Uuid::new_v4(),
+                    source: Some(source_span(input, pos, pos + pitch_str.len(), line_num, line_start_doc_index)),
+                    value: Some(pitch_str),
+                    pitch_code,
+                    octave,
+                    notation_system,
+                    numerator: None, // Will be populated by rhythm analysis
+                    denominator: None, // Will be populated by rhythm analysis
+                    divisi: None,
+                    vibrato: false,
+                    bend: None,
+                    cautionary: false,
+                    tie,
+                    grace_notes: Vec::new(),
+                    lyrics: Vec::new(),
+                    microtone,
+                    articulations: Vec::new(),
+                    ornament: None,
+                    slur: None,
+                    phrase: None,
+                }));
+            }
+            Some(&(_, ch)) if is_rest_start(ch, notation_system) => {
+                elements.extend(parse_rest(chars, line_num, input, line_start_doc_index));
+            }
+            Some(&(pos, '|')) | Some(&(pos, '\n')) => return Err(error(ParseErrorKind::UnterminatedGroup, "Unclosed tuplet, expected ']'".to_string(), pos)),
+            None => return Err(error(ParseErrorKind::UnterminatedGroup, "Unclosed tuplet, expected ']'".to_string(), input.len())),
+            Some(&(pos, ch)) => return Err(error(ParseErrorKind::UnexpectedToken, format!("Unexpected '{}' in tuplet", ch), pos)),
+        }
+    }
+    if !elements.iter().any(|e| matches!(e, BeatElement::Note(_) | BeatElement::Rest(_) | BeatElement::Dash(_))) {
+        return Err(error(ParseErrorKind::InvalidGroup, "Tuplet has no notes".to_string(), beat_start_pos));
+    }
+
+    let beat_end_pos = chars.peek().map(|(pos, _)| *pos).unwrap_or(input.len());
+    Ok(Beat {
+        id: uuid::Uuid::new_v4(),
+        source: Some(source_span(input, beat_start_pos, beat_end_pos, line_num, line_start_doc_index)),
+        elements,
+        value: Some(input[beat_start_pos..beat_end_pos].to_string()),
+        divisions: None,        // Will be populated by rhythm analysis
+        total_duration: None,   // Will be populated by rhythm analysis
+        is_tuplet: None,        // Will be populated by rhythm analysis
+        tuplet_ratio: None,     // Will be populated by rhythm analysis
+        tied_to_previous: None, // Will be populated by rhythm analysis
+        repeat_count: None,
+        chord: None,
+        dynamic: None,
+        feather: None,
+        explicit_tuplet: Some((ratio[0], ratio[1])),
+    })
+}
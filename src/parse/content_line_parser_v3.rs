@@ -1,7 +1,7 @@
 use crate::parse::model::{ContentLine, ContentElement, NotationSystem, Barline, SingleBarline, DoubleBarline, FinalBarline, RepeatStartBarline, RepeatEndBarline, RepeatBothBarline};
 use crate::parse::beat::parse_beat;
-use crate::parse::pitch::is_pitch_start;
-use crate::parse::ParseError;
+use crate::parse::pitch::{is_pitch_start, is_rest_start, ScientificPitch};
+use crate::parse::{ParseError, ParseErrorKind, source_span};
 use std::str::CharIndices;
 use std::iter::Peekable;
 
@@ -94,6 +94,7 @@ pub fn parse_content_line(
                 elements.push(ContentElement::Whitespace(crate::parse::model::Whitespace {
                     id: uuid::Uuid::new_v4(),
                     value: Some(whitespace_content),
+                    source: Some(source_span(input, start_pos, start_pos + space_count, line_num, line_start_doc_index)),
                 }));
             }
 
@@ -102,6 +103,7 @@ pub fn parse_content_line(
                 let beat = parse_beat(
                     &mut chars,
                     notation_system,
+                    ScientificPitch::default(),
                     line_num,
                     input,
                     line_start_doc_index,
@@ -110,11 +112,12 @@ pub fn parse_content_line(
                 elements.push(ContentElement::Beat(beat));
             }
 
-            ch if is_pitch_start(ch, notation_system) => {
+            ch if is_pitch_start(ch, notation_system) || is_rest_start(ch, notation_system) => {
                 // Parse beat
                 let beat = parse_beat(
                     &mut chars,
                     notation_system,
+                    ScientificPitch::default(),
                     line_num,
                     input,
                     line_start_doc_index,
@@ -142,6 +145,7 @@ pub fn parse_content_line(
                     let unknown_token = crate::parse::model::UnknownToken {
                         id: uuid::Uuid::new_v4(),
                         value: Some(token.clone()),
+                        source: Some(source_span(input, start_pos, start_pos + token.len(), line_num, line_start_doc_index)),
                             token_value: token,
                     };
 
@@ -219,22 +223,34 @@ fn parse_barline(
             }
         } else {
             return Err(ParseError {
+                kind: ParseErrorKind::MissingBarline,
                 message: "Expected '|' after ':' in barline".to_string(),
                 line: line_num,
                 column: column_from_pos(input, start_pos + 1),
-            });
+                suggestions: Vec::new(),
+            }.with_suggestion("did you forget a barline? A repeat is closed with ':|' and opened with '|:'"));
         }
     }
 
     let value = Some(barline_str.clone());
+    let id = uuid::Uuid::new_v4();
+    let source = Some(source_span(input, start_pos, start_pos + barline_str.len(), line_num, line_start_doc_index));
 
     // Create specific barline object based on pattern
     let barline = match barline_str.as_str() {
+        "|" => Barline::Single(SingleBarline { id, value, source }),
+        "||" => Barline::Double(DoubleBarline { id, value, source }),
+        "|." => Barline::Final(FinalBarline { id, value, source }),
+        "|:" => Barline::RepeatStart(RepeatStartBarline { id, value, source }),
+        ":|" => Barline::RepeatEnd(RepeatEndBarline { id, value, source }),
+        ":|:" | "|:|" => Barline::RepeatBoth(RepeatBothBarline { id, value, source }),
         _ => {
             return Err(ParseError {
+                kind: ParseErrorKind::InvalidBarline,
                 message: format!("Invalid barline pattern: {}", barline_str),
                 line: line_num,
                 column: column_from_pos(input, start_pos),
+                suggestions: Vec::new(),
             });
         }
     };
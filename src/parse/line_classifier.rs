@@ -2,8 +2,13 @@
 /// Solves ambiguous cases like single "1" by analyzing the whole document first
 
 use crate::parse::model::NotationSystem;
+use crate::document::divisi::{DIVISI_MARKER, UNISON_MARKER};
+use crate::document::line_parser::dynamics_line_parser::is_dynamic_mark;
+use crate::document::line_parser::slash_line_parser::is_slash_line;
+use crate::parse::pitch::SCIENTIFIC_PITCH_DIRECTIVE;
+use crate::rhythm::measures::multi_measure_rest_count;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LineType {
     Title,
     Directive,
@@ -12,9 +17,24 @@ pub enum LineType {
     Upper,
     Lower,
     Lyrics,
+    Articulation,
 }
 
 impl LineType {
+    /// Role name without the notation system, e.g. "content" or "lyrics"
+    pub fn role(&self) -> &'static str {
+        match self {
+            LineType::Title => "title",
+            LineType::Directive => "directive",
+            LineType::Text => "text",
+            LineType::Content(_) => "content",
+            LineType::Upper => "upper",
+            LineType::Lower => "lower",
+            LineType::Lyrics => "lyrics",
+            LineType::Articulation => "articulation",
+        }
+    }
+
     pub fn to_prefix(&self) -> String {
         match self {
             LineType::Title => "#title#".to_string(),
@@ -27,33 +47,259 @@ impl LineType {
                     NotationSystem::Western => "western",
                     NotationSystem::Bhatkhande => "bhatkhande",
                     NotationSystem::Tabla => "tabla",
+                    NotationSystem::Jianpu => "jianpu",
+                    NotationSystem::Scat => "scat",
                 };
                 format!("#content {}#", system_name)
             }
             LineType::Upper => "#upper#".to_string(),
             LineType::Lower => "#lower#".to_string(),
             LineType::Lyrics => "#lyrics#".to_string(),
+            LineType::Articulation => "#articulation#".to_string(),
         }
     }
 }
 
+/// Sigil starting an explicit line-type marker, e.g. "@lyrics do re mi"
+pub const LINE_TYPE_SIGIL: char = '@';
+
+/// Directive declaring the notation system instead of detecting it, e.g. "System: sargam"
+pub const SYSTEM_DIRECTIVE: &str = "System";
+
+/// Directive naming the title; a leading text line is then never taken as one
+pub const TITLE_DIRECTIVE: &str = "Title";
+/// Directive turning off reading a leading text line as the title (`TitleLine: off`)
+pub const TITLE_LINE_DIRECTIVE: &str = "TitleLine";
+
+/// Value of a `key: value` header line for the given key
+fn directive_value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let (name, value) = line.split_once(':')?;
+    name.trim().eq_ignore_ascii_case(key).then(|| value.trim())
+}
+
+/// Index of the line read as the title: the first line of the document, when it has
+/// words, is not a directive, stands on its own (a blank line, a directive or nothing after it)
+/// or is spaced out like a title, and is not directly above music it could be the
+/// lyrics of, nor a line with a line type marker. Never when a `Title:` directive is given
+/// or `TitleLine: off` is set.
+fn title_line_index(lines: &[&str], musical_line_indices: &[usize]) -> Option<usize> {
+    let declared = |key: &str| lines.iter().find_map(|line| directive_value(line, key));
+    if declared(TITLE_DIRECTIVE).is_some()
+        || declared(TITLE_LINE_DIRECTIVE).is_some_and(|value| matches!(value.to_lowercase().as_str(), "off" | "false" | "no")) {
+        return None;
+    }
+    let idx = lines.iter().position(|line| !line.trim().is_empty())?;
+    if musical_line_indices.contains(&idx) || is_directive_line(lines[idx]) || musical_line_indices.contains(&(idx + 1))
+        || explicit_line_type(lines[idx]).is_some()
+        || !lines[idx].chars().any(char::is_alphabetic) {
+        return None;
+    }
+    let standalone = lines.get(idx + 1).is_none_or(|next| next.trim().is_empty() || is_directive_line(next));
+    (standalone || is_spaced_title(lines[idx])).then_some(idx)
+}
+
+/// The document title: a `Title:` directive, otherwise the leading title line
+/// (up to any wide gap before a composer name)
+pub fn document_title(input: &str) -> Option<String> {
+    let lines: Vec<&str> = input.lines().collect();
+    if let Some(title) = lines.iter().find_map(|line| directive_value(line, TITLE_DIRECTIVE)) {
+        return Some(title.to_string());
+    }
+    let context = analyze_document_context(&lines);
+    let idx = title_line_index(&lines, &context.musical_line_indices)?;
+    lines[idx].split("   ").map(str::trim).find(|part| !part.is_empty()).map(str::to_string)
+}
+
+/// Notation system named by a `System: ...` directive line
+fn system_directive(line: &str) -> Option<NotationSystem> {
+    let (key, value) = line.split_once(':')?;
+    if !key.trim().eq_ignore_ascii_case(SYSTEM_DIRECTIVE) {
+        return None;
+    }
+    notation_system_named(value)
+}
+
+/// Notation system of a `System` directive value ("sargam", "Number", ...)
+pub fn notation_system_named(name: &str) -> Option<NotationSystem> {
+    match name.trim().to_lowercase().as_str() {
+        "number" => Some(NotationSystem::Number),
+        "sargam" => Some(NotationSystem::Sargam),
+        "western" => Some(NotationSystem::Western),
+        "bhatkhande" => Some(NotationSystem::Bhatkhande),
+        "tabla" => Some(NotationSystem::Tabla),
+        "jianpu" => Some(NotationSystem::Jianpu),
+        _ => None,
+    }
+}
+
+/// Declared notation system of each line. A `System:` directive before any music
+/// sets the system of the whole document (also returned on its own), in place of
+/// `system` given from outside the input; one after music sets it for the rest of its
+/// stave, up to the next blank line.
+fn declared_systems(lines: &[&str], first_music: Option<usize>, system: Option<NotationSystem>) -> (Option<NotationSystem>, Vec<Option<NotationSystem>>) {
+    let mut document_system = system;
+    let mut stave_system = None;
+    let mut declared = Vec::with_capacity(lines.len());
+    for (idx, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            stave_system = None;
+        }
+        if let Some(system) = system_directive(line) {
+            if first_music.is_none_or(|first| idx < first) {
+                document_system = Some(system);
+            } else {
+                stave_system = Some(system);
+            }
+        }
+        declared.push(stave_system.or(document_system));
+    }
+    (document_system, declared)
+}
+
+/// A line's role forced by a leading marker (`@content`, `@scat`, `@upper`, `@lower`, `@lyrics`,
+/// `@articulation` or `@text`), with the rest of the line after the marker and one space.
+/// Articulation lines are only ever marked, as their dots would read as octave dots, and so
+/// are scat lines, whose syllables would read as words.
+/// Content lines take their notation system from the document later.
+fn explicit_line_type(line: &str) -> Option<(LineType, &str)> {
+    let rest = line.strip_prefix(LINE_TYPE_SIGIL)?;
+    let (marker, text) = rest.split_once(' ').unwrap_or((rest, ""));
+    let line_type = match marker {
+        "content" => LineType::Content(NotationSystem::Number),
+        "scat" => LineType::Content(NotationSystem::Scat),
+        "upper" => LineType::Upper,
+        "lower" => LineType::Lower,
+        "lyrics" => LineType::Lyrics,
+        "articulation" => LineType::Articulation,
+        "text" => LineType::Text,
+        _ => return None,
+    };
+    Some((line_type, text))
+}
+
 pub fn classify_lines(input: &str) -> Vec<String> {
+    classify_line_types(input).into_iter()
+        .map(|(line_type, text)| format!("{} {}", line_type.to_prefix(), text))
+        .collect()
+}
+
+/// Type of each line of `input` along with its text (explicit markers stripped)
+pub fn classify_line_types(input: &str) -> Vec<(LineType, &str)> {
+    classify_with_scores(input, None).0
+}
+
+/// Line types of `input` read as if it opened with a `System:` directive for `system`
+pub fn classify_line_types_in(input: &str, system: Option<NotationSystem>) -> Vec<(LineType, &str)> {
+    classify_with_scores(input, system).0
+}
+
+/// Confidence scores of the notation systems the music of `input` could be read in,
+/// summing to 1 and most likely first. The first is the system content lines are
+/// classified in unless they declare their own: a `System:` directive for the whole
+/// document takes all the confidence.
+pub fn notation_system_scores(input: &str) -> Vec<(NotationSystem, f32)> {
+    classify_with_scores(input, None).1
+}
+
+/// Notation system scores of `input` read as if it opened with a `System:` directive for `system`
+pub fn notation_system_scores_in(input: &str, system: Option<NotationSystem>) -> Vec<(NotationSystem, f32)> {
+    classify_with_scores(input, system).1
+}
+
+/// Type of each line along with its text
+type ClassifiedLines<'a> = Vec<(LineType, &'a str)>;
+
+/// Line types of `input` with the notation system scores they were classified by,
+/// taking `system` as the document's declared system unless a directive names another
+fn classify_with_scores(input: &str, system: Option<NotationSystem>) -> (ClassifiedLines<'_>, Vec<(NotationSystem, f32)>) {
     let lines: Vec<&str> = input.lines().collect();
+    let explicit: Vec<Option<(LineType, &str)>> = lines.iter().map(|line| explicit_line_type(line)).collect();
+
+    // First pass: analyze document context. Forced content lines count as music;
+    // other forced lines, scat among them, are left out so they can't sway notation
+    // system detection.
+    let analyzed_lines: Vec<&str> = lines.iter().zip(&explicit)
+        .map(|(line, explicit)| match explicit {
+            Some((LineType::Content(system), text)) if *system != NotationSystem::Scat => *text,
+            Some(_) => "",
+            None => *line,
+        })
+        .collect();
+    let mut context = analyze_document_context(&analyzed_lines);
+    for (idx, explicit) in explicit.iter().enumerate() {
+        if matches!(explicit, Some((LineType::Content(system), _)) if *system != NotationSystem::Scat) && !context.musical_line_indices.contains(&idx) {
+            context.musical_line_indices.push(idx);
+            context.detected_notation_system.get_or_insert_with(|| detect_notation_system_from_lines(&[idx], &analyzed_lines));
+        }
+    }
 
-    // First pass: analyze document context
-    let context = analyze_document_context(&lines);
+    // Lines under a System directive are music only if they read as notes of that
+    // system, and they are left out of detection for the remaining lines
+    let (document_system, declared) = declared_systems(&analyzed_lines, context.musical_line_indices.iter().min().copied(), system);
+    for (idx, line) in analyzed_lines.iter().enumerate() {
+        let Some(system) = declared[idx] else { continue };
+        if matches!(explicit[idx], Some((LineType::Content(_), _))) {
+            continue;
+        }
+        let trimmed = line.trim();
+        let musical = trimmed.contains('|') || is_slash_line(trimmed)
+            || (!is_dynamics_under(idx, trimmed, &context.musical_line_indices) && is_musical_sequence_with(trimmed, |token| is_musical_note_in(token, system)));
+        context.musical_line_indices.retain(|&i| i != idx);
+        if musical {
+            context.musical_line_indices.push(idx);
+        }
+    }
+    context.musical_line_indices.sort_unstable();
+    let undeclared_music: Vec<usize> = context.musical_line_indices.iter().copied().filter(|&idx| declared[idx].is_none()).collect();
+    let scores = if let Some(system) = document_system {
+        vec![(system, 1.0)]
+    } else if !undeclared_music.is_empty() {
+        rank_notation_systems_of_lines(&undeclared_music, &analyzed_lines)
+    } else if let Some(system) = context.musical_line_indices.first().and_then(|&idx| declared[idx]) {
+        vec![(system, 1.0)]
+    } else {
+        Vec::new()
+    };
+    if let Some(&(system, _)) = scores.first() {
+        context.detected_notation_system = Some(system);
+    }
+    context.has_musical_content = !context.musical_line_indices.is_empty();
 
     // Second pass: classify each line with context
     let mut classified_lines = Vec::new();
     let mut in_header = true;
+    let title_line = title_line_index(&analyzed_lines, &context.musical_line_indices);
 
     for (idx, line) in lines.iter().enumerate() {
-        let line_type = classify_line(line, idx, &lines, &context, &mut in_header);
-        let prefix = line_type.to_prefix();
-        classified_lines.push(format!("{} {}", prefix, line));
+        let content_system = declared[idx].or(context.detected_notation_system).unwrap_or(NotationSystem::Number);
+        if let Some((line_type, text)) = &explicit[idx] {
+            let line_type = match line_type {
+                LineType::Content(NotationSystem::Scat) => LineType::Content(NotationSystem::Scat),
+                LineType::Content(_) => LineType::Content(content_system),
+                other => other.clone(),
+            };
+            classified_lines.push((line_type, *text));
+            continue;
+        }
+        if system_directive(line).is_some() {
+            classified_lines.push((LineType::Directive, *line));
+            continue;
+        }
+        let line_type = match classify_line(line, idx, &lines, &context, &mut in_header) {
+            LineType::Content(_) => LineType::Content(content_system),
+            LineType::Text | LineType::Title if title_line == Some(idx) => LineType::Title,
+            LineType::Title => LineType::Text,
+            other => other,
+        };
+        classified_lines.push((line_type, *line));
     }
 
-    classified_lines
+    (classified_lines, scores)
+}
+
+/// Notation system a single line looks like on its own, without document context
+pub fn detect_line_notation_system(line: &str) -> NotationSystem {
+    detect_notation_system_from_lines(&[0], &[line])
 }
 
 #[derive(Debug)]
@@ -65,6 +311,7 @@ struct DocumentContext {
 }
 
 fn analyze_document_context(lines: &[&str]) -> DocumentContext {
+    let scientific_pitch = scientific_pitch_on(lines);
     let mut has_musical_content = false;
     let mut musical_line_indices = Vec::new();
     let mut has_barlines = false;
@@ -81,8 +328,18 @@ fn analyze_document_context(lines: &[&str]) -> DocumentContext {
             continue;
         }
 
-        // Check for musical sequences (multiple notes with spaces)
-        if is_musical_sequence(trimmed) {
+        // Dynamics right under the music are marks, not notes ("p   f")
+        if is_dynamics_under(idx, trimmed, &musical_line_indices) {
+            continue;
+        }
+
+        // Check for musical sequences (multiple notes with spaces) and rhythm slashes
+        let musical = if scientific_pitch {
+            is_musical_sequence_with(trimmed, |token| is_musical_note(without_octave_digit(token)))
+        } else {
+            is_musical_sequence(trimmed)
+        };
+        if musical || is_slash_line(trimmed) {
             has_musical_content = true;
             musical_line_indices.push(idx);
         }
@@ -104,6 +361,15 @@ fn analyze_document_context(lines: &[&str]) -> DocumentContext {
 }
 
 fn is_musical_sequence(line: &str) -> bool {
+    is_musical_sequence_with(line, is_musical_note)
+}
+
+fn is_musical_sequence_with(line: &str, is_note: impl Fn(&str) -> bool) -> bool {
+    // A lone note repetition ("3*2") or multi-measure rest ("R*8") is unambiguous
+    if is_note_repetition(line.trim(), &is_note) || multi_measure_rest_count(line.trim()).is_some() {
+        return true;
+    }
+
     // Check if line looks like a sequence of musical notes
     if !line.contains(' ') {
         return false; // Single characters are ambiguous
@@ -116,12 +382,21 @@ fn is_musical_sequence(line: &str) -> bool {
 
     // Check if most tokens are musical notes
     let musical_tokens = tokens.iter()
-        .filter(|&token| is_musical_note(token))
+        .filter(|&token| is_note(token) || is_note_repetition(token, &is_note) || multi_measure_rest_count(token).is_some())
         .count();
 
     musical_tokens as f32 / tokens.len() as f32 > 0.7 // 70% threshold
 }
 
+/// Note repetition shorthand: a single pitch and a count ("1*4"), but not a
+/// multi-measure rest ("R*4")
+fn is_note_repetition(token: &str, is_note: impl Fn(&str) -> bool) -> bool {
+    multi_measure_rest_count(token).is_none()
+        && token.split_once('*').is_some_and(|(pitch, count)| {
+            !count.is_empty() && count.chars().all(|c| c.is_ascii_digit()) && !matches!(pitch, "-" | ",") && is_note(pitch)
+        })
+}
+
 fn is_musical_note(token: &str) -> bool {
     // Check for musical note patterns
     if token.is_empty() {
@@ -132,7 +407,7 @@ fn is_musical_note(token: &str) -> bool {
 
     // Number system: 1-7 (but only if it's just a number or has musical modifiers)
     if matches!(first_char, '1'..='7') {
-        return token.len() <= 3 && token.chars().all(|c| matches!(c, '1'..='7' | '#' | 'b' | '-'));
+        return token.len() <= 3 && token.chars().all(|c| matches!(c, '1'..='7' | '#' | 'b' | '-' | '+'));
     }
 
     // Sargam system: Single letter notes only
@@ -140,12 +415,12 @@ fn is_musical_note(token: &str) -> bool {
                               's' | 'r' | 'g' | 'm' | 'p' | 'd' | 'n') {
         return token.len() <= 3 && token.chars().all(|c| matches!(c, 'S' | 'R' | 'G' | 'M' | 'P' | 'D' | 'N' |
                                                                      's' | 'r' | 'g' | 'm' | 'p' | 'd' | 'n' |
-                                                                     '#' | 'b' | '-'));
+                                                                     '#' | 'b' | '-' | '+'));
     }
 
     // Western system: Single letter notes only
     if matches!(first_char, 'A'..='G' | 'a'..='g') {
-        return token.len() <= 3 && token.chars().all(|c| matches!(c, 'A'..='G' | 'a'..='g' | '#' | 'b' | '-'));
+        return token.len() <= 3 && token.chars().all(|c| matches!(c, 'A'..='G' | 'a'..='g' | '#' | '-' | '+'));
     }
 
     // Extensions: dashes, rests
@@ -156,16 +431,74 @@ fn is_musical_note(token: &str) -> bool {
     false
 }
 
+/// Whether `token` is a note, dash or rest of the given notation system
+fn is_musical_note_in(token: &str, system: NotationSystem) -> bool {
+    if token == "-" || token == "," {
+        return true;
+    }
+    let is_base: fn(char) -> bool = match system {
+        NotationSystem::Number | NotationSystem::Jianpu => |c| matches!(c, '1'..='7'),
+        NotationSystem::Sargam => |c| "SRGMPDNsrgmpdn".contains(c),
+        NotationSystem::Western => |c| matches!(c, 'A'..='G' | 'a'..='g'),
+        NotationSystem::Bhatkhande | NotationSystem::Tabla | NotationSystem::Scat => return is_musical_note(token),
+    };
+    // Western pitches may carry a scientific pitch octave digit ("C4")
+    let token = match system {
+        NotationSystem::Western => without_octave_digit(token),
+        _ => token,
+    };
+    token.len() <= 3
+        && token.chars().next().is_some_and(is_base)
+        && token.chars().all(|c| is_base(c) || matches!(c, '#' | 'b' | '-' | '+'))
+}
+
+/// Whether the header switches on scientific pitch names (`ScientificPitch: on`), so
+/// capital Western pitches may carry an octave digit whatever the notation system
+fn scientific_pitch_on(lines: &[&str]) -> bool {
+    lines.iter()
+        .filter_map(|line| directive_value(line, SCIENTIFIC_PITCH_DIRECTIVE))
+        .any(|value| matches!(value.to_lowercase().as_str(), "true" | "yes" | "on" | "1"))
+}
+
+/// A capital Western pitch without its scientific pitch octave digit ("C4" is "C")
+fn without_octave_digit(token: &str) -> &str {
+    if token.starts_with(|c: char| matches!(c, 'A'..='G')) {
+        token.strip_suffix(|c: char| matches!(c, '0'..='8')).unwrap_or(token)
+    } else {
+        token
+    }
+}
+
 fn detect_notation_system_from_lines(musical_indices: &[usize], lines: &[&str]) -> NotationSystem {
+    rank_notation_systems_of_lines(musical_indices, lines)[0].0
+}
+
+/// Notation systems the given lines could be written in, scored as by
+/// `rank_notation_systems`. When nothing reads as notes of any system, the system
+/// guessed from the characters present takes all the confidence.
+fn rank_notation_systems_of_lines(musical_indices: &[usize], lines: &[&str]) -> Vec<(NotationSystem, f32)> {
+    let scientific_pitch = scientific_pitch_on(lines);
     let mut musical_text = String::new();
 
     for &idx in musical_indices {
-        musical_text.push_str(lines[idx]);
-        musical_text.push(' ');
+        // Divisi markers are words and multi-measure rests ("R*8") rests, not pitches
+        let line = lines[idx].replace(DIVISI_MARKER, " ").replace(UNISON_MARKER, " ");
+        for token in line.split(|c: char| c.is_whitespace() || c == '|') {
+            if multi_measure_rest_count(token).is_none() {
+                musical_text.push_str(if scientific_pitch { without_octave_digit(token) } else { token });
+                musical_text.push(' ');
+            }
+        }
     }
 
-    // Detect based on character presence
-    if musical_text.chars().any(|c| matches!(c, 'S' | 'R' | 'G' | 'M' | 'P' | 'D' | 'N' |
+    // The system spelling out the most notes first
+    let ranked = crate::models::pitch_systems::rank_notation_systems(&musical_text);
+    if !ranked.is_empty() {
+        return ranked;
+    }
+
+    // Nothing reads as notes of any system: detect based on character presence
+    let system = if musical_text.chars().any(|c| matches!(c, 'S' | 'R' | 'G' | 'M' | 'P' | 'D' | 'N' |
                                                 's' | 'r' | 'g' | 'm' | 'p' | 'd' | 'n')) {
         NotationSystem::Sargam
     } else if musical_text.chars().any(|c| matches!(c, '1'..='7')) {
@@ -174,7 +507,8 @@ fn detect_notation_system_from_lines(musical_indices: &[usize], lines: &[&str])
         NotationSystem::Tabla
     } else {
         NotationSystem::Western
-    }
+    };
+    vec![(system, 1.0)]
 }
 
 fn classify_line(
@@ -213,6 +547,10 @@ fn classify_line(
     }
 
     // Annotation line classification (only after content)
+    if is_dynamics_line(trimmed) {
+        return LineType::Lower;
+    }
+
     if is_upper_annotation(trimmed) {
         return LineType::Upper;
     }
@@ -235,32 +573,36 @@ fn has_content_after_blank_line(blank_idx: usize, all_lines: &[&str], context: &
 }
 
 fn classify_header_line(line: &str, _context: &DocumentContext) -> LineType {
-    let trimmed = line.trim();
-
-    // Check for directive pattern (key: value) - must have colon
-    if let Some(colon_pos) = trimmed.find(':') {
-        let before_colon = trimmed[..colon_pos].trim();
-        // Make sure it's a simple identifier before the colon (not a complex title)
-        if before_colon.chars().all(|c| c.is_alphanumeric() || c == '_') && before_colon.len() <= 20 {
-            return LineType::Directive;
-        }
+    if is_directive_line(line) {
+        return LineType::Directive;
     }
 
-    // Check for title pattern (indented with large spacing)
-    if line.len() > 6 {
-        let leading_spaces = line.len() - line.trim_start().len();
-        if leading_spaces >= 3 {
-            let content = line.trim_start();
-            if has_large_internal_spacing(content, 3) {
-                return LineType::Title;
-            }
-        }
+    if is_spaced_title(line) {
+        return LineType::Title;
     }
 
     // Default to text for header
     LineType::Text
 }
 
+fn is_directive_line(line: &str) -> bool {
+    // Check for directive pattern (key: value) - must have colon
+    let trimmed = line.trim();
+    let Some(colon_pos) = trimmed.find(':') else { return false };
+    let before_colon = trimmed[..colon_pos].trim();
+    // Make sure it's a simple identifier before the colon (not a complex title)
+    before_colon.chars().all(|c| c.is_alphanumeric() || c == '_') && before_colon.len() <= 20
+}
+
+fn is_spaced_title(line: &str) -> bool {
+    // Check for title pattern (indented with large spacing)
+    if line.len() <= 6 {
+        return false;
+    }
+    let leading_spaces = line.len() - line.trim_start().len();
+    leading_spaces >= 3 && has_large_internal_spacing(line.trim_start(), 3)
+}
+
 fn has_large_internal_spacing(s: &str, min_spaces: usize) -> bool {
     let chars: Vec<char> = s.chars().collect();
     let mut space_count = 0;
@@ -290,6 +632,17 @@ fn is_lower_annotation(line: &str) -> bool {
     line.contains('.') || line.contains(':') || line.contains("__")
 }
 
+/// A line of nothing but dynamic marks, such as "mf  <   f"
+fn is_dynamics_line(line: &str) -> bool {
+    let mut marks = line.split_whitespace().peekable();
+    marks.peek().is_some() && marks.all(is_dynamic_mark)
+}
+
+/// Whether line `idx` is a dynamics line right under a line of music
+fn is_dynamics_under(idx: usize, line: &str, musical_line_indices: &[usize]) -> bool {
+    idx > 0 && musical_line_indices.contains(&(idx - 1)) && is_dynamics_line(line)
+}
+
 fn is_lyrics_line(line: &str) -> bool {
     // Lyrics: mostly alphabetic with hyphens/apostrophes
     line.split_whitespace()
@@ -314,6 +667,24 @@ mod tests {
         assert!(result[1].starts_with("#content number#"));
     }
 
+    #[test]
+    fn test_dynamics_line_under_notes_is_lower() {
+        for dynamics in ["f", "mf < f", "p       f", "pp  >"] {
+            let result = classify_lines(&format!("1 2 3 4 5\n{}", dynamics));
+            assert_eq!(result[1], format!("#lower# {}", dynamics));
+        }
+        // Without music above, the same letters stay Western notes
+        assert!(classify_lines("System: western\n\nf f f f")[2].starts_with("#content western#"));
+    }
+
+    #[test]
+    fn test_slash_line_is_content() {
+        let result = classify_lines("[C] [F]\n/ / / /");
+        assert_eq!(result[0], "#text# [C] [F]");
+        assert!(result[1].starts_with("#content "), "{:?}", result);
+        assert!(classify_lines("System: sargam\n\n/ - / /")[2].starts_with("#content sargam#"));
+    }
+
     #[test]
     fn test_classify_title_and_directive() {
         let input = "        Amazing Grace        Bach\nAuthor: John Newton\n\n|1 2 3 4|";
@@ -323,6 +694,11 @@ mod tests {
         assert_eq!(result[1], "#directive# Author: John Newton");
         assert_eq!(result[2], "#text# ");
         assert!(result[3].starts_with("#content number#"));
+
+        // Every directive the pipeline reads is classified as one
+        for key in crate::pipeline::KNOWN_DIRECTIVES {
+            assert_eq!(classify_lines(&format!("{}: on\n\n|1 2 3 4|", key))[0], format!("#directive# {}: on", key));
+        }
     }
 
     #[test]
@@ -335,4 +711,69 @@ mod tests {
         let result = classify_lines(number_input);
         assert!(result[0].contains("#content number#"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_explicit_line_type_overrides_classifier() {
+        let input = "|S R G M|\n@lyrics do re mi\n@lyrics S R G";
+        let result = classify_lines(input);
+        assert!(result[0].starts_with("#content sargam#"));
+        assert_eq!(result[1], "#lyrics# do re mi");
+        // Would otherwise be read as sargam notes
+        assert_eq!(result[2], "#lyrics# S R G");
+
+        // A forced content line sets the notation system like any other
+        let result = classify_lines("@content 1\n@text S R G");
+        assert_eq!(result, vec!["#content number# 1", "#text# S R G"]);
+
+        let result = classify_lines("1 2 3\n@articulation . > -");
+        assert_eq!(result[1], "#articulation# . > -");
+
+        // Scat lines keep their own system and leave detection to the other lines
+        let result = classify_lines("@scat ta ka di mi\n\nC D E F");
+        assert_eq!(result[0], "#content scat# ta ka di mi");
+        assert!(result[2].starts_with("#content western#"));
+    }
+
+    #[test]
+    fn test_system_directive_overrides_detection() {
+        // Letters are not notes of the number system
+        let result = classify_lines("System: number\n\nA B C");
+        assert_eq!(result[0], "#directive# System: number");
+        assert!(!result[2].starts_with("#content"), "{:?}", result);
+
+        let result = classify_lines("System: western\n\nA B C");
+        assert_eq!(result[2], "#content western# A B C");
+
+        // D, G and M would otherwise be detected as sargam
+        assert!(classify_lines("|D G M|")[0].starts_with("#content sargam#"));
+        assert!(classify_lines("System: western\n|D G M|")[1].starts_with("#content western#"));
+    }
+
+    #[test]
+    fn test_system_directive_per_stave() {
+        let result = classify_lines("|S R G|\n\nSystem: western\n|D E F|\n\n|D G|");
+        assert!(result[0].starts_with("#content sargam#"));
+        assert_eq!(result[2], "#directive# System: western");
+        assert!(result[3].starts_with("#content western#"));
+        assert!(result[5].starts_with("#content sargam#"));
+    }
+
+    #[test]
+    fn test_leading_line_title_detection() {
+        // Directly above music it could be the lyrics of
+        let result = classify_lines("Amazing grace how sweet\n|1 2 3 4|");
+        assert_eq!(result[0], "#text# Amazing grace how sweet");
+        assert_eq!(document_title("Amazing grace how sweet\n|1 2 3 4|"), None);
+
+        let result = classify_lines("Amazing Grace\n\n|1 2 3 4|");
+        assert_eq!(result[0], "#title# Amazing Grace");
+        assert_eq!(document_title("        Amazing Grace        Bach\n\n|1 2 3 4|").as_deref(), Some("Amazing Grace"));
+
+        // The directive always wins
+        let input = "Amazing Grace\nTitle: New Britain\n\n|1 2 3 4|";
+        assert_eq!(classify_lines(input)[0], "#text# Amazing Grace");
+        assert_eq!(document_title(input).as_deref(), Some("New Britain"));
+
+        assert_eq!(classify_lines("Amazing Grace\nTitleLine: off\n\n|1 2 3 4|")[0], "#text# Amazing Grace");
+    }
+}
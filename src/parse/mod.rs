@@ -14,18 +14,90 @@ pub mod line_classifier;
 pub mod pitch;
 pub mod beat;
 pub mod html;
+pub mod normalize;
 
 // Re-export key types and functions for convenience
 pub use model::{Document, Directive, Stave, ContentLine, ContentElement, TextLine, PitchCode, NotationSystem, WhitespaceLine, Beat, BeatElement, Note, Dash, BreathMark};
 pub use pitch::{parse_pitch, is_pitch_start};
 pub use beat::parse_beat;
 
+/// What went wrong in a `ParseError`, for tools that match on errors rather than messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// A ':' that doesn't make a repeat barline, usually a barline left out (e.g. "2 :3")
+    MissingBarline,
+    InvalidBarline,
+    /// A pitch that doesn't exist in the notation system
+    InvalidPitch,
+    UnexpectedToken,
+    UnexpectedEnd,
+    /// Grace notes or a tuplet opened and never closed
+    UnterminatedGroup,
+    /// Grace notes or a tuplet that are empty, nested or have a bad ratio
+    InvalidGroup,
+}
+
+impl ParseErrorKind {
+    /// Stable machine-readable code, e.g. "missing_barline"
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseErrorKind::MissingBarline => "missing_barline",
+            ParseErrorKind::InvalidBarline => "invalid_barline",
+            ParseErrorKind::InvalidPitch => "invalid_pitch",
+            ParseErrorKind::UnexpectedToken => "unexpected_token",
+            ParseErrorKind::UnexpectedEnd => "unexpected_end",
+            ParseErrorKind::UnterminatedGroup => "unterminated_group",
+            ParseErrorKind::InvalidGroup => "invalid_group",
+        }
+    }
+}
+
 // ParseError is defined below
 #[derive(Debug)]
 pub struct ParseError {
+    pub kind: ParseErrorKind,
     pub message: String,
     pub line: usize,
     pub column: usize,
+    pub suggestions: Vec<String>, // Hints for fixing the input, e.g. a missing barline
+}
+
+impl ParseError {
+    pub fn with_suggestion<S: Into<String>>(mut self, suggestion: S) -> Self {
+        self.suggestions.push(suggestion.into());
+        self
+    }
+
+    /// Rustc-style diagnostic: the message, the offending line of `source` with a caret
+    /// under the error column, then any suggestions
+    ///
+    /// ```text
+    /// error: Expected '|' after ':' in barline
+    ///   --> line 1, column 6
+    ///   |
+    /// 1 | 1 2 :3 4
+    ///   |      ^
+    ///   = help: did you forget a barline? ...
+    /// ```
+    pub fn render_pretty(&self, source: &str) -> String {
+        let mut output = format!("error: {}\n", self.message);
+        let line_number = self.line.max(1);
+        let gutter = " ".repeat(line_number.to_string().len());
+        output.push_str(&format!("{} --> line {}, column {}\n", gutter, line_number, self.column));
+        if let Some(text) = source.lines().nth(line_number - 1) {
+            let offset: String = text.chars()
+                .take(self.column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            output.push_str(&format!("{} |\n", gutter));
+            output.push_str(&format!("{} | {}\n", line_number, text));
+            output.push_str(&format!("{} | {}^\n", gutter, offset));
+        }
+        for suggestion in &self.suggestions {
+            output.push_str(&format!("{} = help: {}\n", gutter, suggestion));
+        }
+        output
+    }
 }
 
 impl std::fmt::Display for ParseError {
@@ -42,6 +114,16 @@ impl From<ParseError> for String {
     }
 }
 
+/// Build the `Source` location for the text between byte offsets `start` and `end` of `input`
+pub fn source_span(input: &str, start: usize, end: usize, line: usize, line_start_doc_index: usize) -> model::Source {
+    model::Source {
+        line,
+        column: input[..start].chars().rev().take_while(|&c| c != '\n').count() + 1,
+        char_index: line_start_doc_index + input[..start].chars().count(),
+        length: input[start..end].chars().count(),
+    }
+}
+
 /// Trait for elements that have a unique identifier
 pub trait HasId {
     fn id(&self) -> &Uuid;
@@ -1,12 +1,18 @@
 
 // Re-export domain models from the models crate for convenience
 pub use crate::models::*;
+use crate::parse::HasId;
+use crate::rhythm::measures::MeasureCounter;
+use fraction::Fraction;
+use uuid::Uuid;
 
 /// Trait for elements that have position and value information
 /// This trait is used by the parsing infrastructure to work with parsed elements uniformly
 pub trait HasPosition {
     fn value(&self) -> Option<&String>;
     fn type_name(&self) -> &'static str;
+    /// Source location recorded by the parser (None for elements created by edits)
+    fn source(&self) -> Option<&Source>;
 }
 
 // Implementations for enum variants
@@ -21,6 +27,15 @@ fn value(&self) -> Option<&String> {
         }
     }
 
+    fn source(&self) -> Option<&Source> {
+        match self {
+            BeatElement::Note(note) => note.source.as_ref(),
+            BeatElement::Dash(dash) => dash.source.as_ref(),
+            BeatElement::BreathMark(breath) => breath.source.as_ref(),
+            BeatElement::Rest(rest) => rest.source.as_ref(),
+        }
+    }
+
 
     fn type_name(&self) -> &'static str {
         match self {
@@ -45,6 +60,17 @@ fn value(&self) -> Option<&String> {
         }
     }
 
+    fn source(&self) -> Option<&Source> {
+        match self {
+            Barline::Single(b) => b.source.as_ref(),
+            Barline::Double(b) => b.source.as_ref(),
+            Barline::Final(b) => b.source.as_ref(),
+            Barline::RepeatStart(b) => b.source.as_ref(),
+            Barline::RepeatEnd(b) => b.source.as_ref(),
+            Barline::RepeatBoth(b) => b.source.as_ref(),
+        }
+    }
+
 
     fn type_name(&self) -> &'static str {
         match self {
@@ -63,9 +89,430 @@ fn value(&self) -> Option<&String> {
         self.value.as_ref()
     }
 
+    fn source(&self) -> Option<&Source> {
+        self.source.as_ref()
+    }
+
 
     fn type_name(&self) -> &'static str {
         "Beat"
     }
 }
 
+impl HasPosition for ContentElement {
+    fn value(&self) -> Option<&String> {
+        match self {
+            ContentElement::Barline(barline) => barline.value(),
+            ContentElement::Whitespace(ws) => ws.value.as_ref(),
+            ContentElement::Beat(beat) => beat.value(),
+            ContentElement::UnknownToken(token) => token.value.as_ref(),
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            ContentElement::Barline(barline) => barline.type_name(),
+            ContentElement::Whitespace(_) => "Whitespace",
+            ContentElement::Beat(beat) => beat.type_name(),
+            ContentElement::UnknownToken(_) => "UnknownToken",
+        }
+    }
+
+    fn source(&self) -> Option<&Source> {
+        match self {
+            ContentElement::Barline(barline) => barline.source(),
+            ContentElement::Whitespace(ws) => ws.source.as_ref(),
+            ContentElement::Beat(beat) => beat.source(),
+            ContentElement::UnknownToken(token) => token.source.as_ref(),
+        }
+    }
+}
+
+impl HasId for Note {
+    fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    fn set_id(&mut self, id: Uuid) {
+        self.id = id;
+    }
+}
+
+impl HasId for Beat {
+    fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    fn set_id(&mut self, id: Uuid) {
+        self.id = id;
+    }
+}
+
+impl HasId for Barline {
+    fn id(&self) -> &Uuid {
+        match self {
+            Barline::Single(b) => &b.id,
+            Barline::Double(b) => &b.id,
+            Barline::Final(b) => &b.id,
+            Barline::RepeatStart(b) => &b.id,
+            Barline::RepeatEnd(b) => &b.id,
+            Barline::RepeatBoth(b) => &b.id,
+        }
+    }
+
+    fn set_id(&mut self, id: Uuid) {
+        match self {
+            Barline::Single(b) => b.id = id,
+            Barline::Double(b) => b.id = id,
+            Barline::Final(b) => b.id = id,
+            Barline::RepeatStart(b) => b.id = id,
+            Barline::RepeatEnd(b) => b.id = id,
+            Barline::RepeatBoth(b) => b.id = id,
+        }
+    }
+}
+
+/// Element of a document found by its id with [`Document::find_by_id`]
+#[derive(Debug, Clone, Copy)]
+pub enum ElementRef<'a> {
+    Note(&'a Note),
+    Beat(&'a Beat),
+    Barline(&'a Barline),
+}
+
+/// Element of a document found by its id with [`Document::find_by_id_mut`]
+#[derive(Debug)]
+pub enum ElementMut<'a> {
+    Note(&'a mut Note),
+    Beat(&'a mut Beat),
+    Barline(&'a mut Barline),
+}
+
+impl ContentLine {
+    /// Notes of every beat of the line, in order
+    pub fn notes_mut(&mut self) -> impl Iterator<Item = &mut Note> {
+        self.elements.iter_mut()
+            .filter_map(|element| match element {
+                ContentElement::Beat(beat) => Some(beat),
+                _ => None,
+            })
+            .flat_map(|beat| beat.elements.iter_mut())
+            .filter_map(|element| match element {
+                BeatElement::Note(note) => Some(note),
+                _ => None,
+            })
+    }
+}
+
+impl Stave {
+    /// Number of measures holding at least one beat. Barlines end measures, so music
+    /// without barlines is one measure and a stave without beats has none; leading,
+    /// trailing and doubled barlines add no empty measures.
+    pub fn measure_count(&self) -> usize {
+        let mut counter = MeasureCounter::default();
+        let mut last_measure = 0;
+        for element in self.content_elements() {
+            match element {
+                ContentElement::Beat(_) => last_measure = counter.beat(),
+                ContentElement::Barline(_) => counter.barline(),
+                _ => {}
+            }
+        }
+        last_measure
+    }
+
+    /// Number of beats, counting a repeated note ("1*4") as the beats it stands for
+    pub fn beat_count(&self) -> usize {
+        self.content_elements()
+            .map(|element| match element {
+                ContentElement::Beat(beat) => beat.repeat_count.unwrap_or(1),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Length of the stave in quarter notes, summing the beat durations found by rhythm
+    /// analysis; a beat that wasn't analyzed counts as a quarter note
+    pub fn duration_in_beats(&self) -> Fraction {
+        self.content_elements()
+            .map(|element| match element {
+                ContentElement::Beat(beat) => {
+                    let duration = beat.total_duration.unwrap_or(Fraction::new(1u64, 4u64));
+                    duration * Fraction::from(4 * beat.repeat_count.unwrap_or(1) as u64)
+                }
+                _ => Fraction::from(0),
+            })
+            .sum()
+    }
+
+    /// Elements of every content line of the stave
+    fn content_elements(&self) -> impl Iterator<Item = &ContentElement> {
+        self.lines.iter()
+            .filter_map(|line| match line {
+                StaveLine::ContentLine(content_line) => Some(content_line),
+                _ => None,
+            })
+            .flat_map(|content_line| &content_line.elements)
+    }
+}
+
+impl Document {
+    /// The note, beat or barline with the given id, in any stave of the document
+    pub fn find_by_id(&self, id: &Uuid) -> Option<ElementRef<'_>> {
+        self.content_elements().find_map(|element| match element {
+            ContentElement::Beat(beat) if beat.id() == id => Some(ElementRef::Beat(beat)),
+            ContentElement::Beat(beat) => beat.elements.iter().find_map(|beat_element| match beat_element {
+                BeatElement::Note(note) if note.id() == id => Some(ElementRef::Note(note)),
+                _ => None,
+            }),
+            ContentElement::Barline(barline) if barline.id() == id => Some(ElementRef::Barline(barline)),
+            _ => None,
+        })
+    }
+
+    /// Mutable counterpart of [`Document::find_by_id`]
+    pub fn find_by_id_mut(&mut self, id: &Uuid) -> Option<ElementMut<'_>> {
+        self.content_elements_mut().find_map(|element| match element {
+            ContentElement::Beat(beat) => {
+                if beat.id() == id {
+                    Some(ElementMut::Beat(beat))
+                } else {
+                    beat.elements.iter_mut().find_map(|beat_element| match beat_element {
+                        BeatElement::Note(note) if note.id() == id => Some(ElementMut::Note(note)),
+                        _ => None,
+                    })
+                }
+            }
+            ContentElement::Barline(barline) if barline.id() == id => Some(ElementMut::Barline(barline)),
+            _ => None,
+        })
+    }
+
+    /// Measures in all staves together, see [`Stave::measure_count`]
+    pub fn measure_count(&self) -> usize {
+        self.staves().map(Stave::measure_count).sum()
+    }
+
+    /// Beats in all staves together, see [`Stave::beat_count`]
+    pub fn beat_count(&self) -> usize {
+        self.staves().map(Stave::beat_count).sum()
+    }
+
+    /// Length of all staves together in quarter notes, see [`Stave::duration_in_beats`]
+    pub fn duration_in_beats(&self) -> Fraction {
+        self.staves().map(Stave::duration_in_beats).sum()
+    }
+
+    fn staves(&self) -> impl Iterator<Item = &Stave> {
+        self.elements.iter().filter_map(|element| match element {
+            DocumentElement::Stave(stave) => Some(stave),
+            _ => None,
+        })
+    }
+
+    /// Elements of every content line, stave by stave
+    fn content_elements(&self) -> impl Iterator<Item = &ContentElement> {
+        self.staves().flat_map(Stave::content_elements)
+    }
+
+    fn content_elements_mut(&mut self) -> impl Iterator<Item = &mut ContentElement> {
+        self.elements.iter_mut()
+            .filter_map(|element| match element {
+                DocumentElement::Stave(stave) => Some(stave),
+                _ => None,
+            })
+            .flat_map(|stave| &mut stave.lines)
+            .filter_map(|line| match line {
+                StaveLine::ContentLine(content_line) => Some(content_line),
+                _ => None,
+            })
+            .flat_map(|content_line| &mut content_line.elements)
+    }
+
+    /// All notes in the document, stave by stave and left to right within each content line.
+    /// Dashes, rests and breath marks are skipped.
+    ///
+    /// ```
+    /// use music_text::document::line_parser::content_line_parser::unused_parse_content_line;
+    /// use music_text::parse::model::{Document, DocumentElement, NotationSystem, PitchCode, Stave, StaveLine};
+    ///
+    /// let stave = |text: &str, line: usize| DocumentElement::Stave(Stave {
+    ///     id: uuid::Uuid::new_v4(),
+    ///     value: Some(text.to_string()),
+    ///     notation_system: NotationSystem::Number,
+    ///     line,
+    ///     column: 0,
+    ///     index_in_line: 0,
+    ///     index_in_doc: 0,
+    ///     clef: None,
+    ///     grand_staff: None,
+    ///     lines: vec![StaveLine::ContentLine(
+    ///         unused_parse_content_line(text, line, NotationSystem::Number, 0).unwrap(),
+    ///     )],
+    /// });
+    /// let document = Document {
+    ///     elements: vec![stave("1- 2", 1), stave("3 -", 3)],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let pitches: Vec<PitchCode> = document.notes().map(|note| note.pitch_code).collect();
+    /// assert_eq!(pitches, vec![PitchCode::N1, PitchCode::N2, PitchCode::N3]);
+    /// ```
+    pub fn notes(&self) -> impl Iterator<Item = &Note> {
+        self.elements.iter()
+            .filter_map(|element| match element {
+                DocumentElement::Stave(stave) => Some(stave),
+                _ => None,
+            })
+            .flat_map(|stave| &stave.lines)
+            .filter_map(|line| match line {
+                StaveLine::ContentLine(content_line) => Some(content_line),
+                _ => None,
+            })
+            .flat_map(|content_line| &content_line.elements)
+            .filter_map(|element| match element {
+                ContentElement::Beat(beat) => Some(beat),
+                _ => None,
+            })
+            .flat_map(|beat| &beat.elements)
+            .filter_map(|element| match element {
+                BeatElement::Note(note) => Some(note),
+                _ => None,
+            })
+    }
+
+    /// Notation text of the document: the title and directives, then its staves and
+    /// blank lines in order. Content lines are rebuilt from their elements, each placed
+    /// at the column it was parsed from; annotation and lyrics lines are kept as written.
+    /// Parsing the result with `pipeline::document_from_notation` gives the same document.
+    /// Directives come out sorted by key and every line ends with a newline.
+    pub fn to_plain_text(&self) -> String {
+        use crate::document::line_parser::content_line_parser::element_to_source;
+
+        let mut text = String::new();
+        let titled_by_directive = directive(&self.directives, crate::parse::line_classifier::TITLE_DIRECTIVE).is_some();
+        if let (Some(title), false) = (&self.title, titled_by_directive) {
+            text.push_str(title);
+            text.push('\n');
+        }
+        let mut directives: Vec<(&String, &String)> = self.directives.iter().collect();
+        directives.sort();
+        for (key, value) in directives {
+            text.push_str(&format!("{}: {}\n", key, value));
+        }
+
+        for element in &self.elements {
+            let stave = match element {
+                DocumentElement::Stave(stave) => stave,
+                DocumentElement::BlankLines(blank_lines) => {
+                    text.push_str(blank_lines.value.as_deref().unwrap_or("\n"));
+                    continue;
+                }
+            };
+            for line in &stave.lines {
+                let value = match line {
+                    StaveLine::ContentLine(content_line) => {
+                        let mut columns = String::new();
+                        for content_element in &content_line.elements {
+                            if let Some(source) = content_element.source() {
+                                let width = columns.chars().count();
+                                columns.extend(std::iter::repeat_n(' ', source.column.saturating_sub(1).saturating_sub(width)));
+                            }
+                            columns.push_str(&element_to_source(content_element));
+                        }
+                        columns
+                    }
+                    StaveLine::Text(text_line) => text_line.value.clone().unwrap_or_default(),
+                    StaveLine::Lyrics(lyrics_line) => lyrics_line.value.clone().unwrap_or_default(),
+                    StaveLine::Whitespace(whitespace_line) => whitespace_line.value.clone().unwrap_or_default(),
+                    StaveLine::BlankLines(blank_lines) => {
+                        text.push_str(blank_lines.value.as_deref().unwrap_or("\n"));
+                        continue;
+                    }
+                    StaveLine::Content(_) => continue,
+                };
+                text.push_str(&value);
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    /// Mutable counterpart of [`Document::notes`], visiting the same notes in the same order
+    pub fn notes_mut(&mut self) -> impl Iterator<Item = &mut Note> {
+        self.elements.iter_mut()
+            .filter_map(|element| match element {
+                DocumentElement::Stave(stave) => Some(stave),
+                _ => None,
+            })
+            .flat_map(|stave| &mut stave.lines)
+            .filter_map(|line| match line {
+                StaveLine::ContentLine(content_line) => Some(content_line),
+                _ => None,
+            })
+            .flat_map(|content_line| &mut content_line.elements)
+            .filter_map(|element| match element {
+                ContentElement::Beat(beat) => Some(beat),
+                _ => None,
+            })
+            .flat_map(|beat| &mut beat.elements)
+            .filter_map(|element| match element {
+                BeatElement::Note(note) => Some(note),
+                _ => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::document_from_notation;
+
+    #[test]
+    fn test_find_by_id_in_two_staves() {
+        let mut document = document_from_notation("|1 2 3 4|\n\n|5 6 7 1|\n").unwrap();
+        let note = document.notes().nth(5).expect("second stave's second note");
+        let (note_id, pitch_code) = (note.id, note.pitch_code);
+        assert_eq!(pitch_code, PitchCode::N6);
+
+        match document.find_by_id(&note_id) {
+            Some(ElementRef::Note(found)) => assert_eq!(found.pitch_code, PitchCode::N6),
+            other => panic!("expected note, found {:?}", other),
+        }
+
+        let barline_id = *document.content_elements()
+            .find_map(|element| match element {
+                ContentElement::Barline(barline) => Some(barline.id()),
+                _ => None,
+            })
+            .expect("barline");
+        assert!(matches!(document.find_by_id(&barline_id), Some(ElementRef::Barline(_))));
+        assert!(document.find_by_id(&Uuid::new_v4()).is_none());
+
+        if let Some(ElementMut::Note(note)) = document.find_by_id_mut(&note_id) {
+            note.pitch_code = PitchCode::N2;
+        }
+        assert_eq!(document.notes().nth(5).map(|note| note.pitch_code), Some(PitchCode::N2));
+    }
+
+    #[test]
+    fn test_measure_and_beat_counts() {
+        let document = document_from_notation("1 2 | 3 4 | 5\n").unwrap();
+        assert_eq!(document.measure_count(), 3);
+        assert_eq!(document.beat_count(), 5);
+        assert_eq!(document.duration_in_beats(), Fraction::from(5));
+
+        // Unbarred music is one measure; extra barlines add no empty ones
+        assert_eq!(document_from_notation("1 2 3\n").unwrap().measure_count(), 1);
+        assert_eq!(document_from_notation("|1 2 || 3 4|\n").unwrap().measure_count(), 2);
+
+        // Counts add up over staves, and beat durations follow the beat unit
+        let document = document_from_notation("BeatUnit: 1/8\n\n|1 2 3|\n\n|4 5 6|\n").unwrap();
+        assert_eq!((document.measure_count(), document.beat_count()), (2, 6));
+        assert_eq!(document.duration_in_beats(), Fraction::from(3));
+
+        let empty = Document::default();
+        assert_eq!((empty.measure_count(), empty.beat_count()), (0, 0));
+        assert_eq!(empty.duration_in_beats(), Fraction::from(0));
+    }
+}
@@ -0,0 +1,138 @@
+// Unicode normalization of notation text before parsing
+// Text pasted from other editors may spell an accented letter or a nukta consonant
+// as a base character plus a combining mark (NFD). Columns are counted in characters,
+// so such a pair would push everything after it one column to the right of where it
+// appears; composing it to the single NFC character keeps annotation lines aligned.
+// This is not a full NFC implementation: only the pairs in `COMPOSITIONS` are composed.
+
+use std::borrow::Cow;
+
+/// Directive turning normalization off (`UnicodeNormalization: off`); `nfc` is the default
+pub const UNICODE_NORMALIZATION_DIRECTIVE: &str = "UnicodeNormalization";
+
+/// The limited composition table: combining marks with the characters they compose
+/// with, (mark, [(base, composed)]). Pairs not listed are left decomposed.
+const COMPOSITIONS: &[(char, &[(char, char)])] = &[
+    ('\u{300}', &[('A', 'À'), ('E', 'È'), ('I', 'Ì'), ('O', 'Ò'), ('U', 'Ù'), ('a', 'à'), ('e', 'è'), ('i', 'ì'), ('o', 'ò'), ('u', 'ù')]),
+    ('\u{301}', &[('A', 'Á'), ('E', 'É'), ('I', 'Í'), ('O', 'Ó'), ('U', 'Ú'), ('Y', 'Ý'), ('a', 'á'), ('e', 'é'), ('i', 'í'), ('o', 'ó'), ('u', 'ú'), ('y', 'ý')]),
+    ('\u{302}', &[('A', 'Â'), ('E', 'Ê'), ('I', 'Î'), ('O', 'Ô'), ('U', 'Û'), ('a', 'â'), ('e', 'ê'), ('i', 'î'), ('o', 'ô'), ('u', 'û')]),
+    ('\u{303}', &[('A', 'Ã'), ('N', 'Ñ'), ('O', 'Õ'), ('a', 'ã'), ('n', 'ñ'), ('o', 'õ')]),
+    ('\u{308}', &[('A', 'Ä'), ('E', 'Ë'), ('I', 'Ï'), ('O', 'Ö'), ('U', 'Ü'), ('a', 'ä'), ('e', 'ë'), ('i', 'ï'), ('o', 'ö'), ('u', 'ü'), ('y', 'ÿ')]),
+    ('\u{30A}', &[('A', 'Å'), ('a', 'å')]),
+    ('\u{327}', &[('C', 'Ç'), ('c', 'ç')]),
+    // Devanagari nukta; the other nukta letters (क़ ख़ ग़ ...) stay decomposed in NFC
+    ('\u{93C}', &[('न', 'ऩ'), ('र', 'ऱ'), ('ळ', 'ऴ')]),
+];
+
+/// Compose the base character and combining mark pairs listed in `COMPOSITIONS` into
+/// their NFC characters: the Latin accents and Devanagari nukta letters that occur in
+/// lyrics and sargam. Other text, including other decomposed pairs, is left as it is.
+pub fn compose_listed_marks(input: &str) -> Cow<'_, str> {
+    if input.is_ascii() {
+        return Cow::Borrowed(input);
+    }
+    let mut output = String::with_capacity(input.len());
+    let mut changed = false;
+    for ch in input.chars() {
+        let composed = output.chars().next_back().and_then(|base| compose(base, ch));
+        match composed {
+            Some(composed) => {
+                output.pop();
+                output.push(composed);
+                changed = true;
+            }
+            None => output.push(ch),
+        }
+    }
+    if changed { Cow::Owned(output) } else { Cow::Borrowed(input) }
+}
+
+fn compose(base: char, mark: char) -> Option<char> {
+    let (_, pairs) = COMPOSITIONS.iter().find(|(m, _)| *m == mark)?;
+    pairs.iter().find(|(b, _)| *b == base).map(|(_, composed)| *composed)
+}
+
+/// Notation text ready for parsing, with the listed marks composed unless a directive
+/// line turns it off. `pipeline::document_from_notation` applies it to its input.
+pub fn normalize_input(input: &str) -> Cow<'_, str> {
+    let disabled = input.lines().any(|line| {
+        line.split_once(':').is_some_and(|(key, value)| {
+            key.trim().eq_ignore_ascii_case(UNICODE_NORMALIZATION_DIRECTIVE)
+                && matches!(value.trim().to_lowercase().as_str(), "off" | "none" | "false")
+        })
+    });
+    if disabled { Cow::Borrowed(input) } else { compose_listed_marks(input) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::ornaments::apply_ornaments;
+    use crate::document::octaves::apply_octave_markers;
+    use crate::document::line_parser::content_line_parser::unused_parse_content_line;
+    use crate::parse::model::{BeatElement, ContentElement, NotationSystem};
+
+    /// Pitch, column and whether it has an ornament, for every note of the content line under the upper line
+    fn parse(text: &str) -> Vec<(Option<String>, usize, bool)> {
+        let (upper, content) = text.split_once('\n').unwrap();
+        let mut line = unused_parse_content_line(content, 2, NotationSystem::Bhatkhande, 0).unwrap();
+        apply_ornaments(&mut line, upper);
+        line.elements.iter()
+            .filter_map(|element| match element {
+                ContentElement::Beat(beat) => Some(beat.elements.iter()),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|element| match element {
+                BeatElement::Note(note) => Some((note.value.clone(), note.source.unwrap().column, note.ornament.is_some())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_nfd_input_parses_like_nfc() {
+        let nfc = "ऱé   ~\n|स ग म प|";
+        let nfd = "र\u{93C}e\u{301}   ~\n|स ग म प|";
+        assert_ne!(nfc, nfd);
+        assert_eq!(normalize_input(nfd), nfc);
+
+        let notes = parse(&normalize_input(nfd));
+        assert_eq!(notes, parse(nfc));
+        // The mordent stays over म; unnormalized, the two combining marks move it off
+        assert_eq!(notes.iter().filter(|(_, _, ornament)| *ornament).collect::<Vec<_>>(), vec![&(Some("म".to_string()), 6, true)]);
+        assert!(parse(nfd).iter().all(|(_, _, ornament)| !ornament));
+        // The pipeline reads documents from the normalized text
+        let document = crate::pipeline::document_from_notation(nfd).unwrap();
+        assert_eq!(document.value.as_deref(), Some(nfc));
+
+        let disabled = format!("UnicodeNormalization: off\n{}", nfd);
+        assert_eq!(normalize_input(&disabled), disabled);
+    }
+
+    #[test]
+    fn test_nfd_upper_line_keeps_octave_dots_aligned() {
+        let octaves = |text: &str| {
+            let (upper, content) = text.split_once('\n').unwrap();
+            let mut line = unused_parse_content_line(content, 2, NotationSystem::Bhatkhande, 0).unwrap();
+            apply_octave_markers(&mut line, upper, 1);
+            line.elements.iter()
+                .filter_map(|element| match element {
+                    ContentElement::Beat(beat) => Some(beat.elements.iter()),
+                    _ => None,
+                })
+                .flatten()
+                .filter_map(|element| match element {
+                    BeatElement::Note(note) => Some((note.value.clone().unwrap(), note.octave)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let nfd = "र\u{93C}e\u{301}   .\n|स ग म प|";
+        let raised = octaves(&normalize_input(nfd));
+        assert_eq!(raised, octaves("ऱé   .\n|स ग म प|"));
+        // The dot raises म; unnormalized, the two combining marks move it past the last note
+        assert_eq!(raised.iter().filter(|(_, octave)| *octave == 1).collect::<Vec<_>>(), vec![&("म".to_string(), 1)]);
+        assert!(octaves(nfd).iter().all(|(_, octave)| *octave == 0));
+    }
+}
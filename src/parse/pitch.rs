@@ -1,5 +1,6 @@
-use crate::parse::model::{NotationSystem, PitchCode};
-use crate::parse::ParseError;
+use crate::parse::model::{directive, NotationSystem, PitchCode};
+use crate::parse::{ParseError, ParseErrorKind};
+use std::collections::HashMap;
 use std::iter::Peekable;
 use std::str::{Chars, CharIndices};
 
@@ -16,33 +17,114 @@ pub fn parse_pitch(
 ) -> Result<(String, PitchCode, usize), ParseError> {
     let first_char = chars.next().ok_or_else(|| {
         ParseError {
+            kind: ParseErrorKind::UnexpectedEnd,
             message: "Unexpected end of input, expected pitch".to_string(),
             line,
             column,
+            suggestions: Vec::new(),
         }
     })?;
 
     match notation_system {
-        NotationSystem::Number => parse_number_pitch(first_char, chars, line, column),
+        NotationSystem::Number | NotationSystem::Jianpu => parse_number_pitch(first_char, chars, line, column),
         NotationSystem::Western => parse_western_pitch(first_char, chars, line, column),
         NotationSystem::Sargam => parse_sargam_pitch(first_char, chars, line, column),
         NotationSystem::Bhatkhande => parse_bhatkhande_pitch(first_char, chars, line, column),
-        NotationSystem::Tabla => parse_tabla_pitch(first_char, chars, line, column),
+        NotationSystem::Tabla | NotationSystem::Scat => parse_tabla_pitch(first_char, chars, line, column),
     }
 }
 
 /// Check if a character can start a pitch in the given notation system
 pub fn is_pitch_start(ch: char, notation_system: NotationSystem) -> bool {
     match notation_system {
-        NotationSystem::Number => matches!(ch, '1'..='7'),
+        NotationSystem::Number | NotationSystem::Jianpu => matches!(ch, '1'..='7'),
         NotationSystem::Western => matches!(ch, 'A'..='G' | 'a'..='g'),
         NotationSystem::Sargam => matches!(ch, 'S' | 'R' | 'G' | 'M' | 'P' | 'D' | 'N' | 's' | 'r' | 'g' | 'm' | 'p' | 'd' | 'n'),
         NotationSystem::Bhatkhande => matches!(ch, 'स' | 'र' | 'ग' | 'म' | 'प' | 'ध' | 'न'),
-        NotationSystem::Tabla => matches!(ch, 'd' | 'D' | 't' | 'T' | 'k' | 'K' | 'g' | 'G'),
+        NotationSystem::Tabla | NotationSystem::Scat => matches!(ch, 'd' | 'D' | 't' | 'T' | 'k' | 'K' | 'g' | 'G'),
     }
 }
 
-/// Parse a pitch using CharIndices and regex patterns from models
+/// Check if a character is an explicit rest in the given notation system:
+/// 'r' in Number and Western notation, '0' in Number, Sargam and Bhatkhande.
+/// Tabla and scat have no rest token since their syllables use the letters.
+pub fn is_rest_start(ch: char, notation_system: NotationSystem) -> bool {
+    match notation_system {
+        NotationSystem::Number | NotationSystem::Jianpu => matches!(ch, 'r' | '0'),
+        NotationSystem::Western => ch == 'r',
+        NotationSystem::Sargam | NotationSystem::Bhatkhande => ch == '0',
+        NotationSystem::Tabla | NotationSystem::Scat => false,
+    }
+}
+
+/// Directive switching on scientific pitch names for Western notation (`ScientificPitch: on`)
+pub const SCIENTIFIC_PITCH_DIRECTIVE: &str = "ScientificPitch";
+
+/// Whether a digit written right after a capital Western pitch ("C4", "G5") gives its
+/// octave, with C4 in the middle octave. Defaults to `false`: the digit is not read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ScientificPitch(pub bool);
+
+impl ScientificPitch {
+    /// Read the option from document directives, falling back to the default
+    pub fn from_directives(directives: &HashMap<String, String>) -> Self {
+        let enabled = directive(directives, SCIENTIFIC_PITCH_DIRECTIVE)
+            .is_some_and(|value| matches!(value.to_lowercase().as_str(), "true" | "yes" | "on" | "1"));
+        ScientificPitch(enabled)
+    }
+}
+
+/// Consume the octave digit of a scientific pitch name after `pitch_str` was parsed.
+///
+/// Only applies to Western pitches starting with a capital letter when `scientific_pitch`
+/// is on; the digit 0-8 is added to the pitch text and gives the octave relative to the
+/// middle one (C4 is 0, C5 is 1, C3 is -1). Any other pitch keeps octave 0.
+pub fn parse_octave_digit(
+    chars: &mut Peekable<CharIndices>,
+    mut pitch_str: String,
+    notation_system: NotationSystem,
+    scientific_pitch: ScientificPitch,
+) -> (String, i8) {
+    let capital = pitch_str.starts_with(|c: char| c.is_ascii_uppercase());
+    if !scientific_pitch.0 || notation_system != NotationSystem::Western || !capital {
+        return (pitch_str, 0);
+    }
+    match chars.next_if(|(_, c)| matches!(c, '0'..='8')) {
+        Some((_, digit)) => {
+            pitch_str.push(digit);
+            (pitch_str, digit as i8 - '4' as i8)
+        }
+        None => (pitch_str, 0),
+    }
+}
+
+/// Quarter-tone sharp written right after a pitch and its accidentals ("1+", "1#+", "1𝄲")
+pub const QUARTER_SHARP: [char; 2] = ['+', '𝄲'];
+/// Quarter-tone flat written right after a pitch and its accidentals ("1𝄳", "1b𝄳");
+/// there is no ASCII form since '-' already extends a note
+pub const QUARTER_FLAT: char = '𝄳';
+
+/// Consume a quarter-tone accidental after `pitch_str` was parsed, returning the pitch
+/// text including it and the microtone in quarter steps (1 raises the pitch a quarter
+/// tone, -1 lowers it). Tabla syllables have no pitch to alter.
+pub fn parse_microtone(
+    chars: &mut Peekable<CharIndices>,
+    mut pitch_str: String,
+    notation_system: NotationSystem,
+) -> (String, Option<i8>) {
+    if notation_system == NotationSystem::Tabla {
+        return (pitch_str, None);
+    }
+    match chars.next_if(|(_, c)| QUARTER_SHARP.contains(c) || *c == QUARTER_FLAT) {
+        Some((_, mark)) => {
+            pitch_str.push(mark);
+            (pitch_str, Some(if mark == QUARTER_FLAT { -1 } else { 1 }))
+        }
+        None => (pitch_str, None),
+    }
+}
+
+/// Parse a pitch using CharIndices and the precomputed pitch tables from models
 pub fn parse_pitch_with_indices(
     chars: &mut Peekable<CharIndices>,
     notation_system: NotationSystem,
@@ -53,20 +135,15 @@ pub fn parse_pitch_with_indices(
     let start_pos = chars.peek().map(|(pos, _)| *pos).unwrap_or(0);
     let remaining_input = &input[start_pos..];
 
-    // Use regex from models to find pitch match
-    let regex = crate::models::pitch_systems::get_regex_for_system(notation_system);
-
-    if let Some(mat) = regex.find(remaining_input) {
-        let pitch_str = mat.as_str();
-
-        // Use models lookup to get the Degree
-        let notation = system_to_notation(notation_system);
-        let degree = crate::models::pitch_systems::lookup_pitch(pitch_str, notation)
-            .ok_or_else(|| ParseError {
-                message: format!("Invalid pitch '{}' for notation system {:?}", pitch_str, notation_system),
-                line,
-                column: column_from_pos(input, start_pos),
-            })?;
+    // Match against the precomputed symbol table of the notation system
+    if let Some((pitch_str, degree)) = crate::models::pitch_systems::match_pitch(remaining_input, notation_system) {
+        let degree = degree.ok_or_else(|| ParseError {
+            kind: ParseErrorKind::InvalidPitch,
+            message: format!("Invalid pitch '{}' for notation system {:?}", pitch_str, notation_system),
+            line,
+            column: column_from_pos(input, start_pos),
+            suggestions: Vec::new(),
+        })?;
 
         // Convert Degree to PitchCode using the bridge
         let pitch_code = crate::models::pitch_systems::degree_to_pitch_code(degree);
@@ -79,24 +156,15 @@ pub fn parse_pitch_with_indices(
         Ok((pitch_str.to_string(), pitch_code))
     } else {
         Err(ParseError {
+            kind: ParseErrorKind::UnexpectedToken,
             message: format!("Expected pitch for notation system {:?}", notation_system),
             line,
             column: column_from_pos(input, start_pos),
+            suggestions: Vec::new(),
         })
     }
 }
 
-/// Helper function to convert NotationSystem to Notation
-fn system_to_notation(system: NotationSystem) -> crate::models::Notation {
-    match system {
-        NotationSystem::Western => crate::models::Notation::Western,
-        NotationSystem::Number => crate::models::Notation::Number,
-        NotationSystem::Sargam => crate::models::Notation::Sargam,
-        NotationSystem::Bhatkhande => crate::models::Notation::Bhatkhande,
-        NotationSystem::Tabla => crate::models::Notation::Tabla,
-    }
-}
-
 /// Helper function to calculate column from position in input
 fn column_from_pos(input: &str, pos: usize) -> usize {
     input[..pos].chars().rev().take_while(|&c| c != '\n').count() + 1
@@ -111,9 +179,11 @@ fn parse_number_pitch(
 ) -> Result<(String, PitchCode, usize), ParseError> {
     if !matches!(base, '1'..='7') {
         return Err(ParseError {
+            kind: ParseErrorKind::UnexpectedToken,
             message: format!("Expected number pitch (1-7), found '{}'", base),
             line,
             column,
+            suggestions: Vec::new(),
         });
     }
 
@@ -138,9 +208,11 @@ fn parse_number_pitch(
     }
 
     let pitch_code = PitchCode::from_source(&pitch_str).ok_or_else(|| ParseError {
+        kind: ParseErrorKind::InvalidPitch,
         message: format!("Invalid number pitch: {}", pitch_str),
         line,
         column,
+        suggestions: Vec::new(),
     })?;
     Ok((pitch_str, pitch_code, length))
 }
@@ -155,9 +227,11 @@ fn parse_western_pitch(
     let base_upper = base.to_ascii_uppercase();
     if !matches!(base_upper, 'A'..='G') {
         return Err(ParseError {
+            kind: ParseErrorKind::UnexpectedToken,
             message: format!("Expected Western pitch (A-G), found '{}'", base),
             line,
             column,
+            suggestions: Vec::new(),
         });
     }
 
@@ -182,9 +256,11 @@ fn parse_western_pitch(
     }
 
     let pitch_code = PitchCode::from_source(&pitch_str).ok_or_else(|| ParseError {
+        kind: ParseErrorKind::InvalidPitch,
         message: format!("Invalid Western pitch: {}", pitch_str),
         line,
         column,
+        suggestions: Vec::new(),
     })?;
     Ok((pitch_str, pitch_code, length))
 }
@@ -199,9 +275,11 @@ fn parse_sargam_pitch(
     let valid_sargam = matches!(base, 'S' | 'R' | 'G' | 'M' | 'P' | 'D' | 'N' | 's' | 'r' | 'g' | 'm' | 'p' | 'd' | 'n');
     if !valid_sargam {
         return Err(ParseError {
+            kind: ParseErrorKind::UnexpectedToken,
             message: format!("Expected Sargam pitch (S,R,G,M,P,D,N), found '{}'", base),
             line,
             column,
+            suggestions: Vec::new(),
         });
     }
 
@@ -228,9 +306,11 @@ fn parse_sargam_pitch(
     }
 
     let pitch_code = PitchCode::from_source_with_context(&pitch_str, NotationSystem::Sargam).ok_or_else(|| ParseError {
+        kind: ParseErrorKind::InvalidPitch,
         message: format!("Invalid Sargam pitch: {}", pitch_str),
         line,
         column,
+        suggestions: Vec::new(),
     })?;
     Ok((pitch_str, pitch_code, length))
 }
@@ -249,34 +329,42 @@ fn parse_bhatkhande_pitch(
             let mut pitch_str = String::from(base);
             pitch_str.push(chars.next().unwrap());
             let pitch_code = PitchCode::from_source(&pitch_str).ok_or_else(|| ParseError {
+                kind: ParseErrorKind::InvalidPitch,
                 message: format!("Invalid Bhatkhande pitch: {}", pitch_str),
                 line,
                 column,
+                suggestions: Vec::new(),
             })?;
             return Ok((pitch_str, pitch_code, 2));
         } else if base == 'न' && chars.peek() == Some(&'ि') {
             let mut pitch_str = String::from(base);
             pitch_str.push(chars.next().unwrap());
             let pitch_code = PitchCode::from_source(&pitch_str).ok_or_else(|| ParseError {
+                kind: ParseErrorKind::InvalidPitch,
                 message: format!("Invalid Bhatkhande pitch: {}", pitch_str),
                 line,
                 column,
+                suggestions: Vec::new(),
             })?;
             return Ok((pitch_str, pitch_code, 2));
         }
 
         return Err(ParseError {
+            kind: ParseErrorKind::UnexpectedToken,
             message: format!("Expected Bhatkhande pitch, found '{}'", base),
             line,
             column,
+            suggestions: Vec::new(),
         });
     }
 
     let pitch_str = String::from(base);
     let pitch_code = PitchCode::from_source(&pitch_str).ok_or_else(|| ParseError {
+        kind: ParseErrorKind::InvalidPitch,
         message: format!("Invalid Bhatkhande pitch: {}", pitch_str),
         line,
         column,
+        suggestions: Vec::new(),
     })?;
     Ok((pitch_str, pitch_code, 1))
 }
@@ -368,9 +456,11 @@ fn parse_tabla_pitch(
         }
         _ => {
             return Err(ParseError {
+                kind: ParseErrorKind::UnexpectedToken,
                 message: format!("Expected Tabla syllable, found '{}'", first),
                 line,
                 column,
+                suggestions: Vec::new(),
             });
         }
     }
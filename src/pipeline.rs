@@ -1,6 +1,25 @@
 use crate::parse::Document;
 use crate::renderers::lilypond::renderer::convert_processed_document_to_lilypond_src;
 use crate::renderers::vexflow::VexFlowRenderer;
+use crate::rhythm::beaming::BEAM_OVER_RESTS_DIRECTIVE;
+use crate::rhythm::measures::{EMPTY_MEASURE_DIRECTIVE, ENDINGS_DIRECTIVE, REHEARSAL_DIRECTIVE, SLASH_DIRECTIVE};
+use crate::renderers::note_names::NOTE_NAMES_DIRECTIVE;
+use crate::renderers::noteheads::NOTEHEAD_STYLE_DIRECTIVE;
+use crate::renderers::transposition::{TRANSPOSE_DIRECTIVE, TRANSPOSE_MODE_DIRECTIVE};
+use crate::renderers::lilypond::renderer::LILYPOND_MIDI_DIRECTIVE;
+use crate::parse::line_classifier::{SYSTEM_DIRECTIVE, TITLE_DIRECTIVE, TITLE_LINE_DIRECTIVE};
+use crate::parse::model::{AUTHOR_DIRECTIVE, CLEF_DIRECTIVE, COMPOSER_DIRECTIVE, KEY_DIRECTIVE};
+use crate::parse::normalize::UNICODE_NORMALIZATION_DIRECTIVE;
+use crate::parse::pitch::{ScientificPitch, SCIENTIFIC_PITCH_DIRECTIVE};
+use crate::analysis::{COUNT_LINE_DIRECTIVE, TIME_DIRECTIVE, TIME_SIGNATURE_DIRECTIVE};
+use crate::renderers::midi::events::TEMPO_DIRECTIVE;
+use crate::renderers::midi::tuning::{TUNING_DIRECTIVE, TUNING_REFERENCE_DIRECTIVE};
+use crate::renderers::midi::swing::{FEEL_DIRECTIVE, SWING_DIRECTIVE};
+use crate::renderers::ottava::OTTAVA_DIRECTIVE;
+use crate::rhythm::pickup::PICKUP_DIRECTIVE;
+use crate::rhythm::tala::TALA_DIRECTIVE;
+use crate::rhythm::analyzer::BEAT_UNIT_DIRECTIVE;
+use crate::renderers::lilypond::format::{LILYPOND_INDENT_DIRECTIVE, LILYPOND_LAYOUT_DIRECTIVE, LILYPOND_MEASURE_NUMBERS_DIRECTIVE};
 use serde::{Deserialize, Serialize};
 /// The complete processing pipeline output
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,9 +29,443 @@ pub struct ProcessingResult {
     pub lilypond: String,
     pub vexflow_svg: String,
     pub vexflow_data: serde_json::Value,
+    #[serde(default)]
+    pub directives: DirectiveReport,
 }
 
+/// Directive keys that some part of the pipeline reads (matched case-insensitively).
+/// Each is the constant its reader passes to `directive`, so a key is only reported
+/// as applied when something actually looks it up.
+pub const KNOWN_DIRECTIVES: &[&str] = &[
+    TITLE_DIRECTIVE, AUTHOR_DIRECTIVE, KEY_DIRECTIVE, TEMPO_DIRECTIVE, TIME_DIRECTIVE, TIME_SIGNATURE_DIRECTIVE,
+    TALA_DIRECTIVE, PICKUP_DIRECTIVE, TUNING_REFERENCE_DIRECTIVE, OTTAVA_DIRECTIVE, BEAM_OVER_RESTS_DIRECTIVE,
+    EMPTY_MEASURE_DIRECTIVE, NOTE_NAMES_DIRECTIVE, LILYPOND_MIDI_DIRECTIVE, SLASH_DIRECTIVE, LILYPOND_INDENT_DIRECTIVE,
+    LILYPOND_LAYOUT_DIRECTIVE, LILYPOND_MEASURE_NUMBERS_DIRECTIVE, SYSTEM_DIRECTIVE, TITLE_LINE_DIRECTIVE,
+    REHEARSAL_DIRECTIVE, ENDINGS_DIRECTIVE, TRANSPOSE_DIRECTIVE, TRANSPOSE_MODE_DIRECTIVE,
+    UNICODE_NORMALIZATION_DIRECTIVE, COUNT_LINE_DIRECTIVE, NOTEHEAD_STYLE_DIRECTIVE, BEAT_UNIT_DIRECTIVE,
+    SCIENTIFIC_PITCH_DIRECTIVE, CLEF_DIRECTIVE, SWING_DIRECTIVE, FEEL_DIRECTIVE,
+    TUNING_DIRECTIVE, COMPOSER_DIRECTIVE,
+];
 
+/// Which directives took effect and which were ignored
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DirectiveReport {
+    pub applied: Vec<String>,
+    pub unrecognized: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Sort directive keys into recognized and unrecognized ones, suggesting the
+/// closest known key for each typo
+pub fn check_directives<'a>(keys: impl IntoIterator<Item = &'a str>) -> DirectiveReport {
+    let mut report = DirectiveReport::default();
+    for key in keys {
+        if KNOWN_DIRECTIVES.iter().any(|known| known.eq_ignore_ascii_case(key)) {
+            report.applied.push(key.to_string());
+            continue;
+        }
+        let warning = match closest_directive(key) {
+            Some(suggestion) => format!("unknown directive '{}', did you mean '{}'?", key, suggestion),
+            None => format!("unknown directive '{}'", key),
+        };
+        report.unrecognized.push(key.to_string());
+        report.warnings.push(warning);
+    }
+    report.applied.sort();
+    report
+}
+
+/// Check the `key: value` directive lines in the header of notation text
+pub fn process_notation_directives(input: &str) -> DirectiveReport {
+    let classified = crate::parse::line_classifier::classify_lines(input);
+    let keys: Vec<&str> = classified.iter()
+        .filter_map(|line| line.strip_prefix("#directive# "))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, _)| key.trim())
+        .collect();
+    check_directives(keys)
+}
+
+/// Build a rhythm-analyzed document from notation text: directives and the title from
+/// the header, one stave per content line, and dynamics and lyrics verses from the lines
+/// under them. Every other line is kept in order, so `Document::to_plain_text` can write
+/// the text back: lines up to a content line go on its stave before it, lines after it
+/// go on the same stave until a blank line, and blank lines between staves become
+/// `BlankLines` elements. Slurs, phrase marks, chord symbols such as `[Cm7]`, feathered
+/// beams (`<<`, `>>`), vibrato (`~~~`), bends (`^2`), ornaments (`~`, `tr`, `turn`) and
+/// grace notes come from the lines above the content line, and `@articulation` lines
+/// may be above or below it.
+/// A content line with a `div.` span takes its second pitch stream from the next
+/// content line of the same stave. Staves between a `{` line and a `}` line are
+/// braced into one grand staff.
+pub fn document_from_notation(input: &str) -> Result<Document, String> {
+    document_from_notation_with_system(input, None)
+}
+
+/// Build a document as `document_from_notation` does, reading the music in `system`
+/// as a `System:` directive would (a directive in the input still takes precedence)
+pub fn document_from_notation_with_system(input: &str, system: Option<crate::parse::model::NotationSystem>) -> Result<Document, String> {
+    use crate::parse::line_classifier::{classify_line_types_in, document_title};
+    use crate::parse::model::DocumentElement;
+
+    let input = &*crate::parse::normalize::normalize_input(input);
+    let mut document = Document {
+        value: Some(input.to_string()),
+        title: document_title(input),
+        ..Default::default()
+    };
+    let classified = classify_line_types_in(input, system);
+    let mut pending = add_notation_lines(&mut document, input, &classified, 0..classified.len(), 0)?;
+    // Lines after the last stave stay with it
+    if let Some(DocumentElement::Stave(stave)) = document.elements.iter_mut().rev().find(|e| matches!(e, DocumentElement::Stave(_))) {
+        stave.lines.append(&mut pending);
+    }
+    crate::rhythm::analyzer::analyze_rhythm_into_document(&mut document)?;
+    Ok(document)
+}
+
+/// Add the staves and blank lines of the classified notation lines in `lines` (0-based
+/// indices into `input.lines()`) to `document`, the first of them starting at character
+/// `line_start` of `input`. Returns the lines left waiting for a content line at the end.
+pub(crate) fn add_notation_lines(
+    document: &mut Document,
+    input: &str,
+    classified: &[(crate::parse::line_classifier::LineType, &str)],
+    lines: std::ops::Range<usize>,
+    mut line_start: usize,
+) -> Result<Vec<crate::parse::model::StaveLine>, String> {
+    use crate::document::line_parser::content_line_parser::{parse_content_line_with_options, ParseMode};
+    use crate::document::line_parser::dynamics_line_parser::attach_dynamics;
+    use crate::document::line_parser::slash_line_parser::{attach_chords, is_slash_line, parse_slash_content_line};
+    use crate::document::line_parser::scat_line_parser::parse_scat_content_line;
+    use crate::document::line_parser::articulation_line_parser::attach_articulations;
+    use crate::document::slurs::attach_slurs;
+    use crate::document::expression::apply_expression_markers;
+    use crate::document::ornaments::apply_ornaments;
+    use crate::document::divisi::{apply_divisi, has_divisi};
+    use crate::document::grace::apply_grace_notes;
+    use crate::rhythm::beaming::attach_feathers;
+    use crate::document::octaves::apply_octave_markers;
+    use crate::document::lyrics::{apply_lyrics, lyrics_line};
+    use crate::parse::line_classifier::LineType;
+    use crate::parse::model::{BlankLines, Clef, DocumentElement, NotationSystem, Stave, StaveLine, TextLine, GRAND_STAFF_CLOSE, GRAND_STAFF_OPEN};
+
+    // Lines waiting for the content line of the next stave
+    let mut pending: Vec<StaveLine> = Vec::new();
+    // Whether lines still belong to the last stave (until a blank line)
+    let mut in_stave = false;
+    // Lines above the next content line and above the last one, without line type markers
+    let mut upper_lines: Vec<&str> = Vec::new();
+    let mut stave_upper_lines: Vec<&str> = Vec::new();
+    // Articulation lines above the next content line
+    let mut articulation_lines: Vec<&str> = Vec::new();
+    // Whether the last stave has a `div.` span still waiting for its second stream
+    let mut divisi_pending = false;
+    // Clef of a `clef:` line waiting for the content line of the next stave
+    let mut stave_clef: Option<Clef> = None;
+    // Grand staff the next staves are braced into, between a `{` line and a `}` line
+    let mut grand_staff: Option<usize> = None;
+    let mut grand_staves = 0;
+    let rows = classified.iter().cloned().zip(input.lines()).enumerate().skip(lines.start).take(lines.len());
+    for (idx, ((line_type, text), raw)) in rows {
+        let line = idx + 1;
+        let line_index = line_start;
+        line_start += raw.chars().count() + 1;
+        let text_line = || TextLine { id: uuid::Uuid::new_v4(), value: Some(raw.to_string()) };
+        // A `clef:` line starts the next stave; its colon is not an octave marker
+        if !matches!(line_type, LineType::Directive | LineType::Content(_)) {
+            if let Some(clef) = Clef::from_marker(text) {
+                stave_clef = Some(clef);
+                in_stave = false;
+                upper_lines.clear();
+                pending.push(StaveLine::Text(text_line()));
+                continue;
+            }
+            if text.trim() == GRAND_STAFF_OPEN {
+                grand_staff = Some(grand_staves);
+                grand_staves += 1;
+                in_stave = false;
+                upper_lines.clear();
+                pending.push(StaveLine::Text(text_line()));
+                continue;
+            }
+            if text.trim() == GRAND_STAFF_CLOSE {
+                grand_staff = None;
+                match document.elements.last_mut() {
+                    Some(DocumentElement::Stave(stave)) if in_stave => stave.lines.push(StaveLine::Text(text_line())),
+                    _ => pending.push(StaveLine::Text(text_line())),
+                }
+                in_stave = false;
+                continue;
+            }
+        }
+        match line_type {
+            LineType::Directive => {
+                if let Some((key, value)) = text.split_once(':') {
+                    document.directives.insert(key.trim().to_string(), value.trim().to_string());
+                }
+            }
+            LineType::Title => {}
+            LineType::Content(notation_system) => {
+                // Explicit line type markers are stripped from the front of the line
+                let text_start = line_index + raw.chars().count() - text.chars().count();
+                // Directives are all in the header, so they are known by the first content line
+                let scientific_pitch = ScientificPitch::from_directives(&document.directives);
+                let mut content_line = if notation_system == NotationSystem::Scat {
+                    parse_scat_content_line(text, line, text_start)
+                } else if is_slash_line(text) {
+                    parse_slash_content_line(text, line, text_start)
+                } else {
+                    parse_content_line_with_options(text, line, notation_system, text_start, ParseMode::Standard, scientific_pitch)
+                }.map_err(|error| error.render_pretty(input))?;
+                if in_stave && divisi_pending {
+                    // A content line right under a `div.` line gives the second pitch stream
+                    let Some(DocumentElement::Stave(stave)) = document.elements.last_mut() else { continue };
+                    if let Some(StaveLine::ContentLine(divisi_line)) = stave.lines.iter_mut().find(|line| matches!(line, StaveLine::ContentLine(_))) {
+                        apply_divisi(divisi_line, &content_line).map_err(|error| format!("Line {}: {}", line, error))?;
+                    }
+                    stave.lines.push(StaveLine::Text(text_line()));
+                    divisi_pending = false;
+                    continue;
+                }
+                divisi_pending = has_divisi(&content_line);
+                for upper_line in &upper_lines {
+                    attach_chords(&mut content_line, upper_line);
+                    attach_feathers(&mut content_line, upper_line);
+                    attach_slurs(&mut content_line, upper_line);
+                    apply_expression_markers(&mut content_line, upper_line);
+                    apply_ornaments(&mut content_line, upper_line);
+                    apply_grace_notes(&mut content_line, upper_line, notation_system);
+                    apply_octave_markers(&mut content_line, upper_line, 1);
+                }
+                for articulation_line in articulation_lines.drain(..) {
+                    attach_articulations(&mut content_line, articulation_line);
+                }
+                let mut lines = std::mem::take(&mut pending);
+                lines.push(StaveLine::ContentLine(content_line));
+                document.elements.push(DocumentElement::Stave(Stave {
+                    id: uuid::Uuid::new_v4(),
+                    value: Some(text.to_string()),
+                    notation_system,
+                    line,
+                    column: 1,
+                    index_in_line: 0,
+                    index_in_doc: text_start,
+                    clef: stave_clef.take().or_else(|| Clef::from_directives(&document.directives)),
+                    grand_staff,
+                    lines,
+                }));
+                in_stave = true;
+                stave_upper_lines = std::mem::take(&mut upper_lines);
+            }
+            _ if raw.trim().is_empty() => {
+                in_stave = false;
+                upper_lines.clear();
+                articulation_lines.clear();
+                let blank = format!("{}\n", raw);
+                if !pending.is_empty() {
+                    pending.push(StaveLine::BlankLines(BlankLines {
+                        id: uuid::Uuid::new_v4(),
+                        value: Some(blank),
+                        line,
+                        column: 1,
+                        index_in_line: 0,
+                        index_in_doc: line_index,
+                    }));
+                } else if let Some(DocumentElement::BlankLines(blank_lines)) = document.elements.last_mut() {
+                    blank_lines.value.get_or_insert_with(String::new).push_str(&blank);
+                } else {
+                    document.elements.push(DocumentElement::BlankLines(BlankLines {
+                        id: uuid::Uuid::new_v4(),
+                        value: Some(blank),
+                        line,
+                        column: 1,
+                        index_in_line: 0,
+                        index_in_doc: line_index,
+                    }));
+                }
+            }
+            LineType::Articulation if !in_stave => {
+                articulation_lines.push(text);
+                pending.push(StaveLine::Text(text_line()));
+            }
+            _ if !in_stave => {
+                upper_lines.push(text);
+                pending.push(StaveLine::Text(text_line()));
+            }
+            _ => {
+                let Some(DocumentElement::Stave(stave)) = document.elements.last_mut() else { continue };
+                let content_index = stave.lines.iter().position(|line| matches!(line, StaveLine::ContentLine(_)));
+                match line_type {
+                    // Annotation lines under the notes are lower lines, whatever marks they hold
+                    LineType::Lower | LineType::Upper => {
+                        if let Some(StaveLine::ContentLine(content_line)) = content_index.map(|i| &mut stave.lines[i]) {
+                            attach_dynamics(content_line, text);
+                            apply_octave_markers(content_line, text, -1);
+                        }
+                        stave.lines.push(StaveLine::Text(text_line()));
+                    }
+                    LineType::Articulation => {
+                        if let Some(StaveLine::ContentLine(content_line)) = content_index.map(|i| &mut stave.lines[i]) {
+                            attach_articulations(content_line, text);
+                        }
+                        stave.lines.push(StaveLine::Text(text_line()));
+                    }
+                    LineType::Lyrics => {
+                        let verse = stave.lines.iter().filter(|line| matches!(line, StaveLine::Lyrics(_))).count();
+                        if let Some(StaveLine::ContentLine(content_line)) = content_index.map(|i| &mut stave.lines[i]) {
+                            apply_lyrics(content_line, verse, text, &stave_upper_lines);
+                        }
+                        let mut lyrics = lyrics_line(text);
+                        lyrics.value = Some(raw.to_string());
+                        stave.lines.push(StaveLine::Lyrics(lyrics));
+                    }
+                    _ => stave.lines.push(StaveLine::Text(text_line())),
+                }
+            }
+        }
+    }
+    Ok(pending)
+}
+
+/// Every content line that fails to parse, in line order. `document_from_notation`
+/// stops at the first of these; a linter wants them all.
+pub fn content_parse_errors(input: &str) -> Vec<crate::parse::ParseError> {
+    use crate::document::line_parser::content_line_parser::{parse_content_line_with_options, ParseMode};
+    use crate::parse::line_classifier::{classify_line_types, LineType};
+    use std::collections::HashMap;
 
+    let mut directives = HashMap::new();
+    let mut errors = Vec::new();
+    let mut line_start = 0;
+    for (idx, ((line_type, text), raw)) in classify_line_types(input).into_iter().zip(input.lines()).enumerate() {
+        let line_index = line_start;
+        line_start += raw.chars().count() + 1;
+        match line_type {
+            LineType::Directive => {
+                if let Some((key, value)) = text.split_once(':') {
+                    directives.insert(key.trim().to_string(), value.trim().to_string());
+                }
+            }
+            LineType::Content(notation_system) => {
+                let text_start = line_index + raw.chars().count() - text.chars().count();
+                let scientific_pitch = ScientificPitch::from_directives(&directives);
+                if let Err(error) = parse_content_line_with_options(text, idx + 1, notation_system, text_start, ParseMode::Standard, scientific_pitch) {
+                    errors.push(error);
+                }
+            }
+            _ => {}
+        }
+    }
+    errors
+}
+
+/// Problems in a parsed document that the renderers would otherwise pass on silently:
+/// slurs, phrasing slurs, beams and tuplets that cross instead of nesting, and ties
+/// between different pitches or with no note to end on
+pub fn notation_warnings(document: &Document) -> Vec<crate::document::spans::SpanWarning> {
+    use crate::document::spans::{document_spans, validate_span_nesting};
+    use crate::document::ties::validate_ties;
+    let mut warnings = validate_span_nesting(&document_spans(document));
+    warnings.extend(validate_ties(document));
+    warnings
+}
+
+/// Known directive within a small edit distance of `key`
+fn closest_directive(key: &str) -> Option<&'static str> {
+    let key = key.to_lowercase();
+    KNOWN_DIRECTIVES.iter()
+        .map(|known| (*known, edit_distance(&key, &known.to_lowercase())))
+        .filter(|(known, distance)| *distance <= (known.len() / 3).max(2))
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(known, _)| known)
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    #[test]
+    fn test_misspelled_directive_suggests_known_key() {
+        let report = process_notation_directives("Tiempo: 90\nTitle: Blues\n\n|1 2 3 4|");
+        assert_eq!(report.applied, vec!["Title"]);
+        assert_eq!(report.unrecognized, vec!["Tiempo"]);
+        assert_eq!(report.warnings, vec!["unknown directive 'Tiempo', did you mean 'Tempo'?"]);
+    }
+
+    #[test]
+    fn test_document_from_notation() {
+        let document = document_from_notation("Title: Scale\nTempo: 90\n\n|1 2 3-|\n f").unwrap();
+        assert_eq!(document.title.as_deref(), Some("Scale"));
+        assert_eq!(document.directives.get("Tempo").map(String::as_str), Some("90"));
+        let durations: Vec<(Option<u32>, Option<u32>)> = document.notes().map(|note| (note.numerator, note.denominator)).collect();
+        assert_eq!(durations, vec![(Some(1), Some(4)), (Some(1), Some(4)), (Some(1), Some(4))]);
+        assert!(document_from_notation("|1 2 :3|").is_err());
+    }
+
+    #[test]
+    fn test_scientific_pitch_directive() {
+        let octaves = |input: &str| document_from_notation(input).unwrap().notes().map(|note| note.octave).collect::<Vec<_>>();
+        assert_eq!(octaves("System: western\nScientificPitch: on\n\nC4 E4 G4 C5\n"), vec![0, 0, 0, 1]);
+        assert_eq!(octaves("System: western\nScientificPitch: on\n\n| C3 - G3 |\n"), vec![-1, -1]);
+        assert_eq!(octaves("ScientificPitch: on\n\n| 1 4 5 |\n"), vec![0, 0, 0]);
+        // Detected as Western with the octave digits, without a System directive
+        assert_eq!(octaves("ScientificPitch: on\n\n| C4 E4 G5 C3 |\n"), vec![0, 0, 1, -1]);
+        assert_eq!(octaves("ScientificPitch: on\n\nC4 E4 G4 C5\n"), vec![0, 0, 0, 1]);
+        assert!(check_directives(["ScientificPitch"]).unrecognized.is_empty());
+    }
+
+    #[test]
+    fn test_content_parse_errors_reports_every_line() {
+        use crate::parse::ParseErrorKind;
+        let errors = content_parse_errors("Title: Scale\n\n|1 2 :3|\n\n|1 2 3 4|\n\n[3:2 1 2 3\n");
+        let found: Vec<_> = errors.iter().map(|error| (error.kind, error.line)).collect();
+        assert_eq!(found, vec![(ParseErrorKind::MissingBarline, 3), (ParseErrorKind::UnterminatedGroup, 7)]);
+        assert!(content_parse_errors("|1 2 3 4|\n").is_empty());
+    }
+
+    #[test]
+    fn test_plain_text_roundtrip() {
+        for input in [
+            ".\n1 2 3\n",
+            "1 2 3\n.\n",
+            "  .  :\n1 2 3\n  .\n",
+            "| 1-2  3 |\n   .\n\n\n|: 4 5 :|\n",
+            "Key: D\nTime: 3/4\n\n_____\n1 2 3\nla la la\nfa so mi\n",
+            "1 2 3\n f < p\n\nsome words\n\n5 6 7\n",
+        ] {
+            let document = document_from_notation(input).unwrap();
+            assert_eq!(document.to_plain_text(), input);
+        }
+    }
+
+    #[test]
+    fn test_only_directives_that_are_read_are_applied() {
+        let report = check_directives(["tala", "TUNINGREFERENCE", "Arranger"]);
+        assert_eq!(report.applied, vec!["TUNINGREFERENCE", "tala"]);
+        assert_eq!(report.unrecognized, vec!["Arranger"]);
+        assert!(KNOWN_DIRECTIVES.iter().all(|key| key.chars().next().is_some_and(|c| c.is_ascii_uppercase()) && !key.contains('_')));
+    }
+
+    #[test]
+    fn test_unrelated_directive_has_no_suggestion() {
+        let report = check_directives(["Flavor", "BeamOverRests"]);
+        assert_eq!(report.applied, vec!["BeamOverRests"]);
+        assert_eq!(report.warnings, vec!["unknown directive 'Flavor'"]);
+    }
+}
@@ -0,0 +1,213 @@
+/// ABC notation export
+/// A tune header (X, T, M, L, K) followed by one line of ABC per content line, for
+/// exchanging melodies with ABC tools. Pitches come out as in LilyPond output (degree 1
+/// is C); accidentals are written wherever the `K:` signature would alter them.
+use crate::analysis::TimeSignature;
+use crate::models::BarlineType;
+use crate::parse::line_classifier::TITLE_DIRECTIVE;
+use crate::parse::model::{directive, Beat, BeatElement, ContentElement, ContentLine, Document, DocumentElement, Note, StaveLine, KEY_DIRECTIVE};
+use crate::document::ties::with_matching_ties;
+use crate::renderers::incipit::{key_alterations, key_short_name, key_signature_fifths};
+use crate::renderers::transposition::pitchcode_to_scale_position;
+use fraction::Fraction;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+const LETTERS: [char; 7] = ['C', 'D', 'E', 'F', 'G', 'A', 'B'];
+
+/// Length of a note written without a multiplier (`L:1/8`)
+const UNIT_NOTE_LENGTH: u64 = 8;
+
+/// Render a rhythm-analyzed document as an ABC tune
+pub fn render_abc(document: &Document) -> String {
+    let key = directive(&document.directives, KEY_DIRECTIVE);
+    let signature = key_alterations(key.and_then(key_signature_fifths).unwrap_or(0));
+    let time_signature = TimeSignature::from_directives(&document.directives);
+
+    let mut abc = String::from("X:1\n");
+    if let Some(title) = document.title.as_deref().or_else(|| directive(&document.directives, TITLE_DIRECTIVE)) {
+        writeln!(abc, "T:{}", title).unwrap();
+    }
+    if let Some(composer) = document.composer() {
+        writeln!(abc, "C:{}", composer).unwrap();
+    }
+    writeln!(abc, "M:{}/{}", time_signature.beats, time_signature.unit).unwrap();
+    writeln!(abc, "L:1/{}", UNIT_NOTE_LENGTH).unwrap();
+    writeln!(abc, "K:{}", key.and_then(key_short_name).unwrap_or_else(|| "C".to_string())).unwrap();
+
+    for element in &document.elements {
+        let DocumentElement::Stave(stave) = element else { continue };
+        for line in &stave.lines {
+            if let StaveLine::ContentLine(content_line) = line {
+                abc.push_str(&render_line(&with_matching_ties(content_line), signature));
+                abc.push('\n');
+            }
+        }
+    }
+    abc
+}
+
+/// Multiplier of the unit note length: "" for an eighth, "2" for a quarter, "/2" for a sixteenth
+fn length(duration: Fraction) -> String {
+    let multiplier = duration * Fraction::from(UNIT_NOTE_LENGTH);
+    match (multiplier.numer().copied().unwrap_or(1), multiplier.denom().copied().unwrap_or(1)) {
+        (1, 1) => String::new(),
+        (numer, 1) => numer.to_string(),
+        (1, denom) => format!("/{}", denom),
+        (numer, denom) => format!("{}/{}", numer, denom),
+    }
+}
+
+/// Letter with octave marks: octave 0 is the C-B from middle C ("C"), 1 the octave above ("c")
+fn pitch_letter(degree: usize, octave: i8) -> String {
+    let letter = LETTERS[degree];
+    if octave > 0 {
+        format!("{}{}", letter.to_ascii_lowercase(), "'".repeat(octave as usize - 1))
+    } else {
+        format!("{}{}", letter, ",".repeat(octave.unsigned_abs() as usize))
+    }
+}
+
+/// Writes the notes of one content line, tracking the accidentals in force in the current bar
+struct LineWriter {
+    output: String,
+    signature: [i8; 7],
+    bar_accidentals: HashMap<(usize, i8), i8>,
+    /// Pitch of the last note written, to repeat after a tie
+    last_pitch: Option<String>,
+}
+
+fn render_line(content_line: &ContentLine, signature: [i8; 7]) -> String {
+    let mut writer = LineWriter { output: String::new(), signature, bar_accidentals: HashMap::new(), last_pitch: None };
+    for element in &content_line.elements {
+        match element {
+            ContentElement::Beat(beat) => writer.beat(beat),
+            ContentElement::Barline(barline) => {
+                let symbol = match barline.barline_type() {
+                    BarlineType::Single => "|",
+                    BarlineType::Double => "||",
+                    BarlineType::Final => "|]",
+                    BarlineType::RepeatStart => "|:",
+                    BarlineType::RepeatEnd => ":|",
+                    BarlineType::RepeatBoth => "::",
+                };
+                writer.separate();
+                writer.output.push_str(symbol);
+                writer.bar_accidentals.clear();
+            }
+            ContentElement::Whitespace(_) | ContentElement::UnknownToken(_) => {}
+        }
+    }
+    writer.output
+}
+
+impl LineWriter {
+    /// Space between beats, so each beat is beamed on its own
+    fn separate(&mut self) {
+        if !self.output.is_empty() {
+            self.output.push(' ');
+        }
+    }
+
+    fn beat(&mut self, beat: &Beat) {
+        let tied = beat.tied_to_previous.unwrap_or(false) && self.last_pitch.is_some();
+        if tied {
+            self.output.push('-');
+        }
+        self.separate();
+
+        // Tuplet notes are written at their nominal value, e.g. "(3:2:3CDE" for a triplet
+        let mut scale = Fraction::from(1);
+        if let (Some(true), Some((actual, normal))) = (beat.is_tuplet, beat.tuplet_ratio) {
+            let events = beat.elements.iter()
+                .filter(|e| match e {
+                    BeatElement::Note(_) | BeatElement::Rest(_) => true,
+                    BeatElement::Dash(dash) => dash.numerator.is_some(),
+                    BeatElement::BreathMark(_) => false,
+                })
+                .count();
+            write!(self.output, "({}:{}:{}", actual, normal, events).unwrap();
+            scale = Fraction::new(actual as u64, normal as u64);
+        }
+
+        // Leading dashes of a tied beat continue the previous note
+        let leading_dashes = beat.elements.iter().take_while(|e| matches!(e, BeatElement::Dash(_))).count();
+        if let (true, Some(pitch), Some(divisions)) = (tied, self.last_pitch.clone(), beat.divisions) {
+            let beat_duration = beat.total_duration.unwrap_or(Fraction::new(1u64, 4u64));
+            let duration = Fraction::new(leading_dashes as u64, divisions as u64) * beat_duration * scale;
+            write!(self.output, "{}{}", pitch, length(duration)).unwrap();
+        }
+
+        for element in &beat.elements {
+            match element {
+                BeatElement::Note(note) => self.note(note, scale),
+                BeatElement::Dash(dash) => {
+                    if let (Some(numer), Some(denom)) = (dash.numerator, dash.denominator) {
+                        write!(self.output, "z{}", length(Fraction::new(numer, denom) * scale)).unwrap();
+                    }
+                }
+                BeatElement::Rest(rest) => {
+                    let duration = Fraction::new(rest.numerator.unwrap_or(1), rest.denominator.unwrap_or(4));
+                    write!(self.output, "z{}", length(duration * scale)).unwrap();
+                }
+                BeatElement::BreathMark(_) => {}
+            }
+        }
+    }
+
+    fn note(&mut self, note: &Note, scale: Fraction) {
+        let (degree, alteration) = pitchcode_to_scale_position(note.pitch_code);
+        let in_force = self.bar_accidentals.get(&(degree, note.octave)).copied().unwrap_or(self.signature[degree]);
+        if alteration != in_force {
+            let accidental = match alteration {
+                -2 => "__",
+                -1 => "_",
+                1 => "^",
+                2 => "^^",
+                _ => "=",
+            };
+            self.output.push_str(accidental);
+            self.bar_accidentals.insert((degree, note.octave), alteration);
+        }
+        let pitch = pitch_letter(degree, note.octave);
+        let duration = Fraction::new(note.numerator.unwrap_or(1), note.denominator.unwrap_or(4));
+        write!(self.output, "{}{}", pitch, length(duration * scale)).unwrap();
+        if note.tie.is_some() {
+            self.output.push('-');
+        }
+        self.last_pitch = Some(pitch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn abc(notation: &str) -> String {
+        render_abc(&crate::pipeline::document_from_notation(notation).unwrap())
+    }
+
+    #[test]
+    fn test_scale_to_abc_tune() {
+        assert_eq!(abc("Title: Scale\nTime: 3/4\n\n1 2 3\n"), "X:1\nT:Scale\nM:3/4\nL:1/8\nK:C\nC2 D2 E2\n");
+
+        // Eighths beam together; dashes lengthen notes and tie across beats
+        let tune = abc("12 -3 | 1-23 | 5 1\n");
+        assert!(tune.ends_with("K:C\nCD- DE | CD/2E/2 | G2 C2\n"), "{}", tune);
+    }
+
+    #[test]
+    fn test_composer_field() {
+        let tune = abc("Title: Air\nComposer: J. S. Bach\n\n|1|\n");
+        assert!(tune.starts_with("X:1\nT:Air\nC:J. S. Bach\nM:"), "{}", tune);
+        assert!(abc("Author: Trad.\n\n|1|\n").contains("\nC:Trad.\n"));
+    }
+
+    #[test]
+    fn test_accidentals_against_key_signature() {
+        // F is sharp in D major: a natural fourth needs "=", and accidentals last to the barline
+        let tune = abc("Key: D major\n\n4 4 4# | 4 5\n");
+        assert!(tune.contains("K:D\n=F2 F2 ^F2 | =F2 G2\n"), "{}", tune);
+        assert!(abc("Key: Am\n\n|1|\n").contains("K:Am\n"));
+    }
+}
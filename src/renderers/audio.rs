@@ -0,0 +1,92 @@
+/// WAV audio export
+/// Synthesizes the timed note events as sine tones, tuned to the document's `TuningReference` (or `Tuning`)
+/// and as loud as their dynamics, swung when the document asks for it
+use crate::parse::model::Document;
+use crate::renderers::midi::{playback_events, Tuning, DEFAULT_VELOCITY};
+
+pub const SAMPLE_RATE: u32 = 44_100;
+const AMPLITUDE: f64 = 0.25;
+/// Fade in/out at note edges, in seconds, to avoid clicks
+const FADE_SECONDS: f64 = 0.005;
+
+/// Render an analyzed document as 16-bit mono PCM WAV bytes
+pub fn render_wav(document: &Document, tempo_bpm: u32) -> Vec<u8> {
+    let tuning = Tuning::from_directives(&document.directives);
+    let events = playback_events(document, tempo_bpm);
+
+    let total_seconds = events.iter()
+        .map(|e| e.start_seconds + e.duration_seconds)
+        .fold(0.0, f64::max);
+    let mut samples = vec![0.0f64; (total_seconds * SAMPLE_RATE as f64).ceil() as usize];
+
+    for event in &events {
+        let frequency = tuning.frequency(event.midi_note);
+        let start = (event.start_seconds * SAMPLE_RATE as f64).round() as usize;
+        let length = (event.duration_seconds * SAMPLE_RATE as f64).round() as usize;
+        // Full amplitude at the default (mezzo-forte) velocity, scaled by the note's dynamic
+        let amplitude = AMPLITUDE * event.velocity as f64 / DEFAULT_VELOCITY as f64;
+        let fade = ((FADE_SECONDS * SAMPLE_RATE as f64) as usize).min(length / 2).max(1);
+        for (i, sample) in samples.iter_mut().skip(start).take(length).enumerate() {
+            let envelope = (i.min(length - 1 - i) as f64 / fade as f64).min(1.0);
+            let t = i as f64 / SAMPLE_RATE as f64;
+            *sample += amplitude * envelope * (2.0 * std::f64::consts::PI * frequency * t).sin();
+        }
+    }
+
+    write_wav(&samples)
+}
+
+/// Encode samples in -1.0..1.0 as a 16-bit mono PCM WAV file
+fn write_wav(samples: &[f64]) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes()); // byte rate
+    wav.extend_from_slice(&2u16.to_le_bytes()); // block align
+    wav.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f64).round() as i16;
+        wav.extend_from_slice(&value.to_le_bytes());
+    }
+    wav
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frequency of the tone in a WAV file, measured from its upward zero crossings
+    fn measured_frequency(wav: &[u8]) -> f64 {
+        let samples: Vec<i16> = wav[44..].chunks(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+        let crossings: Vec<usize> = samples.windows(2).enumerate()
+            .filter(|(_, w)| w[0] < 0 && w[1] >= 0)
+            .map(|(i, _)| i)
+            .collect();
+        let (first, last) = (crossings[0], crossings[crossings.len() - 1]);
+        (crossings.len() - 1) as f64 * SAMPLE_RATE as f64 / (last - first) as f64
+    }
+
+    #[test]
+    fn test_tuning_reference_shifts_a4() {
+        // "6" in number notation is A4
+        let document = |header: &str| crate::pipeline::document_from_notation(&format!("{}\n| 6 |\n", header)).unwrap();
+        let wav = render_wav(&document(""), 120);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert!((measured_frequency(&wav) - 440.0).abs() < 1.0);
+
+        let tuned = document("Tuning: 432\n");
+        assert!((measured_frequency(&render_wav(&tuned, 120)) - 432.0).abs() < 1.0);
+
+        let tuned = document("Tuning: 432\nTuningReference: 415\n");
+        assert!((measured_frequency(&render_wav(&tuned, 120)) - 415.0).abs() < 1.0);
+    }
+}
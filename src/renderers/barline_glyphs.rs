@@ -0,0 +1,94 @@
+/// Barline glyph table shared by the text renderers
+use crate::models::barlines::{Barline, BarlineType};
+use std::collections::HashMap;
+
+/// Maps each barline type to the characters used to draw it.
+/// `ascii()` reproduces the input syntax; the default is Unicode musical symbols.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BarlineGlyphs {
+    glyphs: HashMap<BarlineType, String>,
+}
+
+impl BarlineGlyphs {
+    /// Glyphs matching the music-text input syntax (`|`, `||`, `|.`, `|:`, `:|`, `:|:`)
+    pub fn ascii() -> Self {
+        Self::from_pairs(&[
+            (BarlineType::Single, "|"),
+            (BarlineType::Double, "||"),
+            (BarlineType::Final, "|."),
+            (BarlineType::RepeatStart, "|:"),
+            (BarlineType::RepeatEnd, ":|"),
+            (BarlineType::RepeatBoth, ":|:"),
+        ])
+    }
+
+    /// Unicode musical symbol glyphs
+    pub fn unicode() -> Self {
+        Self::from_pairs(&[
+            (BarlineType::Single, "\u{1D100}"),      // 𝄀
+            (BarlineType::Double, "\u{1D101}"),      // 𝄁
+            (BarlineType::Final, "\u{1D102}"),       // 𝄂
+            (BarlineType::RepeatStart, "\u{1D106}"), // 𝄆
+            (BarlineType::RepeatEnd, "\u{1D107}"),   // 𝄇
+            (BarlineType::RepeatBoth, "\u{1D107}\u{1D106}"),
+        ])
+    }
+
+    fn from_pairs(pairs: &[(BarlineType, &str)]) -> Self {
+        Self {
+            glyphs: pairs.iter().map(|(t, g)| (*t, g.to_string())).collect(),
+        }
+    }
+
+    /// Override the glyph for one barline type
+    pub fn with_glyph<S: Into<String>>(mut self, barline_type: BarlineType, glyph: S) -> Self {
+        self.glyphs.insert(barline_type, glyph.into());
+        self
+    }
+
+    pub fn glyph(&self, barline_type: BarlineType) -> &str {
+        self.glyphs.get(&barline_type).map(|g| g.as_str()).unwrap_or("|")
+    }
+
+    pub fn glyph_for(&self, barline: &Barline) -> &str {
+        self.glyph(barline.barline_type())
+    }
+}
+
+impl Default for BarlineGlyphs {
+    fn default() -> Self {
+        Self::unicode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_unicode_glyphs() {
+        let glyphs = BarlineGlyphs::default();
+        assert_eq!(glyphs.glyph(BarlineType::Single), "𝄀");
+        assert_eq!(glyphs.glyph(BarlineType::Double), "𝄁");
+        assert_eq!(glyphs.glyph(BarlineType::Final), "𝄂");
+        assert_eq!(glyphs.glyph(BarlineType::RepeatStart), "𝄆");
+        assert_eq!(glyphs.glyph(BarlineType::RepeatEnd), "𝄇");
+        assert_eq!(glyphs.glyph(BarlineType::RepeatBoth), "𝄇𝄆");
+    }
+
+    #[test]
+    fn test_ascii_glyphs_match_input_syntax() {
+        let glyphs = BarlineGlyphs::ascii();
+        let expected = ["|", "||", "|.", "|:", ":|", ":|:"];
+        for (barline_type, glyph) in BarlineType::ALL.iter().zip(expected) {
+            assert_eq!(glyphs.glyph(*barline_type), glyph);
+        }
+    }
+
+    #[test]
+    fn test_override_final_glyph() {
+        let glyphs = BarlineGlyphs::ascii().with_glyph(BarlineType::Final, "|]");
+        assert_eq!(glyphs.glyph(BarlineType::Final), "|]");
+        assert_eq!(glyphs.glyph(BarlineType::Double), "||");
+    }
+}
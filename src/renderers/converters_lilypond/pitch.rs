@@ -52,6 +52,24 @@ pub fn pitchcode_to_lilypond(pitchcode: PitchCode, octave: i8, tonic: Option<Pit
     Ok(format!("{}{}", base_note, octave_marks))
 }
 
+/// Add a microtone in quarter steps to an English LilyPond pitch (e.g. "cs'" raised a
+/// quarter tone is "ctqs'"), up to a double sharp or flat
+pub fn with_microtone(pitch: &str, quarter_steps: i8) -> Result<String, String> {
+    let name = pitch.trim_end_matches([',', '\'']);
+    let octave_marks = &pitch[name.len()..];
+    let (letter, accidental) = name.split_at(1);
+    let alteration = match accidental {
+        "ff" => -4, "f" => -2, "" => 0, "s" => 2, "ss" => 4,
+        _ => return Err(format!("Can't add a quarter tone to '{}'", pitch)),
+    };
+    let accidental = match alteration + quarter_steps as i32 {
+        -4 => "ff", -3 => "tqf", -2 => "f", -1 => "qf", 0 => "",
+        1 => "qs", 2 => "s", 3 => "tqs", 4 => "ss",
+        _ => return Err(format!("Can't add {} quarter tones to '{}'", quarter_steps, pitch)),
+    };
+    Ok(format!("{}{}{}", letter, accidental, octave_marks))
+}
+
 /// Convert PitchCode without octave information to LilyPond note name (for simple cases)
 pub fn pitchcode_to_lilypond_simple(pitchcode: PitchCode) -> &'static str {
     match pitchcode {
@@ -76,6 +94,15 @@ pub fn pitchcode_to_lilypond_simple(pitchcode: PitchCode) -> &'static str {
 mod tests {
     use super::*;
     
+    #[test]
+    fn test_with_microtone() {
+        assert_eq!(with_microtone("c'", 1).unwrap(), "cqs'");
+        assert_eq!(with_microtone("cs'", 1).unwrap(), "ctqs'");
+        assert_eq!(with_microtone("e,", -1).unwrap(), "eqf,");
+        assert_eq!(with_microtone("bf", -1).unwrap(), "btqf");
+        assert!(with_microtone("css", 1).is_err());
+    }
+
     #[test]
     fn test_pitchcode_to_lilypond_basic() {
         // Test basic conversion without transposition
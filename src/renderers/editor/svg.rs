@@ -53,6 +53,21 @@ pub struct EditorSvgConfig {
     pub show_selection: bool,
     pub selection_start: usize,
     pub selection_end: usize,
+    pub barline_glyphs: crate::renderers::barline_glyphs::BarlineGlyphs,
+    pub render_options: RenderOptions,
+}
+
+/// Output settings that keep the SVG byte-identical across runs, for snapshot tests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Decimal places written for every coordinate and size
+    pub coord_precision: u8,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self { coord_precision: 1 }
+    }
 }
 
 impl Default for EditorSvgConfig {
@@ -66,6 +81,8 @@ impl Default for EditorSvgConfig {
             show_selection: false,
             selection_start: 0,
             selection_end: 0,
+            barline_glyphs: crate::renderers::barline_glyphs::BarlineGlyphs::default(),
+            render_options: RenderOptions::default(),
         }
     }
 }
@@ -78,6 +95,11 @@ pub struct EditorSvgRenderer {
     char_positions: std::collections::HashMap<usize, (f32, f32)>,  // Maps character position to (x, y) coordinates
     element_coordinates: Vec<ElementCoordinate>,  // Track all element coordinates for JS access
     element_id_counter: usize,  // Simple counter for unique element IDs
+    ottava: Option<crate::renderers::ottava::Ottava>,
+    count_line: Option<crate::analysis::TimeSignature>,  // Meter of the counting line drawn under each content line
+    notehead_style: crate::renderers::noteheads::NoteheadStyle,  // Shape outlined around each note
+    ottava_shifts: std::collections::HashMap<uuid::Uuid, i8>,  // Octave shift of each bracketed note on the current line
+    note_extents: std::collections::HashMap<uuid::Uuid, (f32, f32)>,  // Start and end x of each note on the current line
 }
 
 /// Coordinate information for an element
@@ -101,9 +123,19 @@ impl EditorSvgRenderer {
             char_positions: std::collections::HashMap::new(),
             element_coordinates: Vec::new(),
             element_id_counter: 0,
+            ottava: None,
+            count_line: None,
+            notehead_style: Default::default(),
+            ottava_shifts: std::collections::HashMap::new(),
+            note_extents: std::collections::HashMap::new(),
         }
     }
 
+    /// Decimal places for coordinates in the output
+    fn coord_precision(&self) -> usize {
+        self.config.render_options.coord_precision as usize
+    }
+
     fn emit_newline(&mut self) {
         self.current_y += 60.0;
         self.current_x = 0.0;
@@ -112,6 +144,9 @@ impl EditorSvgRenderer {
     /// Render document to SVG optimized for canvas display
     pub fn render(&mut self, document: &Document, notation_type: &str, input_text: &str) -> Result<String, String> {
         let mut svg = String::new();
+        self.ottava = crate::renderers::ottava::Ottava::from_directives(&document.directives);
+        self.count_line = crate::analysis::TimeSignature::for_count_line(&document.directives);
+        self.notehead_style = crate::renderers::noteheads::NoteheadStyle::from_directives(&document.directives);
 
         // SVG header
         writeln!(svg, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
@@ -180,8 +215,8 @@ impl EditorSvgRenderer {
                         writeln!(svg, r##"<g class="{line_class}">"##).unwrap();
                         writeln!(
                             svg,
-                            r#"    <rect class="line-hitbox" x="-20" y="{}" width="{}" height="60" fill="transparent" />"#,
-                            self.current_y - 40.0, self.config.width
+                            r#"    <rect class="line-hitbox" x="-20" y="{:.p$}" width="{}" height="60" fill="transparent" />"#,
+                            self.current_y - 40.0, self.config.width, p = self.coord_precision()
                         ).unwrap();
 
                         match line {
@@ -243,8 +278,8 @@ impl EditorSvgRenderer {
                                             writeln!(svg, r##"<g class="blank-line">"##).unwrap();
                                             writeln!(
                                                 svg,
-                                                r#"    <rect class="line-hitbox" x="-20" y="{}" width="{}" height="60" fill="transparent" />"#,
-                                                self.current_y - 40.0, self.config.width
+                                                r#"    <rect class="line-hitbox" x="-20" y="{:.p$}" width="{}" height="60" fill="transparent" />"#,
+                                                self.current_y - 40.0, self.config.width, p = self.coord_precision()
                                             ).unwrap();
                                         }
                                     }
@@ -283,10 +318,10 @@ impl EditorSvgRenderer {
             writeln!(svg, r#"        {{"#).unwrap();
             writeln!(svg, r#"          "charStart": {},"#, coord.char_start).unwrap();
             writeln!(svg, r#"          "charEnd": {},"#, coord.char_end).unwrap();
-            writeln!(svg, r#"          "x": {:.1},"#, coord.x).unwrap();
-            writeln!(svg, r#"          "y": {:.1},"#, coord.y).unwrap();
-            writeln!(svg, r#"          "width": {:.1},"#, coord.width).unwrap();
-            writeln!(svg, r#"          "height": {:.1},"#, coord.height).unwrap();
+            writeln!(svg, r#"          "x": {:.p$},"#, coord.x, p = self.coord_precision()).unwrap();
+            writeln!(svg, r#"          "y": {:.p$},"#, coord.y, p = self.coord_precision()).unwrap();
+            writeln!(svg, r#"          "width": {:.p$},"#, coord.width, p = self.coord_precision()).unwrap();
+            writeln!(svg, r#"          "height": {:.p$},"#, coord.height, p = self.coord_precision()).unwrap();
             writeln!(svg, r#"          "type": "{}""#, coord.element_type).unwrap();
             writeln!(svg, r#"        }}{}"#, comma).unwrap();
         }
@@ -300,7 +335,7 @@ impl EditorSvgRenderer {
 
         for (i, (pos, (x, y))) in char_positions.iter().enumerate() {
             let comma = if i < char_positions.len() - 1 { "," } else { "" };
-            writeln!(svg, r#"        "{}": {{"x": {:.1}, "y": {:.1}}}{}"#, pos, x, y, comma).unwrap();
+            writeln!(svg, r#"        "{}": {{"x": {:.p$}, "y": {:.p$}}}{}"#, pos, x, y, comma, p = self.coord_precision()).unwrap();
         }
 
         writeln!(svg, r#"      }}"#).unwrap();
@@ -324,13 +359,12 @@ impl EditorSvgRenderer {
         if content_line.elements.is_empty() {
             // Create an invisible insertion point that can be targeted by UUID
             let char_width = self.get_char_width(" ");
-            writeln!(svg, r#"    <text id="char-{}" x="{:.1}" y="{}" class="char" data-source-uuid="{}" data-char-index="{}" data-width="{:.1}" style="opacity: 0;"> </text>"#,
+            writeln!(svg, r#"    <text id="char-{}" x="{:.p$}" y="{:.p$}" class="char" data-source-uuid="{}" data-char-index="{}" data-width="{:.p$}" style="opacity: 0;"> </text>"#,
                 self.element_id_counter,
                 self.current_x, self.current_y,
                 content_line.id,
                 *char_position,
-                char_width
-            ).unwrap();
+                char_width, p = self.coord_precision()).unwrap();
 
             // Store character position for cursor tracking
             self.char_positions.insert(*char_position, (self.current_x, self.current_y));
@@ -342,8 +376,13 @@ impl EditorSvgRenderer {
             return Ok(());
         }
 
+        let multi_measure_rests = crate::rhythm::measures::multi_measure_rests(&content_line.elements);
+        let ottava_spans = self.ottava.map(|o| o.spans(content_line)).unwrap_or_default();
+        self.ottava_shifts = crate::renderers::ottava::note_shifts(&ottava_spans);
+        self.note_extents.clear();
+
         // Iterate through content elements
-        for element in &content_line.elements {
+        for (index, element) in content_line.elements.iter().enumerate() {
             match element {
                 crate::models::ContentElement::Beat(beat) => {
                     self.render_beat_element(svg, beat, char_position)?;
@@ -355,13 +394,74 @@ impl EditorSvgRenderer {
                     self.render_whitespace_element(svg, whitespace, char_position)?;
                 }
                 crate::models::ContentElement::UnknownToken(token) => {
-                    self.render_unknown_token(svg, token, char_position)?;
+                    match multi_measure_rests.get(&index) {
+                        Some(&measures) => self.render_multi_measure_rest(svg, token, measures, char_position)?,
+                        None => self.render_unknown_token(svg, token, char_position)?,
+                    }
                 }
             }
         }
+
+        for span in &ottava_spans {
+            self.render_ottava_bracket(svg, span);
+        }
+        for (from, to) in crate::document::ties::tie_pairs(content_line) {
+            self.render_tie(svg, from.id, to.id);
+        }
+        if let Some(time_signature) = self.count_line {
+            self.render_count_line(svg, content_line, time_signature);
+        }
         Ok(())
     }
 
+    /// Write the counting syllable ("1 e & a") of each note under its first character
+    fn render_count_line(&self, svg: &mut String, content_line: &crate::models::ContentLine, time_signature: crate::analysis::TimeSignature) {
+        let y = self.current_y + 18.0;
+        writeln!(svg, r#"    <g class="count-line">"#).unwrap();
+        for (note, syllable) in crate::analysis::count_line(content_line, time_signature) {
+            let Some(&(start_x, _)) = self.note_extents.get(&note.id) else { continue };
+            if !syllable.is_empty() {
+                writeln!(svg, r#"      <text x="{:.p$}" y="{:.p$}" class="count-syllable">{}</text>"#,
+                    start_x, y, syllable, p = self.coord_precision()).unwrap();
+            }
+        }
+        writeln!(svg, "    </g>").unwrap();
+    }
+
+    /// Draw a tie arching over two notes, from the end of the first to the start of the second
+    fn render_tie(&self, svg: &mut String, from: uuid::Uuid, to: uuid::Uuid) {
+        let (Some(&(first_start, first_end)), Some(&(second_start, second_end))) =
+            (self.note_extents.get(&from), self.note_extents.get(&to)) else { return };
+        // Anchor at the note centres, like a tie between noteheads
+        let start_x = (first_start + first_end) / 2.0;
+        let end_x = (second_start + second_end) / 2.0;
+        let y = self.current_y - self.config.font_size * 0.9;
+        let control_y = y - 6.0;
+
+        writeln!(svg, r#"    <path d="M {:.p$} {:.p$} Q {:.p$} {:.p$} {:.p$} {:.p$}" class="tie" data-from="{}" data-to="{}"/>"#,
+            start_x, y, (start_x + end_x) / 2.0, control_y, end_x, y, from, to, p = self.coord_precision()).unwrap();
+    }
+
+    /// Draw a dashed "8va" bracket over (or "8vb" under) the notes of an ottava span
+    fn render_ottava_bracket(&self, svg: &mut String, span: &crate::renderers::ottava::OttavaSpan) {
+        let extents: Vec<(f32, f32)> = span.notes.iter().filter_map(|id| self.note_extents.get(id).copied()).collect();
+        let (Some(first), Some(last)) = (extents.first(), extents.last()) else { return };
+        let (start_x, end_x) = (first.0, last.1);
+        let (y, hook) = if span.shift > 0 {
+            (self.current_y - self.config.font_size * 1.6, 5.0)
+        } else {
+            (self.current_y + 28.0, -5.0)
+        };
+        let label_width = self.get_char_width(span.label()) * 0.7;
+
+        writeln!(svg, r#"    <g class="ottava" data-shift="{}">"#, span.shift).unwrap();
+        writeln!(svg, r#"      <text x="{:.p$}" y="{:.p$}" class="ottava-label">{}</text>"#,
+            start_x, y + 4.0, span.label(), p = self.coord_precision()).unwrap();
+        writeln!(svg, r#"      <path d="M {:.p$} {:.p$} H {:.p$} V {:.p$}" class="ottava-bracket"/>"#,
+            start_x + label_width, y, end_x, y + hook, p = self.coord_precision()).unwrap();
+        writeln!(svg, "    </g>").unwrap();
+    }
+
     /// Render a beat element with semantic styling
     fn render_beat_element(
         &mut self,
@@ -420,6 +520,10 @@ impl EditorSvgRenderer {
         char_position: &mut usize
     ) -> Result<(), String> {
         if let Some(value) = &note.value {
+            // Notes under an ottava bracket are written an octave nearer the staff
+            let written_octave = note.octave - self.ottava_shifts.get(&note.id).copied().unwrap_or(0);
+            let start_x = self.current_x;
+
             // Start note group
             writeln!(svg, r#"      <g class="note" data-note-id="{}" data-octave="{}">"#,
                 note.id, note.octave).unwrap();
@@ -432,16 +536,31 @@ impl EditorSvgRenderer {
                 self.char_positions.insert(*char_position, (self.current_x, self.current_y));
 
                 // Render the character
-                writeln!(svg, r#"        <text x="{:.1}" y="{:.1}" class="note-char" data-char-index="{}" data-width="{:.1}">{}</text>"#,
-                    self.current_x, self.current_y, char_position, char_width, ch).unwrap();
+                writeln!(svg, r#"        <text x="{:.p$}" y="{:.p$}" class="note-char" data-char-index="{}" data-width="{:.p$}">{}</text>"#,
+                    self.current_x, self.current_y, char_position, char_width, ch, p = self.coord_precision()).unwrap();
 
                 self.current_x += char_width;
                 *char_position += 1;
             }
 
+            if let Some(shape) = self.notehead_style.shape(note.pitch_code) {
+                self.render_notehead_shape(svg, shape, start_x, self.current_x);
+            }
+
             // Add octave dots if present
-            if note.octave != 0 {
-                self.render_octave_dots(svg, note.octave);
+            if written_octave != 0 {
+                self.render_octave_dots(svg, written_octave);
+            }
+            self.note_extents.insert(note.id, (start_x, self.current_x));
+
+            // The tie marker is part of the source text, drawn faintly after the note
+            if let Some(marker) = note.tie {
+                let char_width = self.get_char_width(&marker.to_string());
+                self.char_positions.insert(*char_position, (self.current_x, self.current_y));
+                writeln!(svg, r#"        <text x="{:.p$}" y="{:.p$}" class="tie-marker" data-char-index="{}" data-width="{:.p$}">{}</text>"#,
+                    self.current_x, self.current_y, char_position, char_width, marker, p = self.coord_precision()).unwrap();
+                self.current_x += char_width;
+                *char_position += 1;
             }
 
             writeln!(svg, "      </g>").unwrap();
@@ -463,8 +582,8 @@ impl EditorSvgRenderer {
                 let char_width = self.get_char_width(&ch.to_string());
                 self.char_positions.insert(*char_position, (self.current_x, self.current_y));
 
-                writeln!(svg, r#"        <text x="{:.1}" y="{:.1}" class="rest-char" data-char-index="{}" data-width="{:.1}">{}</text>"#,
-                    self.current_x, self.current_y, char_position, char_width, ch).unwrap();
+                writeln!(svg, r#"        <text x="{:.p$}" y="{:.p$}" class="rest-char" data-char-index="{}" data-width="{:.p$}">{}</text>"#,
+                    self.current_x, self.current_y, char_position, char_width, ch, p = self.coord_precision()).unwrap();
 
                 self.current_x += char_width;
                 *char_position += 1;
@@ -487,8 +606,8 @@ impl EditorSvgRenderer {
                 let char_width = self.get_char_width(&ch.to_string());
                 self.char_positions.insert(*char_position, (self.current_x, self.current_y));
 
-                writeln!(svg, r#"        <text x="{:.1}" y="{:.1}" class="dash-char" data-char-index="{}" data-width="{:.1}">{}</text>"#,
-                    self.current_x, self.current_y, char_position, char_width, ch).unwrap();
+                writeln!(svg, r#"        <text x="{:.p$}" y="{:.p$}" class="dash-char" data-char-index="{}" data-width="{:.p$}">{}</text>"#,
+                    self.current_x, self.current_y, char_position, char_width, ch, p = self.coord_precision()).unwrap();
 
                 self.current_x += char_width;
                 *char_position += 1;
@@ -509,8 +628,8 @@ impl EditorSvgRenderer {
                 let char_width = self.get_char_width(&ch.to_string());
                 self.char_positions.insert(*char_position, (self.current_x, self.current_y));
 
-                writeln!(svg, r#"        <text x="{:.1}" y="{:.1}" class="breath-char" data-char-index="{}" data-width="{:.1}">{}</text>"#,
-                    self.current_x, self.current_y, char_position, char_width, ch).unwrap();
+                writeln!(svg, r#"        <text x="{:.p$}" y="{:.p$}" class="breath-char" data-char-index="{}" data-width="{:.p$}">{}</text>"#,
+                    self.current_x, self.current_y, char_position, char_width, ch, p = self.coord_precision()).unwrap();
 
                 self.current_x += char_width;
                 *char_position += 1;
@@ -526,14 +645,9 @@ impl EditorSvgRenderer {
         barline: &crate::models::barlines::Barline,
         char_position: &mut usize
     ) -> Result<(), String> {
-        let barline_text = match barline {
-            crate::models::barlines::Barline::Single(_) => "|",
-            crate::models::barlines::Barline::Double(_) => "||",
-            crate::models::barlines::Barline::Final(_) => "|]",
-            crate::models::barlines::Barline::RepeatStart(_) => "|:",
-            crate::models::barlines::Barline::RepeatEnd(_) => ":|",
-            crate::models::barlines::Barline::RepeatBoth(_) => ":|:",
-        };
+        // Editable text is drawn one character per source character, so use the input syntax
+        let ascii_glyphs = crate::renderers::barline_glyphs::BarlineGlyphs::ascii();
+        let barline_text = ascii_glyphs.glyph_for(barline);
 
         writeln!(svg, r#"    <g class="barline">"#).unwrap();
 
@@ -541,8 +655,8 @@ impl EditorSvgRenderer {
             let char_width = self.get_char_width(&ch.to_string());
             self.char_positions.insert(*char_position, (self.current_x, self.current_y));
 
-            writeln!(svg, r#"      <text x="{:.1}" y="{:.1}" class="barline-char" data-char-index="{}" data-width="{:.1}">{}</text>"#,
-                self.current_x, self.current_y, char_position, char_width, ch).unwrap();
+            writeln!(svg, r#"      <text x="{:.p$}" y="{:.p$}" class="barline-char" data-char-index="{}" data-width="{:.p$}">{}</text>"#,
+                self.current_x, self.current_y, char_position, char_width, ch, p = self.coord_precision()).unwrap();
 
             self.current_x += char_width;
             *char_position += 1;
@@ -566,8 +680,8 @@ impl EditorSvgRenderer {
 
                 // Don't render visible text for spaces, just track position
                 if ch != ' ' {
-                    writeln!(svg, r#"    <text x="{:.1}" y="{:.1}" class="whitespace-char" data-char-index="{}" data-width="{:.1}">{}</text>"#,
-                        self.current_x, self.current_y, char_position, char_width, ch).unwrap();
+                    writeln!(svg, r#"    <text x="{:.p$}" y="{:.p$}" class="whitespace-char" data-char-index="{}" data-width="{:.p$}">{}</text>"#,
+                        self.current_x, self.current_y, char_position, char_width, ch, p = self.coord_precision()).unwrap();
                 }
 
                 self.current_x += char_width;
@@ -590,8 +704,8 @@ impl EditorSvgRenderer {
             let char_width = self.get_char_width(&ch.to_string());
             self.char_positions.insert(*char_position, (self.current_x, self.current_y));
 
-            writeln!(svg, r#"      <text x="{:.1}" y="{:.1}" class="unknown-char" data-char-index="{}" data-width="{:.1}">{}</text>"#,
-                self.current_x, self.current_y, char_position, char_width, ch).unwrap();
+            writeln!(svg, r#"      <text x="{:.p$}" y="{:.p$}" class="unknown-char" data-char-index="{}" data-width="{:.p$}">{}</text>"#,
+                self.current_x, self.current_y, char_position, char_width, ch, p = self.coord_precision()).unwrap();
 
             self.current_x += char_width;
             *char_position += 1;
@@ -601,6 +715,44 @@ impl EditorSvgRenderer {
         Ok(())
     }
 
+    /// Render a multi-measure rest (`R*8`): the typed text stays editable, with the
+    /// rest bar drawn above it and the number of measures centered over the bar
+    fn render_multi_measure_rest(
+        &mut self,
+        svg: &mut String,
+        token: &crate::models::UnknownToken,
+        measures: usize,
+        char_position: &mut usize
+    ) -> Result<(), String> {
+        writeln!(svg, r#"    <g class="multi-measure-rest" data-measures="{}">"#, measures).unwrap();
+
+        let start_x = self.current_x;
+        for ch in token.token_value.chars() {
+            let char_width = self.get_char_width(&ch.to_string());
+            self.char_positions.insert(*char_position, (self.current_x, self.current_y));
+
+            writeln!(svg, r#"      <text x="{:.p$}" y="{:.p$}" class="rest-char" data-char-index="{}" data-width="{:.p$}">{}</text>"#,
+                self.current_x, self.current_y, char_position, char_width, ch, p = self.coord_precision()).unwrap();
+
+            self.current_x += char_width;
+            *char_position += 1;
+        }
+
+        let bar_y = self.current_y - self.config.font_size * 1.2;
+        let center_x = (start_x + self.current_x) / 2.0;
+        writeln!(svg, r#"      <rect x="{:.p$}" y="{:.p$}" width="{:.p$}" height="4.0" class="multi-rest-bar"/>"#,
+            start_x, bar_y - 2.0, self.current_x - start_x, p = self.coord_precision()).unwrap();
+        for serif_x in [start_x, self.current_x] {
+            writeln!(svg, r#"      <line x1="{:.p$}" y1="{:.p$}" x2="{:.p$}" y2="{:.p$}" class="multi-rest-serif"/>"#,
+                serif_x, bar_y - 5.0, serif_x, bar_y + 5.0, p = self.coord_precision()).unwrap();
+        }
+        writeln!(svg, r#"      <text x="{:.p$}" y="{:.p$}" text-anchor="middle" class="multi-rest-count">{}</text>"#,
+            center_x, bar_y - 8.0, measures, p = self.coord_precision()).unwrap();
+
+        writeln!(svg, "    </g>").unwrap();
+        Ok(())
+    }
+
     /// Render text content
     fn render_text_content(
         &mut self,
@@ -616,8 +768,8 @@ impl EditorSvgRenderer {
                 self.current_y += 60.0;
                 self.current_x = 0.0;
             } else {
-                writeln!(svg, r#"    <text x="{:.1}" y="{:.1}" class="text-char" data-char-index="{}" data-width="{:.1}">{}</text>"#,
-                    self.current_x, self.current_y, char_position, char_width, ch).unwrap();
+                writeln!(svg, r#"    <text x="{:.p$}" y="{:.p$}" class="text-char" data-char-index="{}" data-width="{:.p$}">{}</text>"#,
+                    self.current_x, self.current_y, char_position, char_width, ch, p = self.coord_precision()).unwrap();
                 self.current_x += char_width;
             }
 
@@ -642,8 +794,8 @@ impl EditorSvgRenderer {
                 self.current_y += 60.0;
                 self.current_x = 0.0;
             } else {
-                writeln!(svg, r#"      <text x="{:.1}" y="{:.1}" class="lyrics-char" data-char-index="{}" data-width="{:.1}">{}</text>"#,
-                    self.current_x, self.current_y, char_position, char_width, ch).unwrap();
+                writeln!(svg, r#"      <text x="{:.p$}" y="{:.p$}" class="lyrics-char" data-char-index="{}" data-width="{:.p$}">{}</text>"#,
+                    self.current_x, self.current_y, char_position, char_width, ch, p = self.coord_precision()).unwrap();
                 self.current_x += char_width;
             }
 
@@ -671,8 +823,8 @@ impl EditorSvgRenderer {
                 self.current_y += 60.0;
                 self.current_x = 0.0;
             } else {
-                writeln!(svg, r#"      <text x="{:.1}" y="{:.1}" class="{}-char" data-char-index="{}" data-width="{:.1}">{}</text>"#,
-                    self.current_x, self.current_y, annotation_type, char_position, char_width, ch).unwrap();
+                writeln!(svg, r#"      <text x="{:.p$}" y="{:.p$}" class="{}-char" data-char-index="{}" data-width="{:.p$}">{}</text>"#,
+                    self.current_x, self.current_y, annotation_type, char_position, char_width, ch, p = self.coord_precision()).unwrap();
                 self.current_x += char_width;
             }
 
@@ -698,8 +850,8 @@ impl EditorSvgRenderer {
                 self.current_x = 0.0;
             } else if ch != ' ' {
                 // Only render visible whitespace characters
-                writeln!(svg, r#"    <text x="{:.1}" y="{}" class="whitespace-char" data-char-index="{}" data-width="{:.1}">{}</text>"#,
-                    self.current_x, self.current_y, char_position, char_width, ch).unwrap();
+                writeln!(svg, r#"    <text x="{:.p$}" y="{:.p$}" class="whitespace-char" data-char-index="{}" data-width="{:.p$}">{}</text>"#,
+                    self.current_x, self.current_y, char_position, char_width, ch, p = self.coord_precision()).unwrap();
             }
 
             if ch != '\n' {
@@ -710,6 +862,25 @@ impl EditorSvgRenderer {
         }
     }
 
+    /// Outline a shape-note notehead around the characters of a note
+    fn render_notehead_shape(&self, svg: &mut String, shape: crate::renderers::noteheads::NoteheadShape, start_x: f32, end_x: f32) {
+        use crate::renderers::noteheads::NoteheadShape::*;
+        let r = self.config.font_size * 0.45;
+        let (cx, cy) = ((start_x + end_x) / 2.0, self.current_y - self.config.font_size * 0.35);
+        let (left, right, top, bottom) = (cx - r, cx + r, cy - r, cy + r);
+        let p = self.coord_precision();
+        let d = match shape {
+            Triangle => format!("M {:.p$} {:.p$} L {:.p$} {:.p$} L {:.p$} {:.p$} Z", left, bottom, cx, top, right, bottom),
+            Moon => format!("M {:.p$} {:.p$} L {:.p$} {:.p$} A {:.p$} {:.p$} 0 0 1 {:.p$} {:.p$} Z", left, cy, right, cy, r, r, left, cy),
+            Diamond => format!("M {:.p$} {:.p$} L {:.p$} {:.p$} L {:.p$} {:.p$} L {:.p$} {:.p$} Z", cx, top, right, cy, cx, bottom, left, cy),
+            RightTriangle => format!("M {:.p$} {:.p$} L {:.p$} {:.p$} L {:.p$} {:.p$} Z", left, top, right, bottom, left, bottom),
+            Round => format!("M {:.p$} {:.p$} A {:.p$} {:.p$} 0 1 0 {:.p$} {:.p$} A {:.p$} {:.p$} 0 1 0 {:.p$} {:.p$} Z", left, cy, r, r, right, cy, r, r, left, cy),
+            Square => format!("M {:.p$} {:.p$} H {:.p$} V {:.p$} H {:.p$} Z", left, top, right, bottom, left),
+            Cone => format!("M {:.p$} {:.p$} A {:.p$} {:.p$} 0 0 1 {:.p$} {:.p$} L {:.p$} {:.p$} Z", left, cy, r, r, right, cy, cx, bottom),
+        };
+        writeln!(svg, r#"        <path d="{}" class="notehead-shape" data-shape="{}"/>"#, d, shape.name()).unwrap();
+    }
+
     /// Render octave dots above or below the current position
     fn render_octave_dots(&self, svg: &mut String, octave: i8) {
         let dot_offset = if octave > 0 { -10.0 } else { 10.0 };
@@ -717,8 +888,8 @@ impl EditorSvgRenderer {
 
         for i in 0..num_dots {
             let y = self.current_y + dot_offset - (i as f32 * 3.0 * octave.signum() as f32);
-            writeln!(svg, r#"        <circle cx="{:.1}" cy="{:.1}" r="1.5" class="octave-dot"/>"#,
-                self.current_x - 10.0, y).unwrap();
+            writeln!(svg, r#"        <circle cx="{:.p$}" cy="{:.p$}" r="1.5" class="octave-dot"/>"#,
+                self.current_x - 10.0, y, p = self.coord_precision()).unwrap();
         }
     }
 
@@ -832,6 +1003,61 @@ impl EditorSvgRenderer {
       cursor: text;
     }}
 
+    .ottava-label {{
+      font-size: 12px;
+      font-style: italic;
+      font-weight: bold;
+      fill: #333333;
+      font-family: serif;
+    }}
+
+    .ottava-bracket {{
+      fill: none;
+      stroke: #333333;
+      stroke-width: 1;
+      stroke-dasharray: 4 3;
+    }}
+
+    .tie {{
+      fill: none;
+      stroke: #333333;
+      stroke-width: 1.2;
+    }}
+
+    .notehead-shape {{
+      fill: none;
+      stroke: #555555;
+      stroke-width: 1;
+    }}
+
+    .count-syllable {{
+      font-size: 12px;
+      fill: #666666;
+      font-family: monospace, 'Courier New', monospace;
+    }}
+
+    .tie-marker {{
+      font-size: 12px;
+      fill: #999999;
+      font-family: monospace, 'Courier New', monospace;
+      cursor: text;
+    }}
+
+    .multi-rest-bar {{
+      fill: #333333;
+    }}
+
+    .multi-rest-serif {{
+      stroke: #333333;
+      stroke-width: 1.5;
+    }}
+
+    .multi-rest-count {{
+      font-size: 14px;
+      font-weight: bold;
+      fill: #333333;
+      font-family: serif;
+    }}
 
     .octave-dot {{
       fill: #ff0000;
@@ -943,57 +1169,53 @@ impl EditorSvgRenderer {
         line_text: &str,
         start_char_position: usize
     ) -> Result<(), String> {
-        // Extract character-to-UUID mapping from content line elements
+        // Extract character-to-UUID mapping from content line elements.
+        // Parsed elements carry their source span; the running index is only a
+        // fallback for elements created by edits, which have no source yet.
+        use crate::parse::model::HasPosition;
         let mut char_uuid_map = std::collections::HashMap::new();
+        // Characters of elements without an id of their own point at the line, so
+        // rendering the same document twice gives the same output
+        let line_uuid = content_line.id.to_string();
         let mut current_char_index = 0;
 
+        let mut map_span = |source: Option<&crate::parse::model::Source>, fallback_len: usize, uuid: String, current_char_index: &mut usize| {
+            let (start, len) = match source {
+                Some(source) => (source.column - 1, source.length),
+                None => (*current_char_index, fallback_len),
+            };
+            for idx in start..start + len {
+                char_uuid_map.insert(idx, uuid.clone());
+            }
+            *current_char_index = start + len;
+        };
+
         for content_element in &content_line.elements {
             match content_element {
                 crate::parse::model::ContentElement::Beat(beat) => {
                     for beat_element in &beat.elements {
-                        match beat_element {
-                            crate::parse::model::BeatElement::Note(note) => {
-                                if let Some(ref value) = note.value {
-                                    for _ in value.chars() {
-                                        char_uuid_map.insert(current_char_index, note.id.to_string());
-                                        current_char_index += 1;
-                                    }
-                                }
-                            }
-                            crate::parse::model::BeatElement::Dash(dash) => {
-                                if let Some(ref value) = dash.value {
-                                    for _ in value.chars() {
-                                        char_uuid_map.insert(current_char_index, dash.id.to_string());
-                                        current_char_index += 1;
-                                    }
-                                }
-                            }
-                            _ => {
-                                // Handle other beat elements
-                                current_char_index += 1;
-                            }
-                        }
+                        let fallback_len = beat_element.value().map_or(1, |v| v.chars().count());
+                        let uuid = match beat_element {
+                            crate::parse::model::BeatElement::Note(note) => note.id.to_string(),
+                            crate::parse::model::BeatElement::Dash(dash) => dash.id.to_string(),
+                            crate::parse::model::BeatElement::BreathMark(breath) => breath.id.to_string(),
+                            crate::parse::model::BeatElement::Rest(rest) => rest.id.to_string(),
+                        };
+                        map_span(beat_element.source(), fallback_len, uuid, &mut current_char_index);
                     }
                 }
                 crate::parse::model::ContentElement::Whitespace(ws) => {
-                    if let Some(ref value) = ws.value {
-                        for _ in value.chars() {
-                            // Use a fallback UUID for whitespace
-                            char_uuid_map.insert(current_char_index, uuid::Uuid::new_v4().to_string());
-                            current_char_index += 1;
-                        }
-                    }
+                    let fallback_len = ws.value.as_ref().map_or(0, |v| v.chars().count());
+                    // Whitespace has no UUID; fall back to the line's
+                    map_span(ws.source.as_ref(), fallback_len, line_uuid.clone(), &mut current_char_index);
                 }
                 crate::parse::model::ContentElement::UnknownToken(token) => {
-                    for _ in token.token_value.chars() {
-                        // Use a fallback UUID for unknown tokens
-                        char_uuid_map.insert(current_char_index, uuid::Uuid::new_v4().to_string());
-                        current_char_index += 1;
-                    }
+                    // Unknown tokens have no UUID; fall back to the line's
+                    map_span(token.source.as_ref(), token.token_value.chars().count(), line_uuid.clone(), &mut current_char_index);
                 }
-                _ => {
-                    // Handle other content elements
-                    current_char_index += 1;
+                crate::parse::model::ContentElement::Barline(barline) => {
+                    let fallback_len = barline.value().map_or(1, |v| v.chars().count());
+                    map_span(barline.source(), fallback_len, line_uuid.clone(), &mut current_char_index);
                 }
             }
         }
@@ -1006,20 +1228,19 @@ impl EditorSvgRenderer {
             // Get the UUID for this character, or generate fallback
             let char_uuid = char_uuid_map.get(&char_idx)
                 .cloned()
-                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+                .unwrap_or_else(|| line_uuid.clone());
 
             // Store character position
             self.char_positions.insert(char_pos, (self.current_x, self.current_y));
 
             // Render character with document UUID
-            writeln!(svg, r#"    <text id="char-{}" x="{:.1}" y="{}" class="char" data-source-uuid="{}" data-char-index="{}" data-width="{:.1}">{}</text>"#,
+            writeln!(svg, r#"    <text id="char-{}" x="{:.p$}" y="{:.p$}" class="char" data-source-uuid="{}" data-char-index="{}" data-width="{:.p$}">{}</text>"#,
                 self.element_id_counter,
                 self.current_x, self.current_y,
                 char_uuid,
                 char_idx,
                 char_width,
-                ch
-            ).unwrap();
+                ch, p = self.coord_precision()).unwrap();
 
             self.element_id_counter += 1;
             self.current_x += char_width;
@@ -1092,25 +1313,25 @@ impl EditorSvgRenderer {
                     });
 
                     // Render note with unique ID and data attributes for coordinate tracking
-                    writeln!(svg, r#"    <text id="el-{}" x="{:.1}" y="{}" class="canvas-note" data-char-start="{}" data-char-end="{}" data-element-type="note" data-width="{:.1}" data-height="{:.1}" data-note-id="{}">{}</text>"#,
+                    writeln!(svg, r#"    <text id="el-{}" x="{:.p$}" y="{:.p$}" class="canvas-note" data-char-start="{}" data-char-end="{}" data-element-type="note" data-width="{:.p$}" data-height="{:.p$}" data-note-id="{}">{}</text>"#,
                             self.element_id_counter,  // Use counter for unique ID
                             self.current_x, self.current_y,
                             start_char_position + chars_consumed,
                             start_char_position + chars_consumed + note_value.len(),
                             note_width, self.config.font_size,
                             note.id, // Include the note's UUID
-                            note_value).unwrap();
+                            note_value, p = self.coord_precision()).unwrap();
                     self.element_id_counter += 1;
 
                     // Render octave markers
                     if note.octave > 0 {
                         let dots = "•".repeat(note.octave as usize);
-                        writeln!(svg, r#"    <text x="{:.1}" y="{}" class="canvas-octave-upper">{}</text>"#,
-                                self.current_x + 3.0, self.current_y - 15.0, dots).unwrap();
+                        writeln!(svg, r#"    <text x="{:.p$}" y="{:.p$}" class="canvas-octave-upper">{}</text>"#,
+                                self.current_x + 3.0, self.current_y - 15.0, dots, p = self.coord_precision()).unwrap();
                     } else if note.octave < 0 {
                         let dots = "•".repeat((-note.octave) as usize);
-                        writeln!(svg, r#"    <text x="{:.1}" y="{}" class="canvas-octave-lower">{}</text>"#,
-                                self.current_x + 3.0, self.current_y + 20.0, dots).unwrap();
+                        writeln!(svg, r#"    <text x="{:.p$}" y="{:.p$}" class="canvas-octave-lower">{}</text>"#,
+                                self.current_x + 3.0, self.current_y + 20.0, dots, p = self.coord_precision()).unwrap();
                     }
 
                     self.current_x += note_width;
@@ -1140,11 +1361,11 @@ impl EditorSvgRenderer {
                         element_type: "dash".to_string(),
                     });
 
-                    writeln!(svg, r#"    <text id="el-{}" x="{:.1}" y="{}" class="canvas-note" data-char-start="{}" data-char-end="{}" data-element-type="dash" data-width="{:.1}" data-height="{:.1}">-</text>"#,
+                    writeln!(svg, r#"    <text id="el-{}" x="{:.p$}" y="{:.p$}" class="canvas-note" data-char-start="{}" data-char-end="{}" data-element-type="dash" data-width="{:.p$}" data-height="{:.p$}">-</text>"#,
                             self.element_id_counter,
                             self.current_x, self.current_y,
                             dash_pos, dash_pos + 1,
-                            dash_width, self.config.font_size).unwrap();
+                            dash_width, self.config.font_size, p = self.coord_precision()).unwrap();
                     self.element_id_counter += 1;
 
                     self.current_x += dash_width;
@@ -1165,12 +1386,12 @@ impl EditorSvgRenderer {
         // Add an invisible clickable rectangle covering the entire beat
         let beat_width = self.current_x - beat_start_x;
         if beat_width > 0.0 {
-            writeln!(svg, r#"      <rect x="{:.1}" y="{}" width="{:.1}" height="{:.1}" fill="transparent" data-char-start="{}" data-char-end="{}" data-beat-id="{}" data-element-type="beat"/>"#,
+            writeln!(svg, r#"      <rect x="{:.p$}" y="{:.p$}" width="{:.p$}" height="{:.p$}" fill="transparent" data-char-start="{}" data-char-end="{}" data-beat-id="{}" data-element-type="beat"/>"#,
                     beat_start_x, beat_y - self.config.font_size,
                     beat_width, self.config.font_size + 10.0,
                     start_char_position,
                     start_char_position + chars_consumed,
-                    beat.id).unwrap();
+                    beat.id, p = self.coord_precision()).unwrap();
         }
 
         // Close the beat group
@@ -1184,21 +1405,19 @@ impl EditorSvgRenderer {
     /// Render barline
     /// Render barline using SMuFL music font symbols
     fn render_barline(&mut self, svg: &mut String, barline: &crate::parse::model::Barline, char_position: usize) -> Result<f32, String> {
-        let symbol = match barline {
-            crate::parse::model::Barline::Single(_) => "|",
-            crate::parse::model::Barline::Double(_) => "||",
-            crate::parse::model::Barline::Final(_) => "||",
-            crate::parse::model::Barline::RepeatStart(_) => "|:",
-            crate::parse::model::Barline::RepeatEnd(_) => ":|",
-            crate::parse::model::Barline::RepeatBoth(_) => ":|:",
-        };
+        let symbol = self.config.barline_glyphs.glyph_for(barline).to_string();
+        let symbol = symbol.as_str();
+        // Source span length, not the glyph length, maps back to editor positions
+        let source_len = crate::parse::model::HasPosition::source(barline)
+            .map(|source| source.length)
+            .unwrap_or_else(|| crate::renderers::barline_glyphs::BarlineGlyphs::ascii().glyph_for(barline).len());
 
         let width_adjustment = self.get_char_width(symbol);
 
         // Track element coordinates
         self.element_coordinates.push(ElementCoordinate {
             char_start: char_position,
-            char_end: char_position + symbol.len(),
+            char_end: char_position + source_len,
             x: self.current_x,
             y: self.current_y,
             width: width_adjustment,
@@ -1206,12 +1425,12 @@ impl EditorSvgRenderer {
             element_type: "barline".to_string(),
         });
 
-        writeln!(svg, r#"    <text id="el-{}" x="{:.1}" y="{}" class="canvas-barline" data-char-start="{}" data-char-end="{}" data-element-type="barline" data-width="{:.1}" data-height="{:.1}">{}</text>"#,
+        writeln!(svg, r#"    <text id="el-{}" x="{:.p$}" y="{:.p$}" class="canvas-barline" data-char-start="{}" data-char-end="{}" data-element-type="barline" data-width="{:.p$}" data-height="{:.p$}">{}</text>"#,
                 self.element_id_counter,
                 self.current_x, self.current_y,
-                char_position, char_position + symbol.len(),
+                char_position, char_position + source_len,
                 width_adjustment, self.config.font_size,
-                symbol).unwrap();
+                symbol, p = self.coord_precision()).unwrap();
         self.element_id_counter += 1;
 
         self.current_x += width_adjustment + 10.0;
@@ -1221,8 +1440,8 @@ impl EditorSvgRenderer {
     /// Render cursor at specific position (DISABLED)
     fn render_cursor_at_position(&self, svg: &mut String, x: f32, y: f32) {
         // Line cursor disabled - no cursor rendering
-        // writeln!(svg, r#"    <line x1="{:.1}" y1="{}" x2="{:.1}" y2="{}" class="canvas-cursor" id="svg-cursor"/>"#,
-        //         x, y - 20.0, x, y + 5.0).unwrap();
+        // writeln!(svg, r#"    <line x1="{:.p$}" y1="{:.p$}" x2="{:.p$}" y2="{:.p$}" class="canvas-cursor" id="svg-cursor"/>"#,
+        //         x, y - 20.0, x, y + 5.0, p = self.coord_precision()).unwrap();
     }
 
     /// Render arc connecting beat elements
@@ -1246,8 +1465,8 @@ impl EditorSvgRenderer {
 
         // Use elliptical arc command: A rx,ry x-axis-rotation large-arc-flag,sweep-flag x,y
         // sweep-flag=0 creates an upward curve (counter-clockwise for hammock under notes)
-        writeln!(svg, r#"    <path d="M {:.1} {:.1} A {:.1} {:.1} 0 0 0 {:.1} {:.1}" class="canvas-beat-arc"/>"#,
-                start_x, arc_start_y, rx, ry, end_x, arc_end_y).unwrap();
+        writeln!(svg, r#"    <path d="M {:.p$} {:.p$} A {:.p$} {:.p$} 0 0 0 {:.p$} {:.p$}" class="canvas-beat-arc"/>"#,
+                start_x, arc_start_y, rx, ry, end_x, arc_end_y, p = self.coord_precision()).unwrap();
     }
 
     /// Render title at the top of the page
@@ -1255,8 +1474,8 @@ impl EditorSvgRenderer {
         let title_x = self.config.width / 2.0;
         let title_y = self.current_y + 30.0; // Position from top
 
-        writeln!(svg, r#"    <text x="{:.1}" y="{:.1}" class="canvas-title" text-anchor="middle">{}</text>"#,
-                title_x, title_y, title).unwrap();
+        writeln!(svg, r#"    <text x="{:.p$}" y="{:.p$}" class="canvas-title" text-anchor="middle">{}</text>"#,
+                title_x, title_y, title, p = self.coord_precision()).unwrap();
 
         Ok(())
     }
@@ -1279,8 +1498,8 @@ impl EditorSvgRenderer {
         let text_x = self.config.width / 2.0;
         let text_y = self.config.height / 2.0;
 
-        writeln!(svg, r#"    <text x="{:.1}" y="{:.1}" class="canvas-placeholder" text-anchor="middle">{}</text>"#,
-                text_x, text_y, display_text).unwrap();
+        writeln!(svg, r#"    <text x="{:.p$}" y="{:.p$}" class="canvas-placeholder" text-anchor="middle">{}</text>"#,
+                text_x, text_y, display_text, p = self.coord_precision()).unwrap();
 
         // Cursor rendering removed - handled by client-side JavaScript
 
@@ -1310,8 +1529,8 @@ impl EditorSvgRenderer {
     fn render_selection_at_range(&self, svg: &mut String, start_x: f32, end_x: f32, y: f32) {
         let selection_height = self.config.font_size + 10.0;
         let selection_y = y - self.config.font_size + 5.0;
-        writeln!(svg, r#"    <rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" class="canvas-selection"/>"#,
-                start_x - 2.0, selection_y, end_x - start_x + 4.0, selection_height).unwrap();
+        writeln!(svg, r#"    <rect x="{:.p$}" y="{:.p$}" width="{:.p$}" height="{:.p$}" class="canvas-selection"/>"#,
+                start_x - 2.0, selection_y, end_x - start_x + 4.0, selection_height, p = self.coord_precision()).unwrap();
     }
 
     /// Get accurate character width using font metrics
@@ -1328,8 +1547,8 @@ impl EditorSvgRenderer {
 
         // Just use the raw text value from the line
         if let Some(ref text) = lyrics_line.value {
-            writeln!(svg, r#"    <text x="{:.1}" y="{}" class="canvas-lyrics">{}</text>"#,
-                    lyrics_x, lyrics_y, text).unwrap();
+            writeln!(svg, r#"    <text x="{:.p$}" y="{:.p$}" class="canvas-lyrics">{}</text>"#,
+                    lyrics_x, lyrics_y, text, p = self.coord_precision()).unwrap();
         }
 
         Ok(())
@@ -1347,8 +1566,8 @@ impl EditorSvgRenderer {
             }
 
             // Render the text
-            writeln!(svg, r#"    <text x="{:.1}" y="{}" class="canvas-text">{}</text>"#,
-                    self.current_x, self.current_y, text_value).unwrap();
+            writeln!(svg, r#"    <text x="{:.p$}" y="{:.p$}" class="canvas-text">{}</text>"#,
+                    self.current_x, self.current_y, text_value, p = self.coord_precision()).unwrap();
 
             *char_position += text_value.len();
         }
@@ -1401,6 +1620,19 @@ pub fn render_editor_svg(
         config.selection_end = end;
     }
 
+    render_with_config(document, config)
+}
+
+/// Render editor SVG with explicit output settings, e.g. a fixed coordinate precision for snapshots
+pub fn render_editor_svg_with_options(document: &Document, options: RenderOptions) -> Result<String, String> {
+    let config = EditorSvgConfig {
+        render_options: options,
+        ..EditorSvgConfig::default()
+    };
+    render_with_config(document, config)
+}
+
+fn render_with_config(document: &Document, config: EditorSvgConfig) -> Result<String, String> {
     let mut renderer = EditorSvgRenderer::new(config);
     // Use the document's value field as the input text
     let empty = String::new();
@@ -1408,3 +1640,96 @@ pub fn render_editor_svg(
     renderer.render(document, "", input_text)
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::document_from_notation;
+
+    fn attribute(tag: &str, name: &str) -> f32 {
+        let start = tag.find(&format!(r#" {}=""#, name)).unwrap() + name.len() + 3;
+        tag[start..].split('"').next().unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn test_multi_measure_rest_count_centered_above_glyph() {
+        let document = document_from_notation("| R*8 |\n").unwrap();
+        let svg = render_editor_svg(&document, None, None, None).unwrap();
+        assert!(svg.contains(r#"<g class="multi-measure-rest" data-measures="8">"#));
+
+        let bar = svg.lines().find(|l| l.contains(r#"class="multi-rest-bar""#)).expect("rest bar");
+        let count = svg.lines().find(|l| l.contains(r#"class="multi-rest-count""#)).expect("measure count");
+        assert!(count.contains(r#"text-anchor="middle""#));
+        assert!(count.contains(">8</text>"));
+
+        let bar_center = attribute(bar, "x") + attribute(bar, "width") / 2.0;
+        assert!((attribute(count, "x") - bar_center).abs() < 0.1);
+        assert!(attribute(count, "y") < attribute(bar, "y"));
+    }
+
+    #[test]
+    fn test_high_run_drawn_under_8va_bracket() {
+        let mut document = document_from_notation("Ottava: auto\n\n1 2 3\n").unwrap();
+        for note in document.notes_mut() {
+            note.octave = 3;
+        }
+
+        let svg = render_editor_svg(&document, None, None, None).unwrap();
+        assert_eq!(svg.matches(r#"<g class="ottava" data-shift="1">"#).count(), 1);
+        assert!(svg.contains(r#"class="ottava-label">8va</text>"#));
+        // Written an octave lower: two dots per note instead of three
+        assert_eq!(svg.matches(r#"class="octave-dot""#).count(), 6);
+    }
+
+    #[test]
+    fn test_rendering_is_byte_identical() {
+        let document = document_from_notation("1-2 | R*2 | x 3 4").unwrap();
+        let options = RenderOptions { coord_precision: 3 };
+        let first = render_editor_svg_with_options(&document, options).unwrap();
+        let second = render_editor_svg_with_options(&document, options).unwrap();
+        assert_eq!(first, second);
+
+        let note = first.lines().find(|l| l.contains(r#"class="note-char""#)).unwrap();
+        assert!(note.contains(r#"x="0.000" y="20.000""#), "{}", note);
+    }
+
+    #[test]
+    fn test_explicit_tie_arcs_across_barline() {
+        let svg = render_editor_svg(&document_from_notation("|1~|1|").unwrap(), None, None, None).unwrap();
+        let ties: Vec<&str> = svg.lines().filter(|l| l.contains(r#"class="tie""#)).collect();
+        assert_eq!(ties.len(), 1);
+        assert!(svg.contains(r#"class="tie-marker" data-char-index="2""#));
+
+        // The arc starts over the first note and ends over the second, past the barline
+        let path = ties[0].split(r#"d="M "#).nth(1).unwrap();
+        let coords: Vec<f32> = path.split(['"', ' ']).take(6).filter_map(|c| c.parse().ok()).collect();
+        let barline = svg.lines().filter(|l| l.contains(r#"class="barline-char""#)).nth(1).unwrap();
+        assert!(coords[0] < attribute(barline, "x") && attribute(barline, "x") < coords[4], "{:?}", coords);
+    }
+
+    #[test]
+    fn test_count_line_under_notes() {
+        let document = document_from_notation("CountLine: on\n\n12 34 56 71\n").unwrap();
+        let svg = render_editor_svg(&document, None, None, None).unwrap();
+        let syllables: Vec<&str> = svg.lines()
+            .filter(|l| l.contains(r#"class="count-syllable""#))
+            .filter_map(|l| l.split('>').nth(1)?.split('<').next())
+            .collect();
+        assert_eq!(syllables, vec!["1", "&", "2", "&", "3", "&", "4", "&"]);
+        let notes: Vec<f32> = svg.lines().filter(|l| l.contains(r#"class="note-char""#)).map(|l| attribute(l, "x")).collect();
+        let counts: Vec<f32> = svg.lines().filter(|l| l.contains(r#"class="count-syllable""#)).map(|l| attribute(l, "x")).collect();
+        assert_eq!(notes, counts);
+    }
+
+    #[test]
+    fn test_shape_notes_outlined_by_degree() {
+        let document = document_from_notation("NoteheadStyle: aiken\n\n1 2 3 4 5 6 7\n").unwrap();
+        let svg = render_editor_svg(&document, None, None, None).unwrap();
+        let shapes: Vec<&str> = svg.lines()
+            .filter(|l| l.contains(r#"class="notehead-shape""#))
+            .filter_map(|l| l.split(r#"data-shape=""#).nth(1)?.split('"').next())
+            .collect();
+        assert_eq!(shapes, vec!["triangle", "moon", "diamond", "right-triangle", "round", "square", "cone"]);
+        assert!(!render_editor_svg(&document_from_notation("1 2 3").unwrap(), None, None, None).unwrap().contains(r#"class="notehead-shape""#));
+    }
+}
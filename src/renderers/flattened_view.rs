@@ -0,0 +1,122 @@
+/// Flattened spatial text view for debugging annotation alignment.
+/// Content lines are shown as written; in the upper, lower and lyrics lines around
+/// them, markers that sit over a note are "consumed" by it and are either
+/// reverse-highlighted or hidden, uniformly for every annotation line.
+use crate::document::line_parser::content_line_parser::unused_parse_content_line;
+use crate::parse::model::{ContentElement, BeatElement, NotationSystem};
+
+const REVERSE: &str = "\x1b[7m";
+const RESET: &str = "\x1b[0m";
+
+/// Characters that may appear in upper and lower annotation lines
+const ANNOTATION_MARKERS: &[char] = &['.', ':', '*', '_', '~', '\''];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FlattenedViewOptions {
+    /// Show consumed markers reverse-highlighted (debugging) instead of hiding them
+    pub show_consumed: bool,
+}
+
+/// Render the flattened view of notation text
+pub fn generate_flattened_spatial_view(input: &str, notation_system: NotationSystem, options: FlattenedViewOptions) -> String {
+    let lines: Vec<&str> = input.lines().collect();
+    let note_columns: Vec<Option<Vec<usize>>> = lines.iter()
+        .map(|line| content_note_columns(line, notation_system))
+        .collect();
+
+    let mut output = Vec::with_capacity(lines.len());
+    for (i, line) in lines.iter().enumerate() {
+        if note_columns[i].is_some() {
+            output.push(line.to_string());
+            continue;
+        }
+
+        // Upper lines belong to the content line below, lower lines to the one above,
+        // and lyrics to the nearest content line above any lower lines
+        let is_annotation = is_annotation_line(line);
+        let below = note_columns.get(i + 1).and_then(|c| c.as_ref());
+        let above = i.checked_sub(1).and_then(|j| note_columns[j].as_ref());
+        let owner = if is_annotation {
+            below.or(above)
+        } else {
+            (0..i).rev()
+                .take_while(|j| note_columns[*j].is_some() || is_annotation_line(lines[*j]))
+                .find_map(|j| note_columns[j].as_ref())
+        };
+
+        match owner {
+            Some(columns) => output.push(render_annotation_line(line, columns, is_annotation, options)),
+            None => output.push(line.to_string()),
+        }
+    }
+    output.join("\n")
+}
+
+fn is_annotation_line(line: &str) -> bool {
+    !line.trim().is_empty() && line.chars().all(|c| c == ' ' || ANNOTATION_MARKERS.contains(&c))
+}
+
+/// 1-based columns of the notes on a content line, or None if it is not one
+fn content_note_columns(line: &str, notation_system: NotationSystem) -> Option<Vec<usize>> {
+    let content_line = unused_parse_content_line(line, 0, notation_system, 0).ok()?;
+    if content_line.elements.iter().any(|e| matches!(e, ContentElement::UnknownToken(_))) {
+        return None;
+    }
+    let columns: Vec<usize> = content_line.elements.iter()
+        .filter_map(|e| match e {
+            ContentElement::Beat(beat) => Some(beat),
+            _ => None,
+        })
+        .flat_map(|beat| beat.elements.iter())
+        .filter_map(|e| match e {
+            BeatElement::Note(note) => note.source.map(|s| s.column),
+            _ => None,
+        })
+        .collect();
+    if columns.is_empty() { None } else { Some(columns) }
+}
+
+/// Markers (or, for lyrics, whole syllables) starting over a note column are consumed
+fn render_annotation_line(line: &str, note_columns: &[usize], is_annotation: bool, options: FlattenedViewOptions) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut rendered = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let token_len = if is_annotation || chars[i] == ' ' {
+            1
+        } else {
+            chars[i..].iter().take_while(|c| **c != ' ').count()
+        };
+        let token: String = chars[i..i + token_len].iter().collect();
+        let consumed = chars[i] != ' ' && note_columns.contains(&(i + 1));
+
+        if !consumed {
+            rendered.push_str(&token);
+        } else if options.show_consumed {
+            rendered.push_str(&format!("{}{}{}", REVERSE, token, RESET));
+        } else {
+            rendered.push_str(&" ".repeat(token_len));
+        }
+        i += token_len;
+    }
+    rendered.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "  .\n1 2 3\n    :\nla la la";
+
+    #[test]
+    fn test_show_consumed_highlights_markers() {
+        let view = generate_flattened_spatial_view(INPUT, NotationSystem::Number, FlattenedViewOptions { show_consumed: true });
+        assert_eq!(view, "  \x1b[7m.\x1b[0m\n1 2 3\n    \x1b[7m:\x1b[0m\n\x1b[7mla\x1b[0m la la");
+    }
+
+    #[test]
+    fn test_hide_consumed_removes_markers() {
+        let view = generate_flattened_spatial_view(INPUT, NotationSystem::Number, FlattenedViewOptions::default());
+        assert_eq!(view, "\n1 2 3\n\n   la la");
+    }
+}
@@ -0,0 +1,60 @@
+/// Self-contained interactive HTML export
+/// Assembles the editor SVG, timed events and MIDI data into one page with playback highlighting
+use crate::parse::model::Document;
+use crate::renderers::midi::{playback_events, write_smf_tuned, Tuning};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+struct InteractiveHtmlContext {
+    title: Option<String>,
+    file_name: String,
+    svg: String,
+    events_json: String,
+    midi_base64: String,
+}
+
+/// Render an analyzed document as a standalone HTML page that plays the music
+/// with Web Audio and highlights each note while it sounds
+pub fn render_interactive_html(document: &Document, tempo_bpm: u32) -> Result<String, String> {
+    let svg = crate::renderers::editor::svg::render_editor_svg(document, None, None, None)?;
+
+    let events = playback_events(document, tempo_bpm);
+    let events_json = serde_json::to_string(&events)
+        .map_err(|e| format!("Failed to serialize timed events: {}", e))?
+        // Keep the JSON from closing its <script> element early
+        .replace("</", "<\\/");
+    let midi_base64 = BASE64.encode(write_smf_tuned(&events, tempo_bpm, Tuning::from_directives(&document.directives)));
+
+    let title = document.title.clone().or_else(|| crate::parse::model::directive(&document.directives, crate::parse::line_classifier::TITLE_DIRECTIVE).map(str::to_string));
+    let context = InteractiveHtmlContext {
+        file_name: title.clone().unwrap_or_else(|| "music-text".to_string()),
+        title,
+        svg,
+        events_json,
+        midi_base64,
+    };
+
+    let template = mustache::compile_str(include_str!("templates/interactive.html.mustache"))
+        .map_err(|e| format!("Template compile error: {}", e))?;
+    template.render_to_string(&context)
+        .map_err(|e| format!("Template render error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interactive_html_assembles_svg_events_and_audio() {
+        let document = crate::pipeline::document_from_notation("Practice\n\n| 1 2 |\n").unwrap();
+        let html = render_interactive_html(&document, 120).unwrap();
+        let events = playback_events(&document, 120);
+        let midi_base64 = BASE64.encode(crate::renderers::midi::write_smf(&events, 120));
+
+        assert!(html.contains("<svg"));
+        assert!(html.contains(&serde_json::to_string(&events).unwrap()));
+        assert!(html.contains(&format!("data:audio/midi;base64,{}", midi_base64)));
+        assert!(html.contains(&events[0].id));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod interactive;
+
+pub use interactive::*;
@@ -0,0 +1,168 @@
+/// Incipit thumbnails
+/// A compact staff notation SVG of a piece's opening - clef, key signature and the first
+/// few measures of its first content line - for catalogs and search results. Titles,
+/// headers and annotation lines are left out.
+use crate::document::line_parser::content_line_parser::to_source;
+use crate::parse::model::{ContentElement, ContentLine, Document, DocumentElement, Stave, StaveLine};
+use crate::renderers::svg::staff_svg;
+
+/// Tonic letter, accidental ("", "#" or "b") and whether the key is minor, for a key such
+/// as "D", "Bb major", "f# minor" or "Am"
+fn parse_key(key: &str) -> Option<(char, &'static str, bool)> {
+    let mut words = key.split_whitespace();
+    let tonic = words.next()?;
+    let (tonic, minor_suffix) = match tonic.strip_suffix('m') {
+        Some(stripped) if !stripped.is_empty() => (stripped, true),
+        _ => (tonic, false),
+    };
+    let mut chars = tonic.chars();
+    let letter = chars.next()?.to_ascii_uppercase();
+    if !('A'..='G').contains(&letter) {
+        return None;
+    }
+    let accidental = match chars.as_str() {
+        "" => "",
+        "#" | "s" => "#",
+        "b" | "f" => "b",
+        _ => return None,
+    };
+    let minor = minor_suffix || matches!(words.next().map(str::to_lowercase).as_deref(), Some("minor") | Some("min"));
+    Some((letter, accidental, minor))
+}
+
+/// Sharps (positive) or flats (negative) in the signature of a key such as "D", "Bb major",
+/// "f# minor" or "Am"; `None` when the tonic can't be read
+pub fn key_signature_fifths(key: &str) -> Option<i8> {
+    let (letter, accidental, minor) = parse_key(key)?;
+    let letter_fifths = match letter {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => -1,
+        'G' => 1,
+        'A' => 3,
+        _ => 5,
+    };
+    let accidental_fifths = match accidental {
+        "#" => 7,
+        "b" => -7,
+        _ => 0,
+    };
+    // A minor key shares its signature with the major key a minor third above
+    let mode_fifths = if minor { -3 } else { 0 };
+    let fifths = letter_fifths + accidental_fifths + mode_fifths;
+    (-7..=7).contains(&fifths).then_some(fifths)
+}
+
+/// Letters (as degree indices) in the order sharps enter a key signature: F C G D A E B.
+/// Flats enter in the reverse order.
+pub(crate) const SHARP_ORDER: [usize; 7] = [3, 0, 4, 1, 5, 2, 6];
+
+/// Alteration of each letter C-B under a key signature of `fifths` sharps (or flats if negative)
+pub(crate) fn key_alterations(fifths: i8) -> [i8; 7] {
+    let mut alterations = [0; 7];
+    for i in 0..fifths.unsigned_abs() as usize {
+        if fifths > 0 {
+            alterations[SHARP_ORDER[i]] = 1;
+        } else {
+            alterations[SHARP_ORDER[6 - i]] = -1;
+        }
+    }
+    alterations
+}
+
+/// Short name of a key as ABC and VexFlow write it: "D", "Bb", "F#m"
+pub fn key_short_name(key: &str) -> Option<String> {
+    key_signature_fifths(key)?;
+    let (letter, accidental, minor) = parse_key(key)?;
+    Some(format!("{}{}{}", letter, accidental, if minor { "m" } else { "" }))
+}
+
+/// The first `measures` measures of a content line; the barline closing the last one is kept
+fn first_measures(content_line: &ContentLine, measures: usize) -> ContentLine {
+    let mut elements = Vec::new();
+    let (mut completed, mut has_beats) = (0, false);
+    for element in &content_line.elements {
+        if completed == measures {
+            break;
+        }
+        match element {
+            ContentElement::Beat(_) => has_beats = true,
+            ContentElement::Barline(_) if has_beats => {
+                completed += 1;
+                has_beats = false;
+            }
+            _ => {}
+        }
+        elements.push(element.clone());
+    }
+    let mut opening = ContentLine { id: content_line.id, elements, value: None };
+    opening.value = Some(to_source(&opening));
+    opening
+}
+
+/// A document holding only the opening of the first content line, keeping the directives
+fn incipit_document(document: &Document, measures: usize) -> Option<Document> {
+    let (stave, content_line) = document.elements.iter().find_map(|element| match element {
+        DocumentElement::Stave(stave) => stave.lines.iter().find_map(|line| match line {
+            StaveLine::ContentLine(content_line) => Some((stave, content_line)),
+            _ => None,
+        }),
+        _ => None,
+    })?;
+    let opening = first_measures(content_line, measures);
+    Some(Document {
+        value: opening.value.clone(),
+        title: None,
+        author: None,
+        elements: vec![DocumentElement::Stave(Stave {
+            value: opening.value.clone(),
+            lines: vec![StaveLine::ContentLine(opening)],
+            ..stave.clone()
+        })],
+        ..document.clone()
+    })
+}
+
+/// Render the first `measures` measures as staff notation, with the clef and the key
+/// signature of the `Key` directive, in a standalone SVG sized to its content
+pub fn render_incipit(document: &Document, measures: usize) -> Result<String, String> {
+    let incipit = incipit_document(document, measures)
+        .ok_or_else(|| "Document has no content line for an incipit".to_string())?;
+    Ok(staff_svg(&incipit, &format!("class=\"incipit\" data-measures=\"{}\"", measures)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incipit_renders_first_measures_with_clef_and_key() {
+        let document = crate::pipeline::document_from_notation(
+            "Opening Theme\nKey: D major\n\n| 2 3 4# 5 | 6 7 1# 2 | 2 2 2 2 |\n",
+        ).unwrap();
+        let svg = render_incipit(&document, 2).unwrap();
+        assert!(svg.contains(r#"class="incipit" data-measures="2""#));
+        assert!(svg.contains(r#"class="clef""#));
+        assert!(svg.contains(r#"<g class="key-signature" data-fifths="2">"#));
+        assert_eq!(svg.matches(r#"class="key-accidental""#).count(), 2);
+        // F# and C# come from the key signature
+        assert_eq!(svg.matches(r#"class="accidental""#).count(), 0);
+        assert_eq!(svg.matches(r#"class="notehead""#).count(), 8);
+        assert_eq!(svg.matches(r#"class="barline""#).count(), 3);
+        assert!(!svg.contains("Opening Theme"));
+    }
+
+    #[test]
+    fn test_key_signature_fifths() {
+        assert_eq!(key_signature_fifths("C"), Some(0));
+        assert_eq!(key_signature_fifths("Bb major"), Some(-2));
+        assert_eq!(key_signature_fifths("f# minor"), Some(3));
+        assert_eq!(key_signature_fifths("D minor"), Some(-1));
+        assert_eq!(key_signature_fifths("Am"), Some(0));
+        assert_eq!(key_signature_fifths("Bbm"), Some(-5));
+        assert_eq!(key_signature_fifths("H"), None);
+        assert_eq!(key_short_name("f# minor").as_deref(), Some("F#m"));
+        assert_eq!(key_short_name("Bb major").as_deref(), Some("Bb"));
+    }
+}
@@ -0,0 +1,206 @@
+/// Jianpu (Chinese numbered notation) as HTML
+/// Each note is its scale degree 1-7 with octave dots above or below it and accidentals
+/// in front; eighths and shorter are underlined once per beam, the underlines running on
+/// under the notes of a beat like beams do. Notes of two beats or more are followed by a
+/// dash per extra beat, dotted values by a dot, and rests are written 0.
+use crate::models::barlines::BarlineType;
+use crate::parse::model::{Beat, BeatElement, ContentElement, Document, DocumentElement, Note, StaveLine};
+use crate::renderers::barline_glyphs::BarlineGlyphs;
+use crate::renderers::note_names::degree_and_accidental;
+use crate::renderers::svg::{beam_count, escape, note_value};
+use fraction::Fraction;
+
+/// Styling for the markup: dots stacked over and under the digit, one bottom border per underline
+const JIANPU_CSS: &str = "\
+.jianpu { font-family: sans-serif; font-size: 20px; }
+.jianpu-line { display: flex; align-items: center; gap: 0.6em; margin: 1.2em 0; }
+.jianpu-beat, .jianpu-underline { display: inline-flex; align-items: flex-start; }
+.jianpu-underline { border-bottom: 1px solid currentColor; padding-bottom: 2px; }
+.jianpu-note { display: inline-flex; flex-direction: column; align-items: center; position: relative; line-height: 1; padding: 0 0.1em; }
+.jianpu-octave-above, .jianpu-octave-below { font-size: 0.5em; line-height: 0.6; writing-mode: vertical-lr; }
+.jianpu-accidental { position: absolute; left: -0.4em; top: 0; font-size: 0.6em; }
+";
+
+/// Render every content line of a rhythm-analyzed document as jianpu, in an HTML
+/// fragment carrying its own stylesheet
+pub fn render_jianpu_html(document: &Document) -> String {
+    let glyphs = BarlineGlyphs::ascii()
+        .with_glyph(BarlineType::Double, "‖")
+        .with_glyph(BarlineType::Final, "|‖")
+        .with_glyph(BarlineType::RepeatStart, "‖:")
+        .with_glyph(BarlineType::RepeatEnd, ":‖")
+        .with_glyph(BarlineType::RepeatBoth, ":‖:");
+
+    let mut html = format!("<div class=\"jianpu\">\n<style>\n{}</style>\n", JIANPU_CSS);
+    if let Some(title) = &document.title {
+        html.push_str(&format!("<div class=\"jianpu-title\">{}</div>\n", escape(title)));
+    }
+    let content_lines = document.elements.iter()
+        .filter_map(|element| match element {
+            DocumentElement::Stave(stave) => Some(stave),
+            _ => None,
+        })
+        .flat_map(|stave| &stave.lines)
+        .filter_map(|line| match line {
+            StaveLine::ContentLine(content_line) => Some(content_line),
+            _ => None,
+        });
+    for content_line in content_lines {
+        html.push_str("<div class=\"jianpu-line\">");
+        for element in &content_line.elements {
+            match element {
+                ContentElement::Beat(beat) => html.push_str(&render_beat(beat)),
+                ContentElement::Barline(barline) => {
+                    html.push_str(&format!("<span class=\"jianpu-barline\">{}</span>", glyphs.glyph_for(barline)));
+                }
+                ContentElement::Whitespace(_) | ContentElement::UnknownToken(_) => {}
+            }
+        }
+        html.push_str("</div>\n");
+    }
+    html.push_str("</div>\n");
+    html
+}
+
+/// A beat's notes, rests and held beats, underlined by value
+fn render_beat(beat: &Beat) -> String {
+    // Markup of each element with its number of underlines
+    let mut items: Vec<(u32, String)> = Vec::new();
+    // Dashes for the beats after the first of a long note
+    let mut after = String::new();
+    // Dashes opening a beat hold the previous note on for their share of the beat
+    let holding = beat.elements.iter().take_while(|element| matches!(element, BeatElement::Dash(dash) if dash.numerator.is_none())).count();
+    if holding > 0 {
+        let divisions = beat.divisions.unwrap_or(beat.elements.len()).max(holding);
+        let (value, dotted) = note_value(Fraction::new(holding as u64, 4 * divisions as u64));
+        items.push((beam_count(value), render_symbol("jianpu-held", "-", dotted)));
+    }
+    for element in &beat.elements {
+        match element {
+            BeatElement::Note(note) => {
+                let duration = Fraction::new(note.numerator.unwrap_or(1), note.denominator.unwrap_or(4));
+                let quarters = duration * Fraction::from(4u64);
+                match (quarters.numer(), quarters.denom()) {
+                    // A note of whole beats takes a dash after it for each beat past the first
+                    (Some(&beats), Some(&1)) if beats >= 2 => {
+                        items.push((0, render_note(note, false)));
+                        after.push_str(&render_symbol("jianpu-held", "-", false).repeat(beats as usize - 1));
+                    }
+                    _ => {
+                        let (value, dotted) = note_value(duration);
+                        items.push((beam_count(value), render_note(note, dotted)));
+                    }
+                }
+            }
+            // Dashes extending a note are already in its value
+            BeatElement::Dash(dash) if dash.numerator.is_none() => {}
+            BeatElement::Dash(dash) => {
+                let (value, dotted) = note_value(Fraction::new(dash.numerator.unwrap_or(1), dash.denominator.unwrap_or(4)));
+                items.push((beam_count(value), render_symbol("jianpu-rest", "0", dotted)));
+            }
+            BeatElement::Rest(rest) => {
+                let (value, dotted) = note_value(Fraction::new(rest.numerator.unwrap_or(1), rest.denominator.unwrap_or(4)));
+                items.push((beam_count(value), render_symbol("jianpu-rest", "0", dotted)));
+            }
+            BeatElement::BreathMark(_) => items.push((0, "<span class=\"jianpu-breath\">∨</span>".to_string())),
+        }
+    }
+
+    let mut html = String::from("<span class=\"jianpu-beat\">");
+    underline(&items, 0, &mut html);
+    html.push_str("</span>");
+    html.push_str(&after);
+    html
+}
+
+/// Wrap each run of items with more than `level` underlines in one underline span,
+/// nesting the runs with more inside it
+fn underline(items: &[(u32, String)], level: u32, html: &mut String) {
+    let mut rest = items;
+    while let Some((count, markup)) = rest.first() {
+        if *count > level {
+            let run = rest.iter().take_while(|(count, _)| *count > level).count();
+            html.push_str("<span class=\"jianpu-underline\">");
+            underline(&rest[..run], level + 1, html);
+            html.push_str("</span>");
+            rest = &rest[run..];
+        } else {
+            html.push_str(markup);
+            rest = &rest[1..];
+        }
+    }
+}
+
+fn render_note(note: &Note, dotted: bool) -> String {
+    let (degree, accidental) = degree_and_accidental(note.pitch_code);
+    let accidental = match accidental {
+        "#" => "♯",
+        "##" => "𝄪",
+        "b" => "♭",
+        "bb" => "𝄫",
+        _ => "",
+    };
+    let dots = |count: i8| "•".repeat(count.unsigned_abs() as usize);
+
+    let mut html = String::from("<span class=\"jianpu-note\">");
+    if !accidental.is_empty() {
+        html.push_str(&format!("<span class=\"jianpu-accidental\">{}</span>", accidental));
+    }
+    if note.octave > 0 {
+        html.push_str(&format!("<span class=\"jianpu-octave-above\">{}</span>", dots(note.octave)));
+    }
+    html.push_str(&format!("<span class=\"jianpu-digit\">{}</span>", degree));
+    if note.octave < 0 {
+        html.push_str(&format!("<span class=\"jianpu-octave-below\">{}</span>", dots(note.octave)));
+    }
+    html.push_str("</span>");
+    if dotted {
+        html.push_str("<span class=\"jianpu-dot\">·</span>");
+    }
+    html
+}
+
+fn render_symbol(class: &str, symbol: &str, dotted: bool) -> String {
+    let dot = if dotted { "<span class=\"jianpu-dot\">·</span>" } else { "" };
+    format!("<span class=\"jianpu-note {}\"><span class=\"jianpu-digit\">{}</span></span>{}", class, symbol, dot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::document_from_notation;
+
+    /// The markup of the notes, without the stylesheet
+    fn body(input: &str) -> String {
+        let html = render_jianpu_html(&document_from_notation(input).unwrap());
+        html[html.find("</style>").unwrap()..].to_string()
+    }
+
+    #[test]
+    fn test_upper_octave_dot() {
+        let input = "System: jianpu\n\n    .\n1 2 3\n";
+        let document = document_from_notation(input).unwrap();
+        assert_eq!(document.get_detected_notation_systems(), vec![crate::parse::model::NotationSystem::Jianpu]);
+        let html = body(input);
+        let digits: Vec<&str> = html.match_indices("jianpu-digit\">").map(|(i, m)| &html[i + m.len()..i + m.len() + 1]).collect();
+        assert_eq!(digits, ["1", "2", "3"]);
+        assert_eq!(html.matches("jianpu-octave-above").count(), 1);
+        assert!(html.contains("<span class=\"jianpu-octave-above\">•</span><span class=\"jianpu-digit\">3</span>"), "{}", html);
+        assert!(!html.contains("jianpu-underline"));
+    }
+
+    #[test]
+    fn test_underlines_follow_beams() {
+        // Two eighths share one underline; the sixteenths of "1234" get a second one
+        let html = body("|12 3 1234|\n");
+        assert_eq!(html.matches("<span class=\"jianpu-underline\">").count(), 3);
+        assert!(html.contains("<span class=\"jianpu-underline\"><span class=\"jianpu-underline\">"));
+
+        // A held beat is a dash; half of one is an underlined dash
+        let html = body("|1 - -2 0|\n");
+        assert_eq!(html.matches("jianpu-held").count(), 2, "{}", html);
+        assert_eq!(html.matches("<span class=\"jianpu-underline\">").count(), 1);
+        assert!(html.contains("<span class=\"jianpu-digit\">0</span>"));
+        assert!(html.contains("<span class=\"jianpu-barline\">|</span>"));
+    }
+}
@@ -0,0 +1,194 @@
+// LilyPond source layout
+// Keeps generated .ly files diff-friendly: music laid out one measure (or one note)
+// per line, indentation following brace depth, and optional measure number comments
+
+use crate::parse::model::directive;
+use std::collections::HashMap;
+
+/// Directive setting the indent width in spaces (e.g. `LilyPondIndent: 4`)
+pub const LILYPOND_INDENT_DIRECTIVE: &str = "LilyPondIndent";
+/// Directive choosing `measure` (default) or `note` per line
+pub const LILYPOND_LAYOUT_DIRECTIVE: &str = "LilyPondLayout";
+/// Directive adding a `% N` comment with the number of every measure (`LilyPondBarNumbers: on`)
+pub const LILYPOND_MEASURE_NUMBERS_DIRECTIVE: &str = "LilyPondBarNumbers";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineLayout {
+    #[default]
+    MeasurePerLine,
+    NotePerLine,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LilyPondFormat {
+    pub indent: usize,
+    pub layout: LineLayout,
+    pub measure_numbers: bool,
+}
+
+impl Default for LilyPondFormat {
+    fn default() -> Self {
+        LilyPondFormat { indent: 2, layout: LineLayout::MeasurePerLine, measure_numbers: false }
+    }
+}
+
+impl LilyPondFormat {
+    /// Read the formatting directives, keeping the default for anything missing or invalid
+    pub fn from_directives(directives: &HashMap<String, String>) -> Self {
+        let get = |name: &str| directive(directives, name).map(str::to_lowercase);
+        let default = LilyPondFormat::default();
+        LilyPondFormat {
+            indent: get(LILYPOND_INDENT_DIRECTIVE)
+                .and_then(|value| value.parse::<usize>().ok())
+                .filter(|&width| width <= 8)
+                .unwrap_or(default.indent),
+            layout: match get(LILYPOND_LAYOUT_DIRECTIVE).as_deref() {
+                Some("note") => LineLayout::NotePerLine,
+                Some("measure") => LineLayout::MeasurePerLine,
+                _ => default.layout,
+            },
+            measure_numbers: get(LILYPOND_MEASURE_NUMBERS_DIRECTIVE)
+                .map(|value| matches!(value.as_str(), "on" | "true" | "yes"))
+                .unwrap_or(default.measure_numbers),
+        }
+    }
+
+    /// Lines of stave music from its tokens (notes, barlines and commands in order).
+    /// Measures end at `|` bar checks and are numbered from `first_measure`
+    /// (0 when the piece starts with a pickup); a multi-measure rest counts all its measures.
+    pub fn music_lines(&self, tokens: &[String], first_measure: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut number = first_measure;
+        for measure in split_measures(tokens) {
+            let span = measure.iter().filter_map(|token| multi_measure_rest_span(token)).sum::<usize>().max(1);
+            let label = if span > 1 { format!("% {}-{}", number, number + span - 1) } else { format!("% {}", number) };
+            number += span;
+            match self.layout {
+                LineLayout::MeasurePerLine if self.measure_numbers => lines.push(format!("{} {}", measure.join(" "), label)),
+                LineLayout::MeasurePerLine => lines.push(measure.join(" ")),
+                LineLayout::NotePerLine => {
+                    if self.measure_numbers {
+                        lines.push(label);
+                    }
+                    lines.extend(measure.iter().map(|token| token.to_string()));
+                }
+            }
+        }
+        lines
+    }
+
+    /// Re-indent rendered LilyPond source by `{ }` and `<< >>` nesting, dropping blank
+    /// lines inside blocks (left by template sections that were not rendered)
+    pub fn reindent(&self, source: &str) -> String {
+        let mut output = Vec::new();
+        let mut depth: usize = 0;
+        for line in source.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                if depth == 0 {
+                    output.push(String::new());
+                }
+                continue;
+            }
+            let (opens, closes, leading_closes) = nesting(line);
+            let level = depth.saturating_sub(leading_closes);
+            output.push(format!("{}{}", " ".repeat(level * self.indent), line));
+            depth = (depth + opens).saturating_sub(closes);
+        }
+        output.join("\n")
+    }
+}
+
+/// Tokens grouped into measures; barlines with nothing before them join the next measure
+fn split_measures(tokens: &[String]) -> Vec<Vec<&str>> {
+    let mut measures: Vec<Vec<&str>> = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for token in tokens.iter().map(|token| token.trim()).filter(|token| !token.is_empty()) {
+        current.push(token);
+        let has_music = current.iter().any(|token| *token != "|");
+        if token == "|" && has_music {
+            measures.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        match measures.last_mut() {
+            Some(last) if current.iter().all(|token| *token == "|") => last.extend(current),
+            _ => measures.push(current),
+        }
+    }
+    measures
+}
+
+/// Measures covered by a `\compressMMRests { R1*N }` (or `{ R1*3/4*N }`) token
+fn multi_measure_rest_span(token: &str) -> Option<usize> {
+    let rest = token.strip_prefix("\\compressMMRests { R1*")?.trim_end_matches(" }");
+    // The count follows the last `*`, after the measure length of meters other than 4/4
+    rest.rsplit('*').next()?.parse().ok()
+}
+
+/// Block openings, closings and closings before anything else on a line,
+/// ignoring quoted strings and comments
+fn nesting(line: &str) -> (usize, usize, usize) {
+    let (mut opens, mut closes, mut leading_closes) = (0, 0, 0);
+    let mut leading = true;
+    let mut in_string = false;
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if in_string {
+            match ch {
+                '\\' => { chars.next(); }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match ch {
+            '%' => break,
+            '"' => { in_string = true; leading = false; }
+            '{' => { opens += 1; leading = false; }
+            '<' if chars.peek() == Some(&'<') => { chars.next(); opens += 1; leading = false; }
+            '}' => { closes += 1; if leading { leading_closes += 1; } }
+            '>' if chars.peek() == Some(&'>') => { chars.next(); closes += 1; if leading { leading_closes += 1; } }
+            ' ' => {}
+            _ => leading = false,
+        }
+    }
+    (opens, closes, leading_closes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(text: &[&str]) -> Vec<String> {
+        text.iter().map(|token| token.to_string()).collect()
+    }
+
+    #[test]
+    fn test_measure_per_line_with_numbers() {
+        let format = LilyPondFormat { measure_numbers: true, ..LilyPondFormat::default() };
+        let lines = format.music_lines(&tokens(&["| ", "c4", "d4", "e4", "f4", "| ", "\\compressMMRests { R1*3 }", "| ", "g1", "| "]), 1);
+        assert_eq!(lines, vec![
+            "| c4 d4 e4 f4 | % 1",
+            "\\compressMMRests { R1*3 } | % 2-4",
+            "g1 | % 5",
+        ]);
+        let lines = format.music_lines(&tokens(&["\\compressMMRests { R1*3/4*2 }", "| ", "g2.", "| "]), 1);
+        assert_eq!(lines, vec!["\\compressMMRests { R1*3/4*2 } | % 1-2", "g2. | % 3"]);
+    }
+
+    #[test]
+    fn test_note_per_line_with_pickup_numbering() {
+        let format = LilyPondFormat { layout: LineLayout::NotePerLine, measure_numbers: true, ..LilyPondFormat::default() };
+        let lines = format.music_lines(&tokens(&["c8", "d8", "| ", "e2", "f2"]), 0);
+        assert_eq!(lines, vec!["% 0", "c8", "d8", "|", "% 1", "e2", "f2"]);
+    }
+
+    #[test]
+    fn test_reindent_follows_nesting() {
+        let format = LilyPondFormat { indent: 4, ..LilyPondFormat::default() };
+        let source = "\\score {\n<<\n\n  \\new Staff { \\fixed c' {\nc4 d4 |\n} }\n>>\n  \\header { title = \"{ odd }\" }\n}";
+        assert_eq!(format.reindent(source), "\\score {\n    <<\n        \\new Staff { \\fixed c' {\n                c4 d4 |\n        } }\n    >>\n    \\header { title = \"{ odd }\" }\n}");
+        assert_eq!(LilyPondFormat::from_directives(&[("lilypondindent".to_string(), "4".to_string())].into_iter().collect()), format);
+    }
+}
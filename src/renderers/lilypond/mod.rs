@@ -2,9 +2,11 @@
 pub mod renderer;
 pub mod templates;
 pub mod generator;
+pub mod format;
 
 pub use renderer::*;
 pub use templates::*;
 pub use generator::*;
+pub use format::*;
 
 
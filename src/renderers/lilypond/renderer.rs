@@ -1,15 +1,69 @@
 // LilyPond Source Code Generator - Works directly with analyzed document
 use crate::models::Degree;
-use crate::renderers::lilypond::templates::{TemplateContext, render_lilypond, LilyPondTemplate};
-use crate::parse::model::{Document, DocumentElement, Beat, BeatElement, StaveLine, ContentElement};
+use crate::renderers::lilypond::templates::{TemplateContext, TemplateContextBuilder, render_lilypond, LilyPondTemplate};
+use crate::renderers::lilypond::format::LilyPondFormat;
+use crate::parse::model::{Articulation, Clef, Ornament, Document, DocumentElement, Stave, Beat, BeatElement, Feather, Note, SpanEdge, StaveLine, ContentElement, ContentLine, NotationSystem, Barline};
+use crate::rhythm::beaming::{split_beam_groups, BeamOverRests};
+use crate::rhythm::measures::{meter_change, MeasureCounter, SlashMeasures};
+use crate::document::line_parser::slash_line_parser::SLASH;
+use crate::renderers::note_names::NoteNames;
+use crate::document::ties::with_matching_ties;
+use crate::rhythm::analyzer::is_breath_token;
+use crate::document::line_parser::dynamics_line_parser::is_hairpin;
+use crate::renderers::noteheads::NoteheadStyle;
+use crate::rhythm::tala::{Tala, TalaMark};
+use crate::renderers::ottava::Ottava;
+use crate::rhythm::pickup::Pickup;
+use crate::renderers::transposition::{Transposition, TranspositionMode};
+use crate::analysis::{count_syllables, TimeSignature};
 use fraction::Fraction;
 // use crate::renderers::transposition::transpose_degree_with_octave; // TODO: Move transposition module
 
+/// Directive adding a `\midi` block to the score, so LilyPond itself writes MIDI
+pub const LILYPOND_MIDI_DIRECTIVE: &str = "LilyPondMidi";
+
+/// Whether the `LilyPondMidi` directive asks for a `\midi` block ("on", "true" or "yes")
+fn lilypond_midi_enabled(directives: &std::collections::HashMap<String, String>) -> bool {
+    crate::parse::model::directive(directives, LILYPOND_MIDI_DIRECTIVE)
+        .is_some_and(|value| matches!(value.to_lowercase().as_str(), "on" | "true" | "yes"))
+}
+
+/// Stave music, wrapped in `\transpose c <tonic>` when transposing in LilyPond
+fn transposed_music(music: String, transposition: Option<Transposition>) -> Result<String, String> {
+    match transposition {
+        Some(Transposition { tonic, mode: TranspositionMode::LilyPond }) => {
+            let target = crate::renderers::converters_lilypond::pitch::pitchcode_to_lilypond(tonic, 0, None)?;
+            Ok(format!("\\transpose c {} {{\n{}\n}}", target, music))
+        }
+        _ => Ok(music),
+    }
+}
+
+/// Tonic the pitches are rewritten with, when transposing in the model
+fn model_tonic(transposition: Option<Transposition>) -> Option<Degree> {
+    transposition.and_then(Transposition::model_tonic).map(crate::models::pitch_systems::pitch_code_to_degree)
+}
+
+/// `\time` command for a time signature
+fn time_signature_to_lilypond(time_signature: TimeSignature) -> String {
+    format!("\\time {}/{}", time_signature.beats, time_signature.unit)
+}
+
+/// Multi-measure rest of `measures` bars of the meter: `R1*8` in 4/4, `R1*3/4*2` in 3/4
+fn multi_measure_rest_to_lilypond(time_signature: TimeSignature, measures: usize) -> String {
+    let measure = if time_signature.beats == time_signature.unit {
+        "R1".to_string()
+    } else {
+        format!("R1*{}/{}", time_signature.beats, time_signature.unit)
+    };
+    format!("\\compressMMRests {{ {}*{} }}", measure, measures)
+}
+
 /// Find the index of the last actual note (not barline, breathmark, etc.) in lilypond_notes
 fn find_last_note_index(lilypond_notes: &[String]) -> Option<usize> {
     // Search backwards for the last actual note (not barline, breathmark, etc.)
     for (i, note) in lilypond_notes.iter().enumerate().rev() {
-        if !note.starts_with("\\bar") && !note.starts_with("\\breathe") && !note.trim().starts_with("|") {
+        if !note.starts_with("\\bar") && !note.starts_with("\\breathe") && !note.starts_with("\\clef") && !note.starts_with("\\time") && !note.starts_with("\\ottava") && !note.starts_with("\\improvisation") && !note.trim().starts_with("|") && !is_repeat_token(note) {
             // Include tuplets and regular notes
             return Some(i);
         }
@@ -17,12 +71,91 @@ fn find_last_note_index(lilypond_notes: &[String]) -> Option<usize> {
     None
 }
 
+/// Whether a token opens or closes a `\repeat volta` block or one of its endings
+fn is_repeat_token(token: &str) -> bool {
+    matches!(token, "{" | "}") || token.starts_with("\\repeat") || token.starts_with("\\alternative")
+}
+
+/// Wraps repeated music in `\repeat volta` blocks and numbered endings in an
+/// `\alternative` block while barlines are converted in order.
+///
+/// A repeat opens at "|:" or, when a ":|" or ending comes without one, at the start of
+/// the music. An ending ("|1.", ":|2.") runs until the next ending or the next barline
+/// other than a single one.
+#[derive(Debug, Default)]
+struct VoltaRepeats {
+    /// Index of the open `\repeat volta` token
+    start: Option<usize>,
+    /// Endings of the open repeat so far
+    endings: usize,
+    ending_open: bool,
+}
+
+impl VoltaRepeats {
+    /// Add the repeat tokens for a barline, after its bar check
+    fn barline(&mut self, barline: &Barline, notes: &mut Vec<String>) {
+        let ending = barline.ending();
+        let repeat_end = matches!(barline, Barline::RepeatEnd(_) | Barline::RepeatBoth(_));
+        if self.ending_open && (ending.is_some() || !matches!(barline, Barline::Single(_))) {
+            notes.push("}".to_string());
+            self.ending_open = false;
+            if ending.is_none() {
+                self.close(notes);
+            }
+        } else if repeat_end || ending.is_some() {
+            if self.start.is_none() {
+                notes.insert(0, "\\repeat volta 2 {".to_string());
+                self.start = Some(0);
+            }
+            if self.endings == 0 {
+                notes.push("}".to_string());
+                if ending.is_some() {
+                    notes.push("\\alternative {".to_string());
+                } else {
+                    self.close(notes);
+                }
+            }
+        }
+        if ending.is_some() && self.start.is_some() {
+            notes.push("{".to_string());
+            self.endings += 1;
+            self.ending_open = true;
+        }
+        if matches!(barline, Barline::RepeatStart(_) | Barline::RepeatBoth(_)) {
+            self.finish(notes);
+            self.start = Some(notes.len());
+            notes.push("\\repeat volta 2 {".to_string());
+        }
+    }
+
+    /// Close whatever is still open at the end of the music
+    fn finish(&mut self, notes: &mut Vec<String>) {
+        // Closes the open ending, or the repeated music when it has no endings
+        if self.ending_open || self.start.is_some() {
+            notes.push("}".to_string());
+            self.ending_open = false;
+            self.close(notes);
+        }
+    }
+
+    /// Finish the open repeat, playing it once per ending
+    fn close(&mut self, notes: &mut Vec<String>) {
+        if let Some(start) = self.start.take() {
+            if self.endings > 0 {
+                notes.push("}".to_string());
+                notes[start] = format!("\\repeat volta {} {{", self.endings.max(2));
+            }
+        }
+        self.endings = 0;
+    }
+}
+
 /// Extract pitch from a LilyPond note string (e.g., "c'8~" -> "c'")
 fn extract_pitch_from_lilypond_note(note: &str) -> String {
     // Remove duration numbers, ties, and other markings to get just the pitch
     let mut pitch = String::new();
     for ch in note.chars() {
-        if ch.is_ascii_digit() || ch == '~' || ch == '(' || ch == ')' {
+        if ch.is_ascii_digit() || ch == '~' || ch == '(' || ch == ')' || ch == '?' {
             break;
         }
         pitch.push(ch);
@@ -30,100 +163,396 @@ fn extract_pitch_from_lilypond_note(note: &str) -> String {
     pitch
 }
 
-pub fn convert_document_to_lilypond_src(
-    document: &Document,
-    source: Option<&str>
-) -> Result<String, String> {
-    // Processing analyzed document with beats
+/// True when every note in the document is an unpitched scat syllable
+fn is_unpitched_document(document: &Document) -> bool {
+    let mut has_notes = false;
+    for beat in document_beats(document) {
+        for beat_element in &beat.elements {
+            if let BeatElement::Note(note) = beat_element {
+                if note.notation_system != NotationSystem::Scat {
+                    return false;
+                }
+                has_notes = true;
+            }
+        }
+    }
+    has_notes
+}
 
-    let mut lilypond_notes: Vec<String> = Vec::new();
-    let mut previous_beat_notes: Vec<String> = Vec::new();
-    let current_tonic: Option<Degree> = None;
+/// True when every note in the document is a fake-book rhythm slash
+fn is_slash_document(document: &Document) -> bool {
+    let mut has_notes = false;
+    for beat in document_beats(document) {
+        for beat_element in &beat.elements {
+            if let BeatElement::Note(note) = beat_element {
+                if note.value.as_deref() != Some(SLASH) {
+                    return false;
+                }
+                has_notes = true;
+            }
+        }
+    }
+    has_notes
+}
 
-    // Extract staves from document
-    for element in &document.elements {
-        if let DocumentElement::Stave(stave) = element {
-            for line in &stave.lines {
-                if let StaveLine::ContentLine(content_line) = line {
-                    for content_element in &content_line.elements {
-                        match content_element {
-                            ContentElement::Beat(beat) => {
-                                let mut beat_notes = convert_beat_to_lilypond(beat, current_tonic)?;
-
-                                // Handle ties from tied_to_previous field
-                                if beat.tied_to_previous.unwrap_or(false) {
-                                    if let Some(last_note_index) = find_last_note_index(&lilypond_notes) {
-                                        // There's a previous note to tie from
-                                        let last_note = &mut lilypond_notes[last_note_index];
-                                        if !last_note.ends_with('~') && !last_note.ends_with(')') {
-                                            *last_note = format!("{}~", last_note);
-                                        } else if last_note.ends_with(')') {
-                                            // Insert tie before the closing slur
-                                            let len = last_note.len();
-                                            last_note.insert(len - 1, '~');
-                                        }
+fn document_staves(document: &Document) -> impl Iterator<Item = &Stave> {
+    document.elements.iter()
+        .filter_map(|element| match element {
+            DocumentElement::Stave(stave) => Some(stave),
+            _ => None,
+        })
+}
 
-                                        // Add continuation note for the tied duration (leading dashes)
-                                        let mut leading_dash_count = 0;
-                                        for element in &beat.elements {
-                                            match element {
-                                                BeatElement::Dash(_) => leading_dash_count += 1,
-                                                BeatElement::Note(_) => break,
-                                                _ => continue,
-                                            }
-                                        }
+fn document_beats(document: &Document) -> impl Iterator<Item = &Beat> {
+    document_staves(document)
+        .flat_map(|stave| stave.lines.iter())
+        .filter_map(|line| match line {
+            StaveLine::ContentLine(content_line) => Some(content_line),
+            _ => None,
+        })
+        .flat_map(|content_line| content_line.elements.iter())
+        .filter_map(|content_element| match content_element {
+            ContentElement::Beat(beat) => Some(beat),
+            _ => None,
+        })
+}
 
-                                        if leading_dash_count > 0 && beat.divisions.is_some() {
-                                            let total_divisions = beat.divisions.unwrap();
-                                            let tied_duration = Fraction::new(leading_dash_count as u64, total_divisions as u64) * Fraction::new(1u64, 4u64);
-                                            let duration_string = fraction_to_lilypond_note(tied_duration);
+/// Convert a chord symbol such as "Bb7" or "F#m" to LilyPond chordmode ("bf:7", "fs:m")
+fn chord_to_lilypond(symbol: &str) -> Option<String> {
+    let mut chars = symbol.chars().peekable();
+    let root = match chars.next()? {
+        letter @ 'A'..='G' => letter.to_ascii_lowercase().to_string(),
+        _ => return None,
+    };
+    let accidental = match chars.peek() {
+        Some('b') => { chars.next(); "f" }
+        Some('#') => { chars.next(); "s" }
+        _ => "",
+    };
+    let quality: String = chars.collect();
+    if quality.is_empty() {
+        Some(format!("{}{}", root, accidental))
+    } else {
+        Some(format!("{}{}:{}", root, accidental, quality))
+    }
+}
 
-                                            // Extract pitch from the previous note to create continuation
-                                            let prev_note_pitch = extract_pitch_from_lilypond_note(&lilypond_notes[last_note_index]);
-                                            let continuation_note = format!("{}{}", prev_note_pitch, duration_string);
-                                            beat_notes.insert(0, continuation_note);
-                                        }
-                                    } else {
-                                        // No previous note - add rest for leading dashes
-                                        let mut leading_dash_count = 0;
-                                        for element in &beat.elements {
-                                            match element {
-                                                BeatElement::Dash(_) => leading_dash_count += 1,
-                                                BeatElement::Note(_) => break,
-                                                _ => continue,
-                                            }
-                                        }
+/// One chordmode entry per beat, lasting as long as the beat: the beat's chord, or a
+/// spacer when it has none. Returns None when the document has no chords.
+fn chordmode_content(document: &Document) -> Option<String> {
+    let entries: Vec<(String, bool)> = document_beats(document)
+        .map(|beat| {
+            let duration = fraction_to_lilypond_note(beat_duration(beat));
+            match beat.chord.as_deref().and_then(chord_to_lilypond) {
+                Some(chord) => (format!("{}{}", chord, duration), true),
+                None => (format!("s{}", duration), false),
+            }
+        })
+        .collect();
+    if !entries.iter().any(|(_, is_chord)| *is_chord) {
+        return None;
+    }
+    Some(entries.into_iter().map(|(entry, _)| entry).collect::<Vec<_>>().join(" "))
+}
 
-                                        if leading_dash_count > 0 && beat.divisions.is_some() {
-                                            let total_divisions = beat.divisions.unwrap();
-                                            let rest_duration = Fraction::new(leading_dash_count as u64, total_divisions as u64) * Fraction::new(1u64, 4u64);
-                                            let duration_string = fraction_to_lilypond_note(rest_duration);
-                                            beat_notes.insert(0, format!("r{}", duration_string));
-                                        }
-                                    }
+/// Easy-notation heads with the seven natural note names replaced by the chosen labels
+fn note_names_settings(note_names: NoteNames) -> Option<String> {
+    let labels = note_names.natural_labels()?;
+    let quoted: Vec<String> = labels.iter().map(|label| format!("\"{}\"", label)).collect();
+    Some(format!("\\easyHeadsOn \\override NoteHead.note-names = ##({})", quoted.join(" ")))
+}
+
+/// One measure per tala cycle, beamed and counted by vibhag (e.g. "\\time 4,4,4,4 16/4")
+fn tala_time_signature(tala: &Tala) -> String {
+    let groups: Vec<String> = tala.vibhags.iter().map(|beats| beats.to_string()).collect();
+    format!("\\time {} {}/4", groups.join(","), tala.beats())
+}
+
+/// Sam is accented and marked X, khali gets an open circle, tali the vibhag number
+fn tala_mark_to_lilypond(mark: TalaMark) -> String {
+    match mark {
+        TalaMark::Sam => "->^\"X\"".to_string(),
+        TalaMark::Khali => "^\\markup { \\draw-circle #0.6 #0.15 ##f }".to_string(),
+        TalaMark::Tali(number) => format!("^\"{}\"", number),
+    }
+}
+
+/// Append a mark to the last note of a beat, inside any tuplet or feathered group
+fn mark_last_note(beat_notes: &mut [String], mark: &str) {
+    let Some(last) = beat_notes.last_mut() else { return };
+    match last.rfind(" }") {
+        Some(close) => last.insert_str(close, mark),
+        None => last.push_str(mark),
+    }
+}
+
+/// Id of the last beat of a line left inside a crescendo or diminuendo that no later
+/// dynamic ends; LilyPond needs a `\!` there to finish the hairpin
+fn open_hairpin_end(content_line: &ContentLine) -> Option<uuid::Uuid> {
+    let mut beats = content_line.elements.iter().filter_map(|element| match element {
+        ContentElement::Beat(beat) => Some(beat),
+        _ => None,
+    });
+    let last_mark = beats.clone().rev().find_map(|beat| beat.dynamic.as_deref())?;
+    if !is_hairpin(last_mark) {
+        return None;
+    }
+    beats.next_back().map(|beat| beat.id)
+}
+
+/// Append an articulation to the first note (or rest) of a beat, looking inside tuplets
+/// and past grace notes
+fn mark_first_note(beat_notes: &mut [String], mark: String) {
+    let Some(first) = beat_notes.iter_mut().find(|note| !note.starts_with("\\grace")) else { return };
+    if let Some(open) = first.find("{ ") {
+        let mut start = open + 2;
+        if first[start..].starts_with("\\grace") {
+            start += first[start..].find("} ").map_or(0, |close| close + 2);
+        }
+        let end = first[start..].find(' ').map(|i| start + i).unwrap_or(first.len());
+        first.insert_str(end, &mark);
+    } else {
+        first.push_str(&mark);
+    }
+}
+
+/// Lyrics of each verse in LilyPond syntax. Notes tied from the previous one are
+/// skipped as LilyPond holds the syllable over ties itself; other notes without a
+/// syllable in a verse (melismas) get a `_` skip. A word ending on a slurred melisma
+/// gets a `__` extender line; a syllable continuing the word keeps its `--` hyphen.
+fn lyrics_verses(document: &Document) -> Vec<String> {
+    let notes: Vec<&crate::parse::model::Note> = document.notes().collect();
+    let verse_count = notes.iter().map(|note| note.lyrics.len()).max().unwrap_or(0);
+    (0..verse_count).map(|verse| {
+        let mut syllables: Vec<String> = Vec::new();
+        let mut tied_from_previous = false;
+        let mut in_slur = false;
+        for note in &notes {
+            // Notes after the first under a slur hold the syllable before them
+            let melisma = in_slur;
+            match note.slur {
+                Some(SpanEdge::Start) => in_slur = true,
+                Some(SpanEdge::End) => in_slur = false,
+                None => {}
+            }
+            if !tied_from_previous {
+                let syllable = match note.lyrics.get(verse).map(String::as_str) {
+                    Some("") | None => "_".to_string(),
+                    Some(syllable) => match syllable.strip_suffix('-') {
+                        Some(start) => format!("{} --", start),
+                        None => syllable.to_string(),
+                    },
+                };
+                if melisma && syllable == "_" {
+                    if let Some(held) = syllables.last_mut().filter(|held| *held != "_" && !held.ends_with("--") && !held.ends_with("__")) {
+                        held.push_str(" __");
+                    }
+                }
+                syllables.push(syllable);
+            }
+            tied_from_previous = note.tie.is_some();
+        }
+        while syllables.last().is_some_and(|syllable| syllable == "_") {
+            syllables.pop();
+        }
+        syllables.join(" ")
+    }).collect()
+}
+
+/// Tie the last note so far into a beat continuing it, prefixing the beat with the
+/// held continuation (or a rest when there is nothing to tie from)
+fn tie_to_previous_notes(lilypond_notes: &mut [String], beat: &Beat, beat_notes: &mut Vec<String>) {
+    if beat.tied_to_previous.unwrap_or(false) {
+        if let Some(last_note_index) = find_last_note_index(lilypond_notes) {
+            // There's a previous note to tie from
+            let last_note = &mut lilypond_notes[last_note_index];
+            if !last_note.ends_with('~') && (!last_note.ends_with(')') || last_note.ends_with("\\)")) {
+                // Ties go after the \) ending a phrasing slur, which can't be split
+                *last_note = format!("{}~", last_note);
+            } else if last_note.ends_with(')') {
+                // Insert tie before the closing slur
+                let len = last_note.len();
+                last_note.insert(len - 1, '~');
+            }
+
+            // Add continuation note for the tied duration (leading dashes)
+            let mut leading_dash_count = 0;
+            for element in &beat.elements {
+                match element {
+                    BeatElement::Dash(_) => leading_dash_count += 1,
+                    BeatElement::Note(_) => break,
+                    _ => continue,
+                }
+            }
+
+            if let Some(total_divisions) = beat.divisions.filter(|_| leading_dash_count > 0) {
+                let tied_duration = Fraction::new(leading_dash_count as u64, total_divisions as u64) * beat_duration(beat);
+                let duration_string = fraction_to_lilypond_note(tied_duration);
+
+                // Extract pitch from the previous note to create continuation
+                let prev_note_pitch = extract_pitch_from_lilypond_note(&lilypond_notes[last_note_index]);
+                let continuation_note = format!("{}{}", prev_note_pitch, duration_string);
+                beat_notes.insert(0, continuation_note);
+            }
+        } else {
+            // No previous note - add rest for leading dashes
+            let mut leading_dash_count = 0;
+            for element in &beat.elements {
+                match element {
+                    BeatElement::Dash(_) => leading_dash_count += 1,
+                    BeatElement::Note(_) => break,
+                    _ => continue,
+                }
+            }
+
+            if let Some(total_divisions) = beat.divisions.filter(|_| leading_dash_count > 0) {
+                let rest_duration = Fraction::new(leading_dash_count as u64, total_divisions as u64) * beat_duration(beat);
+                let duration_string = fraction_to_lilypond_note(rest_duration);
+                beat_notes.insert(0, format!("r{}", duration_string));
+            }
+        }
+    }
+}
+
+/// Music of some staves of a document, laid out and transposed: their notes with clefs,
+/// the pickup, slash notation, tala marks, ottava brackets, multi-measure rests, breath
+/// marks and meter changes
+fn staves_to_lilypond_music<'a>(
+    document: &Document,
+    staves: impl IntoIterator<Item = &'a Stave>
+) -> Result<String, String> {
+    let mut lilypond_notes: Vec<String> = Vec::new();
+    let mut previous_beat_notes: Vec<String> = Vec::new();
+    let transposition = Transposition::from_directives(&document.directives);
+    let current_tonic = model_tonic(transposition);
+    let beam_over_rests = BeamOverRests::from_directives(&document.directives);
+    let tala = Tala::from_directives(&document.directives);
+    let ottava = Ottava::from_directives(&document.directives);
+    let pickup = Pickup::resolve(document)?;
+    let slash_measures = SlashMeasures::from_directives(&document.directives);
+    // A pickup is measure 0, so it isn't counted as a full bar
+    let mut measures = if pickup.is_some() { MeasureCounter::starting_at(0) } else { MeasureCounter::default() };
+    let mut slashes_on = false;
+    let mut meter = TimeSignature::from_directives(&document.directives);
+    let mut repeats = VoltaRepeats::default();
+    // Beat position in the tala cycle; pickup beats come before the first sam
+    let mut beat_index: isize = -(pickup.map(|p| p.beats()).unwrap_or(0) as isize);
+
+    for stave in staves {
+        if let Some(clef) = stave.clef {
+            lilypond_notes.push(clef_to_lilypond(clef));
+        }
+        for line in &stave.lines {
+            if let StaveLine::ContentLine(content_line) = line {
+                let content_line = &with_matching_ties(content_line);
+                let hairpin_end = open_hairpin_end(content_line);
+                let multi_measure_rests = crate::rhythm::measures::multi_measure_rests(&content_line.elements);
+                let ottava_spans = ottava.map(|o| o.spans(content_line)).unwrap_or_default();
+                for (index, content_element) in content_line.elements.iter().enumerate() {
+                    match content_element {
+                        ContentElement::Beat(beat) => {
+                            let mut beat_notes = convert_beat_to_lilypond(beat, current_tonic, beam_over_rests)?;
+                            if hairpin_end == Some(beat.id) {
+                                mark_last_note(&mut beat_notes, "\\!");
+                            }
+
+                            tie_to_previous_notes(&mut lilypond_notes, beat, &mut beat_notes);
+
+                            // Slash notation switches at the first beat of a measure
+                            let measure = measures.beat();
+                            if let Some(slashed) = slash_measures.as_ref().map(|s| s.is_slashed(measure)) {
+                                if slashed != slashes_on {
+                                    lilypond_notes.push(if slashed { "\\improvisationOn" } else { "\\improvisationOff" }.to_string());
+                                    slashes_on = slashed;
                                 }
+                            }
 
-                                lilypond_notes.extend(beat_notes.clone());
-                                previous_beat_notes = beat_notes;
-                            },
-                            ContentElement::Barline(barline) => {
-                                let lily_barline = format!("| ");
-                                lilypond_notes.push(lily_barline);
-                            },
-                            ContentElement::Whitespace(_) => {
-                                // Skip whitespace
-                            },
-                            ContentElement::UnknownToken(_) => {
-                                // Skip unknown tokens (behave like whitespace)
-                            },
-                        }
+                            let cycle_position = usize::try_from(beat_index).ok();
+                            if let Some(mark) = tala.zip(cycle_position).and_then(|(t, i)| t.mark(i)) {
+                                mark_first_note(&mut beat_notes, tala_mark_to_lilypond(mark));
+                            }
+                            beat_index += 1;
+
+                            // Ottava brackets open and close at the beats holding their first and last notes
+                            if let Some(span) = ottava_spans.iter().find(|span| span.starts_in(beat)) {
+                                lilypond_notes.push(span.lilypond_command());
+                            }
+                            lilypond_notes.extend(beat_notes.clone());
+                            if ottava_spans.iter().any(|span| span.ends_in(beat)) {
+                                lilypond_notes.push("\\ottava #0".to_string());
+                            }
+                            previous_beat_notes = beat_notes;
+                        },
+                        ContentElement::Barline(barline) => {
+                            measures.barline();
+                            // Within a tala cycle barlines separate vibhags, not measures
+                            let lily_barline = if tala.is_some() { "\\bar \"|\"".to_string() } else { "| ".to_string() };
+                            lilypond_notes.push(lily_barline);
+                            repeats.barline(barline, &mut lilypond_notes);
+                        },
+                        ContentElement::Whitespace(_) => {
+                            // Skip whitespace
+                        },
+                        ContentElement::UnknownToken(_) => {
+                            // Multi-measure rests print their measure count, breath marks
+                            // breathe and meter changes set the new time; skip other unknown
+                            // tokens (behave like whitespace)
+                            if let Some(&measures) = multi_measure_rests.get(&index) {
+                                lilypond_notes.push(multi_measure_rest_to_lilypond(meter, measures));
+                            } else if is_breath_token(content_element) {
+                                lilypond_notes.push("\\breathe".to_string());
+                            } else if let Some(time_signature) = meter_change(content_element) {
+                                meter = time_signature;
+                                lilypond_notes.push(time_signature_to_lilypond(time_signature));
+                            }
+                        },
                     }
                 }
             }
         }
     }
 
-    let staves = lilypond_notes.join(" ");
+    repeats.finish(&mut lilypond_notes);
+
+    // A pickup is measure 0, so numbered measures match LilyPond's bar numbers
+    if let Some(pickup) = pickup {
+        lilypond_notes.insert(0, format!("\\partial {}", pickup.lilypond_duration()));
+    }
+    let format = LilyPondFormat::from_directives(&document.directives);
+    transposed_music(format.music_lines(&lilypond_notes, if pickup.is_some() { 0 } else { 1 }).join("\n"), transposition)
+}
+
+/// Settings every staff opens with: rhythmic or slash notation, the meter (a tala's
+/// cycle or the `Time` directive), note names inside noteheads and the notehead shape
+fn staff_context(document: &Document) -> TemplateContextBuilder {
+    let mut context = TemplateContext::builder()
+        .rhythmic(is_unpitched_document(document))
+        .slashes(is_slash_document(document));
+
+    if let Some(tala) = Tala::from_directives(&document.directives) {
+        context = context.time_signature(tala_time_signature(tala));
+    } else if let Some(time_signature) = TimeSignature::declared(&document.directives) {
+        context = context.time_signature(time_signature_to_lilypond(time_signature));
+    }
+
+    if let Some(settings) = note_names_settings(NoteNames::from_directives(&document.directives)) {
+        context = context.note_names(settings);
+    }
+
+    if let Some(command) = NoteheadStyle::from_directives(&document.directives).lilypond_command() {
+        context = context.noteheads(command);
+    }
+    context
+}
+
+pub fn convert_document_to_lilypond_src(
+    document: &Document,
+    source: Option<&str>
+) -> Result<String, String> {
+    // Processing analyzed document with beats
+
+    let format = LilyPondFormat::from_directives(&document.directives);
+    let staves = staves_to_lilypond_music(document, document_staves(document))?;
 
     // Extract lyrics from beat elements
     let mut lyrics_parts: Vec<String> = Vec::new();
@@ -135,9 +564,13 @@ pub fn convert_document_to_lilypond_src(
                         if let ContentElement::Beat(beat) = content_element {
                             for beat_element in &beat.elements {
                                 match beat_element {
-                                    BeatElement::Note(_note) => {
+                                    BeatElement::Note(note) => {
+                                        // Scat syllables are printed beneath their rhythm notes
                                         // TODO: Extract syllables from spatial assignments if available
-                                        lyrics_parts.push("_".to_string());
+                                        match (&note.notation_system, &note.value) {
+                                            (NotationSystem::Scat, Some(syllable)) => lyrics_parts.push(syllable.clone()),
+                                            _ => lyrics_parts.push("_".to_string()),
+                                        }
                                     },
                                     BeatElement::Dash(_) => {
                                         // Skip dashes - they are duration extenders, not separate syllables
@@ -158,18 +591,51 @@ pub fn convert_document_to_lilypond_src(
     }
     
     // Build template context
-    let mut context = TemplateContext::builder()
-        .staves(staves);
+    let mut context = staff_context(document).staves(staves);
+
+    if let Some(chords) = chordmode_content(document) {
+        context = context.chords(chords);
+    }
+
+    if let Some(tempo) = tempo_mark(document) {
+        context = context.tempo(tempo);
+    }
+
+    if lilypond_midi_enabled(&document.directives) {
+        context = context.midi_tempo(crate::renderers::midi::tempo_from_directives(&document.directives));
+    }
     
     // Add lyrics if any syllables were found
     if !lyrics_parts.is_empty() && lyrics_parts.iter().any(|s| s != "_") {
         let lyrics_string = lyrics_parts.join(" ");
         context = context.lyrics(lyrics_string);
     }
+
+    let verses = lyrics_verses(document);
+    if !verses.is_empty() {
+        context = context.verses(verses);
+    }
+
+    // Counting syllables go under every note; uncounted positions are skipped
+    if let Some(time_signature) = TimeSignature::for_count_line(&document.directives) {
+        let staves = document.elements.iter().filter_map(|element| match element {
+            DocumentElement::Stave(stave) => Some(stave),
+            _ => None,
+        });
+        let syllables: Vec<String> = count_syllables(staves, time_signature).into_iter()
+            .map(|(_, syllable)| if syllable.is_empty() { "_".to_string() } else { syllable })
+            .collect();
+        if !syllables.is_empty() {
+            context = context.count_line(syllables.join(" "));
+        }
+    }
     
     if let Some(title) = &document.title {
         context = context.title(title);
     }
+    if let Some(composer) = document.composer() {
+        context = context.composer(lilypond_string(composer));
+    }
 
     if let Some(source) = source {
         context = context.source_comment(source);
@@ -178,7 +644,7 @@ pub fn convert_document_to_lilypond_src(
     let context = context.build();
 
     // Auto-select template based on document complexity
-    let template = if document.title.is_some() {
+    let template = if document.title.is_some() || document.composer().is_some() {
         LilyPondTemplate::Standard
     } else {
         LilyPondTemplate::Minimal
@@ -186,9 +652,110 @@ pub fn convert_document_to_lilypond_src(
     
     // Render template
     render_lilypond(template, &context)
+        .map(|rendered| format.reindent(&rendered))
         .map_err(|e| format!("Template render error: {}", e))
 }
 
+/// LilyPond pitch of a note; divisi notes become a chord of both streams (e.g. "<c e>")
+/// and cautionary accidentals are parenthesized (e.g. "fs'?")
+fn note_to_lilypond_pitch(note: &Note, current_tonic: Option<Degree>) -> Result<String, String> {
+    let tonic = current_tonic.map(crate::models::pitch_systems::degree_to_pitch_code);
+    let mut pitch = crate::renderers::converters_lilypond::pitch::pitchcode_to_lilypond(note.pitch_code, note.octave, tonic)?;
+    if let Some(quarter_steps) = note.microtone {
+        pitch = crate::renderers::converters_lilypond::pitch::with_microtone(&pitch, quarter_steps)?;
+    }
+    if note.cautionary {
+        // `?` prints the accidental in parentheses
+        pitch.push('?');
+    }
+    match &note.divisi {
+        Some(divisi) => {
+            let second = crate::renderers::converters_lilypond::pitch::pitchcode_to_lilypond(divisi.pitch_code, divisi.octave, tonic)?;
+            Ok(format!("<{} {}>", pitch, second))
+        }
+        None => Ok(pitch),
+    }
+}
+
+/// Grace notes leading into a note as one 16th-note group (e.g. "\\grace { f16 e16 }").
+/// The group follows any bar check before the note, so a measure's first note keeps
+/// its grace notes in its own measure and bar checks still fall on the downbeat.
+fn grace_notes_to_lilypond(note: &Note, current_tonic: Option<Degree>) -> Result<Option<String>, String> {
+    if note.grace_notes.is_empty() {
+        return Ok(None);
+    }
+    let tonic = current_tonic.map(crate::models::pitch_systems::degree_to_pitch_code);
+    // A single grace note is engraved as an eighth, a group as sixteenths
+    let duration = if note.grace_notes.len() == 1 { 8 } else { 16 };
+    let pitches = note.grace_notes.iter()
+        .map(|grace| crate::renderers::converters_lilypond::pitch::pitchcode_to_lilypond(grace.pitch_code, grace.octave, tonic).map(|pitch| format!("{}{}", pitch, duration)))
+        .collect::<Result<Vec<String>, String>>()?;
+    Ok(Some(format!("\\grace {{ {} }}", pitches.join(" "))))
+}
+
+/// Articulations ("-.", "->", "--"), ornaments ("\\mordent", "\\trill", "\\turn"),
+/// slurs and phrasing slurs (a phrase mark opens outside the slur it starts with and
+/// closes outside the one it ends with), then best-effort notation for playback
+/// markers: a "vib." text mark and a bend arrow
+fn expression_marks(note: &Note) -> String {
+    let mut marks = String::new();
+    for articulation in &note.articulations {
+        marks.push_str(match articulation {
+            Articulation::Staccato => "-.",
+            Articulation::Accent => "->",
+            Articulation::Tenuto => "--",
+        });
+    }
+    if let Some(ornament) = note.ornament {
+        marks.push_str(match ornament {
+            Ornament::Mordent => "\\mordent",
+            Ornament::Trill => "\\trill",
+            Ornament::Turn => "\\turn",
+        });
+    }
+    if note.phrase == Some(SpanEdge::Start) {
+        marks.push_str("\\(");
+    }
+    match note.slur {
+        Some(SpanEdge::Start) => marks.push('('),
+        Some(SpanEdge::End) => marks.push(')'),
+        None => {}
+    }
+    if note.phrase == Some(SpanEdge::End) {
+        marks.push_str("\\)");
+    }
+    if note.vibrato {
+        marks.push_str("^\"vib.\"");
+    }
+    if let Some(semitones) = note.bend {
+        // Trailing space ends the Scheme number before any beam or tie marker
+        marks.push_str(&format!("\\bendAfter #{} ", semitones));
+    }
+    marks
+}
+
+/// Metronome mark for the `Tempo` directive, e.g. "\\tempo 4 = 96" or "\\tempo 4. = 60"
+fn tempo_mark(document: &Document) -> Option<String> {
+    let tempo = crate::renderers::midi::Tempo::from_directives(&document.directives)?;
+    let duration = crate::renderers::converters_lilypond::rhythm::duration_to_lilypond(tempo.numerator, tempo.denominator);
+    Some(format!("\\tempo {} = {}", duration, tempo.bpm))
+}
+
+/// Length of a beat: a quarter unless the meter beats in other units (dotted quarters in 6/8)
+fn beat_duration(beat: &Beat) -> Fraction {
+    beat.total_duration.unwrap_or(Fraction::new(1u64, 4u64))
+}
+
+/// `\clef` command for a stave's clef
+fn clef_to_lilypond(clef: Clef) -> String {
+    format!("\\clef {}", clef.name())
+}
+
+/// "~" for a note written with an explicit tie marker
+fn tie_mark(note: &Note) -> &'static str {
+    if note.tie.is_some() { "~" } else { "" }
+}
+
 fn convert_beat_with_leading_rest(beat: &Beat, current_tonic: Option<Degree>) -> Result<Vec<String>, String> {
     let mut notes = Vec::new();
 
@@ -206,9 +773,8 @@ fn convert_beat_with_leading_rest(beat: &Beat, current_tonic: Option<Degree>) ->
     }
 
     // Calculate rest duration based on subdivisions
-    if leading_dash_count > 0 && beat.divisions.is_some() {
-        let total_divisions = beat.divisions.unwrap();
-        let rest_duration = Fraction::new(leading_dash_count as u64, total_divisions as u64) * Fraction::new(1u64, 4u64);
+    if let Some(total_divisions) = beat.divisions.filter(|_| leading_dash_count > 0) {
+        let rest_duration = Fraction::new(leading_dash_count as u64, total_divisions as u64) * beat_duration(beat);
         let duration_string = fraction_to_lilypond_note(rest_duration);
         notes.push(format!("r{}", duration_string));
     }
@@ -226,12 +792,9 @@ fn convert_beat_with_leading_rest(beat: &Beat, current_tonic: Option<Degree>) ->
                     "4".to_string()
                 };
 
-                let lily_note = crate::renderers::converters_lilypond::pitch::pitchcode_to_lilypond(
-                    note.pitch_code,
-                    note.octave,
-                    current_tonic.map(|d| crate::models::pitch_systems::degree_to_pitch_code(d))
-                )?;
-                notes.push(format!("{}{}", lily_note, duration_string));
+                let lily_note = note_to_lilypond_pitch(note, current_tonic)?;
+                notes.extend(grace_notes_to_lilypond(note, current_tonic)?);
+                notes.push(format!("{}{}{}{}", lily_note, duration_string, expression_marks(note), tie_mark(note)));
             },
             BeatElement::Dash(_) => {
                 if past_leading_dashes {
@@ -266,28 +829,36 @@ fn convert_beat_with_leading_rest(beat: &Beat, current_tonic: Option<Degree>) ->
     }
 }
 
-fn convert_beat_to_lilypond(beat: &Beat, current_tonic: Option<Degree>) -> Result<Vec<String>, String> {
+fn convert_beat_to_lilypond(beat: &Beat, current_tonic: Option<Degree>, beam_over_rests: BeamOverRests) -> Result<Vec<String>, String> {
     let mut notes = Vec::new();
+    // Indices into `notes` of beamable events, paired with `true` for rests
+    let mut beam_events: Vec<(usize, bool)> = Vec::new();
+    let mut beamable = true;
+    // Notes inside a tuplet are written at their nominal value: a triplet eighth lasts
+    // 1/12 but is written as an eighth under \tuplet 3/2
+    let tuplet_scale = match (beat.is_tuplet, beat.tuplet_ratio) {
+        (Some(true), Some((actual, normal))) => fraction::Fraction::new(actual as u64, normal as u64),
+        _ => fraction::Fraction::from(1),
+    };
     for beat_element in &beat.elements {
         match beat_element {
             BeatElement::Note(note) => {
                 // Use rhythm-analyzed duration if available, fallback to quarter note
                 let duration_string = if let (Some(numer), Some(denom)) = (note.numerator, note.denominator) {
                     let duration = fraction::Fraction::new(numer, denom);
-                    fraction_to_lilypond_note(duration)
+                    fraction_to_lilypond_note(duration * tuplet_scale)
                 } else {
                     "4".to_string() // fallback to quarter note
                 };
 
-                let lily_note = crate::renderers::converters_lilypond::pitch::pitchcode_to_lilypond(
-                    note.pitch_code,
-                    note.octave,
-                    current_tonic.map(|d| crate::models::pitch_systems::degree_to_pitch_code(d))
-                )?;
-                let note_str = format!("{}{}", lily_note, duration_string);
+                let lily_note = note_to_lilypond_pitch(note, current_tonic)?;
+                let note_str = format!("{}{}{}{}", lily_note, duration_string, expression_marks(note), tie_mark(note));
 
                 // TODO: Add slur markers from spatial assignments if available
 
+                beamable &= note.denominator.unwrap_or(4) >= 8;
+                notes.extend(grace_notes_to_lilypond(note, current_tonic)?);
+                beam_events.push((notes.len(), false));
                 notes.push(note_str);
             },
             BeatElement::Dash(dash) => {
@@ -295,9 +866,11 @@ fn convert_beat_to_lilypond(beat: &Beat, current_tonic: Option<Degree>) -> Resul
                 if let (Some(numer), Some(denom)) = (dash.numerator, dash.denominator) {
                     use fraction::Fraction;
                     let duration = Fraction::new(numer, denom);
-                    let duration_string = fraction_to_lilypond_note(duration);
+                    let duration_string = fraction_to_lilypond_note(duration * tuplet_scale);
 
                     // For now, treat as rest. Could be extended to detect tied notes based on context
+                    beamable &= denom >= 8;
+                    beam_events.push((notes.len(), true));
                     notes.push(format!("r{}", duration_string));
                 } else {
                     // Skip dashes without rhythm data - they are duration extenders handled by rhythm analyzer
@@ -311,10 +884,12 @@ fn convert_beat_to_lilypond(beat: &Beat, current_tonic: Option<Degree>) -> Resul
                 // Use rhythm-analyzed duration if available, fallback to quarter note
                 let duration_string = if let (Some(numer), Some(denom)) = (rest.numerator, rest.denominator) {
                     let duration = fraction::Fraction::new(numer, denom);
-                    fraction_to_lilypond_note(duration)
+                    fraction_to_lilypond_note(duration * tuplet_scale)
                 } else {
                     "4".to_string() // fallback to quarter note
                 };
+                beamable &= rest.denominator.unwrap_or(4) >= 8;
+                beam_events.push((notes.len(), true));
                 notes.push(format!("r{}", duration_string));
             },
         }
@@ -323,20 +898,60 @@ fn convert_beat_to_lilypond(beat: &Beat, current_tonic: Option<Degree>) -> Resul
     // Add manual beaming for eighth notes and shorter
     // add_manual_beaming(&mut notes)?;
 
+    // Auto-beaming never spans rests, so beats containing rests get explicit beams.
+    // A feathered beat is beamed explicitly as one group, across any rests.
+    let mut feather = beat.feather.filter(|_| beamable);
+    if beamable && (feather.is_some() || beam_events.iter().any(|(_, is_rest)| *is_rest)) {
+        let beam_over_rests = if feather.is_some() { BeamOverRests(true) } else { beam_over_rests };
+        let groups = split_beam_groups(&beam_events, beam_over_rests);
+        if groups.is_empty() {
+            feather = None;
+        }
+        for group in groups {
+            if let (Some(&first), Some(&last)) = (group.first(), group.last()) {
+                notes[first].push('[');
+                notes[last].push(']');
+            }
+        }
+    }
+
     // Use analyzer-provided tuplet information
-    if beat.is_tuplet.unwrap_or(false) {
+    let mut notes = if beat.is_tuplet.unwrap_or(false) {
         if let Some((tuplet_num, tuplet_den)) = beat.tuplet_ratio {
             // For now, just use the notes as-is and let fraction_to_lilypond_note handle durations
             let tuplet_content = notes.join(" ");
-            Ok(vec![format!("\\tuplet {}/{} {{ {} }}", tuplet_num, tuplet_den, tuplet_content)])
+            vec![format!("\\tuplet {}/{} {{ {} }}", tuplet_num, tuplet_den, tuplet_content)]
         } else {
-            Ok(notes)
+            notes
         }
     } else {
-        Ok(notes)
+        notes
+    };
+
+    // A dynamic from the dynamics line is written on the beat's first note: \f, \<, \cresc
+    if let Some(mark) = &beat.dynamic {
+        mark_first_note(&mut notes, format!("\\{}", mark));
+    }
+
+    match feather {
+        Some(feather) => Ok(vec![feathered_beam(feather, &notes)]),
+        None => Ok(notes),
     }
 }
 
+/// A beamed group with its beams fanning out and its playback durations scaled to
+/// match: the first note lasts twice as long as the last in an accelerando
+fn feathered_beam(feather: Feather, notes: &[String]) -> String {
+    let (direction, ratio) = match feather {
+        Feather::Accelerando => ("RIGHT", "2/1"),
+        Feather::Ritardando => ("LEFT", "1/2"),
+    };
+    format!(
+        "\\override Beam.grow-direction = #{} \\featherDurations #(ly:make-moment {}) {{ {} }} \\revert Beam.grow-direction",
+        direction, ratio, notes.join(" ")
+    )
+}
+
 /// Adjust note durations for tuplets - convert from compressed durations to target durations
 fn adjust_tuplet_note_durations(notes: &[String], tuplet_num: usize, tuplet_den: usize) -> Vec<String> {
     // Use systematic subdivision approach - denominator determines subdivision note value
@@ -531,7 +1146,7 @@ pub fn convert_processed_document_to_lilypond_src(
 }}
 "#,
                     document.title.as_ref().unwrap_or(&String::new()),
-                    document.author.as_ref().unwrap_or(&String::new())
+                    lilypond_string(document.composer().unwrap_or_default())
                 ));
             }
         };
@@ -552,6 +1167,11 @@ pub fn convert_processed_document_to_lilypond_src(
     }
 }
 
+/// Text escaped for a LilyPond string literal
+fn lilypond_string(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
 /// Convert multiple staves to LilyPond using multi-stave template
 fn convert_multistave_to_lilypond_src(
     document: &Document,
@@ -559,107 +1179,63 @@ fn convert_multistave_to_lilypond_src(
 ) -> Result<String, String> {
     // Convert each stave to LilyPond content
     let mut stave_contents = Vec::new();
-
-    for element in &document.elements {
-        if let DocumentElement::Stave(stave) = element {
-            // Get LilyPond content for this stave (without template wrapper)
-            let stave_lilypond = convert_stave_to_lilypond_content(stave)?;
-            stave_contents.push(format!("\\new Staff {{\n  \\fixed c' {{\n    \\key c \\major\n    \\time 4/4\n    % \\autoBeamOff\n    % \\set Score.measureBarType = #\"\"\n    % \\set Score.startRepeatBarType = #\"\"\n    % \\set Score.endRepeatBarType = #\"\"\n    \n    {}\n  }}\n}}", stave_lilypond));
-        }
+    let format = LilyPondFormat::from_directives(&document.directives);
+    let settings = staff_context(document).build();
+
+    // The metronome mark is printed once, over the top staff
+    let mut tempo = tempo_mark(document);
+    for stave in document_staves(document) {
+        let music = staves_to_lilypond_music(document, [stave])?;
+        let staff = TemplateContext { tempo: tempo.take(), ..settings.clone() };
+        stave_contents.push((stave.grand_staff, staff_block(&staff, &music)));
     }
 
     // Create template context for multi-stave template
     let mut context = TemplateContext::new();
     context.set_title(document.title.clone());
+    context.composer = document.composer().map(lilypond_string);
     context.set_source_comment(source.map(|s| s.to_string()));
-    context.set_staves(stave_contents.join("\n"));
+    context.set_staves(group_grand_staves(stave_contents).join("\n"));
+    if lilypond_midi_enabled(&document.directives) {
+        context.midi_tempo = Some(crate::renderers::midi::tempo_from_directives(&document.directives));
+    }
 
     // Render using multi-stave template
-    render_lilypond(LilyPondTemplate::MultiStave, &context).map_err(|e| e.to_string())
+    render_lilypond(LilyPondTemplate::MultiStave, &context)
+        .map(|rendered| format.reindent(&rendered))
+        .map_err(|e| e.to_string())
 }
 
-/// Convert a single stave to LilyPond content (without template wrapper)
-fn convert_stave_to_lilypond_content(stave: &crate::parse::model::Stave) -> Result<String, String> {
-    let mut lilypond_notes: Vec<String> = Vec::new();
-    let current_tonic: Option<Degree> = None;
-
-    for line in &stave.lines {
-        if let StaveLine::ContentLine(content_line) = line {
-            for content_element in &content_line.elements {
-                match content_element {
-                    ContentElement::Beat(beat) => {
-                        let mut beat_notes = convert_beat_to_lilypond(beat, current_tonic)?;
-
-                        // Handle ties from tied_to_previous field
-                        if beat.tied_to_previous.unwrap_or(false) {
-                            if let Some(last_note_index) = find_last_note_index(&lilypond_notes) {
-                                // There's a previous note to tie from
-                                let last_note = &mut lilypond_notes[last_note_index];
-                                if !last_note.ends_with('~') && !last_note.ends_with(')') {
-                                    *last_note = format!("{}~", last_note);
-                                } else if last_note.ends_with(')') {
-                                    // Insert tie before the closing slur
-                                    let len = last_note.len();
-                                    last_note.insert(len - 1, '~');
-                                }
-
-                                // Add continuation note for the tied duration (leading dashes)
-                                let mut leading_dash_count = 0;
-                                for element in &beat.elements {
-                                    match element {
-                                        BeatElement::Dash(_) => leading_dash_count += 1,
-                                        BeatElement::Note(_) => break,
-                                        _ => continue,
-                                    }
-                                }
-
-                                if leading_dash_count > 0 && beat.divisions.is_some() {
-                                    let total_divisions = beat.divisions.unwrap();
-                                    let tied_duration = Fraction::new(leading_dash_count as u64, total_divisions as u64) * Fraction::new(1u64, 4u64);
-                                    let duration_string = fraction_to_lilypond_note(tied_duration);
-
-                                    // Extract pitch from the previous note to create continuation
-                                    let prev_note_pitch = extract_pitch_from_lilypond_note(&lilypond_notes[last_note_index]);
-                                    let continuation_note = format!("{}{}", prev_note_pitch, duration_string);
-                                    beat_notes.insert(0, continuation_note);
-                                }
-                            } else {
-                                // No previous note - add rest for leading dashes
-                                let mut leading_dash_count = 0;
-                                for element in &beat.elements {
-                                    match element {
-                                        BeatElement::Dash(_) => leading_dash_count += 1,
-                                        BeatElement::Note(_) => break,
-                                        _ => continue,
-                                    }
-                                }
-
-                                if leading_dash_count > 0 && beat.divisions.is_some() {
-                                    let total_divisions = beat.divisions.unwrap();
-                                    let rest_duration = Fraction::new(leading_dash_count as u64, total_divisions as u64) * Fraction::new(1u64, 4u64);
-                                    let duration_string = fraction_to_lilypond_note(rest_duration);
-                                    beat_notes.insert(0, format!("r{}", duration_string));
-                                }
-                            }
-                        }
+/// A staff of the multi-staff score, opened with the same settings as a single staff
+fn staff_block(settings: &TemplateContext, music: &str) -> String {
+    let staff = if settings.rhythmic { "RhythmicStaff" } else { "Staff" };
+    let lines: Vec<&str> = [
+        settings.slashes.then_some("\\improvisationOn"),
+        settings.time_signature.as_deref(),
+        settings.tempo.as_deref(),
+        settings.note_names.as_deref(),
+        settings.noteheads.as_deref(),
+        Some(music),
+    ].into_iter().flatten().collect();
+    format!("\\new {} {{\n  \\fixed c' {{\n    {}\n  }}\n}}", staff, lines.join("\n    "))
+}
 
-                        lilypond_notes.extend(beat_notes);
-                    },
-                    ContentElement::Barline(_) => {
-                        lilypond_notes.push("| ".to_string());
-                    },
-                    ContentElement::Whitespace(_) => {
-                        // Skip whitespace
-                    },
-                    ContentElement::UnknownToken(_) => {
-                        // Skip unknown tokens (behave like whitespace)
-                    },
-                }
-            }
+/// Staff blocks with each run of staves from the same grand staff stacked in a `\new PianoStaff`
+fn group_grand_staves(staves: Vec<(Option<usize>, String)>) -> Vec<String> {
+    let mut blocks: Vec<String> = Vec::new();
+    let mut staves = staves.into_iter().peekable();
+    while let Some((grand_staff, staff)) = staves.next() {
+        let Some(grand_staff) = grand_staff else {
+            blocks.push(staff);
+            continue;
+        };
+        let mut group = vec![staff];
+        while let Some((_, staff)) = staves.next_if(|(next, _)| *next == Some(grand_staff)) {
+            group.push(staff);
         }
+        blocks.push(format!("\\new PianoStaff <<\n{}\n>>", group.join("\n")));
     }
-
-    Ok(lilypond_notes.join(" "))
+    blocks
 }
 
 // Removed old convert_processed_document_to_lilypond_minimal function - not needed with new architecture
@@ -713,13 +1289,11 @@ pub fn convert_processed_document_to_minimal_lilypond_src(
     // Extract just the musical content without headers/layout
     let mut stave_content = String::new();
 
-    for element in &document.elements {
-        if let DocumentElement::Stave(stave) = element {
-            let stave_lilypond = convert_stave_to_lilypond_content(stave)?;
-            if !stave_lilypond.trim().is_empty() {
-                stave_content = stave_lilypond;
-                break; // Just use the first stave for minimal output
-            }
+    for stave in document_staves(document) {
+        let stave_lilypond = staves_to_lilypond_music(document, [stave])?;
+        if !stave_lilypond.trim().is_empty() {
+            stave_content = stave_lilypond;
+            break; // Just use the first stave for minimal output
         }
     }
 
@@ -728,7 +1302,8 @@ pub fn convert_processed_document_to_minimal_lilypond_src(
     }
 
     // Build minimal template context
-    let mut context_builder = TemplateContext::builder()
+    let format = LilyPondFormat::from_directives(&document.directives);
+    let mut context_builder = staff_context(document)
         .staves(stave_content);
 
     if let Some(src) = source {
@@ -739,6 +1314,672 @@ pub fn convert_processed_document_to_minimal_lilypond_src(
 
     // Use minimal template
     render_lilypond(LilyPondTemplate::Minimal, &context)
+        .map(|rendered| format.reindent(&rendered))
         .map_err(|e| format!("Minimal template render error: {}", e))
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Note, Rest, NotationSystem, PitchCode};
+    use crate::pipeline::document_from_notation;
+
+    /// Beat `1 r 1` with three sixteenth-note-sized subdivisions
+    fn note_rest_note_beat() -> Beat {
+        let note = |value: &str| BeatElement::Note(Note {
+            numerator: Some(1),
+            denominator: Some(16),
+            ..Note::new(Some(value.to_string()), PitchCode::N1, NotationSystem::Number)
+        });
+        Beat {
+            id: uuid::Uuid::new_v4(),
+            value: Some("1r1".to_string()),
+            divisions: Some(4),
+            is_tuplet: Some(false),
+            tuplet_ratio: None,
+            tied_to_previous: Some(false),
+            total_duration: None,
+            repeat_count: None,
+            chord: None,
+            dynamic: None,
+            feather: None,
+            explicit_tuplet: None,
+            source: None,
+            elements: vec![
+                note("1"),
+                BeatElement::Rest(Rest { id: uuid::Uuid::new_v4(), value: Some("r".to_string()), numerator: Some(1), denominator: Some(8), source: None }),
+                note("1"),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_rest_breaks_beam_by_default() {
+        let notes = convert_beat_to_lilypond(&note_rest_note_beat(), None, BeamOverRests::default()).unwrap();
+        assert_eq!(notes, vec!["c16", "r8", "c16"]);
+    }
+
+    #[test]
+    fn test_beam_over_rest() {
+        let notes = convert_beat_to_lilypond(&note_rest_note_beat(), None, BeamOverRests(true)).unwrap();
+        assert_eq!(notes, vec!["c16[", "r8", "c16]"]);
+    }
+
+    #[test]
+    fn test_scat_renders_rhythm_with_syllables() {
+        let document = crate::pipeline::document_from_notation("@scat ta ka di mi\n").unwrap();
+        assert_eq!(document.title, None);
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\new RhythmicStaff"));
+        assert!(lilypond.contains("c4 c4 c4 c4"));
+        assert!(lilypond.contains("\\addlyrics { ta ka di mi }"));
+    }
+
+    #[test]
+    fn test_chord_to_lilypond() {
+        assert_eq!(chord_to_lilypond("C"), Some("c".to_string()));
+        assert_eq!(chord_to_lilypond("Bb7"), Some("bf:7".to_string()));
+        assert_eq!(chord_to_lilypond("F#m"), Some("fs:m".to_string()));
+        assert_eq!(chord_to_lilypond("x"), None);
+    }
+
+    #[test]
+    fn test_fake_book_slashes_with_chords() {
+        let document = crate::pipeline::document_from_notation("[C] [F]\n/ / / /\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\improvisationOn\n        b4 b4 b4 b4"), "{}", lilypond);
+        assert!(lilypond.contains("\\chordmode { c4 s4 f4 s4 }"));
+
+        // Chords last as long as the beats they sit on
+        let document = crate::pipeline::document_from_notation("TimeSignature: 6/8\n\n[C]   [F]\n| / / / / |\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\chordmode { c4. s4. f4. s4. }"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_articulations() {
+        let lilypond_of = |input: &str| {
+            let document = crate::pipeline::document_from_notation(input).unwrap();
+            convert_document_to_lilypond_src(&document, None).unwrap()
+        };
+        let lilypond = lilypond_of("1 2 3 4\n@articulation . > -\n");
+        assert!(lilypond.contains("c4-. d4-> e4-- f4"), "{}", lilypond);
+
+        // Above the notes too, where the dot is still a staccato and not an octave dot
+        let lilypond = lilypond_of("@articulation .   .\n1 2 3 4\n");
+        assert!(lilypond.contains("c4-. d4 e4-. f4"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_ornaments() {
+        let lilypond_of = |input: &str| {
+            let document = crate::pipeline::document_from_notation(input).unwrap();
+            convert_document_to_lilypond_src(&document, None).unwrap()
+        };
+        let lilypond = lilypond_of("~\n1 2 3 4\n");
+        assert!(lilypond.contains("c4\\mordent d4 e4 f4"), "{}", lilypond);
+        let lilypond = lilypond_of("@upper   tr\n1 2 3 4\n");
+        assert!(lilypond.contains("c4 d4\\trill e4 f4"), "{}", lilypond);
+        let lilypond = lilypond_of("@upper     turn\n1 2 3 4\n");
+        assert!(lilypond.contains("c4 d4 e4\\turn f4"), "{}", lilypond);
+        let lilypond = lilypond_of("      \u{1D197}\n1 2 3 4\n");
+        assert!(lilypond.contains("c4 d4 e4 f4\\turn"), "{}", lilypond);
+
+        // A longer run of tildes is still a vibrato span, not a mordent
+        let lilypond = lilypond_of("~~~\n1 2 3 4\n");
+        assert!(!lilypond.contains("\\mordent"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_phrase_mark_over_two_slurs() {
+        let document = crate::pipeline::document_from_notation("@upper (     )\n@upper ___ ___\n1 2 3 4\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("c4\\(( d4) e4( f4)\\)"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_chord_symbols_over_beats() {
+        // Chords in the line above go to the beats under their '[', two in the same measure
+        let document = crate::pipeline::document_from_notation("[C] [G]\n1   2 | 3\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\chordmode { c4 g4 s4 }"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_divisi_renders_stacked_noteheads_until_unison() {
+        use crate::document::line_parser::content_line_parser::unused_parse_content_line;
+        let mut line = unused_parse_content_line("div. 3 4 unis. 5", 0, NotationSystem::Number, 0).unwrap();
+        let second = unused_parse_content_line("1 2", 1, NotationSystem::Number, 17).unwrap();
+        crate::document::divisi::apply_divisi(&mut line, &second).unwrap();
+        crate::rhythm::analyzer::analyze_content_line_rhythm(&mut line.elements).unwrap();
+
+        let notes: Vec<String> = line.elements.iter()
+            .filter_map(|e| match e {
+                ContentElement::Beat(beat) => Some(convert_beat_to_lilypond(beat, None, BeamOverRests::default()).unwrap()),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        assert_eq!(notes, vec!["<e c>4", "<f d>4", "g4"]);
+    }
+
+    #[test]
+    fn test_divisi_from_notation() {
+        // The content line under the `div.` line is the second stream of the span
+        let input = "| div. 3 4 | unis. 5 |\n   1 2\n";
+        let document = crate::pipeline::document_from_notation(input).unwrap();
+        assert_eq!(document.to_plain_text(), input);
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("<e c>4 <f d>4 |\n        g4 |"), "{}", lilypond);
+        assert!(crate::pipeline::document_from_notation("| div. 3 4 |\n1 2 3\n").is_err());
+    }
+
+    #[test]
+    fn test_note_names_inside_noteheads() {
+        assert_eq!(
+            note_names_settings(NoteNames::ScaleDegree).unwrap(),
+            "\\easyHeadsOn \\override NoteHead.note-names = ##(\"1\" \"2\" \"3\" \"4\" \"5\" \"6\" \"7\")"
+        );
+        assert_eq!(
+            note_names_settings(NoteNames::Letter).unwrap(),
+            "\\easyHeadsOn \\override NoteHead.note-names = ##(\"C\" \"D\" \"E\" \"F\" \"G\" \"A\" \"B\")"
+        );
+        assert_eq!(note_names_settings(NoteNames::Off), None);
+    }
+
+    #[test]
+    fn test_teentaal_cycles_emphasize_sam() {
+        let document = document_from_notation("Tala: Teentaal\n\n| 1 2 3 4 | 5 6 7 1 | 1 2 3 4 | 5 6 7 1 | 2\n").unwrap();
+
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\time 4,4,4,4 16/4"));
+        // Sam on beats 1 and 17, tali on 5 and 13, khali on 9
+        assert!(lilypond.contains("c4->^\"X\" d4"));
+        assert!(lilypond.contains("g4^\"2\""));
+        assert!(lilypond.contains("c4^\\markup { \\draw-circle"));
+        assert!(lilypond.contains("d4->^\"X\""));
+    }
+
+    #[test]
+    fn test_inline_grace_note() {
+        let document = document_from_notation("| (2)1 3 |\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\grace { d8 } c4 e4"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_dynamics_under_notes() {
+        let document = document_from_notation("1 2 34 5\nf < mf\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("c4\\f d4\\< e8\\mf f8 g4"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_clef_per_stave() {
+        let lilypond = |input: &str| {
+            let document = crate::pipeline::document_from_notation(input).unwrap();
+            convert_processed_document_to_lilypond_src(&document, None).unwrap()
+        };
+        let bass = lilypond("1 2 3 4\n\nclef: bass\n5 6 7 1\n");
+        assert_eq!(bass.matches("\\clef").count(), 1, "{}", bass);
+        assert!(bass.contains("\\clef bass g4 a4 b4 c4"), "{}", bass);
+        // The directive sets the clef of every stave; the colon of a clef line is not an octave mark
+        let all_bass = lilypond("Clef: bass\n\n1 2\nclef: tenor\n3 4\n");
+        assert!(all_bass.contains("\\clef bass c4 d4"), "{}", all_bass);
+        assert!(all_bass.contains("\\clef tenor e4 f4"), "{}", all_bass);
+    }
+
+    #[test]
+    fn test_breath_mark_between_beats() {
+        let lilypond = |input: &str| {
+            let document = crate::pipeline::document_from_notation(input).unwrap();
+            convert_document_to_lilypond_src(&document, None).unwrap()
+        };
+        let breath = lilypond("1 2 ' 3 4\n");
+        assert!(breath.contains("c4 d4 \\breathe e4 f4"), "{}", breath);
+        // A dash after a breath is a rest, not a held note
+        let rest = lilypond("1 ' - 2\n");
+        assert!(rest.contains("c4 \\breathe r4 d4"), "{}", rest);
+    }
+
+    #[test]
+    fn test_note_repetition_from_notation() {
+        let lilypond = |input: &str| {
+            let document = crate::pipeline::document_from_notation(input).unwrap();
+            convert_document_to_lilypond_src(&document, None).unwrap()
+        };
+        let repeated = lilypond("| 1*4 |\n");
+        assert!(repeated.contains("c4 c4 c4 c4"), "{}", repeated);
+        // The octave dot over the shorthand raises every note it expands to
+        let raised = lilypond(".\n3*2\n");
+        assert!(raised.contains("e'4 e'4"), "{}", raised);
+
+        // Counts are capped, and a multi-measure rest is not a repeated Sargam Re
+        let error = crate::pipeline::document_from_notation("| 1*99999999999 |\n").unwrap_err();
+        assert!(error.contains("more than 64"), "{}", error);
+        let rest = lilypond("| 1 2 3 4 | R*8 | 1 2 3 4 |\n");
+        assert!(rest.contains("c4 d4 e4 f4 |\n        \\compressMMRests { R1*8 } |\n        c4 d4 e4 f4"), "{}", rest);
+    }
+
+    #[test]
+    fn test_multi_measure_rest_takes_measure_length() {
+        let lilypond = |input: &str| {
+            let document = crate::pipeline::document_from_notation(input).unwrap();
+            convert_document_to_lilypond_src(&document, None).unwrap()
+        };
+        let common = lilypond("| R*8 |\n");
+        assert!(common.contains("\\compressMMRests { R1*8 }"), "{}", common);
+        let waltz = lilypond("Time: 3/4\n\n| R*2 | 1 2 3 |\n");
+        assert!(waltz.contains("\\compressMMRests { R1*3/4*2 }"), "{}", waltz);
+        // An inline meter change sets the length of the rests after it
+        let changed = lilypond("| 1 2 3 4 |(3/4) R*2 | 1 2 3 |\n");
+        assert!(changed.contains("\\time 3/4 \\compressMMRests { R1*3/4*2 }"), "{}", changed);
+    }
+
+    #[test]
+    fn test_dynamics_line_from_notation() {
+        let lilypond = |input: &str| {
+            let document = crate::pipeline::document_from_notation(input).unwrap();
+            convert_document_to_lilypond_src(&document, None).unwrap()
+        };
+        assert!(lilypond("1 2 3 4\nf\n").contains("c4\\f d4 e4 f4"));
+        let marked = lilypond("1 2 3 4 5\np       f\n");
+        assert!(marked.contains("c4\\p d4 e4 f4 g4\\f"), "{}", marked);
+        // A hairpin with no level after it is closed on the last note
+        let open = lilypond("1 2 3 4\nmf  <\n");
+        assert!(open.contains("c4\\mf d4 e4\\< f4\\!"), "{}", open);
+        let closed = lilypond("1 2 3 4\nmf  < f\n");
+        assert!(closed.contains("c4\\mf d4 e4\\< f4\\f"), "{}", closed);
+        assert!(!closed.contains("\\!"), "{}", closed);
+    }
+
+    #[test]
+    fn test_compound_meter_groups_eighths_in_threes() {
+        let lilypond_of = |input: &str| {
+            let document = crate::pipeline::document_from_notation(input).unwrap();
+            convert_document_to_lilypond_src(&document, None).unwrap()
+        };
+        let simple = lilypond_of("Time: 4/4\n\n| 123 456 |\n");
+        assert!(simple.contains("| \\tuplet 3/2 { c8 d8 e8 } \\tuplet 3/2 { f8 g8 a8 } |"), "{}", simple);
+        let compound = lilypond_of("TimeSignature: 6/8\n\n| 123 456 |\n");
+        assert!(compound.contains("\\time 6/8"), "{}", compound);
+        assert!(compound.contains("| c8 d8 e8 f8 g8 a8 |"), "{}", compound);
+
+        // A held dash is a third of a dotted-quarter beat
+        let tied = lilypond_of("Time: 6/8\n\n| 1 -23 |\n");
+        assert!(tied.contains("| c4.~ c8 d8 e8 |"), "{}", tied);
+    }
+
+    #[test]
+    fn test_two_verses_of_lyrics() {
+        let document = crate::pipeline::document_from_notation("1 2 3\nhap-py day\ngood night\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\addlyrics { hap -- py day }"), "{}", lilypond);
+        assert!(lilypond.contains("\\addlyrics { good night }"), "{}", lilypond);
+
+        // Both verses hold their first syllable over the slurred notes
+        let document = crate::pipeline::document_from_notation("@upper _____\n1 2 3 4\noh say\nah me\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\addlyrics { oh __ _ _ say }"), "{}", lilypond);
+        assert!(lilypond.contains("\\addlyrics { ah __ _ _ me }"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_melisma_extender_and_hyphen() {
+        // A word held over a two-note slur stretches with an extender line
+        let document = crate::pipeline::document_from_notation("@upper ___\n1 2 3\nlove me\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\addlyrics { love __ _ me }"), "{}", lilypond);
+
+        // A syllable mid-word keeps its hyphen instead
+        let document = crate::pipeline::document_from_notation("@upper ___\n1 2 3\nhap-py\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\addlyrics { hap -- _ py }"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_tie_across_barline() {
+        let document = document_from_notation("1 - | - 2\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("c4~ c4~ |\n        c4 d4"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_quarter_tones() {
+        let document = document_from_notation("| 1+ 1# 3𝄳 7b𝄳 |\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("cqs4 cs4 eqf4 btqf4"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_simple_repeat() {
+        let lilypond = |notation: &str| {
+            convert_document_to_lilypond_src(&document_from_notation(notation).unwrap(), None).unwrap()
+        };
+        let repeated = lilypond("|: 1 2 3 4 :| 5 6 7 1 |.\n");
+        assert!(repeated.contains("| \\repeat volta 2 { c4 d4 e4 f4 |\n        } g4 a4 b4 c4 |"), "{}", repeated);
+        assert!(!repeated.contains("\\alternative"), "{}", repeated);
+
+        // Without "|:" the music repeats from the start
+        let from_start = lilypond("1 2 3 4 :| 5 6 7 1\n");
+        assert!(from_start.contains("\\repeat volta 2 { c4 d4 e4 f4 |\n        } g4 a4 b4 c4"), "{}", from_start);
+    }
+
+    #[test]
+    fn test_repeat_with_two_endings() {
+        let document = document_from_notation("|: 1 2 3 4 |1. 5 6 7 1 :|2. 2 2 2 2 |.\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains(concat!(
+            "| \\repeat volta 2 { c4 d4 e4 f4 |\n",
+            "        } \\alternative { { g4 a4 b4 c4 |\n",
+            "          } { d4 d4 d4 d4 |\n",
+            "        } }",
+        )), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_grace_notes_on_first_note_of_measure() {
+        let document = document_from_notation("         43\n| 1 2 3 4 | 5 6 7 1 |\n").unwrap();
+
+        // The measure's own rhythm is untouched by its grace notes
+        let durations: Vec<(Option<u32>, Option<u32>)> = document_beats(&document)
+            .flat_map(|beat| beat.elements.iter())
+            .filter_map(|element| match element {
+                BeatElement::Note(note) => Some((note.numerator, note.denominator)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(durations, vec![(Some(1), Some(4)); 8]);
+
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("| c4 d4 e4 f4 |\n        \\grace { f16 e16 } g4 a4 b4 c4 |"), "{}", lilypond);
+
+        // Tala marks go on the main note, not its grace notes
+        let document = document_from_notation("Tala: Teentaal\n\n         43\n| 1 2 3 4 | 5 6 7 1 |\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\grace { f16 e16 } g4^\"2\""), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_grace_notes_from_notation() {
+        let document = crate::pipeline::document_from_notation("         43\n| 1 2 3 4 | 5 6 7 1 |\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("| c4 d4 e4 f4 |\n        \\grace { f16 e16 } g4 a4 b4 c4 |"), "{}", lilypond);
+        let durations: Vec<(Option<u32>, Option<u32>)> = document.notes().map(|note| (note.numerator, note.denominator)).collect();
+        assert_eq!(durations, vec![(Some(1), Some(4)); 8]);
+    }
+
+    #[test]
+    fn test_feathered_beam_override() {
+        let document = crate::pipeline::document_from_notation("  <<   >>   >>\n| 1234 5671 1 |\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\override Beam.grow-direction = #RIGHT \\featherDurations #(ly:make-moment 2/1) { c16[ d16 e16 f16] } \\revert Beam.grow-direction"), "{}", lilypond);
+        assert!(lilypond.contains("#LEFT \\featherDurations #(ly:make-moment 1/2) { g16[ a16 b16 c16] }"), "{}", lilypond);
+        // A single note has no beam to feather
+        assert!(lilypond.contains("\\revert Beam.grow-direction c4"), "{}", lilypond);
+
+        let document = crate::pipeline::document_from_notation("  <<\n| 1234 5 6 7 |\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("| \\override Beam.grow-direction = #RIGHT \\featherDurations #(ly:make-moment 2/1) { c16[ d16 e16 f16] } \\revert Beam.grow-direction g4 a4 b4 |"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_count_line_under_eighths() {
+        let document = document_from_notation("12 34 56 71\n").unwrap();
+        assert!(!convert_document_to_lilypond_src(&document, None).unwrap().contains("\\addlyrics"));
+
+        let document = document_from_notation("CountLine: on\n\n12 34 56 71\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\addlyrics { 1 & 2 & 3 & 4 & }"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_shape_note_heads() {
+        let document = document_from_notation("NoteheadStyle: sacred\n\n1 2 3 4\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\sacredHarpHeads"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_six_eight_beats_in_dotted_quarters() {
+        let document = document_from_notation("Time: 6/8\n\n123 456\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\time 6/8"), "{}", lilypond);
+        // Three plain eighths per beat, left to 6/8 auto-beaming rather than triplets
+        assert!(lilypond.contains("c8 d8 e8 f8 g8 a8"), "{}", lilypond);
+        assert!(!lilypond.contains("\\tuplet"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_explicit_tuplet_ratio() {
+        let document = document_from_notation("| [3:2 1 2 3] 123 [5:4 1 2 3 4 5] [3:2 1 2] |\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\tuplet 3/2 { c8 d8 e8 } \\tuplet 3/2 { c8 d8 e8 } \\tuplet 5/4 { c16 d16 e16 f16 g16 }"), "{}", lilypond);
+        // The written ratio holds even when the notes don't add up to it
+        assert!(lilypond.contains("\\tuplet 3/2 { c8. d8. }"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_declared_pickup_emits_partial() {
+        // Two eighths before the first barline, declared as a quarter-note pickup
+        let document = document_from_notation("Pickup: 1/4\n\n12 | 3 4 5 6\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\partial 4 c8 d8"));
+
+        let document = document_from_notation("Pickup: 5/4\n\n12 | 3 4 5 6\n").unwrap();
+        assert!(convert_document_to_lilypond_src(&document, None).is_err());
+    }
+
+    #[test]
+    fn test_braced_staves_render_as_piano_staff() {
+        let input = "|5 6 7 1|\n\n{\nclef: treble\n|1 2 3 4|\nclef: bass\n|1 2 3 4|\n}\n";
+        let document = crate::pipeline::document_from_notation(input).unwrap();
+        let grand_staves: Vec<Option<usize>> = document.elements.iter()
+            .filter_map(|element| match element {
+                DocumentElement::Stave(stave) => Some(stave.grand_staff),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(grand_staves, vec![None, Some(0), Some(0)]);
+        assert_eq!(document.to_plain_text(), input);
+
+        let lilypond = convert_processed_document_to_lilypond_src(&document, None).unwrap();
+        let piano = lilypond.find("\\new PianoStaff <<").expect(&lilypond);
+        let staves: Vec<usize> = lilypond.match_indices("\\new Staff").map(|(i, _)| i).collect();
+        let close = piano + lilypond[piano..].find(">>").unwrap();
+        assert_eq!(staves.len(), 3, "{}", lilypond);
+        assert!(staves[0] < piano && piano < staves[1] && staves[2] < close, "{}", lilypond);
+        assert!(lilypond.contains("\\clef bass"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_staves_share_single_staff_settings() {
+        let input = "Time: 3/4\n\n1 | R*2 | 1 2 3 |(2/4) 4 5 |\n\n5 | 1 2' 3 | 4 5 6 |\n";
+        let document = crate::pipeline::document_from_notation(input).unwrap();
+        let lilypond = convert_processed_document_to_lilypond_src(&document, None).unwrap();
+        assert_eq!(lilypond.matches("\\new Staff").count(), 2, "{}", lilypond);
+        assert_eq!(lilypond.matches("\\time 3/4").count(), 2, "{}", lilypond);
+        assert!(!lilypond.contains("\\time 4/4") && !lilypond.contains("\\key"), "{}", lilypond);
+        assert_eq!(lilypond.matches("\\partial 4").count(), 2, "{}", lilypond);
+        assert!(lilypond.contains("\\compressMMRests { R1*3/4*2 }"), "{}", lilypond);
+        assert!(lilypond.contains("\\time 2/4"), "{}", lilypond);
+        assert!(lilypond.contains("\\breathe"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_detected_pickup_emits_partial() {
+        let lilypond_of = |input: &str| {
+            let document = crate::pipeline::document_from_notation(input).unwrap();
+            convert_document_to_lilypond_src(&document, None).unwrap()
+        };
+        let lilypond = lilypond_of("- - 1 | 2 3 4 5\n");
+        assert!(lilypond.contains("\\partial 2. r4 r4 c4"), "{}", lilypond);
+
+        // A single eighth before the first barline
+        let lilypond = lilypond_of("BeatUnit: 1/8\n\n1 | 2 3 4 5 6 7 8 9\n");
+        assert!(lilypond.contains("\\partial 8 c8"), "{}", lilypond);
+
+        let lilypond = lilypond_of("1 2 3 4 | 5 6 7 8\n");
+        assert!(!lilypond.contains("\\partial"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_cautionary_sharp_is_parenthesized() {
+        let mut document = document_from_notation("| 4# 4# |\n").unwrap();
+        let first_note = document_beats(&document).next()
+            .and_then(|beat| match &beat.elements[0] {
+                BeatElement::Note(note) => Some(note.id.to_string()),
+                _ => None,
+            })
+            .unwrap();
+        crate::document::edit::execute_edit(&mut document, "set_cautionary", &[first_note], &serde_json::json!({})).unwrap();
+
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("fs?4 fs4"));
+    }
+
+    #[test]
+    fn test_high_run_gets_ottava_bracket() {
+        // Three octaves up, after a middle C
+        let document = document_from_notation("Ottava: auto\n\n  : : : :\n  . . . .\n1 1 2 3 5\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("c4 \\ottava #1 c'''4 d'''4 e'''4 g'''4 \\ottava #0"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_explicit_tie_across_barline() {
+        let document = document_from_notation("|1~|1|\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("| c4~ |\n        c4 |"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_tie_between_different_pitches_is_dropped() {
+        let document = crate::pipeline::document_from_notation("|1 2 3 4~|1 2 3 4|\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("f4 |\n        c4"), "{}", lilypond);
+        assert!(!lilypond.contains('~'), "{}", lilypond);
+        let warnings: Vec<String> = crate::pipeline::notation_warnings(&document).into_iter().map(|w| w.message).collect();
+        assert_eq!(warnings, vec!["Tie joins different pitches (4 and 1)"]);
+        // The written marker stays in the document text
+        assert_eq!(document.to_plain_text(), "|1 2 3 4~|1 2 3 4|\n");
+    }
+
+    #[test]
+    fn test_slash_notation_in_toggled_measures_only() {
+        let document = document_from_notation("Slash: on 2, off 4\n\n| 1 1 1 1 | 2 2 2 2 | 3 3 3 3 | 4 4 4 4 |\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("c4 c4 c4 c4 |\n        \\improvisationOn d4 d4 d4 d4 |\n        e4 e4 e4 e4 |\n        \\improvisationOff f4 f4 f4 f4 |"), "{}", lilypond);
+        assert_eq!(lilypond.matches("\\improvisationOn").count(), 1);
+    }
+
+    #[test]
+    fn test_formatting_directives_number_measures_per_line() {
+        let header = "Pickup: 1/4\nLilyPondBarNumbers: on\n";
+        let document = crate::pipeline::document_from_notation(&format!("{}\n1 | 2 3 4 5 | 6 7 1 2\n", header)).unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\n        \\partial 4 c4 | % 0\n        d4 e4 f4 g4 | % 1\n        a4 b4 c4 d4 % 2\n"), "{}", lilypond);
+
+        let header = format!("{}LilyPondLayout: note\nLilyPondIndent: 4\n", header);
+        let document = crate::pipeline::document_from_notation(&format!("{}\n1 | 2 3 4 5 | 6 7 1 2\n", header)).unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\n                % 1\n                d4\n                e4\n"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_transposition_modes() {
+        let document = document_from_notation("Transpose: D\n\n1 2 3 4\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("d4 e4 fs4 g4"), "{}", lilypond);
+        assert!(!lilypond.contains("\\transpose"), "{}", lilypond);
+
+        let document = document_from_notation("Transpose: D\nTransposeMode: lilypond\n\n1 2 3 4\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\transpose c d {\n          c4 d4 e4 f4\n        }"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_lilypond_midi_block_uses_tempo() {
+        let document = document_from_notation("1 2 3 4\n").unwrap();
+        assert!(!convert_document_to_lilypond_src(&document, None).unwrap().contains("\\midi"));
+
+        for title in ["", "Song\n"] {
+            let document = document_from_notation(&format!("{}LilyPondMidi: on\nTempo: 90\n\n1 2 3 4\n", title)).unwrap();
+            let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+            let score = &lilypond[lilypond.find("\\score").expect("score")..];
+            let layout = score.find("\\layout").expect("layout block");
+            let midi = score.find("\\midi").expect("midi block");
+            assert!(layout < midi, "{}", lilypond);
+            assert!(score[midi..].contains("\\tempo 4 = 90"), "{}", lilypond);
+        }
+    }
+
+    #[test]
+    fn test_tempo_directive_becomes_metronome_mark() {
+        for tempo in ["96", "1/4=96"] {
+            let document = document_from_notation(&format!("Tempo: {}\n\n1 2 3 4\n", tempo)).unwrap();
+            let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+            let mark = lilypond.find("\\tempo 4 = 96").unwrap_or_else(|| panic!("{}", lilypond));
+            assert!(mark < lilypond.find("c4").expect("first note"), "{}", lilypond);
+        }
+    }
+
+    #[test]
+    fn test_explicit_rest_tokens() {
+        let lilypond_of = |input: &str| {
+            let document = crate::pipeline::document_from_notation(input).unwrap();
+            convert_document_to_lilypond_src(&document, None).unwrap()
+        };
+        // A quarter rest followed by a quarter note
+        let lilypond = lilypond_of("|0 1 2 3|\n");
+        assert!(lilypond.contains("r4 c4 d4 e4"), "{}", lilypond);
+
+        // Dashes extend the rest like a note
+        let lilypond = lilypond_of("System: number\n\n|r--1 2 3 4|\n");
+        assert!(lilypond.contains("r8. c16 d4 e4 f4"), "{}", lilypond);
+
+        // A dash beat after a rest rests on instead of tying
+        let lilypond = lilypond_of("System: number\n\n|r - 1 2|\n");
+        assert!(lilypond.contains("r4 r4 c4 d4"), "{}", lilypond);
+
+        // A lone r is a rest, not Sargam komal Re, among digits or Western letters
+        let lilypond = lilypond_of("| r - 1 2 |\n");
+        assert!(lilypond.contains("r4 r4 c4 d4"), "{}", lilypond);
+        let lilypond = lilypond_of("| r 1 2 3 |\n");
+        assert!(lilypond.contains("r4 c4 d4 e4"), "{}", lilypond);
+        let lilypond = lilypond_of("| C r D E |\n");
+        assert!(lilypond.contains("c4 r4 d4 e4"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_transposed_sargam_keeps_komal_and_tivra() {
+        let lilypond_of = |input: &str| {
+            let document = crate::pipeline::document_from_notation(input).unwrap();
+            convert_document_to_lilypond_src(&document, None).unwrap()
+        };
+        let lilypond = lilypond_of("Transpose: C\n\n|S r g M d n|\n");
+        assert!(lilypond.contains("c4 df4 ef4 fs4 af4 bf4"), "{}", lilypond);
+        let lilypond = lilypond_of("Transpose: D\n\n|S r g M d n|\n");
+        assert!(lilypond.contains("d4 ef4 f4 gs4 bf4 c'4"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_inline_meter_change() {
+        let document = crate::pipeline::document_from_notation("TimeSignature: 4/4\n\n|1 2 3 4|(3/4) 5 6 7|\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("\\time 4/4"), "{}", lilypond);
+        assert!(lilypond.contains("f4 |\n        \\time 3/4 g4 a4 b4 |"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_composer_in_header() {
+        let document = crate::pipeline::document_from_notation("Composer: Duke \"The Duke\" Ellington\n\n|1 2 3 4|\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("composer = \"Duke \\\"The Duke\\\" Ellington\""), "{}", lilypond);
+
+        let document = crate::pipeline::document_from_notation("Author: Trad.\n\n|1 2|\n\n|3 4|\n").unwrap();
+        let lilypond = convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("composer = \"Trad.\""), "{}", lilypond);
+    }
+}
@@ -18,6 +18,15 @@ pub struct TemplateContext {
     pub time_signature: Option<String>,
     pub key_signature: Option<String>,
     pub lyrics: Option<String>,
+    pub verses: Vec<String>, // Lyrics of each verse, one \addlyrics block apiece
+    pub count_line: Option<String>, // Counting syllables ("1 & 2 &") under the staff
+    pub rhythmic: bool,     // Unpitched (scat) content on a one-line RhythmicStaff
+    pub slashes: bool,      // Fake-book rhythm slashes (\improvisationOn)
+    pub chords: Option<String>, // \chordmode content shown above the staff
+    pub note_names: Option<String>, // Easy-notation settings printing names inside noteheads
+    pub noteheads: Option<String>,  // Shape-note command (\aikenHeads, \sacredHarpHeads)
+    pub midi_tempo: Option<u32>,    // Adds a \midi block at this tempo (quarter notes per minute)
+    pub tempo: Option<String>,      // Metronome mark at the start of the music (e.g. "\tempo 4 = 96")
 }
 
 impl Default for TemplateContext {
@@ -31,6 +40,15 @@ impl Default for TemplateContext {
             time_signature: None,
             key_signature: None,
             lyrics: None,
+            verses: Vec::new(),
+            count_line: None,
+            rhythmic: false,
+            slashes: false,
+            chords: None,
+            note_names: None,
+            noteheads: None,
+            midi_tempo: None,
+            tempo: None,
         }
     }
 }
@@ -108,13 +126,10 @@ impl TemplateContextBuilder {
         self
     }
     
-    // DELETED - unused method
-    /*
     pub fn composer<S: Into<String>>(mut self, composer: S) -> Self {
         self.context.composer = Some(composer.into());
         self
     }
-    */
     
     pub fn source_comment<S: Into<String>>(mut self, source: S) -> Self {
         self.context.source_comment = Some(format_source_comment(&source.into()));
@@ -126,13 +141,10 @@ impl TemplateContextBuilder {
         self
     }
     
-    // DELETED - unused method
-    /*
     pub fn time_signature<S: Into<String>>(mut self, time_sig: S) -> Self {
         self.context.time_signature = Some(time_sig.into());
         self
     }
-    */
     
     // DELETED - unused method
     /*
@@ -147,6 +159,51 @@ impl TemplateContextBuilder {
         self
     }
     
+    pub fn verses(mut self, verses: Vec<String>) -> Self {
+        self.context.verses = verses;
+        self
+    }
+    
+    pub fn count_line<S: Into<String>>(mut self, count_line: S) -> Self {
+        self.context.count_line = Some(count_line.into());
+        self
+    }
+    
+    pub fn rhythmic(mut self, rhythmic: bool) -> Self {
+        self.context.rhythmic = rhythmic;
+        self
+    }
+    
+    pub fn slashes(mut self, slashes: bool) -> Self {
+        self.context.slashes = slashes;
+        self
+    }
+    
+    pub fn chords<S: Into<String>>(mut self, chords: S) -> Self {
+        self.context.chords = Some(chords.into());
+        self
+    }
+    
+    pub fn note_names<S: Into<String>>(mut self, note_names: S) -> Self {
+        self.context.note_names = Some(note_names.into());
+        self
+    }
+    
+    pub fn noteheads<S: Into<String>>(mut self, noteheads: S) -> Self {
+        self.context.noteheads = Some(noteheads.into());
+        self
+    }
+    
+    pub fn midi_tempo(mut self, bpm: u32) -> Self {
+        self.context.midi_tempo = Some(bpm);
+        self
+    }
+
+    pub fn tempo<S: Into<String>>(mut self, tempo: S) -> Self {
+        self.context.tempo = Some(tempo.into());
+        self
+    }
+    
     pub fn build(self) -> TemplateContext {
         self.context
     }
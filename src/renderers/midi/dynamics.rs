@@ -0,0 +1,67 @@
+/// Playback loudness from dynamic marks
+/// Levels set the velocity of the notes that follow; a hairpin ramps it linearly
+/// from the level before it to the next level marked after it
+use crate::document::line_parser::dynamics_line_parser::is_hairpin;
+
+/// Velocity of notes before any dynamic mark (mezzo-forte)
+pub const DEFAULT_VELOCITY: u8 = 80;
+
+/// MIDI velocity of a dynamic level
+pub fn level_velocity(mark: &str) -> Option<u8> {
+    match mark {
+        "ppp" => Some(16),
+        "pp" => Some(33),
+        "p" => Some(49),
+        "mp" => Some(64),
+        "mf" => Some(80),
+        "f" => Some(96),
+        "ff" => Some(112),
+        "fff" => Some(127),
+        _ => None,
+    }
+}
+
+/// Velocity of a note starting at `position`, given the dynamic marks of its stave
+/// as (position, mark) pairs in order. Positions are in beats.
+/// A hairpin without a level after it leaves the velocity unchanged.
+pub fn velocity_at(marks: &[(f64, String)], position: f64) -> u8 {
+    let mut velocity = DEFAULT_VELOCITY;
+    for (index, (start, mark)) in marks.iter().enumerate() {
+        if *start > position {
+            break;
+        }
+        if let Some(level) = level_velocity(mark) {
+            velocity = level;
+        } else if is_hairpin(mark) {
+            let target = marks[index + 1..].iter()
+                .find_map(|(end, mark)| level_velocity(mark).map(|level| (*end, level)));
+            if let Some((end, level)) = target {
+                if position < end {
+                    let progress = (position - start) / (end - start);
+                    return (velocity as f64 + (level as f64 - velocity as f64) * progress).round() as u8;
+                }
+            }
+        }
+    }
+    velocity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marks(marks: &[(f64, &str)]) -> Vec<(f64, String)> {
+        marks.iter().map(|(position, mark)| (*position, mark.to_string())).collect()
+    }
+
+    #[test]
+    fn test_velocity_at() {
+        assert_eq!(velocity_at(&[], 3.0), DEFAULT_VELOCITY);
+        let decrescendo = marks(&[(0.0, "f"), (1.0, ">"), (3.0, "p")]);
+        assert_eq!(velocity_at(&decrescendo, 0.5), 96);
+        assert_eq!(velocity_at(&decrescendo, 2.0), 73);
+        assert_eq!(velocity_at(&decrescendo, 3.5), 49);
+        // No level to ramp towards
+        assert_eq!(velocity_at(&marks(&[(0.0, "p"), (1.0, "<")]), 2.0), 49);
+    }
+}
@@ -0,0 +1,292 @@
+/// Timed note events derived from an analyzed document
+/// Shared by MIDI export and playback highlighting
+use crate::parse::model::{Document, DocumentElement, StaveLine, ContentElement, BeatElement, PitchCode, Source};
+use crate::renderers::transposition::pitchcode_to_semitones;
+use crate::rhythm::analyzer::BeatUnit;
+use crate::rhythm::measures::{meter_change, multi_measure_rests};
+use crate::analysis::TimeSignature;
+use super::dynamics::{velocity_at, DEFAULT_VELOCITY};
+use fraction::Fraction;
+use serde::Serialize;
+
+/// Default playback tempo in quarter-note beats per minute
+pub const DEFAULT_TEMPO_BPM: u32 = 120;
+
+/// Directive giving the tempo in beats per minute, of a quarter note unless another
+/// note is given (e.g. "Tempo: 90", "Tempo: 3/8=60")
+pub const TEMPO_DIRECTIVE: &str = "Tempo";
+
+/// Metronome mark of the `Tempo` directive: beats per minute of a reference note
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tempo {
+    /// Reference note as a fraction of a whole note (1/4 for a quarter)
+    pub numerator: u32,
+    pub denominator: u32,
+    pub bpm: u32,
+}
+
+impl Tempo {
+    /// Read "96", "96 bpm", "♩=96", "4=96" or "1/4=96". Before '=', a fraction or a
+    /// LilyPond-style duration number names the reference note; anything else is a quarter.
+    pub fn from_directives(directives: &std::collections::HashMap<String, String>) -> Option<Self> {
+        let value = crate::parse::model::directive(directives, TEMPO_DIRECTIVE)?;
+        let (reference, bpm) = value.split_once('=').unwrap_or(("", value));
+        let (numerator, denominator) = match reference.trim().split_once('/') {
+            Some((numerator, denominator)) => (numerator.trim().parse().ok()?, denominator.trim().parse().ok()?),
+            None => (1, reference.trim().parse().unwrap_or(4)),
+        };
+        let digits: String = bpm.chars()
+            .skip_while(|c| !c.is_ascii_digit())
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        let bpm = digits.parse::<u32>().ok().filter(|&bpm| bpm > 0)?;
+        (numerator > 0 && denominator > 0).then_some(Tempo { numerator, denominator, bpm })
+    }
+
+    /// The same tempo counted in quarter notes
+    pub fn quarter_notes_per_minute(self) -> u32 {
+        ((self.bpm * 4 * self.numerator) as f64 / self.denominator as f64).round() as u32
+    }
+}
+
+/// Tempo of the `Tempo` directive in quarter notes per minute, or the default when
+/// it is missing or zero
+pub fn tempo_from_directives(directives: &std::collections::HashMap<String, String>) -> u32 {
+    Tempo::from_directives(directives)
+        .map(Tempo::quarter_notes_per_minute)
+        .filter(|&bpm| bpm > 0)
+        .unwrap_or(DEFAULT_TEMPO_BPM)
+}
+
+/// A sounding note with its timing and its location in the source text
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TimedEvent {
+    pub id: String,              // UUID of the Note element
+    pub stave: usize,            // Index of the stave the note belongs to
+    pub midi_note: u8,
+    pub start_beats: f64,        // Onset in quarter-note beats
+    pub duration_beats: f64,
+    pub start_seconds: f64,
+    pub duration_seconds: f64,
+    pub source: Option<Source>,
+    pub vibrato: bool,           // Sent as modulation (CC1) for the length of the note
+    pub bend: Option<i8>,        // Pitch bend in semitones
+    pub microtone: Option<i8>,   // Quarter tones off the MIDI note, sent as pitch bend
+    pub velocity: u8,            // From the dynamic marks, ramped under hairpins
+}
+
+/// MIDI note number for a pitch code and octave (N1 in octave 0 is middle C = 60)
+pub fn pitch_to_midi(pitch_code: PitchCode, octave: i8) -> u8 {
+    let midi = 60 + 12 * octave as i32 + pitchcode_to_semitones(pitch_code) as i32;
+    midi.clamp(0, 127) as u8
+}
+
+/// Collect timed note events for every stave, with source positions for highlighting.
+///
+/// Each beat lasts the document's beat unit (a quarter note unless `BeatUnit` or `Time`
+/// says otherwise) and is split evenly between its subdivisions
+/// (notes, dashes and rests). Leading dashes of a beat tied to the previous one
+/// extend the previous note; otherwise they are silent. A note tied with an explicit
+/// tie marker to a note of the same pitch sounds once, through both.
+pub fn events_with_positions(document: &Document, tempo_bpm: u32) -> Vec<TimedEvent> {
+    let seconds_per_beat = 60.0 / tempo_bpm.max(1) as f64;
+    let beat_unit = BeatUnit::from_directives(&document.directives).0 * Fraction::from(4u64);
+    let beat_quarters = *beat_unit.numer().unwrap_or(&1) as f64 / *beat_unit.denom().unwrap_or(&1) as f64;
+    let mut events: Vec<TimedEvent> = Vec::new();
+    let mut stave_index = 0;
+
+    for element in &document.elements {
+        if let DocumentElement::Stave(stave) = element {
+            let mut beat_start = 0.0;
+            // Index into `events` of the note that dashes currently extend
+            let mut sounding: Option<usize> = None;
+            // Index into `events` of a note written with a tie marker, until the next note
+            let mut tied_from: Option<usize> = None;
+            let first_event = events.len();
+            let mut dynamics: Vec<(f64, String)> = Vec::new();
+            let mut meter = TimeSignature::from_directives(&document.directives);
+
+            for line in &stave.lines {
+                if let StaveLine::ContentLine(content_line) = line {
+                    let rests = multi_measure_rests(&content_line.elements);
+                    for (index, content_element) in content_line.elements.iter().enumerate() {
+                        // A multi-measure rest ("R*N") lasts N measures of the current meter
+                        if let Some(&measures) = rests.get(&index) {
+                            let measure = meter.measure_length() * Fraction::from(4u64);
+                            let measure_quarters = *measure.numer().unwrap_or(&4) as f64 / *measure.denom().unwrap_or(&1) as f64;
+                            beat_start += measures as f64 * measure_quarters;
+                            sounding = None;
+                            tied_from = None;
+                            continue;
+                        }
+                        if let Some(time_signature) = meter_change(content_element) {
+                            meter = time_signature;
+                        }
+                        if let ContentElement::Beat(beat) = content_element {
+                            let divisions = beat.elements.iter()
+                                .filter(|e| !matches!(e, BeatElement::BreathMark(_)))
+                                .count();
+                            if divisions == 0 {
+                                continue;
+                            }
+                            if let Some(mark) = &beat.dynamic {
+                                dynamics.push((beat_start, mark.clone()));
+                            }
+                            let subdivision = beat_quarters / divisions as f64;
+                            if !beat.tied_to_previous.unwrap_or(false) {
+                                sounding = None;
+                            }
+
+                            let mut position = beat_start;
+                            for beat_element in &beat.elements {
+                                match beat_element {
+                                    BeatElement::Note(note) => {
+                                        let midi_note = pitch_to_midi(note.pitch_code, note.octave);
+                                        if let Some(index) = tied_from.take().filter(|&i| events[i].midi_note == midi_note && events[i].microtone == note.microtone) {
+                                            events[index].duration_beats += subdivision;
+                                            sounding = Some(index);
+                                            tied_from = note.tie.map(|_| index);
+                                            position += subdivision;
+                                            continue;
+                                        }
+                                        events.push(TimedEvent {
+                                            id: note.id.to_string(),
+                                            stave: stave_index,
+                                            midi_note,
+                                            start_beats: position,
+                                            duration_beats: subdivision,
+                                            start_seconds: 0.0,
+                                            duration_seconds: 0.0,
+                                            source: note.source,
+                                            vibrato: note.vibrato,
+                                            bend: note.bend,
+                                            microtone: note.microtone,
+                                            velocity: DEFAULT_VELOCITY,
+                                        });
+                                        sounding = Some(events.len() - 1);
+                                        tied_from = note.tie.and(sounding);
+                                        position += subdivision;
+                                    }
+                                    BeatElement::Dash(_) => {
+                                        if let Some(index) = sounding {
+                                            events[index].duration_beats += subdivision;
+                                        }
+                                        position += subdivision;
+                                    }
+                                    BeatElement::Rest(_) => {
+                                        sounding = None;
+                                        tied_from = None;
+                                        position += subdivision;
+                                    }
+                                    BeatElement::BreathMark(_) => {
+                                        sounding = None;
+                                    }
+                                }
+                            }
+                            beat_start += beat_quarters;
+                        }
+                    }
+                }
+            }
+            for event in &mut events[first_event..] {
+                event.velocity = velocity_at(&dynamics, event.start_beats);
+            }
+            stave_index += 1;
+        }
+    }
+
+    for event in &mut events {
+        event.start_seconds = event.start_beats * seconds_per_beat;
+        event.duration_seconds = event.duration_beats * seconds_per_beat;
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::document_from_notation;
+
+    #[test]
+    fn test_tempo_from_directives() {
+        let directives = |value: &str| [("tempo".to_string(), value.to_string())].into_iter().collect();
+        assert_eq!(tempo_from_directives(&directives("90")), 90);
+        assert_eq!(tempo_from_directives(&directives("♩=72 bpm")), 72);
+        assert_eq!(tempo_from_directives(&directives("fast")), DEFAULT_TEMPO_BPM);
+        // A dotted quarter at 60 is a quarter at 90
+        assert_eq!(tempo_from_directives(&directives("3/8=60")), 90);
+        assert_eq!(tempo_from_directives(&directives("1/4=96")), 96);
+        assert_eq!(Tempo::from_directives(&directives("8 = 132")), Some(Tempo { numerator: 1, denominator: 8, bpm: 132 }));
+        assert_eq!(tempo_from_directives(&Default::default()), DEFAULT_TEMPO_BPM);
+    }
+
+    #[test]
+    fn test_pitch_to_midi() {
+        assert_eq!(pitch_to_midi(PitchCode::N1, 0), 60);
+        assert_eq!(pitch_to_midi(PitchCode::N5, 0), 67);
+        assert_eq!(pitch_to_midi(PitchCode::N7b, -1), 58);
+    }
+
+    #[test]
+    fn test_events_timing_and_positions() {
+        let events = events_with_positions(&document_from_notation("|1-23 -4|\n").unwrap(), 120);
+        let timing: Vec<(u8, f64, f64)> = events.iter()
+            .map(|e| (e.midi_note, e.start_beats, e.duration_beats))
+            .collect();
+        // "1-" holds C for half a beat; E is extended by the tied dash of the second beat
+        assert_eq!(timing, vec![(60, 0.0, 0.5), (62, 0.5, 0.25), (64, 0.75, 0.75), (65, 1.5, 0.5)]);
+        assert_eq!(events[1].start_seconds, 0.25);
+        assert_eq!(events[3].source.unwrap().column, 8);
+
+        // Beats of a dotted quarter, and of an eighth
+        let timing = |notation: &str| -> Vec<(f64, f64)> {
+            events_with_positions(&document_from_notation(notation).unwrap(), 120).iter()
+                .map(|e| (e.start_beats, e.duration_beats))
+                .collect()
+        };
+        assert_eq!(timing("Time: 6/8\n\n|1 23|\n"), vec![(0.0, 1.5), (1.5, 0.75), (2.25, 0.75)]);
+        assert_eq!(timing("BeatUnit: 1/8\n\n|1 2 -3|\n"), vec![(0.0, 0.5), (0.5, 0.75), (1.25, 0.25)]);
+    }
+
+    #[test]
+    fn test_multi_measure_rest_takes_its_measures() {
+        let starts = |notation: &str| -> Vec<f64> {
+            events_with_positions(&document_from_notation(notation).unwrap(), 120).iter()
+                .map(|e| e.start_beats)
+                .collect()
+        };
+        assert_eq!(starts("| 1 2 3 4 | R*2 | 5 |\n"), vec![0.0, 1.0, 2.0, 3.0, 12.0]);
+        assert_eq!(starts("Time: 3/4\n\n| R*2 | 1 2 3 |\n"), vec![6.0, 7.0, 8.0]);
+        // An inline meter change sets the length of the rests after it
+        assert_eq!(starts("| 1 |(3/4) R*2 | 5 |\n"), vec![0.0, 7.0]);
+    }
+
+    #[test]
+    fn test_explicit_tie_sounds_once() {
+        let events = events_with_positions(&document_from_notation("|1~|1 2~ 3|\n").unwrap(), 120);
+        let timing: Vec<(u8, f64, f64)> = events.iter()
+            .map(|e| (e.midi_note, e.start_beats, e.duration_beats))
+            .collect();
+        // A tie between different pitches doesn't join them
+        assert_eq!(timing, vec![(60, 0.0, 2.0), (62, 2.0, 1.0), (64, 3.0, 1.0)]);
+    }
+
+    #[test]
+    fn test_expression_markers_from_notation() {
+        let document = document_from_notation("  ~~~ ^-1\n1 2 3 4\n").unwrap();
+        let marks: Vec<(bool, Option<i8>)> = events_with_positions(&document, 120).iter()
+            .map(|e| (e.vibrato, e.bend))
+            .collect();
+        assert_eq!(marks, vec![(false, None), (true, None), (true, None), (false, Some(-1))]);
+    }
+
+    #[test]
+    fn test_crescendo_ramps_velocity() {
+        let document = document_from_notation("1 2 3 4 5\np <     f\n").unwrap();
+        let velocities: Vec<u8> = events_with_positions(&document, 120).iter().map(|e| e.velocity).collect();
+        assert_eq!(velocities.first(), Some(&49));
+        assert_eq!(velocities.last(), Some(&96));
+        // Rising through the hairpin rather than jumping at its end
+        assert!(velocities[1..].windows(2).all(|w| w[0] < w[1]), "{:?}", velocities);
+    }
+}
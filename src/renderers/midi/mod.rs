@@ -0,0 +1,27 @@
+pub mod dynamics;
+pub mod events;
+pub mod smf;
+pub mod swing;
+pub mod tuning;
+
+pub use dynamics::*;
+pub use events::*;
+pub use smf::*;
+pub use swing::*;
+pub use tuning::*;
+
+use crate::parse::model::Document;
+
+/// Render an analyzed document to Standard MIDI File bytes, tuned to its `TuningReference`
+/// and swung when it asks for a swing feel
+pub fn render_midi(document: &Document, tempo_bpm: u32) -> Vec<u8> {
+    let tuning = Tuning::from_directives(&document.directives);
+    write_smf_tuned(&playback_events(document, tempo_bpm), tempo_bpm, tuning)
+}
+
+/// Timed events as they are played: [`events_with_positions`] with the document's swing applied
+pub fn playback_events(document: &Document, tempo_bpm: u32) -> Vec<TimedEvent> {
+    let mut events = events_with_positions(document, tempo_bpm);
+    Swing::from_directives(&document.directives).apply(&mut events, tempo_bpm);
+    events
+}
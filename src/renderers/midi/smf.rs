@@ -0,0 +1,177 @@
+/// Standard MIDI File writer (format 0, single track)
+use super::events::TimedEvent;
+use super::tuning::Tuning;
+
+/// Ticks per quarter note
+pub const TICKS_PER_QUARTER: u32 = 480;
+
+/// Modulation depth sent for vibrato
+const VIBRATO_DEPTH: u8 = 64;
+/// Pitch bend range of a default General MIDI patch, in semitones
+const BEND_RANGE_SEMITONES: i32 = 2;
+const BEND_CENTER: i32 = 8192;
+
+/// Encode timed events as a format 0 Standard MIDI File on channel 1.
+/// Vibrato notes are wrapped in modulation (CC1) on/off messages, and bent notes and
+/// quarter tones in pitch bend / bend reset messages.
+pub fn write_smf(events: &[TimedEvent], tempo_bpm: u32) -> Vec<u8> {
+    write_smf_tuned(events, tempo_bpm, Tuning::default())
+}
+
+/// Like `write_smf`, retuning the whole track with a global pitch bend when the
+/// tuning reference differs from A=440 (note bends are applied on top of it)
+pub fn write_smf_tuned(events: &[TimedEvent], tempo_bpm: u32, tuning: Tuning) -> Vec<u8> {
+    let center = BEND_CENTER + (tuning.cents() * BEND_CENTER as f64 / (BEND_RANGE_SEMITONES * 100) as f64).round() as i32;
+
+    // (tick, order, message) - at the same tick: note-offs, then resets, then controllers, then note-ons
+    let mut messages: Vec<(u32, u8, [u8; 3])> = Vec::new();
+    if center != BEND_CENTER {
+        messages.push((0, 2, pitch_bend(center)));
+    }
+    for event in events {
+        let start = beats_to_ticks(event.start_beats);
+        let end = beats_to_ticks(event.start_beats + event.duration_beats).max(start + 1);
+        messages.push((start, 3, [0x90, event.midi_note, event.velocity]));
+        messages.push((end, 0, [0x80, event.midi_note, 0]));
+        if event.vibrato {
+            messages.push((start, 2, [0xB0, 1, VIBRATO_DEPTH]));
+            messages.push((end, 1, [0xB0, 1, 0]));
+        }
+        // Bends and quarter tones add up, in quarter steps
+        let quarter_steps = event.bend.unwrap_or(0) as i32 * 2 + event.microtone.unwrap_or(0) as i32;
+        if quarter_steps != 0 {
+            messages.push((start, 2, pitch_bend(center + quarter_steps * BEND_CENTER / (BEND_RANGE_SEMITONES * 2))));
+            messages.push((end, 1, pitch_bend(center)));
+        }
+    }
+    messages.sort_by_key(|(tick, order, _)| (*tick, *order));
+
+    let mut track = Vec::new();
+
+    // Tempo meta event (microseconds per quarter note)
+    let micros_per_quarter = 60_000_000 / tempo_bpm.max(1);
+    write_var_len(&mut track, 0);
+    track.extend_from_slice(&[0xFF, 0x51, 0x03]);
+    track.extend_from_slice(&micros_per_quarter.to_be_bytes()[1..]);
+
+    let mut last_tick = 0;
+    for (tick, _, message) in messages {
+        write_var_len(&mut track, tick - last_tick);
+        last_tick = tick;
+        track.extend_from_slice(&message);
+    }
+
+    // End of track
+    write_var_len(&mut track, 0);
+    track.extend_from_slice(&[0xFF, 0x2F, 0x00]);
+
+    let mut smf = Vec::with_capacity(22 + track.len());
+    smf.extend_from_slice(b"MThd");
+    smf.extend_from_slice(&6u32.to_be_bytes());
+    smf.extend_from_slice(&0u16.to_be_bytes()); // format 0
+    smf.extend_from_slice(&1u16.to_be_bytes()); // one track
+    smf.extend_from_slice(&(TICKS_PER_QUARTER as u16).to_be_bytes());
+    smf.extend_from_slice(b"MTrk");
+    smf.extend_from_slice(&(track.len() as u32).to_be_bytes());
+    smf.extend_from_slice(&track);
+    smf
+}
+
+/// Pitch bend message for a 14-bit bend value (8192 = no bend)
+fn pitch_bend(value: i32) -> [u8; 3] {
+    let value = value.clamp(0, 16383) as u16;
+    [0xE0, (value & 0x7F) as u8, (value >> 7) as u8]
+}
+
+fn beats_to_ticks(beats: f64) -> u32 {
+    (beats * TICKS_PER_QUARTER as f64).round() as u32
+}
+
+/// Write a MIDI variable-length quantity
+fn write_var_len(out: &mut Vec<u8>, mut value: u32) {
+    let mut bytes = vec![(value & 0x7F) as u8];
+    value >>= 7;
+    while value > 0 {
+        bytes.push(((value & 0x7F) as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.reverse();
+    out.extend_from_slice(&bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderers::midi::dynamics::DEFAULT_VELOCITY as VELOCITY;
+
+    #[test]
+    fn test_var_len() {
+        let mut out = Vec::new();
+        write_var_len(&mut out, 0x3FFF);
+        assert_eq!(out, vec![0xFF, 0x7F]);
+    }
+
+    fn event(duration_beats: f64) -> TimedEvent {
+        TimedEvent {
+            id: String::new(),
+            stave: 0,
+            midi_note: 60,
+            start_beats: 0.0,
+            duration_beats,
+            start_seconds: 0.0,
+            duration_seconds: duration_beats / 2.0,
+            source: None,
+            vibrato: false,
+            bend: None,
+            microtone: None,
+            velocity: VELOCITY,
+        }
+    }
+
+    #[test]
+    fn test_write_smf_header_and_notes() {
+        let events = vec![event(1.0)];
+        let smf = write_smf(&events, 120);
+        assert_eq!(&smf[0..4], b"MThd");
+        assert_eq!(&smf[14..18], b"MTrk");
+        // Note on at tick 0, note off 480 ticks later (0x83 0x60)
+        let track = &smf[22..];
+        assert!(track.windows(4).any(|w| w == [0x00, 0x90, 60, VELOCITY]));
+        assert!(track.windows(5).any(|w| w == [0x83, 0x60, 0x80, 60, 0]));
+    }
+
+    #[test]
+    fn test_vibrato_modulation_spans_note() {
+        let smf = write_smf(&[TimedEvent { vibrato: true, ..event(1.0) }], 120);
+        let track = &smf[22..];
+        // CC1 on right before the note starts, off 480 ticks later right after the note ends
+        assert!(track.windows(8).any(|w| w == [0x00, 0xB0, 1, VIBRATO_DEPTH, 0x00, 0x90, 60, VELOCITY]));
+        assert!(track.windows(8).any(|w| w == [0x83, 0x60, 0x80, 60, 0, 0x00, 0xB0, 1]));
+        assert!(track.windows(3).any(|w| w == [0xB0, 1, 0]));
+    }
+
+    #[test]
+    fn test_pitch_bend_and_reset() {
+        let smf = write_smf(&[TimedEvent { bend: Some(1), ..event(1.0) }], 120);
+        let track = &smf[22..];
+        assert!(track.windows(3).any(|w| w == pitch_bend(8192 + 4096)));
+        assert!(track.windows(3).any(|w| w == [0xE0, 0x00, 0x40]));
+    }
+
+    #[test]
+    fn test_quarter_tone_bends_half_a_semitone() {
+        let smf = write_smf(&[TimedEvent { microtone: Some(1), ..event(1.0) }], 120);
+        assert!(smf[22..].windows(3).any(|w| w == pitch_bend(8192 + 2048)));
+        let smf = write_smf(&[TimedEvent { bend: Some(1), microtone: Some(-1), ..event(1.0) }], 120);
+        assert!(smf[22..].windows(3).any(|w| w == pitch_bend(8192 + 2048)));
+    }
+
+    #[test]
+    fn test_tuning_reference_bends_whole_track() {
+        let smf = write_smf_tuned(&[event(1.0)], 120, Tuning { concert_a: 415.0 });
+        let track = &smf[22..];
+        // -101 cents of a +-200 cent range, sent at tick 0 before the first note
+        assert!(track.windows(8).any(|w| w == [0x00, 0xE0, 0x4C, 0x1F, 0x00, 0x90, 60, VELOCITY]));
+        assert_eq!(write_smf_tuned(&[event(1.0)], 120, Tuning::default()), write_smf(&[event(1.0)], 120));
+    }
+}
@@ -0,0 +1,105 @@
+// Swing feel for audio and MIDI export
+// `Swing: on` or `Feel: swing` plays each pair of eighths long-short (about 2:1);
+// the notation and rhythm analysis stay straight
+
+use crate::parse::model::directive;
+use super::events::TimedEvent;
+use std::collections::HashMap;
+
+/// Directive key switching swing on (`Swing: on`), matched case-insensitively
+pub const SWING_DIRECTIVE: &str = "Swing";
+
+/// Directive key naming the feel (`Feel: swing`, `Feel: shuffle` or `Feel: straight`)
+pub const FEEL_DIRECTIVE: &str = "Feel";
+
+/// Share of the beat taken by the first eighth of a swung pair (triplet swing, 2:1)
+pub const SWING_RATIO: f64 = 2.0 / 3.0;
+
+/// Whether eighth pairs are swung in playback. Defaults to straight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Swing(pub bool);
+
+impl Swing {
+    /// Read the feel from document directives; `Swing` wins over `Feel` when both are given
+    pub fn from_directives(directives: &HashMap<String, String>) -> Self {
+        if let Some(value) = directive(directives, SWING_DIRECTIVE) {
+            return Swing(matches!(value.to_lowercase().as_str(), "true" | "yes" | "on" | "1"));
+        }
+        let swung = directive(directives, FEEL_DIRECTIVE)
+            .is_some_and(|value| matches!(value.to_lowercase().as_str(), "swing" | "swung" | "shuffle"));
+        Swing(swung)
+    }
+
+    /// Move event onsets and ends so the first half of every quarter-note beat lasts
+    /// `SWING_RATIO` of it and the second half the rest. Times on the beat stay put,
+    /// so quarter notes and longer are unchanged.
+    pub fn apply(self, events: &mut [TimedEvent], tempo_bpm: u32) {
+        if !self.0 {
+            return;
+        }
+        let seconds_per_beat = 60.0 / tempo_bpm.max(1) as f64;
+        for event in events {
+            let start = swung_time(event.start_beats);
+            let end = swung_time(event.start_beats + event.duration_beats);
+            event.start_beats = start;
+            event.duration_beats = end - start;
+            event.start_seconds = start * seconds_per_beat;
+            event.duration_seconds = event.duration_beats * seconds_per_beat;
+        }
+    }
+}
+
+/// Position in beats of the straight time `beats` once the beat it falls in is swung
+fn swung_time(beats: f64) -> f64 {
+    let beat = beats.floor();
+    let offset = beats - beat;
+    let swung = if offset <= 0.5 {
+        offset * 2.0 * SWING_RATIO
+    } else {
+        SWING_RATIO + (offset - 0.5) * 2.0 * (1.0 - SWING_RATIO)
+    };
+    beat + swung
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::document_from_notation;
+    use crate::renderers::midi::events_with_positions;
+
+    fn onsets(input: &str) -> Vec<f64> {
+        let document = document_from_notation(input).unwrap();
+        let mut events = events_with_positions(&document, 120);
+        Swing::from_directives(&document.directives).apply(&mut events, 120);
+        events.iter().map(|e| (e.start_beats * 1000.0).round() / 1000.0).collect()
+    }
+
+    #[test]
+    fn test_swing_directives() {
+        let directives = |key: &str, value: &str| [(key.to_string(), value.to_string())].into_iter().collect();
+        assert_eq!(Swing::from_directives(&directives("swing", "on")), Swing(true));
+        assert_eq!(Swing::from_directives(&directives("Feel", "Shuffle")), Swing(true));
+        assert_eq!(Swing::from_directives(&directives("Feel", "straight")), Swing(false));
+        assert_eq!(Swing::from_directives(&Default::default()), Swing(false));
+    }
+
+    #[test]
+    fn test_swing_moves_offbeat_eighths() {
+        // Quarter notes fall on the beat either way
+        assert_eq!(onsets("1 2 3 4\n"), onsets("Swing: on\n\n1 2 3 4\n"));
+        assert_eq!(onsets("Swing: on\n\n1 2 3 4\n"), vec![0.0, 1.0, 2.0, 3.0]);
+
+        assert_eq!(onsets("12 34\n"), vec![0.0, 0.5, 1.0, 1.5]);
+        assert_eq!(onsets("Feel: swing\n\n12 34\n"), vec![0.0, 0.667, 1.0, 1.667]);
+    }
+
+    #[test]
+    fn test_swing_keeps_notes_back_to_back() {
+        let document = document_from_notation("Swing: on\n\n12 3\n").unwrap();
+        let mut events = events_with_positions(&document, 60);
+        Swing(true).apply(&mut events, 60);
+        assert!((events[0].duration_seconds - SWING_RATIO).abs() < 1e-9);
+        assert!((events[0].start_beats + events[0].duration_beats - events[1].start_beats).abs() < 1e-9);
+        assert_eq!(events[2].duration_beats, 1.0);
+    }
+}
@@ -0,0 +1,81 @@
+// Concert pitch reference for audio and MIDI export
+// `TuningReference: 415` (or `Tuning: A4=432`) tunes A4 to 415 Hz instead of the standard 440 Hz
+
+use crate::parse::model::directive;
+use std::collections::HashMap;
+
+/// Directive key setting the frequency of A4 in Hz, matched case-insensitively
+pub const TUNING_REFERENCE_DIRECTIVE: &str = "TuningReference";
+/// Shorter directive key for the same reference, used when `TuningReference` is absent
+pub const TUNING_DIRECTIVE: &str = "Tuning";
+
+/// Standard concert pitch
+pub const DEFAULT_CONCERT_A: f64 = 440.0;
+
+/// MIDI note number of A4
+const A4: i32 = 69;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tuning {
+    /// Frequency of A4 in Hz
+    pub concert_a: f64,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Tuning { concert_a: DEFAULT_CONCERT_A }
+    }
+}
+
+impl Tuning {
+    /// Read the tuning reference from document directives: "415", "432 Hz" or "A4=432".
+    /// Missing, unparsable or implausible values (outside 300-500 Hz) keep 440 Hz.
+    pub fn from_directives(directives: &HashMap<String, String>) -> Self {
+        directive(directives, TUNING_REFERENCE_DIRECTIVE)
+            .or_else(|| directive(directives, TUNING_DIRECTIVE))
+            .map(|value| value.rsplit('=').next().unwrap_or(value))
+            .and_then(|value| value.trim().trim_end_matches("Hz").trim_end_matches("hz").trim().parse::<f64>().ok())
+            .filter(|hz| (300.0..=500.0).contains(hz))
+            .map(|concert_a| Tuning { concert_a })
+            .unwrap_or_default()
+    }
+
+    /// Equal-tempered frequency of a MIDI note
+    pub fn frequency(&self, midi_note: u8) -> f64 {
+        self.concert_a * 2f64.powf((midi_note as i32 - A4) as f64 / 12.0)
+    }
+
+    /// Offset from standard pitch in cents (about -101 for A=415)
+    pub fn cents(&self) -> f64 {
+        1200.0 * (self.concert_a / DEFAULT_CONCERT_A).log2()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tuning_from_directives() {
+        let mut directives = HashMap::new();
+        assert_eq!(Tuning::from_directives(&directives), Tuning::default());
+        directives.insert("TuningReference".to_string(), "415".to_string());
+        assert_eq!(Tuning::from_directives(&directives).concert_a, 415.0);
+        directives.insert("TuningReference".to_string(), "4150".to_string());
+        assert_eq!(Tuning::from_directives(&directives), Tuning::default());
+
+        let tuning = |value: &str| Tuning::from_directives(&[("tuning".to_string(), value.to_string())].into_iter().collect());
+        assert_eq!(tuning("432").concert_a, 432.0);
+        assert_eq!(tuning("A4 = 432 Hz").concert_a, 432.0);
+        assert_eq!(tuning("A=415hz").concert_a, 415.0);
+        assert_eq!(tuning("equal"), Tuning::default());
+    }
+
+    #[test]
+    fn test_frequency_and_cents() {
+        let baroque = Tuning { concert_a: 415.0 };
+        assert_eq!(baroque.frequency(69), 415.0);
+        assert!((Tuning::default().frequency(60) - 261.626).abs() < 0.001);
+        assert!((baroque.cents() + 101.27).abs() < 0.01);
+    }
+}
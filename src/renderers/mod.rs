@@ -2,4 +2,18 @@ pub mod vexflow;
 pub mod lilypond;
 pub mod converters_lilypond;
 pub mod transposition;
+pub mod barline_glyphs;
 pub mod editor;
+pub mod midi;
+pub mod html;
+pub mod note_names;
+pub mod noteheads;
+pub mod flattened_view;
+pub mod audio;
+pub mod ottava;
+pub mod incipit;
+pub mod abc;
+pub mod musicxml;
+pub mod svg;
+pub mod png;
+pub mod jianpu;
@@ -0,0 +1,391 @@
+/// MusicXML export
+/// A score-partwise document with one part per stave, for opening scores in MuseScore,
+/// Finale or Sibelius. Measures follow the barlines; pitches come out as in LilyPond
+/// output (degree 1 is C, octave 0 the octave from middle C).
+use crate::analysis::TimeSignature;
+use crate::models::BarlineType;
+use crate::parse::line_classifier::TITLE_DIRECTIVE;
+use crate::parse::model::{directive, Beat, BeatElement, ContentElement, Document, DocumentElement, Note, Stave, StaveLine, KEY_DIRECTIVE};
+use crate::document::ties::with_matching_ties;
+use crate::renderers::incipit::key_signature_fifths;
+use crate::renderers::transposition::pitchcode_to_scale_position;
+use fraction::Fraction;
+use std::fmt::Write;
+
+const STEPS: [char; 7] = ['C', 'D', 'E', 'F', 'G', 'A', 'B'];
+
+/// Note types with their length as a fraction of a whole note
+const NOTE_TYPES: [(&str, u64); 8] = [
+    ("whole", 1), ("half", 2), ("quarter", 4), ("eighth", 8),
+    ("16th", 16), ("32nd", 32), ("64th", 64), ("128th", 128),
+];
+
+/// Render a rhythm-analyzed document as MusicXML
+pub fn render_musicxml(document: &Document) -> String {
+    let parts: Vec<Vec<Measure>> = document.elements.iter()
+        .filter_map(|element| match element {
+            DocumentElement::Stave(stave) => Some(stave_measures(stave)),
+            _ => None,
+        })
+        .collect();
+    let divisions = divisions_per_quarter(&parts);
+    let fifths = directive(&document.directives, KEY_DIRECTIVE).and_then(key_signature_fifths).unwrap_or(0);
+    let time_signature = TimeSignature::from_directives(&document.directives);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+    xml.push_str("<!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 4.0 Partwise//EN\" \"http://www.musicxml.org/dtds/partwise.dtd\">\n");
+    xml.push_str("<score-partwise version=\"4.0\">\n");
+    if let Some(title) = document.title.as_deref().or_else(|| directive(&document.directives, TITLE_DIRECTIVE)) {
+        writeln!(xml, "  <work>\n    <work-title>{}</work-title>\n  </work>", escape(title)).unwrap();
+    }
+    if let Some(composer) = document.composer() {
+        writeln!(xml, "  <identification>\n    <creator type=\"composer\">{}</creator>\n  </identification>", escape(composer)).unwrap();
+    }
+
+    xml.push_str("  <part-list>\n");
+    for number in 1..=parts.len() {
+        writeln!(xml, "    <score-part id=\"P{0}\">\n      <part-name>Part {0}</part-name>\n    </score-part>", number).unwrap();
+    }
+    xml.push_str("  </part-list>\n");
+
+    for (index, measures) in parts.iter().enumerate() {
+        writeln!(xml, "  <part id=\"P{}\">", index + 1).unwrap();
+        for (number, measure) in measures.iter().enumerate() {
+            writeln!(xml, "    <measure number=\"{}\">", number + 1).unwrap();
+            if number == 0 {
+                xml.push_str("      <attributes>\n");
+                writeln!(xml, "        <divisions>{}</divisions>", divisions).unwrap();
+                writeln!(xml, "        <key>\n          <fifths>{}</fifths>\n        </key>", fifths).unwrap();
+                writeln!(xml, "        <time>\n          <beats>{}</beats>\n          <beat-type>{}</beat-type>\n        </time>",
+                    time_signature.beats, time_signature.unit).unwrap();
+                xml.push_str("        <clef>\n          <sign>G</sign>\n          <line>2</line>\n        </clef>\n");
+                xml.push_str("      </attributes>\n");
+            }
+            if measure.repeat_start {
+                xml.push_str("      <barline location=\"left\">\n        <bar-style>heavy-light</bar-style>\n        <repeat direction=\"forward\"/>\n      </barline>\n");
+            }
+            for event in &measure.events {
+                write_event(&mut xml, event, divisions);
+            }
+            if let Some(barline) = measure.barline {
+                write_right_barline(&mut xml, barline);
+            }
+            xml.push_str("    </measure>\n");
+        }
+        xml.push_str("  </part>\n");
+    }
+    xml.push_str("</score-partwise>\n");
+    xml
+}
+
+/// A measure of notes and rests, with the barlines around it
+#[derive(Default)]
+struct Measure {
+    events: Vec<Event>,
+    /// Opens with a forward repeat
+    repeat_start: bool,
+    /// Barline closing the measure
+    barline: Option<BarlineType>,
+}
+
+/// A note or rest; `pitch` is `None` for a rest
+struct Event {
+    pitch: Option<Pitch>,
+    /// Sounding length as a fraction of a whole note
+    duration: Fraction,
+    /// Tuplet ratio (actual, normal) the event is played under
+    tuplet: Option<(usize, usize)>,
+    tie_start: bool,
+    tie_stop: bool,
+    /// Syllable of each verse, as (verse number, syllabic, text)
+    lyrics: Vec<(usize, &'static str, String)>,
+}
+
+#[derive(Clone, Copy)]
+struct Pitch {
+    step: char,
+    /// Alteration in quarter tones, so a quarter-tone sharp is 1 and a sharp 2
+    alter_quarters: i8,
+    octave: i8,
+}
+
+/// Collects the events of one stave into measures
+#[derive(Default)]
+struct StaveWriter {
+    measures: Vec<Measure>,
+    current: Measure,
+    /// A tie marker on the last note waits for the next note
+    pending_tie: bool,
+    /// Whether each verse's last syllable ended in a hyphen
+    open_words: Vec<bool>,
+}
+
+fn stave_measures(stave: &Stave) -> Vec<Measure> {
+    let mut writer = StaveWriter::default();
+    for line in &stave.lines {
+        let StaveLine::ContentLine(content_line) = line else { continue };
+        let content_line = with_matching_ties(content_line);
+        for element in &content_line.elements {
+            match element {
+                ContentElement::Beat(beat) => writer.beat(beat),
+                ContentElement::Barline(barline) => writer.barline(barline.barline_type()),
+                ContentElement::Whitespace(_) | ContentElement::UnknownToken(_) => {}
+            }
+        }
+    }
+    if !writer.current.events.is_empty() || writer.measures.is_empty() {
+        writer.measures.push(writer.current);
+    }
+    writer.measures
+}
+
+impl StaveWriter {
+    /// A barline after notes closes the measure; one before any notes only marks a repeat start
+    fn barline(&mut self, barline: BarlineType) {
+        let opens_repeat = matches!(barline, BarlineType::RepeatStart | BarlineType::RepeatBoth);
+        if self.current.events.is_empty() {
+            self.current.repeat_start |= opens_repeat;
+            return;
+        }
+        self.current.barline = Some(barline);
+        self.measures.push(std::mem::take(&mut self.current));
+        self.current.repeat_start = opens_repeat;
+    }
+
+    fn last_pitched_mut(&mut self) -> Option<&mut Event> {
+        self.current.events.iter_mut().rev()
+            .chain(self.measures.iter_mut().rev().flat_map(|m| m.events.iter_mut().rev()))
+            .find(|event| event.pitch.is_some())
+    }
+
+    fn beat(&mut self, beat: &Beat) {
+        let tuplet = match (beat.is_tuplet, beat.tuplet_ratio) {
+            (Some(true), Some(ratio)) => Some(ratio),
+            _ => None,
+        };
+
+        // Leading dashes of a tied beat continue the previous note
+        let leading_dashes = beat.elements.iter().take_while(|e| matches!(e, BeatElement::Dash(_))).count();
+        if let (Some(true), Some(divisions), true) = (beat.tied_to_previous, beat.divisions, leading_dashes > 0) {
+            if let Some(previous) = self.last_pitched_mut() {
+                previous.tie_start = true;
+                let pitch = previous.pitch;
+                let beat_duration = beat.total_duration.unwrap_or(Fraction::new(1u64, 4u64));
+                let duration = Fraction::new(leading_dashes as u64, divisions as u64) * beat_duration;
+                self.current.events.push(Event { pitch, duration, tuplet, tie_start: false, tie_stop: true, lyrics: Vec::new() });
+            }
+        }
+
+        for element in &beat.elements {
+            match element {
+                BeatElement::Note(note) => self.note(note, tuplet),
+                BeatElement::Dash(dash) => {
+                    if let (Some(numer), Some(denom)) = (dash.numerator, dash.denominator) {
+                        self.rest(Fraction::new(numer, denom), tuplet);
+                    }
+                }
+                BeatElement::Rest(rest) => {
+                    self.rest(Fraction::new(rest.numerator.unwrap_or(1), rest.denominator.unwrap_or(4)), tuplet);
+                }
+                BeatElement::BreathMark(_) => {}
+            }
+        }
+    }
+
+    fn rest(&mut self, duration: Fraction, tuplet: Option<(usize, usize)>) {
+        self.current.events.push(Event { pitch: None, duration, tuplet, tie_start: false, tie_stop: false, lyrics: Vec::new() });
+    }
+
+    fn note(&mut self, note: &Note, tuplet: Option<(usize, usize)>) {
+        let (degree, alteration) = pitchcode_to_scale_position(note.pitch_code);
+        let pitch = Pitch {
+            step: STEPS[degree],
+            alter_quarters: alteration * 2 + note.microtone.unwrap_or(0),
+            octave: note.octave + 4,
+        };
+        let tie_stop = std::mem::replace(&mut self.pending_tie, note.tie.is_some());
+        let lyrics = self.syllables(&note.lyrics);
+        self.current.events.push(Event {
+            pitch: Some(pitch),
+            duration: Fraction::new(note.numerator.unwrap_or(1), note.denominator.unwrap_or(4)),
+            tuplet,
+            tie_start: note.tie.is_some(),
+            tie_stop,
+            lyrics,
+        });
+    }
+
+    /// Verse number, syllabic and text of each syllable on a note. A trailing hyphen
+    /// joins a syllable to the next one in its verse.
+    fn syllables(&mut self, lyrics: &[String]) -> Vec<(usize, &'static str, String)> {
+        if self.open_words.len() < lyrics.len() {
+            self.open_words.resize(lyrics.len(), false);
+        }
+        lyrics.iter().enumerate()
+            .filter(|(_, syllable)| !syllable.is_empty())
+            .map(|(verse, syllable)| {
+                let text = syllable.strip_suffix('-');
+                let continues = text.is_some();
+                let syllabic = match (self.open_words[verse], continues) {
+                    (false, false) => "single",
+                    (false, true) => "begin",
+                    (true, true) => "middle",
+                    (true, false) => "end",
+                };
+                self.open_words[verse] = continues;
+                (verse + 1, syllabic, text.unwrap_or(syllable).to_string())
+            })
+            .collect()
+    }
+}
+
+/// Smallest number of divisions per quarter note that gives every event a whole number of divisions
+fn divisions_per_quarter(parts: &[Vec<Measure>]) -> u64 {
+    parts.iter()
+        .flatten()
+        .flat_map(|measure| &measure.events)
+        .map(|event| (event.duration * Fraction::from(4u64)).denom().copied().unwrap_or(1))
+        .fold(1, |divisions, denom| divisions / gcd(divisions, denom) * denom)
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Note type and number of dots for a written length, if it has one
+fn note_type(written: Fraction) -> Option<(&'static str, usize)> {
+    NOTE_TYPES.iter().find_map(|&(name, denom)| {
+        let base = Fraction::new(1u64, denom);
+        (0..=2).find(|&dots| base * (Fraction::from(2u64) - Fraction::new(1u64, 1u64 << dots)) == written)
+            .map(|dots| (name, dots))
+    })
+}
+
+fn write_event(xml: &mut String, event: &Event, divisions: u64) {
+    let quarters = event.duration * Fraction::from(4 * divisions);
+    let duration = quarters.numer().copied().unwrap_or(0) / quarters.denom().copied().unwrap_or(1);
+    // Tuplet notes are written at their nominal value, e.g. an eighth for a third of a quarter
+    let written = match event.tuplet {
+        Some((actual, normal)) => event.duration * Fraction::new(actual as u64, normal as u64),
+        None => event.duration,
+    };
+
+    xml.push_str("      <note>\n");
+    match event.pitch {
+        Some(pitch) => {
+            xml.push_str("        <pitch>\n");
+            writeln!(xml, "          <step>{}</step>", pitch.step).unwrap();
+            if pitch.alter_quarters != 0 {
+                writeln!(xml, "          <alter>{}</alter>", f32::from(pitch.alter_quarters) / 2.0).unwrap();
+            }
+            writeln!(xml, "          <octave>{}</octave>", pitch.octave).unwrap();
+            xml.push_str("        </pitch>\n");
+        }
+        None => xml.push_str("        <rest/>\n"),
+    }
+    writeln!(xml, "        <duration>{}</duration>", duration).unwrap();
+    if event.tie_stop {
+        xml.push_str("        <tie type=\"stop\"/>\n");
+    }
+    if event.tie_start {
+        xml.push_str("        <tie type=\"start\"/>\n");
+    }
+    if let Some((name, dots)) = note_type(written) {
+        writeln!(xml, "        <type>{}</type>", name).unwrap();
+        for _ in 0..dots {
+            xml.push_str("        <dot/>\n");
+        }
+    }
+    if let Some((actual, normal)) = event.tuplet {
+        writeln!(xml, "        <time-modification>\n          <actual-notes>{}</actual-notes>\n          <normal-notes>{}</normal-notes>\n        </time-modification>",
+            actual, normal).unwrap();
+    }
+    if event.tie_start || event.tie_stop {
+        xml.push_str("        <notations>\n");
+        if event.tie_stop {
+            xml.push_str("          <tied type=\"stop\"/>\n");
+        }
+        if event.tie_start {
+            xml.push_str("          <tied type=\"start\"/>\n");
+        }
+        xml.push_str("        </notations>\n");
+    }
+    for (verse, syllabic, text) in &event.lyrics {
+        writeln!(xml, "        <lyric number=\"{}\">\n          <syllabic>{}</syllabic>\n          <text>{}</text>\n        </lyric>",
+            verse, syllabic, escape(text)).unwrap();
+    }
+    xml.push_str("      </note>\n");
+}
+
+fn write_right_barline(xml: &mut String, barline: BarlineType) {
+    let (style, repeat) = match barline {
+        BarlineType::Single => return,
+        BarlineType::Double => ("light-light", false),
+        BarlineType::Final => ("light-heavy", false),
+        BarlineType::RepeatStart => return,
+        BarlineType::RepeatEnd | BarlineType::RepeatBoth => ("light-heavy", true),
+    };
+    xml.push_str("      <barline location=\"right\">\n");
+    writeln!(xml, "        <bar-style>{}</bar-style>", style).unwrap();
+    if repeat {
+        xml.push_str("        <repeat direction=\"backward\"/>\n");
+    }
+    xml.push_str("      </barline>\n");
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::document_from_notation;
+
+    fn musicxml_of(text: &str) -> String {
+        render_musicxml(&document_from_notation(text).unwrap())
+    }
+
+    #[test]
+    fn test_composer_credit() {
+        let xml = musicxml_of("Composer: Gilbert & Sullivan\n\n1 2 3\n");
+        assert!(xml.contains("<creator type=\"composer\">Gilbert &amp; Sullivan</creator>"), "{}", xml);
+        assert!(!musicxml_of("1 2 3\n").contains("<identification>"));
+    }
+
+    #[test]
+    fn test_notes_and_measures() {
+        let xml = musicxml_of("1 2 3 | 4");
+        assert_eq!(xml.matches("<note>").count(), 4, "{}", xml);
+        assert_eq!(xml.matches("<measure ").count(), 2, "{}", xml);
+        assert!(xml.contains("<divisions>1</divisions>"), "{}", xml);
+        assert!(xml.contains("<step>F</step>\n          <octave>4</octave>\n        </pitch>\n        <duration>1</duration>"), "{}", xml);
+    }
+
+    #[test]
+    fn test_rhythm_ties_and_rests() {
+        // Eighths need two divisions; the dash beat continues the previous note under a tie
+        let xml = musicxml_of("- 12 - | -3");
+        assert!(xml.contains("<divisions>2</divisions>"), "{}", xml);
+        assert_eq!(xml.matches("<tie type=\"start\"/>").count(), 2, "{}", xml);
+        assert_eq!(xml.matches("<tied type=\"stop\"/>").count(), 2, "{}", xml);
+        assert!(xml.contains("<rest/>"), "{}", xml);
+
+        let triplet = musicxml_of("123 1");
+        assert!(triplet.contains("<type>eighth</type>\n        <time-modification>\n          <actual-notes>3</actual-notes>"), "{}", triplet);
+    }
+
+    #[test]
+    fn test_alterations_and_lyrics() {
+        let xml = musicxml_of("1# 3b 1+\nhap-py day\n");
+        assert!(xml.contains("<step>C</step>\n          <alter>1</alter>"), "{}", xml);
+        assert!(xml.contains("<step>E</step>\n          <alter>-1</alter>"), "{}", xml);
+        assert!(xml.contains("<alter>0.5</alter>"), "{}", xml);
+        assert!(xml.contains("<syllabic>begin</syllabic>\n          <text>hap</text>"), "{}", xml);
+        assert!(xml.contains("<syllabic>end</syllabic>\n          <text>py</text>"), "{}", xml);
+        assert!(xml.contains("<syllabic>single</syllabic>\n          <text>day</text>"), "{}", xml);
+    }
+}
@@ -0,0 +1,100 @@
+/// Note name labels for educational rendering (names drawn in or under each notehead)
+use crate::models::{directive, PitchCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Directive key selecting note name labels (e.g. `NoteNames: letter`)
+pub const NOTE_NAMES_DIRECTIVE: &str = "NoteNames";
+
+const LETTERS: [&str; 7] = ["C", "D", "E", "F", "G", "A", "B"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NoteNames {
+    #[default]
+    Off,
+    /// Scale degrees 1-7 ("#4", "b7" for altered degrees)
+    ScaleDegree,
+    /// Letter names C-B ("F#", "Bb" for altered notes)
+    Letter,
+}
+
+impl NoteNames {
+    /// Read the option from document directives, falling back to `Off`
+    pub fn from_directives(directives: &HashMap<String, String>) -> Self {
+        let value = directive(directives, NOTE_NAMES_DIRECTIVE).map(str::to_lowercase);
+        match value.as_deref() {
+            // "ScaleDegree" as the variant is spelled, or as written in snake case
+            Some("scaledegree" | "scale_degree" | "degree") => NoteNames::ScaleDegree,
+            Some("letter") => NoteNames::Letter,
+            _ => NoteNames::Off,
+        }
+    }
+
+    /// Label for a pitch, or None when labels are off
+    pub fn label(&self, pitch_code: PitchCode) -> Option<String> {
+        let (degree, accidental) = degree_and_accidental(pitch_code);
+        match self {
+            NoteNames::Off => None,
+            NoteNames::ScaleDegree => Some(format!("{}{}", accidental, degree)),
+            NoteNames::Letter => Some(format!("{}{}", LETTERS[degree - 1], accidental)),
+        }
+    }
+
+    /// Labels for the seven natural degrees, in order from 1 (C) to 7 (B)
+    pub fn natural_labels(&self) -> Option<Vec<String>> {
+        match self {
+            NoteNames::Off => None,
+            NoteNames::ScaleDegree => Some((1..=7).map(|d| d.to_string()).collect()),
+            NoteNames::Letter => Some(LETTERS.iter().map(|l| l.to_string()).collect()),
+        }
+    }
+}
+
+/// Scale degree (1-7) and accidental text of a pitch code
+pub(crate) fn degree_and_accidental(pitch_code: PitchCode) -> (usize, &'static str) {
+    // Variant names follow the pattern N<degree><accidental>, e.g. N4s or N7bb
+    let name = format!("{:?}", pitch_code);
+    let degree = name[1..2].parse().unwrap_or(1);
+    let accidental = match &name[2..] {
+        "bb" => "bb",
+        "b" => "b",
+        "s" => "#",
+        "ss" => "##",
+        _ => "",
+    };
+    (degree, accidental)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const C_MAJOR: [PitchCode; 7] = [
+        PitchCode::N1, PitchCode::N2, PitchCode::N3, PitchCode::N4,
+        PitchCode::N5, PitchCode::N6, PitchCode::N7,
+    ];
+
+    #[test]
+    fn test_scale_degree_labels() {
+        let labels: Vec<String> = C_MAJOR.iter().filter_map(|p| NoteNames::ScaleDegree.label(*p)).collect();
+        assert_eq!(labels, vec!["1", "2", "3", "4", "5", "6", "7"]);
+        assert_eq!(NoteNames::ScaleDegree.label(PitchCode::N4s), Some("#4".to_string()));
+    }
+
+    #[test]
+    fn test_letter_labels() {
+        let labels: Vec<String> = C_MAJOR.iter().filter_map(|p| NoteNames::Letter.label(*p)).collect();
+        assert_eq!(labels, vec!["C", "D", "E", "F", "G", "A", "B"]);
+        assert_eq!(NoteNames::Letter.label(PitchCode::N7b), Some("Bb".to_string()));
+        assert_eq!(NoteNames::Off.label(PitchCode::N1), None);
+    }
+
+    #[test]
+    fn test_note_names_directive_from_notation() {
+        let note_names = |input: &str| NoteNames::from_directives(&crate::pipeline::document_from_notation(input).unwrap().directives);
+        assert_eq!(note_names("NoteNames: letter\n\n|1 2 3 4|\n"), NoteNames::Letter);
+        assert_eq!(note_names("notenames: degree\n\n|1 2 3 4|\n"), NoteNames::ScaleDegree);
+        assert_eq!(note_names("NoteNames: ScaleDegree\n\n|1 2 3 4|\n"), NoteNames::ScaleDegree);
+        assert_eq!(note_names("|1 2 3 4|\n"), NoteNames::Off);
+    }
+}
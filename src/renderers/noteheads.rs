@@ -0,0 +1,112 @@
+/// Shape-note noteheads: each scale degree gets its own notehead shape, as in
+/// Aiken's seven-shape system and the four-shape Sacred Harp (fasola) system
+use crate::models::{directive, PitchCode};
+use crate::renderers::note_names::degree_and_accidental;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Directive key selecting the notehead style (e.g. `NoteheadStyle: aiken`)
+pub const NOTEHEAD_STYLE_DIRECTIVE: &str = "NoteheadStyle";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NoteheadStyle {
+    #[default]
+    Normal,
+    /// Seven shapes, one per degree (do re mi fa sol la ti)
+    Aiken,
+    /// Four shapes sung fa sol la fa sol la mi
+    Sacred,
+}
+
+/// Notehead shape of a scale degree, named by outline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NoteheadShape {
+    /// do in the Aiken system
+    Triangle,
+    /// re: a half circle, flat side up
+    Moon,
+    /// mi
+    Diamond,
+    /// fa: a right triangle
+    RightTriangle,
+    /// sol
+    Round,
+    /// la
+    Square,
+    /// ti: a half circle above a point
+    Cone,
+}
+
+impl NoteheadStyle {
+    /// Read the option from document directives, falling back to `Normal`
+    pub fn from_directives(directives: &HashMap<String, String>) -> Self {
+        let value = directive(directives, NOTEHEAD_STYLE_DIRECTIVE).map(str::to_lowercase);
+        match value.as_deref() {
+            Some("aiken") => NoteheadStyle::Aiken,
+            Some("sacred" | "sacred_harp" | "sacredharp") => NoteheadStyle::Sacred,
+            _ => NoteheadStyle::Normal,
+        }
+    }
+
+    /// Shape for a pitch by its scale degree (accidentals keep the degree's shape),
+    /// or None for normal noteheads
+    pub fn shape(&self, pitch_code: PitchCode) -> Option<NoteheadShape> {
+        use NoteheadShape::*;
+        let (degree, _) = degree_and_accidental(pitch_code);
+        let shapes = match self {
+            NoteheadStyle::Normal => return None,
+            NoteheadStyle::Aiken => [Triangle, Moon, Diamond, RightTriangle, Round, Square, Cone],
+            NoteheadStyle::Sacred => [RightTriangle, Round, Square, RightTriangle, Round, Square, Diamond],
+        };
+        Some(shapes[degree - 1])
+    }
+
+    /// LilyPond command switching to the style's shape noteheads
+    pub fn lilypond_command(&self) -> Option<&'static str> {
+        match self {
+            NoteheadStyle::Normal => None,
+            NoteheadStyle::Aiken => Some("\\aikenHeads"),
+            NoteheadStyle::Sacred => Some("\\sacredHarpHeads"),
+        }
+    }
+}
+
+impl NoteheadShape {
+    /// Name used for the shape in rendered output (e.g. a `data-shape` attribute)
+    pub fn name(&self) -> &'static str {
+        match self {
+            NoteheadShape::Triangle => "triangle",
+            NoteheadShape::Moon => "moon",
+            NoteheadShape::Diamond => "diamond",
+            NoteheadShape::RightTriangle => "right-triangle",
+            NoteheadShape::Round => "round",
+            NoteheadShape::Square => "square",
+            NoteheadShape::Cone => "cone",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use NoteheadShape::*;
+
+    const C_MAJOR: [PitchCode; 7] = [
+        PitchCode::N1, PitchCode::N2, PitchCode::N3, PitchCode::N4,
+        PitchCode::N5, PitchCode::N6, PitchCode::N7,
+    ];
+
+    #[test]
+    fn test_aiken_shapes_for_c_major_scale() {
+        let shapes: Vec<NoteheadShape> = C_MAJOR.iter().filter_map(|p| NoteheadStyle::Aiken.shape(*p)).collect();
+        assert_eq!(shapes, vec![Triangle, Moon, Diamond, RightTriangle, Round, Square, Cone]);
+        assert_eq!(NoteheadStyle::Aiken.shape(PitchCode::N4s), Some(RightTriangle));
+
+        let sacred: Vec<NoteheadShape> = C_MAJOR.iter().filter_map(|p| NoteheadStyle::Sacred.shape(*p)).collect();
+        assert_eq!(sacred, vec![RightTriangle, Round, Square, RightTriangle, Round, Square, Diamond]);
+        assert_eq!(NoteheadStyle::Normal.shape(PitchCode::N1), None);
+
+        let directives = HashMap::from([("NoteheadStyle".to_string(), "Aiken".to_string())]);
+        assert_eq!(NoteheadStyle::from_directives(&directives), NoteheadStyle::Aiken);
+    }
+}
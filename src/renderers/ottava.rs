@@ -0,0 +1,176 @@
+/// Automatic octave-shift brackets (8va/8vb)
+/// Runs of notes far above or below the treble staff are written an octave
+/// nearer to it under a dashed "8va"/"8vb" bracket instead of on many ledger lines
+use crate::models::PitchCode;
+use crate::parse::model::{directive, Beat, BeatElement, ContentElement, ContentLine};
+use crate::renderers::transposition::pitchcode_to_scale_position;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Directive key enabling brackets (`Ottava: auto`, or a ledger-line threshold such as `Ottava: 4`)
+pub const OTTAVA_DIRECTIVE: &str = "Ottava";
+
+/// Ledger lines a note may need before it goes under a bracket with `Ottava: auto`
+pub const DEFAULT_LEDGER_LINES: i8 = 3;
+
+/// Staff steps of the top and bottom lines of the treble staff, counted from middle C
+const TOP_LINE: i32 = 10; // F5
+const BOTTOM_LINE: i32 = 2; // E4
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ottava {
+    /// Notes needing at least this many ledger lines are shifted
+    pub ledger_lines: i8,
+}
+
+/// Consecutive notes written an octave nearer the staff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OttavaSpan {
+    pub notes: Vec<Uuid>,
+    /// 1 for 8va (sounds an octave above the written notes), -1 for 8vb
+    pub shift: i8,
+}
+
+impl Ottava {
+    /// Read the option from document directives; brackets are off unless requested
+    pub fn from_directives(directives: &HashMap<String, String>) -> Option<Self> {
+        let value = directive(directives, OTTAVA_DIRECTIVE)?.to_lowercase();
+        match value.as_str() {
+            "auto" | "on" | "true" => Some(Ottava { ledger_lines: DEFAULT_LEDGER_LINES }),
+            other => other.parse::<i8>().ok().filter(|&n| n > 0).map(|ledger_lines| Ottava { ledger_lines }),
+        }
+    }
+
+    /// Maximal runs of notes that each need the threshold of ledger lines on the same side
+    /// of the staff. Rests, dashes and barlines between such notes don't end a run.
+    pub fn spans(&self, content_line: &ContentLine) -> Vec<OttavaSpan> {
+        let mut spans: Vec<OttavaSpan> = Vec::new();
+        let mut open = false;
+        for note in content_line_notes(content_line) {
+            let lines = ledger_lines(note.pitch_code, note.octave);
+            let shift = if lines >= self.ledger_lines {
+                1
+            } else if lines <= -self.ledger_lines {
+                -1
+            } else {
+                open = false;
+                continue;
+            };
+            match spans.last_mut() {
+                Some(span) if open && span.shift == shift => span.notes.push(note.id),
+                _ => spans.push(OttavaSpan { notes: vec![note.id], shift }),
+            }
+            open = true;
+        }
+        spans
+    }
+}
+
+impl OttavaSpan {
+    /// Bracket text
+    pub fn label(&self) -> &'static str {
+        if self.shift > 0 { "8va" } else { "8vb" }
+    }
+
+    /// LilyPond command opening the bracket; `\ottava #0` closes it
+    pub fn lilypond_command(&self) -> String {
+        format!("\\ottava #{}", self.shift)
+    }
+
+    /// Whether the first bracketed note is in the beat
+    pub fn starts_in(&self, beat: &Beat) -> bool {
+        self.notes.first().is_some_and(|id| beat_has_note(beat, id))
+    }
+
+    /// Whether the last bracketed note is in the beat
+    pub fn ends_in(&self, beat: &Beat) -> bool {
+        self.notes.last().is_some_and(|id| beat_has_note(beat, id))
+    }
+}
+
+/// Octave shift of each bracketed note, by note id
+pub fn note_shifts(spans: &[OttavaSpan]) -> HashMap<Uuid, i8> {
+    spans.iter()
+        .flat_map(|span| span.notes.iter().map(move |id| (*id, span.shift)))
+        .collect()
+}
+
+/// Ledger lines a note needs on the treble staff: positive above it, negative below it.
+/// Octave 0 is the octave of middle C.
+pub fn ledger_lines(pitch_code: PitchCode, octave: i8) -> i8 {
+    let (degree, _) = pitchcode_to_scale_position(pitch_code);
+    let step = degree as i32 + 7 * octave as i32;
+    let lines = if step > TOP_LINE {
+        (step - TOP_LINE) / 2
+    } else if step < BOTTOM_LINE {
+        -((BOTTOM_LINE - step) / 2)
+    } else {
+        0
+    };
+    lines as i8
+}
+
+fn beat_has_note(beat: &Beat, id: &Uuid) -> bool {
+    beat.elements.iter().any(|element| matches!(element, BeatElement::Note(note) if note.id == *id))
+}
+
+fn content_line_notes(content_line: &ContentLine) -> impl Iterator<Item = &crate::parse::model::Note> {
+    content_line.elements.iter()
+        .filter_map(|element| match element {
+            ContentElement::Beat(beat) => Some(beat),
+            _ => None,
+        })
+        .flat_map(|beat| beat.elements.iter())
+        .filter_map(|element| match element {
+            BeatElement::Note(note) => Some(note),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::model::NotationSystem;
+
+    #[test]
+    fn test_ledger_lines() {
+        assert_eq!(ledger_lines(PitchCode::N1, 0), -1); // middle C
+        assert_eq!(ledger_lines(PitchCode::N6, 1), 1); // A5
+        assert_eq!(ledger_lines(PitchCode::N3, 2), 3); // E6
+        assert_eq!(ledger_lines(PitchCode::N4, -1), -3); // F3
+        assert_eq!(ledger_lines(PitchCode::N5, 0), 0);
+    }
+
+    #[test]
+    fn test_spans_group_consecutive_high_notes() {
+        let mut line = crate::document::line_parser::content_line_parser::unused_parse_content_line(
+            "1 2 3 | 4 5", 0, NotationSystem::Number, 0,
+        ).unwrap();
+        let octaves = [0, 3, 3, 3, 0];
+        let mut ids = Vec::new();
+        for (note, octave) in line.elements.iter_mut().filter_map(|e| match e {
+            ContentElement::Beat(beat) => Some(beat),
+            _ => None,
+        }).flat_map(|b| b.elements.iter_mut()).filter_map(|e| match e {
+            BeatElement::Note(note) => Some(note),
+            _ => None,
+        }).zip(octaves) {
+            note.octave = octave;
+            ids.push(note.id);
+        }
+
+        let spans = Ottava { ledger_lines: DEFAULT_LEDGER_LINES }.spans(&line);
+        assert_eq!(spans, vec![OttavaSpan { notes: ids[1..4].to_vec(), shift: 1 }]);
+        assert_eq!(spans[0].label(), "8va");
+    }
+
+    #[test]
+    fn test_ottava_from_directives() {
+        let mut directives = HashMap::new();
+        assert_eq!(Ottava::from_directives(&directives), None);
+        directives.insert("Ottava".to_string(), "auto".to_string());
+        assert_eq!(Ottava::from_directives(&directives), Some(Ottava { ledger_lines: 3 }));
+        directives.insert("Ottava".to_string(), "4".to_string());
+        assert_eq!(Ottava::from_directives(&directives), Some(Ottava { ledger_lines: 4 }));
+    }
+}
@@ -0,0 +1,75 @@
+/// PNG export
+/// Rasterizes the staff SVG from `renderers::svg` with resvg, so images for docs and
+/// previews are made on the server without a browser
+use crate::parse::model::Document;
+use crate::renderers::svg::render_staff_svg;
+use once_cell::sync::Lazy;
+use resvg::{tiny_skia, usvg};
+use std::sync::Arc;
+
+/// Pixels per SVG unit when no scale is asked for
+pub const DEFAULT_SCALE: f32 = 2.0;
+/// Positive scales outside this range are clamped, keeping images visible and of a sane size
+const MIN_SCALE: f32 = 0.25;
+const MAX_SCALE: f32 = 8.0;
+
+/// System fonts for the title and clef glyph, loaded once
+static FONTS: Lazy<Arc<usvg::fontdb::Database>> = Lazy::new(|| {
+    let mut fonts = usvg::fontdb::Database::new();
+    fonts.load_system_fonts();
+    Arc::new(fonts)
+});
+
+/// Render every content line of a rhythm-analyzed document as staff notation in a PNG
+/// on a white background, `scale` pixels per SVG unit. A scale that isn't a positive
+/// number is an error.
+pub fn render_png(document: &Document, scale: f32) -> Result<Vec<u8>, String> {
+    if !(scale.is_finite() && scale > 0.0) {
+        return Err(format!("Scale must be a positive number, not {}", scale));
+    }
+    let scale = scale.clamp(MIN_SCALE, MAX_SCALE);
+    let options = usvg::Options { fontdb: FONTS.clone(), ..Default::default() };
+    let tree = usvg::Tree::from_str(&render_staff_svg(document), &options)
+        .map_err(|e| format!("Staff SVG could not be read: {}", e))?;
+    let size = tree.size().to_int_size().scale_by(scale).ok_or("Staff SVG has no size")?;
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).ok_or("Staff SVG has no size")?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+    resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+    pixmap.encode_png().map_err(|e| format!("PNG encoding failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::document_from_notation;
+
+    /// Width and height from the IHDR chunk
+    fn dimensions(png: &[u8]) -> (u32, u32) {
+        let be = |bytes: &[u8]| u32::from_be_bytes(bytes.try_into().unwrap());
+        (be(&png[16..20]), be(&png[20..24]))
+    }
+
+    #[test]
+    fn test_png_signature_and_size() {
+        let document = document_from_notation("Title: Scale\n\n|1 2 3 4|5 6 7 1|\n").unwrap();
+        let png = render_png(&document, 1.0).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        let (width, height) = dimensions(&png);
+        assert!(width > 100 && height > 100, "{}x{}", width, height);
+
+        let (double_width, double_height) = dimensions(&render_png(&document, 2.0).unwrap());
+        assert_eq!((double_width, double_height), (width * 2, height * 2));
+    }
+
+    #[test]
+    fn test_png_scale_is_checked() {
+        let document = document_from_notation("|1 2 3 4|\n").unwrap();
+        for scale in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(render_png(&document, scale).is_err(), "{}", scale);
+        }
+        // Huge scales are clamped
+        let (width, _) = dimensions(&render_png(&document, 1.0).unwrap());
+        let (huge_width, _) = dimensions(&render_png(&document, 1e9).unwrap());
+        assert_eq!(huge_width, width * MAX_SCALE as u32);
+    }
+}
@@ -0,0 +1,557 @@
+/// Staff notation as SVG, drawn without VexFlow
+/// Five-line treble staves with the key signature of the `Key` directive, note heads,
+/// stems, flags and barlines written directly as SVG shapes, so staff notation can be
+/// rendered on the server without a browser. Accidentals are written where the key
+/// signature or an earlier accidental in the measure doesn't already give the pitch.
+/// One staff per content line. Eighths and shorter within a beat are beamed together, and
+/// a beat continuing the previous note with dashes is drawn as a note tied to it.
+/// Tuplet brackets are not drawn yet: tuplet notes are drawn at their nominal value.
+use crate::parse::model::{directive, Beat, BeatElement, ContentElement, Document, DocumentElement, Note, StaveLine, KEY_DIRECTIVE};
+use crate::renderers::editor::font_metrics::{get_char_width, get_string_width};
+use crate::renderers::incipit::{key_alterations, key_signature_fifths};
+use crate::renderers::transposition::pitchcode_to_scale_position;
+use fraction::Fraction;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Distance between two staff lines
+const STAFF_SPACE: f32 = 10.0;
+const MARGIN: f32 = 20.0;
+/// Height given to each staff, including room for ledger lines above and below
+const STAFF_HEIGHT: f32 = 100.0;
+/// Top of the first staff's upper line within its band
+const STAFF_TOP: f32 = 30.0;
+const TITLE_HEIGHT: f32 = 30.0;
+const TITLE_FONT_SIZE: f32 = 18.0;
+const CLEF_FONT_SIZE: f32 = 40.0;
+const ACCIDENTAL_FONT_SIZE: f32 = 16.0;
+const NOTEHEAD_RX: f32 = 5.5;
+const NOTEHEAD_RY: f32 = 4.0;
+const STEM_LENGTH: f32 = 35.0;
+/// Shortest stem under a beam, and the steepest slope a beam may take
+const MIN_BEAMED_STEM: f32 = 25.0;
+const MAX_BEAM_SLOPE: f32 = 0.25;
+/// Distance between stacked beams, and the length of a partial beam on a lone note
+const BEAM_GAP: f32 = 7.0;
+const PARTIAL_BEAM: f32 = 8.0;
+/// Horizontal space for a note: a fixed part plus a part growing with its length
+const NOTE_SPACING: f32 = 16.0;
+const QUARTER_SPACING: f32 = 22.0;
+const BARLINE_SPACING: f32 = 14.0;
+const CLEF: char = '𝄞';
+/// Steps (from middle C) of the sharps of a treble key signature, F5 C5 G5 D5 A4 E5 B4,
+/// and of its flats, B4 E5 A4 D5 G4 C5 F4
+const SHARP_STEPS: [i32; 7] = [10, 7, 11, 8, 5, 9, 6];
+const FLAT_STEPS: [i32; 7] = [6, 9, 5, 8, 4, 7, 3];
+const KEY_ACCIDENTAL_SPACING: f32 = 8.0;
+
+/// Render every content line of a rhythm-analyzed document as a treble staff
+pub fn render_staff_svg(document: &Document) -> String {
+    staff_svg(document, "class=\"staff-notation\"")
+}
+
+/// Staff notation of a document in an `<svg>` element carrying `attributes` besides its size
+pub(crate) fn staff_svg(document: &Document, attributes: &str) -> String {
+    let fifths = directive(&document.directives, KEY_DIRECTIVE).and_then(key_signature_fifths).unwrap_or(0);
+    let staves: Vec<Vec<&ContentElement>> = document.elements.iter()
+        .filter_map(|element| match element {
+            DocumentElement::Stave(stave) => Some(stave),
+            _ => None,
+        })
+        .flat_map(|stave| &stave.lines)
+        .filter_map(|line| match line {
+            StaveLine::ContentLine(content_line) => Some(content_line.elements.iter().collect()),
+            _ => None,
+        })
+        .collect();
+
+    let title_height = if document.title.is_some() { TITLE_HEIGHT } else { 0.0 };
+    let mut body = String::new();
+    let mut width = document.title.as_deref().map_or(0.0, |title| get_string_width(title, TITLE_FONT_SIZE) + 2.0 * MARGIN);
+    for (index, elements) in staves.iter().enumerate() {
+        let mut staff = Staff {
+            svg: String::new(),
+            top: title_height + index as f32 * STAFF_HEIGHT + STAFF_TOP,
+            x: MARGIN,
+            last_note: None,
+            fifths,
+            signature: key_alterations(fifths),
+            bar_accidentals: HashMap::new(),
+        };
+        staff.draw(elements);
+        width = width.max(staff.x + MARGIN);
+        body.push_str(&staff.svg);
+    }
+    let width = width.max(2.0 * MARGIN);
+    let height = title_height + staves.len() as f32 * STAFF_HEIGHT;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.0} {:.0}\" {}>\n",
+        width, height, width, height, attributes
+    );
+    if let Some(title) = &document.title {
+        writeln!(
+            svg,
+            "  <text class=\"title\" x=\"{:.1}\" y=\"{:.1}\" font-size=\"{}\" text-anchor=\"middle\">{}</text>",
+            width / 2.0, TITLE_HEIGHT - 8.0, TITLE_FONT_SIZE, escape(title)
+        ).unwrap();
+    }
+    svg.push_str(&body);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Written value of a duration: the undotted note value (1 = whole, 4 = quarter) and whether it is dotted.
+/// Durations that aren't written with one note (tuplet parts) take the next longer value,
+/// so a triplet eighth (1/12) is drawn as an eighth.
+pub(crate) fn note_value(duration: Fraction) -> (u64, bool) {
+    let mut value = 1u64;
+    while value < 64 && Fraction::new(1u64, value * 2) >= duration {
+        value *= 2;
+    }
+    if value < 64 && duration == Fraction::new(3u64, value * 4) {
+        return (value * 2, true);
+    }
+    (value, false)
+}
+
+/// Written duration of a note from its rhythm analysis
+fn note_duration(note: &Note) -> Fraction {
+    Fraction::new(note.numerator.unwrap_or(1), note.denominator.unwrap_or(4))
+}
+
+/// Number of beams (or flags) on a note of the written `value` (8 = eighth: one)
+pub(crate) fn beam_count(value: u64) -> u32 {
+    value.trailing_zeros().saturating_sub(2)
+}
+
+/// Indices into `beat.elements` of the notes beamed together: runs of two or more
+/// eighths or shorter that no rest, breath mark or longer note breaks
+fn beam_groups(beat: &Beat) -> Vec<Vec<usize>> {
+    let mut groups = Vec::new();
+    let mut run = Vec::new();
+    for (index, element) in beat.elements.iter().enumerate() {
+        match element {
+            BeatElement::Note(note) if beam_count(note_value(note_duration(note)).0) > 0 => run.push(index),
+            // Dashes extending a note don't break the beam
+            BeatElement::Dash(dash) if dash.numerator.is_none() => {}
+            _ => {
+                if run.len() >= 2 {
+                    groups.push(std::mem::take(&mut run));
+                }
+                run.clear();
+            }
+        }
+    }
+    if run.len() >= 2 {
+        groups.push(run);
+    }
+    groups
+}
+
+/// Note head of a beamed note, whose stem is drawn once the whole group is known
+struct BeamedNote {
+    /// Center of the note head
+    x: f32,
+    y: f32,
+    step: i32,
+    value: u64,
+}
+
+/// One staff being drawn left to right
+struct Staff {
+    svg: String,
+    /// y of the top staff line
+    top: f32,
+    /// Next free x position
+    x: f32,
+    /// Last note drawn and the center of its note head, for a following beat to tie to
+    last_note: Option<(Note, f32, f32)>,
+    /// Sharps (or flats if negative) in the key signature, and the alteration it gives each letter C-B
+    fifths: i8,
+    signature: [i8; 7],
+    /// Alterations written so far in the measure, by letter and octave
+    bar_accidentals: HashMap<(usize, i8), i8>,
+}
+
+impl Staff {
+    fn line(&mut self, class: &str, x1: f32, y1: f32, x2: f32, y2: f32) {
+        writeln!(
+            self.svg,
+            "  <line class=\"{}\" x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"black\" stroke-width=\"{}\"/>",
+            class, x1, y1, x2, y2, match class {
+                "stem" | "barline" => 1.2,
+                "beam" => 4.0,
+                _ => 1.0,
+            }
+        ).unwrap();
+    }
+
+    /// y of a diatonic step counted from middle C (C4 = 0, E4 = 2 is the bottom line)
+    fn step_y(&self, step: i32) -> f32 {
+        self.top + 4.0 * STAFF_SPACE - (step - 2) as f32 * STAFF_SPACE / 2.0
+    }
+
+    fn draw(&mut self, elements: &[&ContentElement]) {
+        writeln!(
+            self.svg,
+            "  <text class=\"clef\" x=\"{:.1}\" y=\"{:.1}\" font-size=\"{}\">{}</text>",
+            self.x, self.top + 3.0 * STAFF_SPACE + 4.0, CLEF_FONT_SIZE, CLEF
+        ).unwrap();
+        self.x += get_char_width(CLEF, CLEF_FONT_SIZE).max(STAFF_SPACE * 2.5) + 8.0;
+
+        if self.fifths != 0 {
+            let (glyph, steps) = if self.fifths > 0 { ("♯", SHARP_STEPS) } else { ("♭", FLAT_STEPS) };
+            writeln!(self.svg, "  <g class=\"key-signature\" data-fifths=\"{}\">", self.fifths).unwrap();
+            for &step in steps.iter().take(self.fifths.unsigned_abs() as usize) {
+                writeln!(
+                    self.svg,
+                    "    <text class=\"key-accidental\" x=\"{:.1}\" y=\"{:.1}\" font-size=\"{}\">{}</text>",
+                    self.x, self.step_y(step) + 5.0, ACCIDENTAL_FONT_SIZE, glyph
+                ).unwrap();
+                self.x += KEY_ACCIDENTAL_SPACING;
+            }
+            self.svg.push_str("  </g>\n");
+            self.x += 6.0;
+        }
+
+        for element in elements {
+            match element {
+                ContentElement::Beat(beat) => self.beat(beat),
+                ContentElement::Barline(_) => {
+                    let x = self.x + BARLINE_SPACING / 2.0;
+                    self.line("barline", x, self.top, x, self.top + 4.0 * STAFF_SPACE);
+                    self.x += BARLINE_SPACING;
+                    self.bar_accidentals.clear();
+                }
+                ContentElement::Whitespace(_) | ContentElement::UnknownToken(_) => {}
+            }
+        }
+
+        // Staff lines are drawn last so they span the music
+        let end = self.x + MARGIN / 2.0;
+        let mut staff_lines = String::new();
+        std::mem::swap(&mut self.svg, &mut staff_lines);
+        for i in 0..5 {
+            let y = self.top + i as f32 * STAFF_SPACE;
+            self.line("staff-line", MARGIN, y, end, y);
+        }
+        self.svg.push_str(&staff_lines);
+        self.x = end;
+    }
+
+    fn beat(&mut self, beat: &Beat) {
+        // Leading dashes of a tied beat hold the previous note on: it is drawn again for
+        // their share of the beat and tied to, or a rest when there is no note to hold
+        let leading_dashes = beat.elements.iter().take_while(|element| matches!(element, BeatElement::Dash(dash) if dash.numerator.is_none())).count();
+        if let (true, Some(divisions)) = (beat.tied_to_previous.unwrap_or(false) && leading_dashes > 0, beat.divisions) {
+            let held = Fraction::new(leading_dashes as u64, divisions as u64) * beat.total_duration.unwrap_or(Fraction::new(1u64, 4u64));
+            match self.last_note.take() {
+                Some((note, from_x, from_y)) => {
+                    let held_note = Note {
+                        numerator: held.numer().map(|&numer| numer as u32),
+                        denominator: held.denom().map(|&denom| denom as u32),
+                        ..note
+                    };
+                    self.note(&held_note, false);
+                    if let Some((_, to_x, to_y)) = self.last_note {
+                        self.tie(from_x, from_y, to_x, to_y);
+                    }
+                }
+                None => self.rest(held),
+            }
+        }
+
+        let groups = beam_groups(beat);
+        let mut beamed = Vec::new();
+        for (index, element) in beat.elements.iter().enumerate() {
+            match element {
+                BeatElement::Note(note) => {
+                    let group = groups.iter().find(|group| group.contains(&index));
+                    beamed.extend(self.note(note, group.is_some()));
+                    if group.is_some_and(|group| group.last() == Some(&index)) {
+                        self.beam(&std::mem::take(&mut beamed));
+                    }
+                }
+                BeatElement::Dash(dash) => {
+                    if let (Some(numer), Some(denom)) = (dash.numerator, dash.denominator) {
+                        self.rest(Fraction::new(numer, denom));
+                    }
+                }
+                BeatElement::Rest(rest) => {
+                    self.rest(Fraction::new(rest.numerator.unwrap_or(1), rest.denominator.unwrap_or(4)));
+                }
+                BeatElement::BreathMark(_) => self.last_note = None,
+            }
+        }
+    }
+
+    /// Tie arc between two note heads, curving away from the stems (below heads on the
+    /// lower half of the staff, whose stems go up)
+    fn tie(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        let below = y1 > self.step_y(6);
+        let (offset, bulge) = if below { (NOTEHEAD_RY + 2.0, 6.0) } else { (-NOTEHEAD_RY - 2.0, -6.0) };
+        writeln!(
+            self.svg,
+            "  <path class=\"tie\" d=\"M {:.1} {:.1} Q {:.1} {:.1} {:.1} {:.1}\" fill=\"none\" stroke=\"black\" stroke-width=\"1.2\"/>",
+            x1 + NOTEHEAD_RX, y1 + offset, (x1 + x2) / 2.0, (y1 + y2) / 2.0 + offset + bulge, x2 - NOTEHEAD_RX, y2 + offset
+        ).unwrap();
+    }
+
+    fn advance(&mut self, duration: Fraction) {
+        let quarters = duration * Fraction::from(4u64);
+        let quarters = *quarters.numer().unwrap_or(&1) as f32 / *quarters.denom().unwrap_or(&1) as f32;
+        self.x += NOTE_SPACING + QUARTER_SPACING * quarters.sqrt();
+    }
+
+    /// Draw a note. A `beamed` note is drawn without its stem, which is returned to
+    /// be drawn with the rest of its beam group.
+    fn note(&mut self, note: &Note, beamed: bool) -> Option<BeamedNote> {
+        let duration = note_duration(note);
+        let (value, dotted) = note_value(duration);
+        let (degree, alteration) = pitchcode_to_scale_position(note.pitch_code);
+        let step = degree as i32 + 7 * note.octave as i32;
+        let y = self.step_y(step);
+
+        let in_force = self.bar_accidentals.get(&(degree, note.octave)).copied().unwrap_or(self.signature[degree]);
+        let accidental = match alteration {
+            _ if alteration == in_force => "",
+            -2 => "𝄫",
+            -1 => "♭",
+            1 => "♯",
+            2 => "𝄪",
+            _ => "♮",
+        };
+        self.bar_accidentals.insert((degree, note.octave), alteration);
+        if !accidental.is_empty() {
+            writeln!(
+                self.svg,
+                "  <text class=\"accidental\" x=\"{:.1}\" y=\"{:.1}\" font-size=\"{}\">{}</text>",
+                self.x, y + 5.0, ACCIDENTAL_FONT_SIZE, accidental
+            ).unwrap();
+            self.x += get_string_width(accidental, ACCIDENTAL_FONT_SIZE).max(7.0) + 2.0;
+        }
+        let x = self.x + NOTEHEAD_RX;
+
+        // Ledger lines below and above the staff
+        let ledger = |staff: &mut Staff, ledger_step: i32| {
+            let ledger_y = staff.step_y(ledger_step);
+            staff.line("ledger-line", x - NOTEHEAD_RX - 3.0, ledger_y, x + NOTEHEAD_RX + 3.0, ledger_y);
+        };
+        for ledger_step in (step..=0).rev().filter(|s| s % 2 == 0) {
+            ledger(self, ledger_step);
+        }
+        for ledger_step in (12..=step).filter(|s| s % 2 == 0) {
+            ledger(self, ledger_step);
+        }
+
+        let filled = value >= 4;
+        writeln!(
+            self.svg,
+            "  <ellipse class=\"notehead\" cx=\"{:.1}\" cy=\"{:.1}\" rx=\"{}\" ry=\"{}\" transform=\"rotate(-20 {:.1} {:.1})\" fill=\"{}\" stroke=\"black\"/>",
+            x, y, NOTEHEAD_RX, NOTEHEAD_RY, x, y, if filled { "black" } else { "white" }
+        ).unwrap();
+        if dotted {
+            // Dots sit in a space, never on a line
+            let dot_y = if step % 2 == 0 { y - STAFF_SPACE / 2.0 } else { y };
+            writeln!(self.svg, "  <circle class=\"dot\" cx=\"{:.1}\" cy=\"{:.1}\" r=\"1.5\"/>", x + NOTEHEAD_RX + 4.0, dot_y).unwrap();
+        }
+
+        self.last_note = Some((note.clone(), x, y));
+        if beamed {
+            self.advance(duration);
+            return Some(BeamedNote { x, y, step, value });
+        }
+        if value >= 2 {
+            // Stems go up below the middle line (B4) and down from it
+            let up = step < 6;
+            let (stem_x, tip_y) = if up {
+                (x + NOTEHEAD_RX - 0.5, y - STEM_LENGTH)
+            } else {
+                (x - NOTEHEAD_RX + 0.5, y + STEM_LENGTH)
+            };
+            self.line("stem", stem_x, y, stem_x, tip_y);
+            for i in 0..beam_count(value) {
+                let flag_y = if up { tip_y + i as f32 * 7.0 } else { tip_y - i as f32 * 7.0 };
+                let (curl, end) = if up { (12.0, 22.0) } else { (-12.0, -22.0) };
+                writeln!(
+                    self.svg,
+                    "  <path class=\"flag\" d=\"M {:.1} {:.1} q 9 {:.1} 7 {:.1}\" fill=\"none\" stroke=\"black\" stroke-width=\"1.5\"/>",
+                    stem_x, flag_y, curl, end
+                ).unwrap();
+            }
+        }
+
+        self.advance(duration);
+        None
+    }
+
+    /// Draw the stems of a beam group and the beams joining them. The beam runs from the
+    /// first stem to the last, sloping with their note heads, and is moved away from the
+    /// heads until every stem is long enough. Notes shorter than their neighbours get
+    /// secondary beams, partial ones pointing into the group on a lone note.
+    fn beam(&mut self, notes: &[BeamedNote]) {
+        let (Some(first), Some(last)) = (notes.first(), notes.last()) else { return };
+        // Stems go up when the heads lie below the middle line on average
+        let up = notes.iter().map(|note| note.step).sum::<i32>() < 6 * notes.len() as i32;
+        let direction = if up { -1.0 } else { 1.0 };
+        let stem_x = |note: &BeamedNote| if up { note.x + NOTEHEAD_RX - 0.5 } else { note.x - NOTEHEAD_RX + 0.5 };
+        let (x1, x2) = (stem_x(first), stem_x(last));
+        let slope = ((last.y - first.y) / (x2 - x1).max(1.0)).clamp(-MAX_BEAM_SLOPE, MAX_BEAM_SLOPE);
+        let mut y1 = first.y + direction * STEM_LENGTH;
+        for note in notes {
+            let length = (y1 + slope * (stem_x(note) - x1) - note.y) * direction;
+            if length < MIN_BEAMED_STEM {
+                y1 += direction * (MIN_BEAMED_STEM - length);
+            }
+        }
+        let beam_y = |x: f32, level: u32| y1 + slope * (x - x1) - direction * (level - 1) as f32 * BEAM_GAP;
+
+        for note in notes {
+            let x = stem_x(note);
+            self.line("stem", x, note.y, x, beam_y(x, 1));
+        }
+        let levels = notes.iter().map(|note| beam_count(note.value)).max().unwrap_or(1);
+        for level in 1..=levels {
+            let mut start = 0;
+            while start < notes.len() {
+                if beam_count(notes[start].value) < level {
+                    start += 1;
+                    continue;
+                }
+                let mut end = start;
+                while end + 1 < notes.len() && beam_count(notes[end + 1].value) >= level {
+                    end += 1;
+                }
+                let (from, to) = if end > start {
+                    (stem_x(&notes[start]), stem_x(&notes[end]))
+                } else if start + 1 < notes.len() {
+                    (stem_x(&notes[start]), stem_x(&notes[start]) + PARTIAL_BEAM)
+                } else {
+                    (stem_x(&notes[start]) - PARTIAL_BEAM, stem_x(&notes[start]))
+                };
+                self.line("beam", from, beam_y(from, level), to, beam_y(to, level));
+                start = end + 1;
+            }
+        }
+    }
+
+    /// Rests are drawn as blocks for now: on the middle line, hanging from it for a whole rest
+    fn rest(&mut self, duration: Fraction) {
+        let (value, _) = note_value(duration);
+        let (y, height) = match value {
+            1 => (self.top + STAFF_SPACE, STAFF_SPACE / 2.0),
+            2 => (self.top + 1.5 * STAFF_SPACE, STAFF_SPACE / 2.0),
+            _ => (self.top + STAFF_SPACE, 2.0 * STAFF_SPACE / value.trailing_zeros() as f32),
+        };
+        self.last_note = None;
+        writeln!(
+            self.svg,
+            "  <rect class=\"rest\" x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\"/>",
+            self.x + 2.0, y, NOTEHEAD_RX * 1.5, height
+        ).unwrap();
+        self.advance(duration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The staff notation of one line of number notation in beats of `beat_unit`
+    fn staff_svg(line: &str, beat_unit: &str) -> String {
+        let document = crate::pipeline::document_from_notation(&format!("BeatUnit: {}\n\n{}\n", beat_unit, line)).unwrap();
+        render_staff_svg(&document)
+    }
+
+    #[test]
+    fn test_one_staff_of_quarter_notes() {
+        let svg = staff_svg("|3 4 5 6|", "1/4");
+        assert!(svg.starts_with("<svg"));
+        // Five staff lines, two barlines and four stems; E4-A4 need no ledger lines
+        assert_eq!(svg.matches("<line").count(), 11);
+        assert_eq!(svg.matches("class=\"staff-line\"").count(), 5);
+        assert_eq!(svg.matches("class=\"notehead\"").count(), 4);
+        assert_eq!(svg.matches("fill=\"black\" stroke").count(), 4);
+    }
+
+    #[test]
+    fn test_note_values() {
+        // In half-note beats: hollow half notes, four beamed eighths, and middle C
+        // (plain and sharpened) on ledger lines
+        let svg = staff_svg("|5 1234 1#|", "1/2");
+        assert_eq!(svg.matches("fill=\"white\"").count(), 2);
+        assert_eq!(svg.matches("class=\"flag\"").count(), 0);
+        assert_eq!(svg.matches("class=\"beam\"").count(), 1);
+        assert_eq!(svg.matches("class=\"ledger-line\"").count(), 2);
+        assert_eq!(svg.matches("class=\"accidental\"").count(), 1);
+        assert_eq!(note_value(Fraction::new(3u64, 8u64)), (4, true));
+        assert_eq!(note_value(Fraction::new(1u64, 12u64)), (8, false));
+        assert_eq!(note_value(Fraction::new(1u64, 4u64)), (4, false));
+    }
+
+    /// x1 and x2 of every beam line
+    fn beams(svg: &str) -> Vec<(f32, f32)> {
+        svg.lines()
+            .filter(|line| line.contains("class=\"beam\""))
+            .map(|line| {
+                let attribute = |name: &str| {
+                    let start = line.find(&format!(" {}=\"", name)).unwrap() + name.len() + 3;
+                    line[start..].split('"').next().unwrap().parse::<f32>().unwrap()
+                };
+                (attribute("x1"), attribute("x2"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_beam_spans_group() {
+        // Four sixteenths in one beat: a primary and a secondary beam across all four stems
+        let svg = staff_svg("|1234|", "1/4");
+        assert_eq!(svg.matches("class=\"flag\"").count(), 0);
+        let stems: Vec<f32> = svg.lines()
+            .filter(|line| line.contains("class=\"stem\""))
+            .map(|line| line.split("x1=\"").nth(1).unwrap().split('"').next().unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(stems.len(), 4);
+        assert_eq!(beams(&svg), vec![(stems[0], stems[3]), (stems[0], stems[3])]);
+    }
+
+    #[test]
+    fn test_partial_secondary_beams() {
+        // An eighth and two sixteenths: the secondary beam joins only the sixteenths
+        let eighth_first = beams(&staff_svg("|1-23|", "1/4"));
+        assert_eq!(eighth_first.len(), 2);
+        assert!(eighth_first[1].0 > eighth_first[0].0 && eighth_first[1].1 == eighth_first[0].1, "{:?}", eighth_first);
+
+        // A sixteenth before a dotted eighth gets a partial beam pointing right
+        let dotted = beams(&staff_svg("|12--|", "1/4"));
+        assert_eq!(dotted.len(), 2);
+        assert_eq!(dotted[1], (dotted[0].0, dotted[0].0 + PARTIAL_BEAM));
+
+        // Quarter notes and rests break beams
+        assert!(beams(&staff_svg("1 2 3 4", "1/4")).is_empty());
+        assert!(beams(&staff_svg("|1-2|", "1/2")).is_empty());
+    }
+
+    #[test]
+    fn test_held_beats_are_tied_notes() {
+        // A note held for four beats: the note and three tied continuations
+        let svg = staff_svg("| 5 - - - |", "1/4");
+        assert_eq!(svg.matches("class=\"notehead\"").count(), 4, "{}", svg);
+        assert_eq!(svg.matches("class=\"tie\"").count(), 3);
+        assert_eq!(svg.matches("class=\"rest\"").count(), 0);
+
+        // Held on for half a beat, and nothing to hold at the start of a line
+        let svg = staff_svg("| 1 -2 - 3 |", "1/4");
+        assert_eq!(svg.matches("class=\"notehead\"").count(), 5, "{}", svg);
+        assert_eq!(svg.matches("class=\"tie\"").count(), 2);
+        let svg = staff_svg("| - 1 2 3 |", "1/4");
+        assert_eq!(svg.matches("class=\"tie\"").count(), 0);
+        assert_eq!(svg.matches("class=\"notehead\"").count(), 3);
+    }
+}
@@ -1,37 +1,209 @@
 /// Shared transposition utilities for tonic-based movable-do system
 use crate::models::PitchCode;
+use serde::{Deserialize, Serialize};
+
+/// How transposed notes that land on a black key are spelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Spelling {
+    /// Always the same spelling per pitch (C#, Eb, F#, Ab, Bb)
+    #[default]
+    Fixed,
+    /// Sharps going up and flats going down, so a run keeps one kind of accidental
+    Smart,
+}
+
+/// Options for transposing a melody (e.g. `{"spell": "Smart"}`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TranspositionOptions {
+    #[serde(default)]
+    pub spell: Spelling,
+}
+
+/// Directive naming the pitch the tonic sounds as (e.g. "Transpose: D")
+pub const TRANSPOSE_DIRECTIVE: &str = "Transpose";
+/// Directive choosing how LilyPond output is transposed: `model` or `lilypond`
+pub const TRANSPOSE_MODE_DIRECTIVE: &str = "TransposeMode";
+
+/// Where transposition happens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TranspositionMode {
+    /// Every pitch is rewritten, matching what MIDI and VexFlow play and show
+    #[default]
+    Model,
+    /// Pitches are kept as written and the music is wrapped in `\transpose`,
+    /// which is easier to edit in the generated LilyPond
+    LilyPond,
+}
+
+/// Transposition requested by the document's directives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transposition {
+    pub tonic: PitchCode,
+    pub mode: TranspositionMode,
+}
+
+impl Transposition {
+    /// Read the `Transpose` and `TransposeMode` directives; `None` without a valid tonic
+    pub fn from_directives(directives: &std::collections::HashMap<String, String>) -> Option<Self> {
+        let get = |name: &str| crate::parse::model::directive(directives, name);
+        let tonic = get(TRANSPOSE_DIRECTIVE)?;
+        let mut chars = tonic.chars();
+        let tonic = chars.next()?.to_ascii_uppercase().to_string() + chars.as_str();
+        let degree = crate::models::pitch_systems::western::lookup(&tonic)?;
+        let mode = match get(TRANSPOSE_MODE_DIRECTIVE).map(str::to_lowercase).as_deref() {
+            Some("lilypond") => TranspositionMode::LilyPond,
+            _ => TranspositionMode::Model,
+        };
+        Some(Transposition { tonic: crate::models::pitch_systems::degree_to_pitch_code(degree), mode })
+    }
+
+    /// Tonic to rewrite pitches with, when transposing in the model
+    pub fn model_tonic(self) -> Option<PitchCode> {
+        (self.mode == TranspositionMode::Model).then_some(self.tonic)
+    }
+}
 
 /// Transpose a PitchCode and octave based on the tonic (movable-do system)
 /// Returns (transposed_pitchcode, adjusted_octave)
+///
+/// The result is spelled on the letter its scale degree falls on in the tonic's key,
+/// so altered degrees keep their accidental: with the tonic (Sa) on C, komal re (N2b)
+/// is Db and tivra ma (N4s) is F#; on D they are Eb and G#.
 pub fn transpose_pitchcode_with_octave(pitchcode: PitchCode, octave: i8, tonic: PitchCode) -> (PitchCode, i8) {
-    // Major scale interval pattern (semitones from tonic): 0, 2, 4, 5, 7, 9, 11
     let major_scale_semitones = [0, 2, 4, 5, 7, 9, 11];
-    
-    // Get the pitch and tonic as scale degrees (0-6) and semitone offsets
-    let (pitch_degree, pitch_semitone_offset) = pitchcode_to_scale_position(pitchcode);
-    let (tonic_degree, tonic_semitone_offset) = pitchcode_to_scale_position(tonic);
-    
-    // Calculate the target semitone value
-    // Start with the major scale interval for this scale degree
-    let target_semitones = major_scale_semitones[pitch_degree];
-    
-    // Add the pitch's semitone offset from its scale degree
-    let pitch_offset = pitch_semitone_offset;
-    
-    // Add the tonic's base semitone position + offset
-    let tonic_base_semitones = major_scale_semitones[tonic_degree];  
-    let tonic_offset = tonic_semitone_offset;
-    
-    // Total semitones = target interval + pitch offset + tonic position + tonic offset
-    let total_semitones = target_semitones + pitch_offset + tonic_base_semitones + tonic_offset;
-    
-    // Convert back to scale position and calculate octave wrap
+    let (pitch_degree, _) = pitchcode_to_scale_position(pitchcode);
+    let (tonic_degree, _) = pitchcode_to_scale_position(tonic);
+    let total_semitones = pitchcode_to_semitones(pitchcode) + pitchcode_to_semitones(tonic);
+
+    let letter = pitch_degree + tonic_degree;
+    let offset = total_semitones - (major_scale_semitones[letter % 7] + 12 * (letter / 7) as i8);
+    if (-2..=2).contains(&offset) {
+        return (scale_position_to_pitchcode(letter % 7, offset), octave + (letter / 7) as i8);
+    }
+    // Spellings past a double sharp or flat fall back to the fixed black-key spellings
+    chromatic_transpose(pitchcode, octave, tonic)
+}
+
+/// Transpose by the semitones the tonic lies above C, spelling black keys the same
+/// way whatever the degree (C#, Eb, F#, Ab, Bb)
+fn chromatic_transpose(pitchcode: PitchCode, octave: i8, tonic: PitchCode) -> (PitchCode, i8) {
+    let total_semitones = pitchcode_to_semitones(pitchcode) + pitchcode_to_semitones(tonic);
     let (final_scale_pos, final_semitone_offset, octave_adjustment) = semitones_to_scale_position(total_semitones);
-    
-    let transposed_pitchcode = scale_position_to_pitchcode(final_scale_pos, final_semitone_offset);
-    let adjusted_octave = octave + octave_adjustment;
-    
-    (transposed_pitchcode, adjusted_octave)
+    (scale_position_to_pitchcode(final_scale_pos, final_semitone_offset), octave + octave_adjustment)
+}
+
+/// Transpose a PitchCode and octave by a number of semitones, e.g. N7 up 1 => (N1, octave + 1).
+/// Transposes as if to a tonic `semitones` above 1, so black keys come out with the
+/// same spellings (C#, Eb, F#, Ab, Bb).
+pub fn transpose_by_semitones(pitchcode: PitchCode, octave: i8, semitones: i32) -> (PitchCode, i8) {
+    let (tonic_degree, tonic_offset, _) = semitones_to_scale_position(semitones.rem_euclid(12) as i8);
+    let tonic = scale_position_to_pitchcode(tonic_degree, tonic_offset);
+    let (transposed, adjusted_octave) = chromatic_transpose(pitchcode, octave, tonic);
+    (transposed, adjusted_octave + semitones.div_euclid(12) as i8)
+}
+
+/// A named interval such as "m3" or "-P5": a number of scale steps and of semitones,
+/// both negative when transposing down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval {
+    pub steps: i32,
+    pub semitones: i32,
+}
+
+impl Interval {
+    /// Parse a quality (P, M, m, A or d) and a number from 1 up, with a leading '-' going down.
+    /// Unisons, fourths, fifths and octaves are perfect; the other numbers major or minor.
+    pub fn parse(name: &str) -> Result<Self, String> {
+        let trimmed = name.trim();
+        let (down, rest) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        };
+        let unknown = || format!("Unknown interval '{}': expected a quality (P, M, m, A, d) and a number, e.g. \"m3\" or \"-P5\"", name);
+        let mut chars = rest.chars();
+        let quality = chars.next().ok_or_else(unknown)?;
+        let number: i32 = chars.as_str().parse().map_err(|_| unknown())?;
+        if number < 1 {
+            return Err(unknown());
+        }
+        let steps = number - 1;
+        let perfect = matches!(steps % 7, 0 | 3 | 4);
+        let major_scale_semitones = [0, 2, 4, 5, 7, 9, 11];
+        let adjustment = match (quality, perfect) {
+            ('P', true) | ('M', false) => 0,
+            ('m', false) => -1,
+            ('A', _) => 1,
+            ('d', true) => -1,
+            ('d', false) => -2,
+            _ => return Err(unknown()),
+        };
+        let semitones = major_scale_semitones[(steps % 7) as usize] + 12 * (steps / 7) + adjustment;
+        Ok(if down {
+            Interval { steps: -steps, semitones: -semitones }
+        } else {
+            Interval { steps, semitones }
+        })
+    }
+}
+
+/// Transpose a PitchCode and octave by an interval, spelled by scale steps: a minor third
+/// above 1 is 3b, not 2#. Falls back to the semitone spelling beyond double accidentals.
+pub fn transpose_by_interval_steps(pitchcode: PitchCode, octave: i8, interval: Interval) -> (PitchCode, i8) {
+    let major_scale_semitones = [0, 2, 4, 5, 7, 9, 11];
+    let (degree, offset) = pitchcode_to_scale_position(pitchcode);
+    let step = octave as i32 * 7 + degree as i32 + interval.steps;
+    let (new_degree, new_octave) = (step.rem_euclid(7) as usize, step.div_euclid(7));
+    let target = octave as i32 * 12 + major_scale_semitones[degree] + offset as i32 + interval.semitones;
+    let new_offset = target - (new_octave * 12 + major_scale_semitones[new_degree]);
+    if new_offset.abs() > 2 {
+        return transpose_by_semitones(pitchcode, octave, interval.semitones);
+    }
+    (scale_position_to_pitchcode(new_degree, new_offset as i8), new_octave as i8)
+}
+
+/// Transpose a melody of (pitch code, octave) notes, then respell it as the options ask
+pub fn transpose_melody(notes: &[(PitchCode, i8)], tonic: PitchCode, options: TranspositionOptions) -> Vec<(PitchCode, i8)> {
+    let transposed: Vec<(PitchCode, i8)> = notes.iter()
+        .map(|&(pitchcode, octave)| chromatic_transpose(pitchcode, octave, tonic))
+        .collect();
+    match options.spell {
+        Spelling::Fixed => transposed,
+        Spelling::Smart => respell_smart(&transposed),
+    }
+}
+
+/// Respell sharps and flats by voice leading: a note moving up to the next one is
+/// spelled as a sharp, moving down as a flat. A repeated or final note follows the
+/// note before it, and otherwise the spelling last chosen in the phrase.
+/// Natural notes and double accidentals are left alone.
+pub fn respell_smart(notes: &[(PitchCode, i8)]) -> Vec<(PitchCode, i8)> {
+    let height = |(pitchcode, octave): (PitchCode, i8)| octave as i32 * 12 + pitchcode_to_semitones(pitchcode) as i32;
+    let mut prefer_sharps: Option<bool> = None;
+    notes.iter().enumerate().map(|(i, &(pitchcode, octave))| {
+        if pitchcode_to_scale_position(pitchcode).1.abs() != 1 {
+            return (pitchcode, octave);
+        }
+        let current = height((pitchcode, octave));
+        let next = notes.get(i + 1).map(|&n| height(n)).filter(|&h| h != current);
+        let previous = i.checked_sub(1).map(|p| height(notes[p])).filter(|&h| h != current);
+        let sharp = match (next, previous) {
+            (Some(next), _) => next > current,
+            (None, Some(previous)) => previous < current,
+            (None, None) => prefer_sharps.unwrap_or(pitchcode_to_scale_position(pitchcode).1 > 0),
+        };
+        prefer_sharps = Some(sharp);
+        (spell_black_key(pitchcode_to_semitones(pitchcode), sharp).unwrap_or(pitchcode), octave)
+    }).collect()
+}
+
+/// Sharp or flat spelling of a black key given in semitones above C
+fn spell_black_key(semitones: i8, sharp: bool) -> Option<PitchCode> {
+    // Scale position below each black key; the flat spelling uses the one above
+    let below = match semitones.rem_euclid(12) {
+        1 => 0, 3 => 1, 6 => 3, 8 => 4, 10 => 5,
+        _ => return None,
+    };
+    Some(if sharp { scale_position_to_pitchcode(below, 1) } else { scale_position_to_pitchcode(below + 1, -1) })
 }
 
 /// Convert total semitones back to scale position, semitone offset, and octave
@@ -64,8 +236,15 @@ pub fn transpose_pitchcode(pitchcode: PitchCode, tonic: PitchCode) -> PitchCode
     transposed_pitchcode
 }
 
+/// Semitones above the tonic (C) for a PitchCode, e.g. N1 => 0, N5 => 7, N7b => 10
+pub fn pitchcode_to_semitones(pitchcode: PitchCode) -> i8 {
+    let major_scale_semitones = [0, 2, 4, 5, 7, 9, 11];
+    let (degree, offset) = pitchcode_to_scale_position(pitchcode);
+    major_scale_semitones[degree] + offset
+}
+
 /// Convert PitchCode to scale position (0-6) and semitone offset
-fn pitchcode_to_scale_position(pitchcode: PitchCode) -> (usize, i8) {
+pub fn pitchcode_to_scale_position(pitchcode: PitchCode) -> (usize, i8) {
     use PitchCode::*;
     match pitchcode {
         N1bb => (0, -2), N1b => (0, -1), N1 => (0, 0), N1s => (0, 1), N1ss => (0, 2),
@@ -104,6 +283,18 @@ mod tests {
         assert_eq!(result, (PitchCode::N1s, 1));
     }
     
+    #[test]
+    fn test_komal_and_tivra_keep_their_accidentals() {
+        use PitchCode::*;
+        // Komal re, ga, dha, ni and tivra ma with Sa on C
+        let with_sa = |tonic| [N2b, N3b, N4s, N6b, N7b].map(|pitch| transpose_pitchcode_with_octave(pitch, 0, tonic));
+        assert_eq!(with_sa(N1), [(N2b, 0), (N3b, 0), (N4s, 0), (N6b, 0), (N7b, 0)]);
+        // Sa on D: Eb, F, G#, Bb, C
+        assert_eq!(with_sa(N2), [(N3b, 0), (N4, 0), (N5s, 0), (N7b, 0), (N1, 1)]);
+        // Sa on Bb: Cb, Db, E, Gb, Ab
+        assert_eq!(with_sa(N7b), [(N1b, 1), (N2b, 1), (N3, 1), (N5b, 1), (N6b, 1)]);
+    }
+
     #[test] 
     fn test_transpose_pitchcode_1_in_d_major() {
         // Scale degree 1 in D major should be D (N2) in same octave
@@ -117,4 +308,42 @@ mod tests {
         let result = transpose_pitchcode_with_octave(PitchCode::N4, 0, PitchCode::N5);
         assert_eq!(result, (PitchCode::N1, 1));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_interval_names() {
+        assert_eq!(Interval::parse("m3"), Ok(Interval { steps: 2, semitones: 3 }));
+        assert_eq!(Interval::parse("-P4"), Ok(Interval { steps: -3, semitones: -5 }));
+        assert_eq!(Interval::parse("A4"), Ok(Interval { steps: 3, semitones: 6 }));
+        assert_eq!(Interval::parse("M9"), Ok(Interval { steps: 8, semitones: 14 }));
+        for bad in ["P3", "M5", "m4", "X2", "M0", "M", ""] {
+            assert!(Interval::parse(bad).is_err(), "{}", bad);
+        }
+
+        // Spelled by steps: a minor third above 1 is 3b, not 2#
+        assert_eq!(transpose_by_interval_steps(PitchCode::N1, 0, Interval::parse("m3").unwrap()), (PitchCode::N3b, 0));
+        assert_eq!(transpose_by_interval_steps(PitchCode::N7, 0, Interval::parse("M2").unwrap()), (PitchCode::N1s, 1));
+    }
+
+    #[test]
+    fn test_smart_spelling_keeps_chromatic_runs_consistent() {
+        use PitchCode::*;
+        let ascending: Vec<(PitchCode, i8)> = [N1, N1s, N2, N2s, N3, N4, N4s, N5, N5s, N6, N6s, N7]
+            .into_iter().map(|p| (p, 0)).chain([(N1, 1)]).collect();
+        let up_a_semitone = |spell| transpose_melody(&ascending, N1s, TranspositionOptions { spell });
+
+        // Fixed spelling alternates between sharps and flats
+        let fixed = up_a_semitone(Spelling::Fixed);
+        assert!(fixed.contains(&(N1s, 0)) && fixed.contains(&(N3b, 0)));
+
+        let smart = up_a_semitone(Spelling::Smart);
+        assert_eq!(smart.iter().map(|(p, _)| *p).collect::<Vec<_>>(),
+            vec![N1s, N2, N2s, N3, N4, N4s, N5, N5s, N6, N6s, N7, N1, N1s]);
+        assert_eq!(smart.last(), Some(&(N1s, 1)));
+
+        // The same run coming down is spelled with flats
+        let descending: Vec<(PitchCode, i8)> = ascending.iter().rev().copied().collect();
+        let smart = transpose_melody(&descending, N1s, TranspositionOptions { spell: Spelling::Smart });
+        assert!(smart.iter().all(|(p, _)| pitchcode_to_scale_position(*p).1 <= 0), "{:?}", smart);
+        assert_eq!(serde_json::from_str::<TranspositionOptions>(r#"{"spell": "Smart"}"#).unwrap().spell, Spelling::Smart);
+    }
+}
@@ -1,12 +1,26 @@
 /// VexFlow JavaScript code generator
 /// Generates self-executing JavaScript that creates VexFlow notation
 
-use crate::parse::model::{Beat, BeatElement, Note, Stave, StaveLine, ContentElement};
+use crate::parse::model::{Barline, Beat, BeatElement, Clef, Note, Stave, StaveLine, ContentElement};
+use crate::rhythm::beaming::{split_beam_groups, BeamOverRests};
+use crate::rhythm::measures::{multi_measure_rests as multi_measure_rests_of, MeasureCounter, MeasureMarks};
+use crate::renderers::note_names::NoteNames;
+
+/// Vertical distance between the tops of the staves of a grand staff
+const GRAND_STAFF_SPACING: usize = 120;
 
 pub struct VexFlowJSGenerator {
     js_code: String,
     note_counter: usize,
     voice_counter: usize,
+    beam_over_rests: BeamOverRests,
+    note_names: NoteNames,
+    tie_from: Option<String>,         // Note written with a tie marker, waiting for the next note
+    ties: Vec<(String, String)>,      // Tied note pairs of the current stave
+    held_note: Option<(String, String)>, // Name and key of the last note, for beats that continue it
+    measure_marks: MeasureMarks,
+    key_signature: Option<String>,    // VexFlow key spec such as "D" or "F#m"
+    clef: Clef,                       // Clef of the current stave
 }
 
 impl VexFlowJSGenerator {
@@ -15,6 +29,56 @@ impl VexFlowJSGenerator {
             js_code: String::new(),
             note_counter: 0,
             voice_counter: 0,
+            beam_over_rests: BeamOverRests::default(),
+            note_names: NoteNames::default(),
+            tie_from: None,
+            ties: Vec::new(),
+            held_note: None,
+            measure_marks: MeasureMarks::default(),
+            key_signature: None,
+            clef: Clef::default(),
+        }
+    }
+
+    /// Configure the rehearsal marks and ending brackets drawn above the stave
+    pub fn with_measure_marks(mut self, measure_marks: MeasureMarks) -> Self {
+        self.measure_marks = measure_marks;
+        self
+    }
+
+    /// Configure the key signature drawn after the clef; accidentals are drawn relative to it
+    pub fn with_key_signature(mut self, key_signature: Option<String>) -> Self {
+        self.key_signature = key_signature;
+        self
+    }
+
+    /// Configure whether beams continue over rests inside a beat
+    pub fn with_beam_over_rests(mut self, beam_over_rests: BeamOverRests) -> Self {
+        self.beam_over_rests = beam_over_rests;
+        self
+    }
+
+    /// Configure note name labels drawn under each notehead
+    pub fn with_note_names(mut self, note_names: NoteNames) -> Self {
+        self.note_names = note_names;
+        self
+    }
+
+    /// Key rests are drawn at: the middle line of the stave's clef
+    fn rest_key(&self) -> &'static str {
+        match self.clef {
+            Clef::Treble => "b/4",
+            Clef::Bass => "d/3",
+            Clef::Alto => "c/4",
+            Clef::Tenor => "a/3",
+        }
+    }
+
+    /// `clef` option placing a StaveNote's keys on a stave other than treble
+    fn clef_option(&self) -> String {
+        match self.clef {
+            Clef::Treble => String::new(),
+            clef => format!(", clef: '{}'", clef.name()),
         }
     }
 
@@ -22,7 +86,42 @@ impl VexFlowJSGenerator {
         self.js_code.clear();
         self.note_counter = 0;
         self.voice_counter = 0;
+        self.open_renderer(container_id, 200);
+        self.add_stave(stave, 40);
 
+        // Close the IIFE
+        self.add_line("})();");
+
+        self.js_code.clone()
+    }
+
+    /// Staves of a grand staff stacked under each other, joined by a brace and a barline
+    /// at their left edge
+    pub fn generate_for_grand_staff(&mut self, staves: &[&Stave], container_id: &str) -> String {
+        self.js_code.clear();
+        self.note_counter = 0;
+        self.voice_counter = 0;
+        self.open_renderer(container_id, 40 + GRAND_STAFF_SPACING * staves.len());
+        self.add_line("  const staves = [];");
+        for (index, stave) in staves.iter().enumerate() {
+            // Each stave's code is in its own block so its `stave` and `formatter` don't clash
+            self.add_line("  {");
+            self.add_stave(stave, 40 + GRAND_STAFF_SPACING * index);
+            self.add_line("  staves.push(stave);");
+            self.add_line("  }");
+        }
+        if staves.len() > 1 {
+            self.add_line("  const first = staves[0], last = staves[staves.length - 1];");
+            self.add_line("  new Vex.Flow.StaveConnector(first, last).setType('brace').setContext(context).draw();");
+            self.add_line("  new Vex.Flow.StaveConnector(first, last).setType('singleLeft').setContext(context).draw();");
+        }
+        self.add_line("})();");
+
+        self.js_code.clone()
+    }
+
+    /// Open the IIFE and set up a renderer of the given height in the container
+    fn open_renderer(&mut self, container_id: &str, height: usize) {
         // Wrap in IIFE to avoid bare return statement
         self.add_line("(function() {");
 
@@ -35,20 +134,31 @@ impl VexFlowJSGenerator {
         self.add_line("  container.innerHTML = '';");
         self.add_line("");
 
-        self.add_line("  const { Renderer, Stave, StaveNote, Voice, Formatter, Tuplet, Beam } = Vex.Flow;");
+        self.add_line("  const { Renderer, Stave, StaveNote, Voice, Formatter, Tuplet, Beam, Annotation } = Vex.Flow;");
         self.add_line("");
 
         self.add_line("  const renderer = new Renderer(container, Renderer.Backends.SVG);");
         self.add_line("  const canvasWidth = 800;");
-        self.add_line("  const canvasHeight = 200;");
+        self.add_line(&format!("  const canvasHeight = {};", height));
         self.add_line("  renderer.resize(canvasWidth, canvasHeight);");
         self.add_line("  const context = renderer.getContext();");
         self.add_line("  context.scale(0.9, 0.9);");
         self.add_line("");
+    }
+
+    /// Draw a stave and its notes at height `y`, as `stave`
+    fn add_stave(&mut self, stave: &Stave, y: usize) {
+        self.tie_from = None;
+        self.ties.clear();
+        self.held_note = None;
+        self.clef = stave.clef.unwrap_or_default();
 
         // Create stave
-        self.add_line("  const stave = new Stave(10, 40, 700);");
-        self.add_line("  stave.addClef('treble');");
+        self.add_line(&format!("  const stave = new Stave(10, {}, 700);", y));
+        self.add_line(&format!("  stave.addClef('{}');", self.clef.name()));
+        if let Some(key_signature) = &self.key_signature {
+            self.add_line(&format!("  stave.addKeySignature('{}');", key_signature));
+        }
         self.add_line("  stave.setContext(context);");
         self.add_line("  stave.draw();");
         self.add_line("");
@@ -57,36 +167,97 @@ impl VexFlowJSGenerator {
         let mut all_notes = Vec::new();
         let mut tuplets = Vec::new();
         let mut beams = Vec::new();
+        let mut multi_measure_rests = Vec::new();
+        let mut measures = MeasureCounter::default();
+        // First and last note of each measure, for marks placed by measure number
+        let mut measure_notes: Vec<(usize, String, String)> = Vec::new();
+        let mut feather_warned = false;
 
         for line in &stave.lines {
             if let StaveLine::ContentLine(content_line) = line {
-                for element in &content_line.elements {
+                let content_line = &crate::document::ties::with_matching_ties(content_line);
+                let line_rests = multi_measure_rests_of(&content_line.elements);
+                for (index, element) in content_line.elements.iter().enumerate() {
                     match element {
                         ContentElement::Beat(beat) => {
-                            if beat.is_tuplet == Some(true) {
+                            if beat.feather.is_some() && !feather_warned {
+                                self.add_line("  console.warn('Feathered beams are drawn as plain beams');");
+                                feather_warned = true;
+                            }
+                            let beat_note_names = if beat.is_tuplet == Some(true) {
                                 let (tuplet_note_names, tuplet_obj) = self.generate_tuplet(beat);
                                 if let Some(tuplet_name) = tuplet_obj {
                                     tuplets.push(tuplet_name);
                                 }
                                 // Tuplets handle their own beaming/bracketing, no additional beams needed
-                                all_notes.extend(tuplet_note_names);
+                                tuplet_note_names
                             } else {
                                 let beat_notes = self.generate_beat_notes(beat);
-                                // Only beam if the beat contains beamable notes (eighth or shorter)
-                                if beat_notes.len() >= 2 && self.is_beat_beamable(beat) {
-                                    beams.push(beat_notes.clone());
+                                // Only beam if the beat contains beamable notes (eighth or shorter);
+                                // rests split the beam unless beaming over rests is enabled
+                                if self.is_beat_beamable(beat) {
+                                    beams.extend(split_beam_groups(&beat_notes, self.beam_over_rests));
+                                }
+                                beat_notes.into_iter().map(|(name, _)| name).collect()
+                            };
+                            if let (Some(chord), Some(first)) = (beat.chord.as_deref(), beat_note_names.first()) {
+                                self.add_chord_symbol(first, chord);
+                            }
+                            if beat.elements.iter().any(|e| matches!(e, BeatElement::BreathMark(_))) {
+                                if let Some(last) = beat_note_names.last() {
+                                    self.add_breath_mark(last);
+                                }
+                            }
+                            let measure = measures.beat();
+                            if let (Some(first), Some(last)) = (beat_note_names.first(), beat_note_names.last()) {
+                                match measure_notes.last_mut() {
+                                    Some((m, _, measure_last)) if *m == measure => *measure_last = last.clone(),
+                                    _ => measure_notes.push((measure, first.clone(), last.clone())),
                                 }
-                                all_notes.extend(beat_notes);
                             }
+                            all_notes.extend(beat_note_names);
                         }
-                        ContentElement::Barline(_) => {
-                            // Skip barlines for now
+                        ContentElement::Barline(barline) => {
+                            measures.barline();
+                            // The stave already opens with a barline; repeat signs are kept
+                            if all_notes.is_empty() && matches!(barline, Barline::Single(_)) {
+                                continue;
+                            }
+                            let bar_name = format!("bar_{}", self.note_counter);
+                            self.note_counter += 1;
+                            self.add_line(&format!(
+                                "  const {} = new Vex.Flow.BarNote(Vex.Flow.Barline.type.{});",
+                                bar_name, barline_type_name(barline)
+                            ));
+                            all_notes.push(bar_name);
                         }
                         ContentElement::Whitespace(_) => {
                             // Skip whitespace
                         }
                         ContentElement::UnknownToken(_) => {
-                            // Skip unknown tokens (behave like whitespace)
+                            // Multi-measure rests hold their place with a ghost note, breath marks
+                            // go after the note before them and meter changes in place; other
+                            // unknown tokens behave like whitespace
+                            if let Some(&measures) = line_rests.get(&index) {
+                                let rest_name = format!("mmrest_{}", self.note_counter);
+                                self.note_counter += 1;
+                                self.add_line(&format!("  const {} = new Vex.Flow.GhostNote({{ duration: 'w' }});", rest_name));
+                                multi_measure_rests.push((all_notes.len(), measures));
+                                all_notes.push(rest_name);
+                            } else if crate::rhythm::analyzer::is_breath_token(element) {
+                                if let Some(last) = all_notes.last().filter(|name| !name.starts_with("bar_") && !name.starts_with("time_")).cloned() {
+                                    self.add_breath_mark(&last);
+                                }
+                            } else if let Some(time_signature) = crate::rhythm::measures::meter_change(element) {
+                                // A meter change is drawn in the stave where its measure starts
+                                let time_name = format!("time_{}", self.note_counter);
+                                self.note_counter += 1;
+                                self.add_line(&format!(
+                                    "  const {} = new Vex.Flow.TimeSigNote('{}/{}');",
+                                    time_name, time_signature.beats, time_signature.unit
+                                ));
+                                all_notes.push(time_name);
+                            }
                         }
                     }
                 }
@@ -105,6 +276,11 @@ impl VexFlowJSGenerator {
 
             let notes_array = format!("[{}]", all_notes.join(", "));
             self.add_line(&format!("  {}.addTickables({});", voice_name, notes_array));
+            // Keys carry their spelling (e.g. 'F#/4'); draw only the accidentals the key signature doesn't imply
+            self.add_line(&format!(
+                "  Vex.Flow.Accidental.applyAccidentals([{}], '{}');",
+                voice_name, self.key_signature.as_deref().unwrap_or("C")
+            ));
             self.add_line("");
 
             // Format and draw
@@ -118,6 +294,23 @@ impl VexFlowJSGenerator {
             self.add_line("");
         }
 
+        // Draw multi-measure rests between the tickables around their ghost notes,
+        // with the measure count centered above
+        for (position, measures) in multi_measure_rests {
+            let left = match position.checked_sub(1).map(|i| &all_notes[i]) {
+                Some(before) => format!("{0}.getAbsoluteX() + {0}.getWidth()", before),
+                None => "stave.getNoteStartX()".to_string(),
+            };
+            let right = match all_notes.get(position + 1) {
+                Some(after) => format!("{}.getAbsoluteX()", after),
+                None => "stave.getNoteEndX()".to_string(),
+            };
+            self.add_line(&format!(
+                "  new Vex.Flow.MultiMeasureRest({}, {{ show_number: true, number_line: -0.5, padding_left: {} - stave.getX(), padding_right: stave.getX() + stave.getWidth() - ({}) }}).setStave(stave).setContext(context).draw();",
+                measures, left, right
+            ));
+        }
+
         // Draw tuplets
         if !tuplets.is_empty() {
             self.add_line("  // Draw tuplets");
@@ -127,6 +320,28 @@ impl VexFlowJSGenerator {
             self.add_line("");
         }
 
+        let measure_marks = stave.lines.iter()
+            .filter_map(|line| match line {
+                StaveLine::ContentLine(content_line) => Some(&content_line.elements),
+                _ => None,
+            })
+            .fold(self.measure_marks.clone(), |marks, elements| marks.with_inline_endings(elements));
+        if !measure_marks.is_empty() {
+            self.draw_measure_marks(&measure_marks, &measure_notes);
+        }
+
+        // Draw explicit ties
+        if !self.ties.is_empty() {
+            self.add_line("  // Draw ties");
+            for (first, last) in self.ties.clone() {
+                self.add_line(&format!(
+                    "  new Vex.Flow.StaveTie({{ first_note: {}, last_note: {}, first_indices: [0], last_indices: [0] }}).setContext(context).draw();",
+                    first, last
+                ));
+            }
+            self.add_line("");
+        }
+
         // Draw beams
         if !beams.is_empty() {
             self.add_line("  // Draw beams");
@@ -141,11 +356,6 @@ impl VexFlowJSGenerator {
                 self.add_line(&format!("    {}.setContext(context).draw();", beam_name));
             }
         }
-
-        // Close the IIFE
-        self.add_line("})();");
-
-        self.js_code.clone()
     }
 
     fn generate_tuplet(&mut self, beat: &Beat) -> (Vec<String>, Option<String>) {
@@ -165,9 +375,12 @@ impl VexFlowJSGenerator {
                     );
 
                     self.add_line(&format!(
-                        "  const {} = new StaveNote({{ keys: ['{}'], duration: '{}' }});",
-                        note_name, key, duration
+                        "  const {} = new StaveNote({{ keys: ['{}'], duration: '{}'{} }});",
+                        note_name, key, duration, self.clef_option()
                     ));
+                    self.add_grace_notes(&note_name, note);
+                    self.add_note_name_label(&note_name, note);
+                    self.track_tie(&note_name, note);
                     note_names.push(note_name.clone());
                 }
                 BeatElement::Dash(dash) => {
@@ -177,13 +390,27 @@ impl VexFlowJSGenerator {
                         let duration = self.duration_to_vexflow_duration(numer, denom);
 
                         self.add_line(&format!(
-                            "  const {} = new StaveNote({{ keys: ['b/4'], duration: '{}r' }});",
-                            rest_name, duration
+                            "  const {} = new StaveNote({{ keys: ['{}'], duration: '{}r'{} }});",
+                            rest_name, self.rest_key(), duration, self.clef_option()
                         ));
                         note_names.push(rest_name.clone());
                     }
                     // Skip dashes without rhythm data (extenders)
                 }
+                BeatElement::Rest(rest) => {
+                    self.tie_from = None;
+                    let rest_name = self.next_note_name();
+                    let duration = self.duration_to_vexflow_duration(
+                        rest.numerator.unwrap_or(1),
+                        rest.denominator.unwrap_or(4)
+                    );
+
+                    self.add_line(&format!(
+                        "  const {} = new StaveNote({{ keys: ['{}'], duration: '{}r'{} }});",
+                        rest_name, self.rest_key(), duration, self.clef_option()
+                    ));
+                    note_names.push(rest_name.clone());
+                }
                 _ => {
                     // Skip other elements
                 }
@@ -211,8 +438,12 @@ impl VexFlowJSGenerator {
         (note_names, tuplet_obj)
     }
 
-    fn generate_beat_notes(&mut self, beat: &Beat) -> Vec<String> {
+    /// Generate notes and rests for a beat; each name is paired with `true` for rests
+    fn generate_beat_notes(&mut self, beat: &Beat) -> Vec<(String, bool)> {
         let mut note_names = Vec::new();
+        if let Some(continuation) = self.generate_tied_continuation(beat) {
+            note_names.push((continuation, false));
+        }
 
         for element in &beat.elements {
             match element {
@@ -227,10 +458,13 @@ impl VexFlowJSGenerator {
                     );
 
                     self.add_line(&format!(
-                        "  const {} = new StaveNote({{ keys: ['{}'], duration: '{}' }});",
-                        note_name, key, duration
+                        "  const {} = new StaveNote({{ keys: ['{}'], duration: '{}'{} }});",
+                        note_name, key, duration, self.clef_option()
                     ));
-                    note_names.push(note_name);
+                    self.add_grace_notes(&note_name, note);
+                    self.add_note_name_label(&note_name, note);
+                    self.track_tie(&note_name, note);
+                    note_names.push((note_name, false));
                 }
                 BeatElement::Dash(dash) => {
                     // Only process dashes that have rhythm data (starting dashes)
@@ -239,13 +473,29 @@ impl VexFlowJSGenerator {
                         let duration = self.duration_to_vexflow_duration(numer, denom);
 
                         self.add_line(&format!(
-                            "  const {} = new StaveNote({{ keys: ['b/4'], duration: '{}r' }});",
-                            rest_name, duration
+                            "  const {} = new StaveNote({{ keys: ['{}'], duration: '{}r'{} }});",
+                            rest_name, self.rest_key(), duration, self.clef_option()
                         ));
-                        note_names.push(rest_name);
+                        note_names.push((rest_name, true));
+                        self.held_note = None;
                     }
                     // Skip dashes without rhythm data (extenders)
                 }
+                BeatElement::Rest(rest) => {
+                    self.tie_from = None;
+                    self.held_note = None;
+                    let rest_name = self.next_note_name();
+                    let duration = self.duration_to_vexflow_duration(
+                        rest.numerator.unwrap_or(1),
+                        rest.denominator.unwrap_or(4)
+                    );
+
+                    self.add_line(&format!(
+                        "  const {} = new StaveNote({{ keys: ['{}'], duration: '{}r'{} }});",
+                        rest_name, self.rest_key(), duration, self.clef_option()
+                    ));
+                    note_names.push((rest_name, true));
+                }
                 _ => {
                     // Skip other elements
                 }
@@ -276,6 +526,13 @@ impl VexFlowJSGenerator {
                         }
                     }
                 }
+                BeatElement::Rest(rest) => {
+                    if let Some(denom) = rest.denominator {
+                        if denom < 8 {
+                            return false;
+                        }
+                    }
+                }
                 _ => {}
             }
         }
@@ -323,7 +580,7 @@ impl VexFlowJSGenerator {
         };
 
         let vexflow_octave = 4 + octave;
-        let key = format!("{}/{}", base_note, vexflow_octave);
+        let key = format!("{}{}/{}", base_note, accidental.unwrap_or(""), vexflow_octave);
 
         let accidentals = if let Some(acc) = accidental {
             vec![acc.to_string()]
@@ -361,6 +618,139 @@ impl VexFlowJSGenerator {
     }
 
 
+    /// Boxed rehearsal marks over the first note of their measures and ending brackets
+    /// from the first note of their first measure to the last note of their last one
+    fn draw_measure_marks(&mut self, measure_marks: &MeasureMarks, measure_notes: &[(usize, String, String)]) {
+        let notes_of = |measure: usize| measure_notes.iter().find(|(m, _, _)| *m == measure);
+        self.add_line("  // Draw rehearsal marks and endings");
+        self.add_line("  function drawRehearsalMark(note, mark) {");
+        self.add_line("    const x = note.getAbsoluteX() - 4;");
+        self.add_line("    const y = stave.getYForTopText(3);");
+        self.add_line("    context.save();");
+        self.add_line("    context.setFont('Arial', 12, 'bold');");
+        self.add_line("    const width = context.measureText(mark).width + 8;");
+        self.add_line("    context.rect(x, y - 13, width, 17);");
+        self.add_line("    context.stroke();");
+        self.add_line("    context.fillText(mark, x + 4, y);");
+        self.add_line("    context.restore();");
+        self.add_line("  }");
+        self.add_line("  function drawVolta(first, last, label) {");
+        self.add_line("    const x1 = first.getAbsoluteX() - 6;");
+        self.add_line("    const x2 = last.getAbsoluteX() + last.getWidth() + 6;");
+        self.add_line("    const y = stave.getYForTopText(1);");
+        self.add_line("    context.save();");
+        self.add_line("    context.beginPath();");
+        self.add_line("    context.moveTo(x1, y + 12);");
+        self.add_line("    context.lineTo(x1, y);");
+        self.add_line("    context.lineTo(x2, y);");
+        self.add_line("    context.stroke();");
+        self.add_line("    context.setFont('Arial', 10, '');");
+        self.add_line("    context.fillText(label, x1 + 4, y + 11);");
+        self.add_line("    context.restore();");
+        self.add_line("  }");
+        for (measure, first, _) in measure_notes {
+            if let Some(mark) = measure_marks.rehearsal_mark(*measure) {
+                let line = format!("  drawRehearsalMark({}, '{}');", first, mark.replace('\'', "\\'"));
+                self.add_line(&line);
+            }
+        }
+        for ending in measure_marks.endings() {
+            if let (Some((_, first, _)), Some((_, _, last))) = (notes_of(ending.first), notes_of(ending.last)) {
+                let line = format!("  drawVolta({}, {}, '{}');", first, last, ending.label());
+                self.add_line(&line);
+            }
+        }
+        self.add_line("");
+    }
+
+    /// Attach a note's grace notes as a slurred group of small slashed eighths before it
+    fn add_grace_notes(&mut self, note_name: &str, note: &Note) {
+        if note.grace_notes.is_empty() {
+            return;
+        }
+        let keys: Vec<String> = note.grace_notes.iter()
+            .map(|grace| self.degree_to_vexflow_key(self.pitch_code_to_degree(grace.pitch_code), grace.octave).0)
+            .collect();
+        let duration = if keys.len() == 1 { "8" } else { "16" };
+        let graces = keys.iter()
+            .map(|key| format!("new Vex.Flow.GraceNote({{ keys: ['{}'], duration: '{}', slash: true }})", key, duration))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.add_line(&format!(
+            "  {}.addModifier(new Vex.Flow.GraceNoteGroup([{}], true).beamNotes(), 0);",
+            note_name, graces
+        ));
+    }
+
+    /// Attach the configured note name label below a note
+    fn add_note_name_label(&mut self, note_name: &str, note: &Note) {
+        if let Some(label) = self.note_names.label(note.pitch_code) {
+            self.add_line(&format!(
+                "  {}.addModifier(new Annotation('{}').setVerticalJustification(Annotation.VerticalJustify.BOTTOM), 0);",
+                note_name, label
+            ));
+        }
+    }
+
+    /// Write a beat's chord symbol above its first note
+    fn add_chord_symbol(&mut self, note_name: &str, chord: &str) {
+        self.add_line(&format!(
+            "  {}.addModifier(new Annotation('{}').setVerticalJustification(Annotation.VerticalJustify.TOP), 0);",
+            note_name, chord.replace('\\', "\\\\").replace('\'', "\\'")
+        ));
+    }
+
+    /// Write a breath mark above and after a note
+    fn add_breath_mark(&mut self, note_name: &str) {
+        self.add_line(&format!(
+            "  {}.addModifier(new Annotation(',').setVerticalJustification(Annotation.VerticalJustify.TOP).setJustification(Annotation.Justify.RIGHT), 0);",
+            note_name
+        ));
+    }
+
+    /// Pair a note with the preceding note's tie marker, and open a tie if it carries one
+    fn track_tie(&mut self, note_name: &str, note: &Note) {
+        if let Some(first) = self.tie_from.take() {
+            self.ties.push((first, note_name.to_string()));
+        }
+        if note.tie.is_some() {
+            self.tie_from = Some(note_name.to_string());
+        }
+        self.held_note = Some((note_name.to_string(), self.note_to_vexflow_key(note).0));
+    }
+
+    /// Draw the leading dashes of a beat tied to the previous one as the held note,
+    /// tied back to it - also across a barline
+    fn generate_tied_continuation(&mut self, beat: &Beat) -> Option<String> {
+        if beat.tied_to_previous != Some(true) {
+            return None;
+        }
+        let (held_name, key) = self.held_note.clone()?;
+        let leading_dashes = beat.elements.iter()
+            .take_while(|element| !matches!(element, BeatElement::Note(_) | BeatElement::Rest(_)))
+            .filter(|element| matches!(element, BeatElement::Dash(_)))
+            .count();
+        let divisions = beat.divisions.filter(|&d| d > 0)?;
+        if leading_dashes == 0 {
+            return None;
+        }
+
+        let beat_duration = beat.total_duration.unwrap_or(fraction::Fraction::new(1u64, 4u64));
+        let held = fraction::Fraction::new(leading_dashes as u64, divisions as u64) * beat_duration;
+        let duration = self.duration_to_vexflow_duration(
+            *held.numer().unwrap() as u32,
+            *held.denom().unwrap() as u32
+        );
+        let note_name = self.next_note_name();
+        self.add_line(&format!(
+            "  const {} = new StaveNote({{ keys: ['{}'], duration: '{}'{} }});",
+            note_name, key, duration, self.clef_option()
+        ));
+        self.ties.push((held_name, note_name.clone()));
+        self.held_note = Some((note_name.clone(), key));
+        Some(note_name)
+    }
+
     fn next_note_name(&mut self) -> String {
         let name = format!("note_{}", self.note_counter);
         self.note_counter += 1;
@@ -377,4 +767,174 @@ impl VexFlowJSGenerator {
         self.js_code.push_str(line);
         self.js_code.push('\n');
     }
-}
\ No newline at end of file
+}
+
+/// VexFlow `Barline.type` drawn for a barline; repeat signs put their dots on the repeated side
+pub(crate) fn barline_type_name(barline: &Barline) -> &'static str {
+    match barline {
+        Barline::Single(_) => "SINGLE",
+        Barline::Double(_) => "DOUBLE",
+        Barline::Final(_) => "END",
+        Barline::RepeatStart(_) => "REPEAT_BEGIN",
+        Barline::RepeatEnd(_) => "REPEAT_END",
+        Barline::RepeatBoth(_) => "REPEAT_BOTH",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::TimeSignature;
+    use crate::parse::model::NotationSystem;
+    use crate::rhythm::analyzer::BeatUnit;
+
+    fn stave(text: &str) -> Stave {
+        stave_in(text, BeatUnit::default())
+    }
+
+    fn stave_in(text: &str, beat_unit: BeatUnit) -> Stave {
+        let mut content_line = crate::document::line_parser::content_line_parser::unused_parse_content_line(
+            text, 0, NotationSystem::Number, 0,
+        ).unwrap();
+        crate::rhythm::analyzer::analyze_content_line_rhythm_in(&mut content_line.elements, beat_unit).unwrap();
+        Stave {
+            id: uuid::Uuid::new_v4(),
+            value: Some(text.to_string()),
+            notation_system: NotationSystem::Number,
+            line: 0,
+            column: 0,
+            index_in_line: 0,
+            index_in_doc: 0,
+            clef: None,
+            grand_staff: None,
+            lines: vec![StaveLine::ContentLine(content_line)],
+        }
+    }
+
+    #[test]
+    fn test_endings_and_rehearsal_mark() {
+        let directives = [
+            ("Rehearsal".to_string(), "A 1".to_string()),
+            ("Endings".to_string(), "1 2, 2 3".to_string()),
+        ].into_iter().collect();
+        let js = VexFlowJSGenerator::new()
+            .with_measure_marks(MeasureMarks::from_directives(&directives))
+            .generate_for_stave(&stave("|: 1 2 3 4 | 5 6 7 1 :| 2 2 2 2 |."), "output");
+
+        assert!(js.contains("new Vex.Flow.BarNote(Vex.Flow.Barline.type.REPEAT_BEGIN)"), "{}", js);
+        assert!(js.contains("new Vex.Flow.BarNote(Vex.Flow.Barline.type.REPEAT_END)"), "{}", js);
+        assert!(js.contains("new Vex.Flow.BarNote(Vex.Flow.Barline.type.END)"), "{}", js);
+        assert!(js.contains("drawRehearsalMark(note_1, 'A');"), "{}", js);
+        assert!(js.contains("drawVolta(note_6, note_9, '1.');"), "{}", js);
+        assert!(js.contains("drawVolta(note_11, note_14, '2.');"), "{}", js);
+    }
+
+    #[test]
+    fn test_endings_marked_on_barlines() {
+        let js = VexFlowJSGenerator::new().generate_for_stave(&stave("|: 1 2 3 4 |1. 5 6 7 1 :|2. 2 2 2 2 |."), "output");
+        assert!(js.contains("drawVolta(note_6, note_9, '1.');"), "{}", js);
+        assert!(js.contains("drawVolta(note_11, note_14, '2.');"), "{}", js);
+    }
+
+    #[test]
+    fn test_grace_note_group() {
+        let js = VexFlowJSGenerator::new().generate_for_stave(&stave("(2)1 (54)3"), "output");
+        assert!(js.contains("note_0.addModifier(new Vex.Flow.GraceNoteGroup([new Vex.Flow.GraceNote({ keys: ['D/4'], duration: '8', slash: true })], true).beamNotes(), 0);"), "{}", js);
+        assert!(js.contains("keys: ['G/4'], duration: '16'"), "{}", js);
+        assert!(js.contains("keys: ['F/4'], duration: '16'"), "{}", js);
+    }
+
+    #[test]
+    fn test_key_signature_and_spelling() {
+        let js = VexFlowJSGenerator::new()
+            .with_key_signature(Some("D".to_string()))
+            .generate_for_stave(&stave("4# 4 1"), "output");
+
+        assert!(js.contains("stave.addKeySignature('D');"), "{}", js);
+        assert!(js.contains("keys: ['F#/4']"), "{}", js);
+        assert!(js.contains("keys: ['F/4']"), "{}", js);
+        assert!(js.contains("Vex.Flow.Accidental.applyAccidentals([voice_0], 'D');"), "{}", js);
+    }
+
+    #[test]
+    fn test_beaming_follows_meter() {
+        let js_in = |time: &str| {
+            let beat_unit = BeatUnit::from_time_signature(TimeSignature::parse(time).unwrap());
+            VexFlowJSGenerator::new().generate_for_stave(&stave_in("123 456", beat_unit), "output")
+        };
+
+        // Quarter-note beats make triplets of the threes; dotted-quarter beats beam plain eighths
+        let simple = js_in("4/4");
+        assert!(simple.contains("new Tuplet([note_0, note_1, note_2]"), "{}", simple);
+        assert!(!simple.contains("new Beam("), "{}", simple);
+        let compound = js_in("6/8");
+        assert!(!compound.contains("new Tuplet("), "{}", compound);
+        assert!(compound.contains("new Beam([note_0, note_1, note_2]);"), "{}", compound);
+        assert!(compound.contains("new Beam([note_3, note_4, note_5]);"), "{}", compound);
+    }
+
+    #[test]
+    fn test_tie_across_barline() {
+        let js = VexFlowJSGenerator::new().generate_for_stave(&stave("1 - | - 2"), "output");
+
+        assert!(js.contains("const note_1 = new StaveNote({ keys: ['C/4'], duration: 'q' });"), "{}", js);
+        assert!(js.contains("const note_3 = new StaveNote({ keys: ['C/4'], duration: 'q' });"), "{}", js);
+        assert!(js.contains("new Vex.Flow.StaveTie({ first_note: note_0, last_note: note_1,"), "{}", js);
+        assert!(js.contains("new Vex.Flow.StaveTie({ first_note: note_1, last_note: note_3,"), "{}", js);
+    }
+
+    #[test]
+    fn test_bass_clef_keeps_absolute_pitch() {
+        let stave = Stave { clef: Some(Clef::Bass), ..stave("- 1") };
+        let js = VexFlowJSGenerator::new().generate_for_stave(&stave, "output");
+        assert!(js.contains("stave.addClef('bass');"), "{}", js);
+        assert!(js.contains("new StaveNote({ keys: ['C/4'], duration: 'q', clef: 'bass' });"), "{}", js);
+        assert!(js.contains("new StaveNote({ keys: ['d/3'], duration: 'qr', clef: 'bass' });"), "{}", js);
+    }
+
+    #[test]
+    fn test_meter_change_draws_time_signature() {
+        let js = VexFlowJSGenerator::new().generate_for_stave(&stave("|1 2 3 4|(3/4) 5 6 7|"), "output");
+        assert!(js.contains("const time_5 = new Vex.Flow.TimeSigNote('3/4');"), "{}", js);
+        assert!(js.contains("bar_4, time_5, note_6"), "{}", js);
+    }
+
+    #[test]
+    fn test_multi_measure_rest_drawn_between_its_barlines() {
+        let js = VexFlowJSGenerator::new().generate_for_stave(&stave("|1 2 3 4| R*2 |1 2 3 4|"), "output");
+        assert!(js.contains("bar_4, mmrest_5, bar_6"), "{}", js);
+        assert!(js.contains("padding_left: bar_4.getAbsoluteX() + bar_4.getWidth() - stave.getX()"), "{}", js);
+        assert!(js.contains("padding_right: stave.getX() + stave.getWidth() - (bar_6.getAbsoluteX())"), "{}", js);
+    }
+
+    #[test]
+    fn test_explicit_rests() {
+        let js = VexFlowJSGenerator::new().generate_for_stave(&stave("r1 0 2 r-1"), "output");
+        assert!(js.contains("const note_0 = new StaveNote({ keys: ['b/4'], duration: '8r' });"), "{}", js);
+        assert!(js.contains("const note_2 = new StaveNote({ keys: ['b/4'], duration: 'qr' });"), "{}", js);
+        // Rests inside a tuplet are drawn in it
+        assert!(js.contains("const note_4 = new StaveNote({ keys: ['b/4'], duration: 'qr' });"), "{}", js);
+        assert!(js.contains("new Tuplet([note_4, note_5]"), "{}", js);
+    }
+
+    #[test]
+    fn test_breath_marks_after_notes() {
+        let js = VexFlowJSGenerator::new().generate_for_stave(&stave("1 2 ' 3 4'"), "output");
+        assert!(js.contains("note_1.addModifier(new Annotation(',')"), "{}", js);
+        assert!(js.contains("note_3.addModifier(new Annotation(',')"), "{}", js);
+        assert_eq!(js.matches("Annotation(',')").count(), 2, "{}", js);
+    }
+
+    #[test]
+    fn test_chord_symbols_above_beats() {
+        let mut stave = stave("1 2 3 4");
+        if let StaveLine::ContentLine(content_line) = &mut stave.lines[0] {
+            crate::document::line_parser::slash_line_parser::attach_chords(content_line, "[C]   [G7]");
+        }
+        let js = VexFlowJSGenerator::new().generate_for_stave(&stave, "output");
+
+        assert!(js.contains("note_0.addModifier(new Annotation('C').setVerticalJustification(Annotation.VerticalJustify.TOP), 0);"), "{}", js);
+        assert!(js.contains("note_3.addModifier(new Annotation('G7')"), "{}", js);
+        assert_eq!(js.matches("Annotation.VerticalJustify.TOP").count(), 2, "{}", js);
+    }
+}
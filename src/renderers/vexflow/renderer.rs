@@ -1,6 +1,11 @@
 // VexFlow renderer - generates self-executing JavaScript
-use crate::parse::model::{Document, DocumentElement};
+use crate::parse::model::{directive, Document, DocumentElement, Stave, KEY_DIRECTIVE};
+use crate::parse::line_classifier::TITLE_DIRECTIVE;
 use crate::models::Degree;
+use crate::rhythm::beaming::BeamOverRests;
+use crate::renderers::note_names::NoteNames;
+use crate::renderers::incipit::key_short_name;
+use crate::rhythm::measures::MeasureMarks;
 use super::js_generator::VexFlowJSGenerator;
 
 #[derive(Debug, Clone)]
@@ -23,17 +28,31 @@ impl VexFlowRenderer {
     
     /// Render VexFlow data directly from Document structure - generates self-executing JavaScript
     pub fn render_data_from_document(&self, document: &Document) -> serde_json::Value {
-        let mut js_generator = VexFlowJSGenerator::new();
+        let key_signature = directive(&document.directives, KEY_DIRECTIVE).and_then(key_short_name);
+        let mut js_generator = VexFlowJSGenerator::new()
+            .with_key_signature(key_signature.clone())
+            .with_beam_over_rests(BeamOverRests::from_directives(&document.directives))
+            .with_note_names(NoteNames::from_directives(&document.directives))
+            .with_measure_marks(MeasureMarks::from_directives(&document.directives));
         let mut generated_js = String::new();
 
         // Generate JavaScript for each stave
-        let mut stave_count = 0;
-        for element in &document.elements {
-            if let DocumentElement::Stave(stave) = element {
-                let stave_js = js_generator.generate_for_stave(stave, "vexflow_svg-output");
-                generated_js = stave_js; // For now, just use the last stave
-                stave_count += 1;
-            }
+        let staves: Vec<&Stave> = document.elements.iter()
+            .filter_map(|element| match element {
+                DocumentElement::Stave(stave) => Some(stave),
+                _ => None,
+            })
+            .collect();
+        let stave_count = staves.len();
+        if let Some(last) = staves.last() {
+            // For now, just use the last stave, stacked with the rest of its grand staff
+            generated_js = match last.grand_staff {
+                Some(grand_staff) => {
+                    let grand_staves: Vec<&Stave> = staves.iter().copied().filter(|stave| stave.grand_staff == Some(grand_staff)).collect();
+                    js_generator.generate_for_grand_staff(&grand_staves, "vexflow_svg-output")
+                }
+                None => js_generator.generate_for_stave(last, "vexflow_svg-output"),
+            };
         }
 
         // If no staves, generate empty stave JavaScript
@@ -43,9 +62,14 @@ impl VexFlowRenderer {
 
         serde_json::json!({
             "vexflow_js": generated_js,
-            "title": document.title.as_ref().or_else(|| document.directives.get("title")),
-            "author": document.author.as_ref().or_else(|| document.directives.get("author")),
-            "stave_count": stave_count
+            "title": document.title.as_deref().or_else(|| directive(&document.directives, TITLE_DIRECTIVE)),
+            "author": document.composer(),
+            "stave_count": stave_count,
+            "keySignature": key_signature,
+            "staves": staves.iter().map(|stave| serde_json::json!({
+                "notes": process_stave_to_vexflow(stave),
+                "key_signature": key_signature.as_deref().unwrap_or("C")
+            })).collect::<Vec<_>>()
         })
     }
 
@@ -85,7 +109,9 @@ fn process_stave_to_vexflow(stave: &crate::parse::model::Stave) -> Vec<serde_jso
 
     for line in &stave.lines {
         if let crate::parse::model::StaveLine::ContentLine(content_line) = line {
-            for element in &content_line.elements {
+            let content_line = &crate::document::ties::with_matching_ties(content_line);
+            let multi_measure_rests = crate::rhythm::measures::multi_measure_rests(&content_line.elements);
+            for (index, element) in content_line.elements.iter().enumerate() {
                 match element {
                     crate::parse::model::ContentElement::Beat(beat) => {
                         let beat_notes = process_beat_to_vexflow(beat);
@@ -108,24 +134,29 @@ fn process_stave_to_vexflow(stave: &crate::parse::model::Stave) -> Vec<serde_jso
                         }
                     }
                     crate::parse::model::ContentElement::Barline(barline) => {
-                        let barline_type = match barline {
-                            crate::parse::model::Barline::Single(_) => "single",
-                            crate::parse::model::Barline::Double(_) => "double",
-                            crate::parse::model::Barline::Final(_) => "end",
-                            crate::parse::model::Barline::RepeatStart(_) => "repeat-start",
-                            crate::parse::model::Barline::RepeatEnd(_) => "repeat-end",
-                            crate::parse::model::Barline::RepeatBoth(_) => "repeat-both",
-                        };
+                        // Named after the VexFlow `Barline.type` constant drawing it
                         notes.push(serde_json::json!({
                             "type": "BarLine",
-                            "bar_type": barline_type
+                            "bar_type": super::js_generator::barline_type_name(barline)
                         }));
                     }
                     crate::parse::model::ContentElement::Whitespace(_) => {
                         // Skip whitespace
                     }
                     crate::parse::model::ContentElement::UnknownToken(_) => {
-                        // Skip unknown tokens (behave like whitespace)
+                        // Multi-measure rests carry the measures they consume and breath marks
+                        // are symbols; skip other unknown tokens (behave like whitespace)
+                        if let Some(&measures) = multi_measure_rests.get(&index) {
+                            notes.push(serde_json::json!({
+                                "type": "MultiMeasureRest",
+                                "measures": measures
+                            }));
+                        } else if crate::rhythm::analyzer::is_breath_token(element) {
+                            notes.push(serde_json::json!({
+                                "type": "Symbol",
+                                "symbol": "breathmark"
+                            }));
+                        }
                     }
                 }
             }
@@ -187,8 +218,21 @@ fn process_beat_to_vexflow(beat: &crate::parse::model::Beat) -> Vec<serde_json::
                     note_obj["dots"] = dots.into();
                 }
 
+                if note.tie.is_some() {
+                    // Tied to the next note in the stave
+                    note_obj["tie"] = true.into();
+                }
+
                 if !accidentals.is_empty() {
-                    note_obj["accidentals"] = accidentals.into();
+                    note_obj["accidentals"] = if note.cautionary {
+                        // Object form lets the client draw the accidental in parentheses
+                        accidentals.into_iter()
+                            .map(|acc| serde_json::json!({ "accidental": acc, "index": 0, "cautionary": true }))
+                            .collect::<Vec<_>>()
+                            .into()
+                    } else {
+                        accidentals.into()
+                    };
                 }
 
                 elements.push(note_obj);
@@ -327,3 +371,39 @@ fn pitch_code_to_degree(pitch_code: crate::models::PitchCode) -> crate::models::
         N7bb => Degree::N7bb, N7b => Degree::N7b, N7 => Degree::N7, N7s => Degree::N7s, N7ss => Degree::N7ss,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_signature_in_vexflow_data() {
+        let document = crate::pipeline::document_from_notation("Key: D major\n\n| 4# 5 |\n").unwrap();
+        let data = VexFlowRenderer::new().render_data_from_document(&document);
+        assert_eq!(data["keySignature"], "D");
+        let js = data["vexflow_js"].as_str().unwrap();
+        assert!(js.contains("stave.addKeySignature('D');"), "{}", js);
+        assert!(js.contains("keys: ['F#/4']"), "{}", js);
+    }
+
+    #[test]
+    fn test_repeat_barlines_in_vexflow_data() {
+        let document = crate::pipeline::document_from_notation("|: 1 2 :|\n").unwrap();
+        let data = VexFlowRenderer::new().render_data_from_document(&document);
+        let bar_types: Vec<&str> = data["staves"][0]["notes"].as_array().unwrap().iter()
+            .filter(|element| element["type"] == "BarLine")
+            .map(|element| element["bar_type"].as_str().unwrap())
+            .collect();
+        assert_eq!(bar_types, ["REPEAT_BEGIN", "REPEAT_END"]);
+    }
+
+    #[test]
+    fn test_grand_staff_staves_are_stacked() {
+        let document = crate::pipeline::document_from_notation("{\nclef: treble\n|1 2 3 4|\nclef: bass\n|1 2 3 4|\n}\n").unwrap();
+        let js = VexFlowRenderer::new().render_data_from_document(&document)["vexflow_js"].as_str().unwrap().to_string();
+        assert!(js.contains("new Stave(10, 40, 700)"), "{}", js);
+        assert!(js.contains("new Stave(10, 160, 700)"), "{}", js);
+        assert!(js.contains("setType('brace')"), "{}", js);
+        assert!(js.contains("stave.addClef('bass');"), "{}", js);
+    }
+}
@@ -5,8 +5,16 @@
 pub mod types;
 pub mod converters;
 pub mod analyzer;
+pub mod beaming;
+pub mod measures;
+pub mod tala;
+pub mod pickup;
 
 // Re-export main functionality
 pub use types::*;
 pub use converters::*;
-pub use analyzer::*;
\ No newline at end of file
+pub use analyzer::*;
+pub use beaming::*;
+pub use measures::*;
+pub use tala::*;
+pub use pickup::*;
\ No newline at end of file
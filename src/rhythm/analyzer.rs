@@ -1,18 +1,90 @@
 // Rhythm analyzer FSM based on bak.src/rhythm_fsm.rs
 // Adapted to work with current parse model structures
-use crate::parse::model::{Document, DocumentElement, StaveLine, ContentElement, Beat, BeatElement};
+use crate::parse::model::{directive, Document, DocumentElement, StaveLine, ContentElement, Barline, Beat, BeatElement};
+use crate::rhythm::measures::{EmptyMeasure, resolve_empty_measures};
+use crate::analysis::TimeSignature;
 use fraction::Fraction;
+use std::collections::HashMap;
+
+/// Directive giving the length of a beat as a fraction of a whole note
+/// (e.g. `BeatUnit: 3/8` for dotted-quarter beats)
+pub const BEAT_UNIT_DIRECTIVE: &str = "BeatUnit";
+
+/// Length of one beat, the space-separated group the FSM divides into subdivisions.
+/// A quarter note unless the time signature or the `BeatUnit` directive says otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BeatUnit(pub Fraction);
+
+impl Default for BeatUnit {
+    fn default() -> Self {
+        BeatUnit(Fraction::new(1u64, 4u64))
+    }
+}
+
+impl BeatUnit {
+    /// Compound meters (6/8, 9/8, 12/8) beat in dotted notes of three; simple meters
+    /// in the time signature's note value
+    pub fn from_time_signature(time_signature: TimeSignature) -> Self {
+        let unit = time_signature.unit as u64;
+        let compound = time_signature.beats > 3 && time_signature.beats.is_multiple_of(3) && unit >= 8;
+        BeatUnit(Fraction::new(if compound { 3u64 } else { 1u64 }, unit))
+    }
+
+    /// The `BeatUnit` directive, else the unit of the `Time` directive, else a quarter
+    pub fn from_directives(directives: &HashMap<String, String>) -> Self {
+        let explicit = directive(directives, BEAT_UNIT_DIRECTIVE)
+            .and_then(|value| {
+                let (numer, denom) = value.split_once('/')?;
+                let numer: u64 = numer.trim().parse().ok().filter(|&n| n > 0)?;
+                let denom: u64 = denom.trim().parse().ok().filter(|&d| d > 0)?;
+                Some(BeatUnit(Fraction::new(numer, denom)))
+            });
+        explicit
+            .or_else(|| TimeSignature::declared(directives).map(Self::from_time_signature))
+            .unwrap_or_default()
+    }
+
+    /// Whether `divisions` equal subdivisions only fit the beat as a tuplet: in a plain
+    /// beat each subdivision is a standard or dotted note value
+    fn is_tuplet(&self, divisions: usize) -> bool {
+        divisions > 0 && !self.is_plain_division(divisions)
+    }
+
+    fn is_plain_division(&self, divisions: usize) -> bool {
+        let subdivision = self.0 / Fraction::from(divisions as u64);
+        is_standard_duration(subdivision) || is_standard_duration(subdivision * Fraction::new(2u64, 3u64))
+    }
+
+    /// Tuplet ratio: the divisions played in the time of the largest plain division
+    /// below them (3 in the time of 2 eighths, 5 in the time of 4 sixteenths for a
+    /// quarter-note beat)
+    fn tuplet_ratio(&self, divisions: usize) -> (usize, usize) {
+        let normal = (1..divisions).rev()
+            .find(|&n| is_standard_duration(self.0 / Fraction::from(n as u64)))
+            .unwrap_or(1);
+        (divisions, normal)
+    }
+}
+
+/// Whole, half, quarter, eighth and shorter note values (1/2^k)
+fn is_standard_duration(duration: Fraction) -> bool {
+    duration.numer() == Some(&1) && duration.denom().is_some_and(|d| d.is_power_of_two())
+}
 
 /// Analyze rhythm patterns and add duration information to the document
 /// This function modifies the document in place, adding duration info to Notes and Beats
 pub fn analyze_rhythm_into_document(document: &mut Document) -> Result<(), String> {
+    let empty_measure = EmptyMeasure::from_directives(&document.directives);
+    let beat_unit = BeatUnit::from_directives(&document.directives);
+
     // Walk through all staves and content lines
     for element in &mut document.elements {
         if let DocumentElement::Stave(stave) = element {
             for line in &mut stave.lines {
                 if let StaveLine::ContentLine(content_line) = line {
+                    resolve_empty_measures(&mut content_line.elements, empty_measure)?;
                     // Analyze rhythm directly with existing elements (no re-parsing)
-                    analyze_content_line_rhythm(&mut content_line.elements)?;
+                    analyze_content_line_rhythm_in(&mut content_line.elements, beat_unit)?;
                 }
             }
         }
@@ -21,8 +93,49 @@ pub fn analyze_rhythm_into_document(document: &mut Document) -> Result<(), Strin
 }
 
 
+/// Expand note repetition shorthand ("1*4") into separate single-note beats.
+/// Copies keep the source note's pitch and octave so octave markers apply to every note.
+/// Counts from documents not made by the parser are held to `MAX_REPEAT_COUNT`.
+pub fn expand_note_repetitions(elements: &mut Vec<ContentElement>) {
+    let mut expanded = Vec::with_capacity(elements.len());
+    for element in elements.drain(..) {
+        match element {
+            ContentElement::Beat(mut beat) if beat.repeat_count.is_some() => {
+                let count = beat.repeat_count.take().unwrap_or(1).min(crate::parse::beat::MAX_REPEAT_COUNT);
+                for i in 0..count {
+                    let mut copy = beat.clone();
+                    if i > 0 {
+                        copy.id = uuid::Uuid::new_v4();
+                        for beat_element in &mut copy.elements {
+                            if let BeatElement::Note(note) = beat_element {
+                                note.id = uuid::Uuid::new_v4();
+                            }
+                        }
+                    }
+                    expanded.push(ContentElement::Beat(copy));
+                }
+            }
+            other => expanded.push(other),
+        }
+    }
+    *elements = expanded;
+}
+
+/// A breath mark standing alone between beats ("1 2 ' 3 4"). Like whitespace it
+/// separates the beats around it, and it takes no time.
+pub fn is_breath_token(element: &ContentElement) -> bool {
+    matches!(element, ContentElement::UnknownToken(token) if token.token_value == "'")
+}
+
 /// Analyze rhythm for a content line (sequence of beats and other elements)
 pub fn analyze_content_line_rhythm(elements: &mut Vec<ContentElement>) -> Result<(), String> {
+    analyze_content_line_rhythm_in(elements, BeatUnit::default())
+}
+
+/// Analyze rhythm for a content line whose beats last `beat_unit`
+pub fn analyze_content_line_rhythm_in(elements: &mut Vec<ContentElement>, beat_unit: BeatUnit) -> Result<(), String> {
+    expand_note_repetitions(elements);
+
     // First pass: determine which beats should be tied
     let mut tie_flags = Vec::new();
     for (i, element) in elements.iter().enumerate() {
@@ -41,7 +154,7 @@ pub fn analyze_content_line_rhythm(elements: &mut Vec<ContentElement>) -> Result
             // Remove the first entry since we're processing in order
             if !tie_flags.is_empty() {
                 let (_, should_tie) = tie_flags.remove(0);
-                analyze_beat_rhythm_fsm(beat, should_tie)?;
+                analyze_beat_rhythm_fsm(beat, should_tie, beat_unit)?;
             }
         }
     }
@@ -55,7 +168,8 @@ enum State {
     InRest { rest_index: usize },
 }
 
-/// Check if a beat starting with dashes should be tied to a previous note/rest
+/// Check if a beat starting with dashes should be tied to a previous note/rest,
+/// looking back across whitespace, beats that are all dashes and plain barlines
 fn should_tie_to_previous(beat: &Beat, all_elements: &[ContentElement], current_index: usize) -> bool {
     // Check if this beat starts with dashes
     let starts_with_dash = beat.elements.first()
@@ -78,7 +192,8 @@ fn should_tie_to_previous(beat: &Beat, all_elements: &[ContentElement], current_
                             return true;
                         }
                         BeatElement::Rest(_) => {
-                            return true;
+                            // Dashes after a rest start a rest of their own, not a tie
+                            return false;
                         }
                         BeatElement::BreathMark(_) => {
                             return false;
@@ -89,12 +204,20 @@ fn should_tie_to_previous(beat: &Beat, all_elements: &[ContentElement], current_
                     }
                 }
             }
+            // A held note carries over plain barlines; repeats and the final barline end it
+            ContentElement::Barline(Barline::Single(_)) | ContentElement::Barline(Barline::Double(_)) => {
+                continue;
+            }
             ContentElement::Barline(_) => {
                 return false;
             }
             ContentElement::Whitespace(_) => {
                 continue;
             }
+            // A breath ends the held note, like a breath mark inside a beat
+            element if is_breath_token(element) => {
+                return false;
+            }
             ContentElement::UnknownToken(_) => {
                 // Unknown tokens behave like whitespace
                 continue;
@@ -106,7 +229,7 @@ fn should_tie_to_previous(beat: &Beat, all_elements: &[ContentElement], current_
 }
 
 /// FSM-based rhythm analyzer for a single beat
-fn analyze_beat_rhythm_fsm(beat: &mut Beat, should_tie: bool) -> Result<(), String> {
+fn analyze_beat_rhythm_fsm(beat: &mut Beat, should_tie: bool, beat_unit: BeatUnit) -> Result<(), String> {
     let element_count = beat.elements.len();
 
     if element_count == 0 {
@@ -166,18 +289,11 @@ fn analyze_beat_rhythm_fsm(beat: &mut Beat, should_tie: bool) -> Result<(), Stri
                 state = State::InRest { rest_index: rest_subdivisions.len() - 1 };
             }
 
-            // Rest encountered
-            (State::Initial, BeatElement::Rest(_)) => {
-                // Rest at beginning
-                total_subdivisions += 1;
-            }
-            (State::InNote { .. }, BeatElement::Rest(_)) => {
-                // Rest after note - treat as new element
-                total_subdivisions += 1;
-            }
-            (State::InRest { .. }, BeatElement::Rest(_)) => {
-                // Rest after rest - treat as new element
+            // Rest encountered - start new rest, extended by the dashes after it
+            (_, BeatElement::Rest(_)) => {
+                rest_subdivisions.push(1);
                 total_subdivisions += 1;
+                state = State::InRest { rest_index: rest_subdivisions.len() - 1 };
             }
 
             // Breath mark encountered (ignored)
@@ -189,15 +305,17 @@ fn analyze_beat_rhythm_fsm(beat: &mut Beat, should_tie: bool) -> Result<(), Stri
 
     // Set beat-level metadata
     beat.divisions = Some(total_subdivisions);
-    beat.total_duration = Some(Fraction::new(1u64, 4u64)); // Beat is always 1/4 note
-
-    // Determine if this is a tuplet (non-power of 2 divisions)
-    let is_tuplet = is_tuplet_division(total_subdivisions);
-    beat.is_tuplet = Some(is_tuplet);
-
-    // Calculate tuplet ratio if applicable
-    if is_tuplet {
-        beat.tuplet_ratio = Some(calculate_tuplet_ratio(total_subdivisions));
+    beat.total_duration = Some(beat_unit.0);
+
+    // A written ratio ("[3:2 ...]") makes a tuplet however the subdivisions add up;
+    // otherwise a tuplet has divisions that aren't note values of the beat unit
+    if let Some(ratio) = beat.explicit_tuplet {
+        beat.is_tuplet = Some(true);
+        beat.tuplet_ratio = Some(ratio);
+    } else {
+        let is_tuplet = beat_unit.is_tuplet(total_subdivisions);
+        beat.is_tuplet = Some(is_tuplet);
+        beat.tuplet_ratio = is_tuplet.then(|| beat_unit.tuplet_ratio(total_subdivisions));
     }
 
     // Apply calculated durations to notes and starting dashes (rests)
@@ -207,22 +325,20 @@ fn analyze_beat_rhythm_fsm(beat: &mut Beat, should_tie: bool) -> Result<(), Stri
 
     for beat_element in &mut beat.elements {
         match beat_element {
-            BeatElement::Note(note) => {
-                if note_index < note_subdivisions.len() {
-                    let subdivisions = note_subdivisions[note_index];
+            BeatElement::Note(note) if note_index < note_subdivisions.len() => {
+                let subdivisions = note_subdivisions[note_index];
 
-                    // Calculate duration as fraction of beat
-                    let duration = Fraction::new(subdivisions as u64, total_subdivisions as u64) * Fraction::new(1u64, 4u64);
+                // Calculate duration as fraction of beat
+                let duration = Fraction::new(subdivisions as u64, total_subdivisions as u64) * beat_unit.0;
 
-                    // Convert to numerator/denominator
-                    let numer = *duration.numer().unwrap() as u32;
-                    let denom = *duration.denom().unwrap() as u32;
-                    note.numerator = Some(numer);
-                    note.denominator = Some(denom);
+                // Convert to numerator/denominator
+                let numer = *duration.numer().unwrap() as u32;
+                let denom = *duration.denom().unwrap() as u32;
+                note.numerator = Some(numer);
+                note.denominator = Some(denom);
 
-                    note_index += 1;
-                    current_state = State::InNote { note_index: note_index - 1 };
-                }
+                note_index += 1;
+                current_state = State::InNote { note_index: note_index - 1 };
             }
             BeatElement::Dash(dash) => {
                 match current_state {
@@ -231,11 +347,13 @@ fn analyze_beat_rhythm_fsm(beat: &mut Beat, should_tie: bool) -> Result<(), Stri
                         if should_tie {
                             // This dash is a tie continuation, don't assign rhythm data
                             // The renderer will handle the tie continuation
+                            rest_index += 1;
+                            current_state = State::InRest { rest_index: rest_index - 1 };
                         } else {
                             // Starting dash for a new rest, assign rhythm data
                             if rest_index < rest_subdivisions.len() {
                                 let subdivisions = rest_subdivisions[rest_index];
-                                let duration = Fraction::new(subdivisions as u64, total_subdivisions as u64) * Fraction::new(1u64, 4u64);
+                                let duration = Fraction::new(subdivisions as u64, total_subdivisions as u64) * beat_unit.0;
                                 let numer = *duration.numer().unwrap() as u32;
                                 let denom = *duration.denom().unwrap() as u32;
                                 dash.numerator = Some(numer);
@@ -256,6 +374,18 @@ fn analyze_beat_rhythm_fsm(beat: &mut Beat, should_tie: bool) -> Result<(), Stri
                     }
                 }
             }
+            BeatElement::Rest(rest) if rest_index < rest_subdivisions.len() => {
+                // Rests inserted with a duration (whole rests for empty measures) keep it
+                if rest.numerator.is_none() {
+                    let subdivisions = rest_subdivisions[rest_index];
+                    let duration = Fraction::new(subdivisions as u64, total_subdivisions as u64) * beat_unit.0;
+                    rest.numerator = Some(*duration.numer().unwrap() as u32);
+                    rest.denominator = Some(*duration.denom().unwrap() as u32);
+                }
+
+                rest_index += 1;
+                current_state = State::InRest { rest_index: rest_index - 1 };
+            }
             _ => {
                 // Other elements don't change state
             }
@@ -265,42 +395,6 @@ fn analyze_beat_rhythm_fsm(beat: &mut Beat, should_tie: bool) -> Result<(), Stri
     Ok(())
 }
 
-/// Check if a division count represents a tuplet (non-power of 2)
-fn is_tuplet_division(divisions: usize) -> bool {
-    // Powers of 2 (1, 2, 4, 8, 16, etc.) are normal subdivisions
-    // Non-powers of 2 (3, 5, 6, 7, etc.) are tuplets
-    divisions > 0 && (divisions & (divisions - 1)) != 0
-}
-
-/// Calculate tuplet ratio using systematic subdivision approach
-fn calculate_tuplet_ratio(divisions: usize) -> (usize, usize) {
-    // Systematic subdivision by note count ranges
-    let denominator = match divisions {
-        1..=2 => 1,    // Quarter note subdivisions
-        3..=3 => 2,    // Eighth note subdivisions
-        4..=4 => 4,    // Quarter note (power of 2, not tuplet)
-        5..=7 => 4,    // Sixteenth note subdivisions
-        8..=8 => 8,    // Eighth note (power of 2, not tuplet)
-        9..=15 => 8,   // Thirty-second note subdivisions
-        16..=16 => 16, // Sixteenth note (power of 2, not tuplet)
-        17..=31 => 16, // Sixty-fourth note subdivisions
-        32..=32 => 32, // Thirty-second note (power of 2, not tuplet)
-        33..=63 => 32, // 128th note subdivisions
-        64..=64 => 64, // Sixty-fourth note (power of 2, not tuplet)
-        65..=127 => 64, // 256th note subdivisions
-        _ => {
-            // For extreme cases, find next power of 2
-            let mut power_of_two = 1;
-            while power_of_two < divisions {
-                power_of_two *= 2;
-            }
-            power_of_two / 2 // Use the subdivision count that fits in a quarter beat
-        }
-    };
-
-    (divisions, denominator)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -310,10 +404,11 @@ mod tests {
     fn test_fsm_dash_extension() {
         // Create a beat with pattern: Note, Dash, Dash, Note (1--2)
         let mut beat = Beat {
+            id: uuid::Uuid::new_v4(),
             elements: vec![
                 BeatElement::Note(Note::new(Some("1".to_string()), PitchCode::N1, NotationSystem::Number)),
-                BeatElement::Dash(Dash { id: uuid::Uuid::new_v4(), value: Some("-".to_string()), numerator: None, denominator: None }),
-                BeatElement::Dash(Dash { id: uuid::Uuid::new_v4(), value: Some("-".to_string()), numerator: None, denominator: None }),
+                BeatElement::Dash(Dash { id: uuid::Uuid::new_v4(), value: Some("-".to_string()), numerator: None, denominator: None, source: None }),
+                BeatElement::Dash(Dash { id: uuid::Uuid::new_v4(), value: Some("-".to_string()), numerator: None, denominator: None, source: None }),
                 BeatElement::Note(Note::new(Some("2".to_string()), PitchCode::N2, NotationSystem::Number)),
             ],
             value: Some("1--2".to_string()),
@@ -322,9 +417,15 @@ mod tests {
             is_tuplet: None,
             tuplet_ratio: None,
             tied_to_previous: None,
+            repeat_count: None,
+            chord: None,
+            dynamic: None,
+            feather: None,
+            explicit_tuplet: None,
+            source: None,
         };
 
-        analyze_beat_rhythm_fsm(&mut beat, false).unwrap(); // Not tied for this test
+        analyze_beat_rhythm_fsm(&mut beat, false, BeatUnit::default()).unwrap(); // Not tied for this test
 
         // Check that first note gets 3/4 of beat (3 subdivisions out of 4)
         if let BeatElement::Note(note1) = &beat.elements[0] {
@@ -346,9 +447,10 @@ mod tests {
         // Test case: "-- -1" (2 beats: rest sequence + dash+note)
         // Beat 1: "--" should have first dash as rest, second as extender
         let mut beat1 = Beat {
+            id: uuid::Uuid::new_v4(),
             elements: vec![
-                BeatElement::Dash(Dash { id: uuid::Uuid::new_v4(), value: Some("-".to_string()), numerator: None, denominator: None }),
-                BeatElement::Dash(Dash { id: uuid::Uuid::new_v4(), value: Some("-".to_string()), numerator: None, denominator: None }),
+                BeatElement::Dash(Dash { id: uuid::Uuid::new_v4(), value: Some("-".to_string()), numerator: None, denominator: None, source: None }),
+                BeatElement::Dash(Dash { id: uuid::Uuid::new_v4(), value: Some("-".to_string()), numerator: None, denominator: None, source: None }),
             ],
             value: Some("--".to_string()),
             divisions: None,
@@ -356,9 +458,15 @@ mod tests {
             is_tuplet: None,
             tuplet_ratio: None,
             tied_to_previous: None,
+            repeat_count: None,
+            chord: None,
+            dynamic: None,
+            feather: None,
+            explicit_tuplet: None,
+            source: None,
         };
 
-        analyze_beat_rhythm_fsm(&mut beat1, false).unwrap(); // Not tied
+        analyze_beat_rhythm_fsm(&mut beat1, false, BeatUnit::default()).unwrap(); // Not tied
 
         // Check first dash gets rhythm data (rest)
         if let BeatElement::Dash(dash1) = &beat1.elements[0] {
@@ -376,8 +484,9 @@ mod tests {
 
         // Beat 2: "-1" should have dash as rest, note gets rhythm data
         let mut beat2 = Beat {
+            id: uuid::Uuid::new_v4(),
             elements: vec![
-                BeatElement::Dash(Dash { id: uuid::Uuid::new_v4(), value: Some("-".to_string()), numerator: None, denominator: None }),
+                BeatElement::Dash(Dash { id: uuid::Uuid::new_v4(), value: Some("-".to_string()), numerator: None, denominator: None, source: None }),
                 BeatElement::Note(Note::new(Some("1".to_string()), PitchCode::N1, NotationSystem::Number)),
             ],
             value: Some("-1".to_string()),
@@ -386,9 +495,15 @@ mod tests {
             is_tuplet: None,
             tuplet_ratio: None,
             tied_to_previous: None,
+            repeat_count: None,
+            chord: None,
+            dynamic: None,
+            feather: None,
+            explicit_tuplet: None,
+            source: None,
         };
 
-        analyze_beat_rhythm_fsm(&mut beat2, false).unwrap(); // Not tied
+        analyze_beat_rhythm_fsm(&mut beat2, false, BeatUnit::default()).unwrap(); // Not tied
 
         // Check dash gets rhythm data (rest)
         if let BeatElement::Dash(dash) = &beat2.elements[0] {
@@ -410,8 +525,9 @@ mod tests {
         // Test case: "-547 -5" where -5 is tied to previous
         // Beat 1: "-547" should have dash as rest, notes get rhythm data
         let mut beat1 = Beat {
+            id: uuid::Uuid::new_v4(),
             elements: vec![
-                BeatElement::Dash(Dash { id: uuid::Uuid::new_v4(), value: Some("-".to_string()), numerator: None, denominator: None }),
+                BeatElement::Dash(Dash { id: uuid::Uuid::new_v4(), value: Some("-".to_string()), numerator: None, denominator: None, source: None }),
                 BeatElement::Note(Note::new(Some("5".to_string()), PitchCode::N5, NotationSystem::Number)),
                 BeatElement::Note(Note::new(Some("4".to_string()), PitchCode::N4, NotationSystem::Number)),
                 BeatElement::Note(Note::new(Some("7".to_string()), PitchCode::N7, NotationSystem::Number)),
@@ -422,9 +538,15 @@ mod tests {
             is_tuplet: None,
             tuplet_ratio: None,
             tied_to_previous: None,
+            repeat_count: None,
+            chord: None,
+            dynamic: None,
+            feather: None,
+            explicit_tuplet: None,
+            source: None,
         };
 
-        analyze_beat_rhythm_fsm(&mut beat1, false).unwrap(); // Not tied
+        analyze_beat_rhythm_fsm(&mut beat1, false, BeatUnit::default()).unwrap(); // Not tied
 
         // Check dash gets rhythm data (rest)
         if let BeatElement::Dash(dash) = &beat1.elements[0] {
@@ -444,8 +566,9 @@ mod tests {
 
         // Beat 2: "-5" tied to previous - dash should NOT get rhythm data
         let mut beat2 = Beat {
+            id: uuid::Uuid::new_v4(),
             elements: vec![
-                BeatElement::Dash(Dash { id: uuid::Uuid::new_v4(), value: Some("-".to_string()), numerator: None, denominator: None }),
+                BeatElement::Dash(Dash { id: uuid::Uuid::new_v4(), value: Some("-".to_string()), numerator: None, denominator: None, source: None }),
                 BeatElement::Note(Note::new(Some("5".to_string()), PitchCode::N5, NotationSystem::Number)),
             ],
             value: Some("-5".to_string()),
@@ -454,9 +577,15 @@ mod tests {
             is_tuplet: None,
             tuplet_ratio: None,
             tied_to_previous: None,
+            repeat_count: None,
+            chord: None,
+            dynamic: None,
+            feather: None,
+            explicit_tuplet: None,
+            source: None,
         };
 
-        analyze_beat_rhythm_fsm(&mut beat2, true).unwrap(); // TIED to previous
+        analyze_beat_rhythm_fsm(&mut beat2, true, BeatUnit::default()).unwrap(); // TIED to previous
 
         // Check dash has NO rhythm data (tie continuation)
         if let BeatElement::Dash(dash) = &beat2.elements[0] {
@@ -473,4 +602,84 @@ mod tests {
         assert_eq!(beat2.divisions, Some(2)); // Total subdivisions
         assert_eq!(beat2.tied_to_previous, Some(true)); // Confirms tied
     }
+
+    fn parse_line(text: &str) -> Vec<ContentElement> {
+        crate::document::line_parser::content_line_parser::unused_parse_content_line(text, 0, NotationSystem::Number, 0)
+            .unwrap()
+            .elements
+    }
+
+    fn notes_of(elements: &[ContentElement]) -> Vec<&Note> {
+        elements.iter()
+            .filter_map(|e| if let ContentElement::Beat(beat) = e { Some(beat) } else { None })
+            .flat_map(|beat| beat.elements.iter())
+            .filter_map(|e| if let BeatElement::Note(note) = e { Some(note) } else { None })
+            .collect()
+    }
+
+    #[test]
+    fn test_dash_after_barline_ties_across_it() {
+        let mut elements = parse_line("1 - | - 2 |: - 3");
+        analyze_content_line_rhythm(&mut elements).unwrap();
+        let tied: Vec<Option<bool>> = elements.iter()
+            .filter_map(|e| if let ContentElement::Beat(beat) = e { Some(beat.tied_to_previous) } else { None })
+            .collect();
+        // The repeat barline ends the held note, so its dash is a rest
+        assert_eq!(tied, vec![Some(false), Some(true), Some(true), Some(false), Some(false), Some(false)]);
+    }
+
+    #[test]
+    fn test_note_repetition_expands_to_quarter_notes() {
+        let mut elements = parse_line("1*4");
+        analyze_content_line_rhythm(&mut elements).unwrap();
+
+        let beats = elements.iter().filter(|e| matches!(e, ContentElement::Beat(_))).count();
+        assert_eq!(beats, 4);
+
+        let notes = notes_of(&elements);
+        assert_eq!(notes.len(), 4);
+        for note in notes {
+            assert_eq!(note.pitch_code, PitchCode::N1);
+            assert_eq!((note.numerator, note.denominator), (Some(1), Some(4)));
+        }
+    }
+
+    #[test]
+    fn test_note_repetition_keeps_octave() {
+        let mut elements = parse_line("3*2");
+        // Octave dot above the shorthand
+        if let ContentElement::Beat(beat) = &mut elements[0] {
+            if let BeatElement::Note(note) = &mut beat.elements[0] {
+                note.octave = 1;
+            }
+        }
+        analyze_content_line_rhythm(&mut elements).unwrap();
+
+        let notes = notes_of(&elements);
+        assert_eq!(notes.len(), 2);
+        assert!(notes.iter().all(|n| n.octave == 1 && n.pitch_code == PitchCode::N3));
+        assert_ne!(notes[0].id, notes[1].id);
+    }
+
+    #[test]
+    fn test_compound_meter_beats_in_dotted_quarters() {
+        let directives = HashMap::from([("Time".to_string(), "6/8".to_string())]);
+        let beat_unit = BeatUnit::from_directives(&directives);
+        assert_eq!(beat_unit, BeatUnit(Fraction::new(3u64, 8u64)));
+
+        let mut elements = parse_line("123 4-5");
+        analyze_content_line_rhythm_in(&mut elements, beat_unit).unwrap();
+        let durations: Vec<(Option<u32>, Option<u32>)> = notes_of(&elements).iter().map(|n| (n.numerator, n.denominator)).collect();
+        assert_eq!(durations, vec![(Some(1), Some(8)); 3].into_iter().chain([(Some(1), Some(4)), (Some(1), Some(8))]).collect::<Vec<_>>());
+        let beats: Vec<&Beat> = elements.iter().filter_map(|e| if let ContentElement::Beat(b) = e { Some(b) } else { None }).collect();
+        assert!(beats.iter().all(|b| b.is_tuplet == Some(false) && b.total_duration == Some(Fraction::new(3u64, 8u64))));
+
+        // Quarter-note beats keep their triplets; an explicit unit overrides the meter
+        let mut elements = parse_line("123");
+        analyze_content_line_rhythm(&mut elements).unwrap();
+        let ContentElement::Beat(beat) = &elements[0] else { panic!() };
+        assert_eq!(beat.tuplet_ratio, Some((3, 2)));
+        let directives = HashMap::from([("Time".to_string(), "6/8".to_string()), ("BeatUnit".to_string(), "1/8".to_string())]);
+        assert_eq!(BeatUnit::from_directives(&directives), BeatUnit(Fraction::new(1u64, 8u64)));
+    }
 }
@@ -0,0 +1,137 @@
+// Beam grouping decisions shared by the VexFlow and LilyPond renderers
+// Decides whether a rest inside a beat interrupts a beamed group
+
+use crate::parse::model::{directive, ContentElement, ContentLine, Feather};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Upper-line marks feathering the beam of the beat beneath: beams fanning out
+/// towards the end of the group (accelerando) or narrowing towards it (ritardando)
+pub const FEATHER_ACCELERANDO: &str = "<<";
+pub const FEATHER_RITARDANDO: &str = ">>";
+
+/// Directive key used to enable beaming over rests (e.g. `BeamOverRests: true`)
+pub const BEAM_OVER_RESTS_DIRECTIVE: &str = "BeamOverRests";
+
+/// Whether beams continue over rests that fall inside a beamed group.
+/// Defaults to `false`: conventional engraving breaks the beam at a rest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct BeamOverRests(pub bool);
+
+impl BeamOverRests {
+    /// Read the option from document directives, falling back to the default
+    pub fn from_directives(directives: &HashMap<String, String>) -> Self {
+        let enabled = directive(directives, BEAM_OVER_RESTS_DIRECTIVE)
+            .is_some_and(|value| matches!(value.to_lowercase().as_str(), "true" | "yes" | "on" | "1"));
+        BeamOverRests(enabled)
+    }
+}
+
+/// Split the beamable items of a beat into beam groups.
+///
+/// Each item is paired with `true` when it is a rest. With `BeamOverRests(false)`
+/// every rest ends the current group; with `BeamOverRests(true)` rests between
+/// notes stay inside the group. Leading and trailing rests are never beamed,
+/// and groups with fewer than two notes are dropped.
+pub fn split_beam_groups<T: Clone>(items: &[(T, bool)], beam_over_rests: BeamOverRests) -> Vec<Vec<T>> {
+    let mut groups = Vec::new();
+    let mut current: Vec<(T, bool)> = Vec::new();
+
+    for (item, is_rest) in items {
+        if *is_rest && !beam_over_rests.0 {
+            flush_beam_group(&mut current, &mut groups);
+            continue;
+        }
+        current.push((item.clone(), *is_rest));
+    }
+    flush_beam_group(&mut current, &mut groups);
+
+    groups
+}
+
+fn flush_beam_group<T>(current: &mut Vec<(T, bool)>, groups: &mut Vec<Vec<T>>) {
+    let mut group = std::mem::take(current);
+
+    // Beams start and end on notes, never on rests
+    while group.last().is_some_and(|(_, is_rest)| *is_rest) {
+        group.pop();
+    }
+    let first_note = group.iter().position(|(_, is_rest)| !*is_rest).unwrap_or(group.len());
+    group.drain(..first_note);
+
+    let note_count = group.iter().filter(|(_, is_rest)| !*is_rest).count();
+    if note_count >= 2 {
+        groups.push(group.into_iter().map(|(item, _)| item).collect());
+    }
+}
+
+/// Mark the beats beneath `<<` and `>>` in an upper line as feathered, matching by
+/// column like chords: a mark belongs to the beat under its first character or,
+/// between beats, to the nearest beat to its right.
+pub fn attach_feathers(content_line: &mut ContentLine, upper_line: &str) {
+    let mut column = 1;
+    for word in upper_line.split(' ') {
+        let feather = match word {
+            FEATHER_ACCELERANDO => Some(Feather::Accelerando),
+            FEATHER_RITARDANDO => Some(Feather::Ritardando),
+            _ => None,
+        };
+        if let Some(feather) = feather {
+            let target = content_line.elements.iter_mut().find_map(|element| match element {
+                ContentElement::Beat(beat) => match beat.source {
+                    Some(source) if source.column + source.length > column => Some(beat),
+                    _ => None,
+                },
+                _ => None,
+            });
+            if let Some(beat) = target {
+                beat.feather = Some(feather);
+            }
+        }
+        column += word.chars().count() + 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rest_breaks_beam_by_default() {
+        // 1 r 1 within one beat
+        let items = vec![("n1", false), ("r", true), ("n2", false)];
+        let groups = split_beam_groups(&items, BeamOverRests::default());
+        assert!(groups.is_empty());
+    }
+
+    #[test]
+    fn test_beam_over_rest_when_enabled() {
+        let items = vec![("n1", false), ("r", true), ("n2", false)];
+        let groups = split_beam_groups(&items, BeamOverRests(true));
+        assert_eq!(groups, vec![vec!["n1", "r", "n2"]]);
+    }
+
+    #[test]
+    fn test_outer_rests_are_not_beamed() {
+        let items = vec![("r1", true), ("n1", false), ("n2", false), ("r2", true)];
+        assert_eq!(split_beam_groups(&items, BeamOverRests(true)), vec![vec!["n1", "n2"]]);
+        assert_eq!(split_beam_groups(&items, BeamOverRests(false)), vec![vec!["n1", "n2"]]);
+    }
+
+    #[test]
+    fn test_from_directives() {
+        let mut directives = HashMap::new();
+        assert_eq!(BeamOverRests::from_directives(&directives), BeamOverRests(false));
+        directives.insert("BeamOverRests".to_string(), "true".to_string());
+        assert_eq!(BeamOverRests::from_directives(&directives), BeamOverRests(true));
+        let directives = HashMap::from([("beamoverrests".to_string(), "on".to_string())]);
+        assert_eq!(BeamOverRests::from_directives(&directives), BeamOverRests(true));
+    }
+
+    #[test]
+    fn test_beam_over_rests_directive_from_notation() {
+        let document = crate::pipeline::document_from_notation("BeamOverRests: true\n\n|1 2 3 4|\n").unwrap();
+        assert_eq!(BeamOverRests::from_directives(&document.directives), BeamOverRests(true));
+        assert!(crate::pipeline::check_directives(["BeamOverRests"]).unrecognized.is_empty());
+    }
+}
@@ -0,0 +1,440 @@
+// Empty measure handling
+// Decides what two barlines with nothing but whitespace between them (`| |`) mean
+
+use crate::parse::model::{directive, ContentElement, Barline, Beat, BeatElement, HasPosition, Rest};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Directive key selecting the empty measure policy (e.g. `EmptyMeasure: ignore`)
+pub const EMPTY_MEASURE_DIRECTIVE: &str = "EmptyMeasure";
+
+/// How an empty measure between adjacent barlines is interpreted.
+/// Defaults to `WholeRest`, matching standard notation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum EmptyMeasure {
+    /// Reject the line
+    Error,
+    /// Fill the measure with a whole rest
+    #[default]
+    WholeRest,
+    /// Treat the second barline as redundant and collapse it
+    Ignore,
+}
+
+impl EmptyMeasure {
+    /// Read the policy from document directives, falling back to the default
+    pub fn from_directives(directives: &HashMap<String, String>) -> Self {
+        let value = directive(directives, EMPTY_MEASURE_DIRECTIVE).map(str::to_lowercase);
+        match value.as_deref() {
+            Some("error") => EmptyMeasure::Error,
+            Some("ignore") => EmptyMeasure::Ignore,
+            _ => EmptyMeasure::WholeRest,
+        }
+    }
+}
+
+/// Apply the empty measure policy to a content line before rhythm analysis
+pub fn resolve_empty_measures(elements: &mut Vec<ContentElement>, policy: EmptyMeasure) -> Result<(), String> {
+    let mut resolved: Vec<ContentElement> = Vec::with_capacity(elements.len());
+    // Index in `resolved` of the last barline, while only whitespace has followed it
+    let mut open_barline: Option<usize> = None;
+
+    for element in elements.drain(..) {
+        match element {
+            ContentElement::Barline(barline) => {
+                if let Some(start) = open_barline {
+                    match policy {
+                        EmptyMeasure::Error => {
+                            let column = barline.source().map(|s| s.column).unwrap_or(0);
+                            return Err(format!("Empty measure before the barline at column {}", column));
+                        }
+                        EmptyMeasure::WholeRest => {
+                            resolved.push(ContentElement::Beat(whole_rest_beat()));
+                        }
+                        EmptyMeasure::Ignore => {
+                            // Drop the whitespace between the barlines along with the second barline
+                            resolved.truncate(start + 1);
+                            continue;
+                        }
+                    }
+                }
+                open_barline = Some(resolved.len());
+                resolved.push(ContentElement::Barline(barline));
+            }
+            ContentElement::Whitespace(_) => resolved.push(element),
+            other => {
+                open_barline = None;
+                resolved.push(other);
+            }
+        }
+    }
+
+    *elements = resolved;
+    Ok(())
+}
+
+/// A beat holding a single whole rest; the rhythm analyzer keeps preset rest durations
+fn whole_rest_beat() -> Beat {
+    Beat {
+        id: uuid::Uuid::new_v4(),
+        value: None,
+        divisions: None,
+        is_tuplet: None,
+        tuplet_ratio: None,
+        tied_to_previous: None,
+        total_duration: None,
+        repeat_count: None,
+        source: None,
+        chord: None,
+        dynamic: None,
+        feather: None,
+        explicit_tuplet: None,
+        elements: vec![BeatElement::Rest(Rest {
+            id: uuid::Uuid::new_v4(),
+            value: None,
+            numerator: Some(1),
+            denominator: Some(1),
+            source: None,
+        })],
+    }
+}
+
+/// Number of measures a multi-measure rest token (`R*8`) stands for
+pub fn multi_measure_rest_count(token: &str) -> Option<usize> {
+    token.strip_prefix("R*")
+        .and_then(|count| count.parse::<usize>().ok())
+        .filter(|&count| count > 0)
+}
+
+/// Multi-measure rests of a content line, keyed by the element index of each rest.
+/// Rests separated only by single barlines and whitespace (`R*4 | R*4`) are consecutive
+/// empty measures and merge into one rest at the first index, counting every measure consumed.
+pub fn multi_measure_rests(elements: &[ContentElement]) -> HashMap<usize, usize> {
+    let mut rests = HashMap::new();
+    // Index of the rest the following measures merge into, while only barlines and whitespace follow it
+    let mut open_rest: Option<usize> = None;
+    for (index, element) in elements.iter().enumerate() {
+        match element {
+            ContentElement::UnknownToken(token) => match multi_measure_rest_count(&token.token_value) {
+                Some(count) => match open_rest {
+                    Some(first) => *rests.get_mut(&first).unwrap() += count,
+                    None => {
+                        rests.insert(index, count);
+                        open_rest = Some(index);
+                    }
+                },
+                None => open_rest = None,
+            },
+            ContentElement::Whitespace(_) | ContentElement::Barline(crate::parse::model::Barline::Single(_)) => {}
+            _ => open_rest = None,
+        }
+    }
+    rests
+}
+
+/// Meter change written right after a barline (`| (3/4) 1 2 3 |`): the measures
+/// from there on are in the new time signature. Beats keep the document's beat unit.
+pub fn meter_change(element: &ContentElement) -> Option<crate::analysis::TimeSignature> {
+    match element {
+        ContentElement::UnknownToken(token) => crate::analysis::TimeSignature::from_inline(&token.token_value),
+        _ => None,
+    }
+}
+
+/// Directive switching slash notation on and off at measure numbers (e.g. `Slash: on 2, off 4`)
+pub const SLASH_DIRECTIVE: &str = "Slash";
+
+/// Measures drawn with slash noteheads, from `on N`/`off N` toggles (a bare `on` starts at measure 1)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlashMeasures {
+    toggles: Vec<(usize, bool)>, // (measure number, slashes on), sorted by measure
+}
+
+impl SlashMeasures {
+    pub fn from_directives(directives: &HashMap<String, String>) -> Option<Self> {
+        let value = directive(directives, SLASH_DIRECTIVE)?.to_lowercase();
+        let mut toggles: Vec<(usize, bool)> = value.split(',')
+            .filter_map(|toggle| {
+                let mut words = toggle.split_whitespace();
+                let on = match words.next()? {
+                    "on" => true,
+                    "off" => false,
+                    _ => return None,
+                };
+                let measure = match words.next() {
+                    Some(number) => number.parse::<usize>().ok().filter(|&m| m > 0)?,
+                    None => 1,
+                };
+                Some((measure, on))
+            })
+            .collect();
+        toggles.sort_by_key(|(measure, _)| *measure);
+        (!toggles.is_empty()).then_some(SlashMeasures { toggles })
+    }
+
+    /// Whether a measure (numbered from 1) is in slash notation
+    pub fn is_slashed(&self, measure: usize) -> bool {
+        self.toggles.iter()
+            .take_while(|(start, _)| *start <= measure)
+            .last()
+            .is_some_and(|(_, on)| *on)
+    }
+}
+
+/// Directive placing rehearsal marks at measure numbers (e.g. `Rehearsal: A 1, B 9`)
+pub const REHEARSAL_DIRECTIVE: &str = "Rehearsal";
+/// Directive placing numbered ending brackets over measures (e.g. `Endings: 1 3, 2 4-5`)
+pub const ENDINGS_DIRECTIVE: &str = "Endings";
+
+/// A first, second, ... ending bracket over measures `first..=last`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ending {
+    pub number: usize,
+    pub first: usize,
+    pub last: usize,
+}
+
+impl Ending {
+    /// Text printed at the start of the bracket, e.g. "1."
+    pub fn label(&self) -> String {
+        format!("{}.", self.number)
+    }
+}
+
+/// Rehearsal marks and endings shared by the renderers, located by measure number
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MeasureMarks {
+    rehearsal: Vec<(usize, String)>, // (measure number, mark)
+    endings: Vec<Ending>,
+}
+
+impl MeasureMarks {
+    pub fn from_directives(directives: &HashMap<String, String>) -> Self {
+        let value = |name: &str| directive(directives, name).unwrap_or("");
+        // Each entry is a label followed by a measure number or range
+        let entries = |value: &str| -> Vec<(String, usize, usize)> {
+            value.split(',')
+                .filter_map(|entry| {
+                    let mut words = entry.split_whitespace();
+                    let label = words.next()?.to_string();
+                    let measures = words.next()?;
+                    let (first, last) = measures.split_once('-').unwrap_or((measures, measures));
+                    let first = first.parse::<usize>().ok().filter(|&m| m > 0)?;
+                    let last = last.parse::<usize>().ok().filter(|&m| m >= first)?;
+                    Some((label, first, last))
+                })
+                .collect()
+        };
+        MeasureMarks {
+            rehearsal: entries(value(REHEARSAL_DIRECTIVE)).into_iter()
+                .map(|(label, measure, _)| (measure, label))
+                .collect(),
+            endings: entries(value(ENDINGS_DIRECTIVE)).into_iter()
+                .filter_map(|(number, first, last)| Some(Ending { number: number.trim_end_matches('.').parse().ok()?, first, last }))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rehearsal.is_empty() && self.endings.is_empty()
+    }
+
+    /// Rehearsal mark printed at the start of a measure
+    pub fn rehearsal_mark(&self, measure: usize) -> Option<&str> {
+        self.rehearsal.iter().find(|(m, _)| *m == measure).map(|(_, mark)| mark.as_str())
+    }
+
+    /// Ending whose bracket opens at a measure
+    pub fn ending_from(&self, measure: usize) -> Option<&Ending> {
+        self.endings.iter().find(|ending| ending.first == measure)
+    }
+
+    pub fn endings(&self) -> &[Ending] {
+        &self.endings
+    }
+
+    /// Add the endings marked on the barlines of a content line (see `inline_endings`)
+    pub fn with_inline_endings(mut self, elements: &[ContentElement]) -> Self {
+        self.endings.extend(inline_endings(elements));
+        self
+    }
+}
+
+/// Endings marked on barlines ("|1.", ":|2."), with measures numbered from 1. An
+/// ending runs until the next ending or the next barline other than a single one.
+pub fn inline_endings(elements: &[ContentElement]) -> Vec<Ending> {
+    let mut endings = Vec::new();
+    let mut measures = MeasureCounter::default();
+    let mut open: Option<(usize, usize)> = None; // (ending number, first measure)
+    let mut last_measure = 0;
+    let mut close = |open: &mut Option<(usize, usize)>, last: usize| {
+        if let Some((number, first)) = open.take().filter(|&(_, first)| last >= first) {
+            endings.push(Ending { number, first, last });
+        }
+    };
+    for element in elements {
+        match element {
+            ContentElement::Beat(_) => last_measure = measures.beat(),
+            ContentElement::Barline(barline) => {
+                measures.barline();
+                if barline.ending().is_some() || !matches!(barline, Barline::Single(_)) {
+                    close(&mut open, last_measure);
+                }
+                if let Some(number) = barline.ending() {
+                    open = Some((number, measures.current()));
+                }
+            }
+            _ => {}
+        }
+    }
+    close(&mut open, last_measure);
+    endings
+}
+
+/// Numbers measures from 1 while walking content elements in order. A barline ends
+/// the current measure only once it has a beat, so leading and doubled barlines don't count.
+#[derive(Debug, Clone, Copy)]
+pub struct MeasureCounter {
+    measure: usize,
+    has_beats: bool,
+}
+
+impl Default for MeasureCounter {
+    fn default() -> Self {
+        MeasureCounter { measure: 1, has_beats: false }
+    }
+}
+
+impl MeasureCounter {
+    /// Count from `measure` instead of 1, e.g. from 0 when a pickup comes first
+    pub fn starting_at(measure: usize) -> Self {
+        MeasureCounter { measure, has_beats: false }
+    }
+
+    /// Measure the next beat belongs to
+    pub fn beat(&mut self) -> usize {
+        self.has_beats = true;
+        self.measure
+    }
+
+    /// Measure the next beat will belong to
+    pub fn current(&self) -> usize {
+        self.measure
+    }
+
+    pub fn barline(&mut self) {
+        if self.has_beats {
+            self.measure += 1;
+            self.has_beats = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::line_parser::content_line_parser::unused_parse_content_line;
+    use crate::parse::model::NotationSystem;
+
+    fn resolve(policy: EmptyMeasure) -> Result<Vec<ContentElement>, String> {
+        let mut line = unused_parse_content_line("1 2 3 4 | | 5 6 7 8", 0, NotationSystem::Number, 0).unwrap();
+        resolve_empty_measures(&mut line.elements, policy)?;
+        Ok(line.elements)
+    }
+
+    fn barline_count(elements: &[ContentElement]) -> usize {
+        elements.iter().filter(|e| matches!(e, ContentElement::Barline(_))).count()
+    }
+
+    #[test]
+    fn test_empty_measure_whole_rest() {
+        let elements = resolve(EmptyMeasure::default()).unwrap();
+        let rest_beat = elements.iter().find_map(|e| match e {
+            ContentElement::Beat(beat) if matches!(beat.elements[0], BeatElement::Rest(_)) => Some(beat),
+            _ => None,
+        }).expect("whole rest beat");
+        if let BeatElement::Rest(rest) = &rest_beat.elements[0] {
+            assert_eq!((rest.numerator, rest.denominator), (Some(1), Some(1)));
+        }
+        assert_eq!(barline_count(&elements), 2);
+    }
+
+    #[test]
+    fn test_empty_measure_whole_rest_survives_rhythm_analysis() {
+        let document = crate::pipeline::document_from_notation("|1 2 3 4| |5 6 7 1|\n").unwrap();
+        let lilypond = crate::renderers::lilypond::renderer::convert_document_to_lilypond_src(&document, None).unwrap();
+        assert!(lilypond.contains("r1 |"), "{}", lilypond);
+    }
+
+    #[test]
+    fn test_empty_measure_ignore_collapses_barlines() {
+        let elements = resolve(EmptyMeasure::Ignore).unwrap();
+        assert_eq!(barline_count(&elements), 1);
+        // Only the second barline and the whitespace before it are dropped
+        let names: Vec<&str> = elements.iter().map(|e| e.type_name()).collect();
+        assert_eq!(&names[7..11], &["Whitespace", "SingleBarline", "Whitespace", "Beat"]);
+    }
+
+    #[test]
+    fn test_empty_measure_error() {
+        assert_eq!(resolve(EmptyMeasure::Error).unwrap_err(), "Empty measure before the barline at column 11");
+    }
+
+    #[test]
+    fn test_empty_measure_from_directives() {
+        let mut directives = HashMap::new();
+        directives.insert(EMPTY_MEASURE_DIRECTIVE.to_string(), "Ignore".to_string());
+        assert_eq!(EmptyMeasure::from_directives(&directives), EmptyMeasure::Ignore);
+        assert_eq!(EmptyMeasure::from_directives(&HashMap::new()), EmptyMeasure::WholeRest);
+        let document = crate::pipeline::document_from_notation("emptymeasure: error\n\n|1 2 3 4|\n").unwrap();
+        assert_eq!(EmptyMeasure::from_directives(&document.directives), EmptyMeasure::Error);
+        assert!(crate::pipeline::document_from_notation("EmptyMeasure: error\n\n|1 2| |3 4|\n").is_err());
+        assert!(crate::pipeline::document_from_notation("|1 2| |3 4|\n").is_ok());
+    }
+
+    #[test]
+    fn test_multi_measure_rests_merge_consumed_measures() {
+        let line = unused_parse_content_line("1 | R*4 | R*4 || R*2 | 1", 0, NotationSystem::Number, 0).unwrap();
+        let rests = multi_measure_rests(&line.elements);
+        let mut counts: Vec<usize> = rests.values().copied().collect();
+        counts.sort();
+        // The double barline ends the first run of rests
+        assert_eq!(counts, vec![2, 8]);
+        assert_eq!(multi_measure_rest_count("R*0"), None);
+        assert_eq!(multi_measure_rest_count("R"), None);
+    }
+
+    #[test]
+    fn test_slash_measure_toggles() {
+        let slash = |value: &str| SlashMeasures::from_directives(&[("slash".to_string(), value.to_string())].into_iter().collect());
+        let measures = slash("on 2, off 4").unwrap();
+        assert_eq!((1..=5).map(|m| measures.is_slashed(m)).collect::<Vec<_>>(), vec![false, true, true, false, false]);
+        assert!(slash("on").unwrap().is_slashed(1));
+        assert_eq!(slash("sometimes"), None);
+    }
+
+    #[test]
+    fn test_endings_marked_on_barlines() {
+        let endings = |text: &str| inline_endings(&unused_parse_content_line(text, 0, NotationSystem::Number, 0).unwrap().elements);
+        assert_eq!(endings("|: 1 | 2 |1. 3 | 4 :|2. 5 || 6"), vec![
+            Ending { number: 1, first: 3, last: 4 },
+            Ending { number: 2, first: 5, last: 5 },
+        ]);
+        assert_eq!(endings("1 :|2. 3 | 4"), vec![Ending { number: 2, first: 2, last: 3 }]);
+        assert_eq!(endings("|: 1 :|"), vec![]);
+    }
+
+    #[test]
+    fn test_rehearsal_marks_and_endings() {
+        let directives = [
+            ("Rehearsal".to_string(), "A 1, B 5".to_string()),
+            ("endings".to_string(), "1. 3, 2 4-5, x 6".to_string()),
+        ].into_iter().collect();
+        let marks = MeasureMarks::from_directives(&directives);
+        assert_eq!(marks.rehearsal_mark(5), Some("B"));
+        assert_eq!(marks.rehearsal_mark(2), None);
+        assert_eq!(marks.endings(), &[Ending { number: 1, first: 3, last: 3 }, Ending { number: 2, first: 4, last: 5 }]);
+        assert_eq!(marks.ending_from(4).map(Ending::label), Some("2.".to_string()));
+        assert!(MeasureMarks::from_directives(&HashMap::new()).is_empty());
+    }
+}
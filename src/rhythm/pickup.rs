@@ -0,0 +1,151 @@
+// Anacrusis (pickup) declaration and detection
+// `Pickup: 1/8` makes the first beats a partial measure of that length; without it,
+// beats before the first barline that don't fill a measure are taken as a pickup
+
+use crate::analysis::TimeSignature;
+use crate::parse::model::{directive, ContentElement, Document, DocumentElement, StaveLine};
+use crate::rhythm::analyzer::BeatUnit;
+use crate::rhythm::tala::Tala;
+use fraction::Fraction;
+use std::collections::HashMap;
+
+/// Directive key declaring the pickup length as a fraction of a whole note, matched case-insensitively
+pub const PICKUP_DIRECTIVE: &str = "Pickup";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pickup {
+    /// Length of the pickup as a fraction of a whole note
+    pub duration: Fraction,
+}
+
+impl Pickup {
+    /// Read the declared pickup, validating that it is shorter than a full measure.
+    /// Returns `Ok(None)` when no pickup is declared.
+    pub fn from_directives(directives: &HashMap<String, String>) -> Result<Option<Self>, String> {
+        let Some(value) = directive(directives, PICKUP_DIRECTIVE) else {
+            return Ok(None);
+        };
+        let duration = parse_fraction(value)
+            .ok_or_else(|| format!("Invalid pickup duration '{}', expected a fraction like 1/8", value.trim()))?;
+
+        let measure = measure_length(directives);
+        if duration >= measure {
+            return Err(format!("Pickup duration {} must be shorter than a full measure ({})", value.trim(), measure));
+        }
+        Ok(Some(Pickup { duration }))
+    }
+
+    /// The declared pickup, else one detected from the beats before the first barline
+    pub fn resolve(document: &Document) -> Result<Option<Self>, String> {
+        match Self::from_directives(&document.directives)? {
+            Some(pickup) => Ok(Some(pickup)),
+            None => Ok(Self::detect(document)),
+        }
+    }
+
+    /// A leading partial measure: the beats of the first content line before its first
+    /// barline, when they add up to less than a measure. Lines without a barline have
+    /// no measure to compare against, so they never start with a pickup.
+    pub fn detect(document: &Document) -> Option<Self> {
+        let content_line = document.elements.iter()
+            .filter_map(|element| match element {
+                DocumentElement::Stave(stave) => Some(stave),
+                _ => None,
+            })
+            .flat_map(|stave| stave.lines.iter())
+            .find_map(|line| match line {
+                StaveLine::ContentLine(content_line) => Some(content_line),
+                _ => None,
+            })?;
+        let beat_unit = BeatUnit::from_directives(&document.directives);
+        let mut duration = Fraction::from(0u64);
+        for element in &content_line.elements {
+            match element {
+                ContentElement::Beat(beat) => duration += beat.total_duration.unwrap_or(beat_unit.0),
+                // A leading barline opens the first full measure
+                ContentElement::Barline(_) if duration == Fraction::from(0u64) => return None,
+                ContentElement::Barline(_) => {
+                    return (duration < measure_length(&document.directives)).then_some(Pickup { duration });
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Number of quarter-note beats the pickup occupies (a partial beat counts as one)
+    pub fn beats(&self) -> usize {
+        let quarters = self.duration * Fraction::new(4u64, 1u64);
+        let numer = *quarters.numer().unwrap_or(&0);
+        let denom = *quarters.denom().unwrap_or(&1);
+        numer.div_ceil(denom) as usize
+    }
+
+    /// LilyPond duration for `\partial` ("8", "4." or "8*5")
+    pub fn lilypond_duration(&self) -> String {
+        let numer = *self.duration.numer().unwrap_or(&1);
+        let denom = *self.duration.denom().unwrap_or(&1);
+        match numer {
+            1 => denom.to_string(),
+            3 if denom.is_multiple_of(2) => format!("{}.", denom / 2),
+            _ => format!("{}*{}", denom, numer),
+        }
+    }
+}
+
+/// Length of one measure: the tala cycle if one is declared, otherwise the time signature (4/4 by default)
+fn measure_length(directives: &HashMap<String, String>) -> Fraction {
+    match Tala::from_directives(directives) {
+        Some(tala) => Fraction::new(tala.beats() as u64, 4u64),
+        None => {
+            let time_signature = TimeSignature::from_directives(directives);
+            Fraction::new(time_signature.beats as u64, time_signature.unit as u64)
+        }
+    }
+}
+
+fn parse_fraction(value: &str) -> Option<Fraction> {
+    let (numer, denom) = value.trim().split_once('/')?;
+    let numer: u64 = numer.trim().parse().ok()?;
+    let denom: u64 = denom.trim().parse().ok()?;
+    if numer == 0 || denom == 0 {
+        return None;
+    }
+    Some(Fraction::new(numer, denom))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pickup(value: &str) -> Result<Option<Pickup>, String> {
+        let mut directives = HashMap::new();
+        directives.insert("Pickup".to_string(), value.to_string());
+        Pickup::from_directives(&directives)
+    }
+
+    #[test]
+    fn test_pickup_durations() {
+        assert_eq!(pickup("1/8").unwrap().unwrap().lilypond_duration(), "8");
+        assert_eq!(pickup("3/8").unwrap().unwrap().lilypond_duration(), "4.");
+        assert_eq!(pickup("5/16").unwrap().unwrap().lilypond_duration(), "16*5");
+        assert_eq!(pickup("3/8").unwrap().unwrap().beats(), 2);
+    }
+
+    #[test]
+    fn test_pickup_must_be_shorter_than_measure() {
+        assert!(pickup("1/1").is_err());
+        assert!(pickup("eighth").is_err());
+        assert_eq!(Pickup::from_directives(&HashMap::new()), Ok(None));
+    }
+
+    #[test]
+    fn test_detected_pickup() {
+        let detect = |input: &str| Pickup::detect(&crate::pipeline::document_from_notation(input).unwrap()).map(|p| p.duration);
+        assert_eq!(detect("- - 1 | 2 3 4 5"), Some(Fraction::new(3u64, 4u64)));
+        assert_eq!(detect("BeatUnit: 1/8\n\n1 | 2 3 4 5 6 7 8 9"), Some(Fraction::new(1u64, 8u64)));
+        assert_eq!(detect("1 2 3 4 | 5 6 7 8"), None);
+        assert_eq!(detect("| 1 2 3 | 4 5 6 7"), None);
+        assert_eq!(detect("1 2 3"), None);
+    }
+}
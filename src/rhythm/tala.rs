@@ -0,0 +1,109 @@
+// Tala (Hindustani rhythmic cycle) definitions
+// A tala directive fixes the cycle length, its vibhag (section) grouping and
+// which vibhags are sam, tali (clap) or khali (wave)
+
+use crate::parse::model::directive;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Directive key naming the tala (e.g. `Tala: Teentaal`), matched case-insensitively
+pub const TALA_DIRECTIVE: &str = "Tala";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Tala {
+    pub name: &'static str,
+    /// Beats per vibhag; the cycle length is their sum
+    pub vibhags: &'static [usize],
+    /// Indexes of the khali (wave) vibhags
+    pub khali: &'static [usize],
+}
+
+/// Role of a beat within the tala cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TalaMark {
+    /// First beat of the cycle
+    Sam,
+    /// First beat of a clapped vibhag, numbered from 2
+    Tali(usize),
+    /// First beat of a waved vibhag
+    Khali,
+}
+
+const TALAS: &[Tala] = &[
+    Tala { name: "Teentaal", vibhags: &[4, 4, 4, 4], khali: &[2] },
+    Tala { name: "Jhaptaal", vibhags: &[2, 3, 2, 3], khali: &[2] },
+    Tala { name: "Ektaal", vibhags: &[2, 2, 2, 2, 2, 2], khali: &[1, 3] },
+    Tala { name: "Rupak", vibhags: &[3, 2, 2], khali: &[0] },
+    Tala { name: "Dadra", vibhags: &[3, 3], khali: &[1] },
+    Tala { name: "Keherwa", vibhags: &[4, 4], khali: &[1] },
+];
+
+impl Tala {
+    /// Look up a tala by name, accepting common spellings ("Tintal", "teental")
+    pub fn by_name(name: &str) -> Option<&'static Tala> {
+        let name = name.trim().to_lowercase();
+        let canonical = match name.as_str() {
+            "tintal" | "teental" | "tintaal" | "trital" => "teentaal",
+            "jhaptal" => "jhaptaal",
+            "ektal" | "ektala" => "ektaal",
+            "rupak" | "rupaktal" => "rupak",
+            "kaharva" | "kehrwa" => "keherwa",
+            other => other,
+        };
+        TALAS.iter().find(|tala| tala.name.to_lowercase() == canonical)
+    }
+
+    /// Read the tala from document directives
+    pub fn from_directives(directives: &HashMap<String, String>) -> Option<&'static Tala> {
+        directive(directives, TALA_DIRECTIVE).and_then(Tala::by_name)
+    }
+
+    /// Number of beats in one cycle
+    pub fn beats(&self) -> usize {
+        self.vibhags.iter().sum()
+    }
+
+    /// Mark for the beat at `index` (0-based, counted from the first sam)
+    pub fn mark(&self, index: usize) -> Option<TalaMark> {
+        let mut position = index % self.beats();
+        for (vibhag, length) in self.vibhags.iter().enumerate() {
+            if position == 0 {
+                return Some(if vibhag == 0 {
+                    TalaMark::Sam
+                } else if self.khali.contains(&vibhag) {
+                    TalaMark::Khali
+                } else {
+                    TalaMark::Tali(1 + (0..vibhag).filter(|v| !self.khali.contains(v)).count())
+                });
+            }
+            if position < *length {
+                return None;
+            }
+            position -= length;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_teentaal_cycle() {
+        let tala = Tala::by_name("Teentaal").unwrap();
+        assert_eq!(tala.beats(), 16);
+        let marks: Vec<(usize, TalaMark)> = (0..32).filter_map(|i| tala.mark(i).map(|m| (i, m))).collect();
+        assert_eq!(marks, vec![
+            (0, TalaMark::Sam), (4, TalaMark::Tali(2)), (8, TalaMark::Khali), (12, TalaMark::Tali(3)),
+            (16, TalaMark::Sam), (20, TalaMark::Tali(2)), (24, TalaMark::Khali), (28, TalaMark::Tali(3)),
+        ]);
+    }
+
+    #[test]
+    fn test_tala_from_directives() {
+        let mut directives = HashMap::new();
+        directives.insert("Tala".to_string(), "jhaptal".to_string());
+        assert_eq!(Tala::from_directives(&directives).map(|t| t.beats()), Some(10));
+    }
+}
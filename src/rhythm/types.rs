@@ -11,15 +11,6 @@ pub struct Position {
     pub col: usize,
 }
 
-/// Types of musical ornaments that can be attached to notes
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum OrnamentType {
-    Mordent,
-    Trill,
-    Turn,
-    Grace,
-}
-
 /// Child elements that can be attached to notes (vertical spatial relationships)
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ParsedChild {
@@ -28,11 +19,15 @@ pub enum ParsedChild {
         symbol: String,
         distance: i8, // Vertical distance from parent note (-1 = above, +1 = below)
     },
-    /// Musical ornaments like mordents, trills
+    /// Mordent, trill or turn from an upper annotation line
     Ornament { 
-        kind: OrnamentType,
+        kind: crate::models::Ornament,
         distance: i8,
     },
+    /// Staccato, accent or tenuto mark from an articulation line
+    Articulation {
+        kind: crate::models::Articulation,
+    },
     /// Syllable/lyric text
     Syllable {
         text: String,
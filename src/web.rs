@@ -1,13 +1,15 @@
 // Web server for live notation parsing
 use axum::{
-    extract::{Multipart, Form, Path, Query},
+    extract::{Path, Query, State},
     response::{IntoResponse, Html, Response},
-    routing::{get, post, put},
+    routing::{get, post},
     Json, Router,
     http::{StatusCode, header},
-    body::Body,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 use chrono;
@@ -15,8 +17,9 @@ use tower_http::{cors::CorsLayer, services::ServeDir};
 use tokio::fs;
 // Removed pest import - using hand-written recursive descent parser
 use crate::import::musicxml::{import_musicxml_to_document, ImportOptions};
+use crate::import::lilypond::import_lilypond;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct ParseRequest {
     input: String,
     system: Option<String>,
@@ -31,21 +34,51 @@ pub struct RoundtripData {
     differences: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+/// Crate version reported to API clients, so they can tell which schema a server speaks
+pub const API_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Clone, Serialize)]
 pub struct ParseResponse {
+    version: &'static str,
     success: bool,
     plain_text: Option<String>,
     document: Option<crate::parse::Document>,
     detected_notation_systems: Option<Vec<String>>,
+    /// Every system the notes could be read in, with confidence scores, most likely first.
+    /// The first is the system detected for the content lines.
+    notation_system_scores: Option<Vec<(crate::models::NotationSystem, f32)>>,
     lilypond: Option<String>,
     lilypond_minimal: Option<String>,
     lilypond_svg: Option<String>,
     vexflow: Option<serde_json::Value>,
     vexflow_svg: Option<String>,
     editor_svg: Option<String>,  // Canvas WYSIWYG SVG
+    warnings: Vec<crate::document::spans::SpanWarning>,
     error: Option<String>,
 }
 
+impl ParseResponse {
+    /// A response with no document or renderings
+    fn failure(error: String) -> Self {
+        ParseResponse {
+            version: API_VERSION,
+            success: false,
+            plain_text: None,
+            document: None,
+            detected_notation_systems: None,
+            notation_system_scores: None,
+            lilypond: None,
+            lilypond_minimal: None,
+            lilypond_svg: None,
+            vexflow: None,
+            vexflow_svg: None,
+            editor_svg: None,
+            warnings: Vec::new(),
+            error: Some(error),
+        }
+    }
+}
+
 // Document-first API structures
 #[derive(Debug, Deserialize)]
 pub struct CreateDocumentRequest {
@@ -92,6 +125,9 @@ pub struct TransformDocumentResponse {
     pub document: serde_json::Value,
     pub updated_elements: Vec<String>,
     pub message: Option<String>,
+    /// Machine-readable error code when the request failed on a parse error (e.g. "missing_barline")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
     pub svg: Option<String>,
     pub formats: Option<DocumentFormats>,
 }
@@ -99,7 +135,7 @@ pub struct TransformDocumentResponse {
 #[derive(Debug, Deserialize)]
 pub struct ExportDocumentRequest {
     pub document: serde_json::Value,
-    pub format: String, // "lilypond", "svg", "midi"
+    pub format: String, // "lilypond", "lilypond-png", "interactive-html"
     pub options: Option<serde_json::Value>,
 }
 
@@ -149,6 +185,100 @@ async fn save_document(documentUUID: &str, document: &serde_json::Value) -> Resu
     Ok(())
 }
 
+/// Documents listed per page when no `limit` is given, and the most that can be asked for
+const DEFAULT_DOCUMENT_PAGE_SIZE: usize = 50;
+const MAX_DOCUMENT_PAGE_SIZE: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct DocumentListQuery {
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct StoredDocumentSummary {
+    id: String,
+    title: Option<String>,
+    timestamp: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DocumentListResponse {
+    documents: Vec<StoredDocumentSummary>,
+    total: usize,
+    limit: usize,
+    offset: usize,
+}
+
+/// One page of the documents stored in `dir`, newest first
+async fn list_stored_documents(dir: &std::path::Path, query: &DocumentListQuery) -> Result<DocumentListResponse, std::io::Error> {
+    let limit = query.limit.unwrap_or(DEFAULT_DOCUMENT_PAGE_SIZE).min(MAX_DOCUMENT_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0);
+    let mut documents = Vec::new();
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(DocumentListResponse { documents, total: 0, limit, offset });
+        }
+        Err(e) => return Err(e),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(id) = path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string) else { continue };
+        // Unreadable files are still listed so they can be deleted
+        let document: serde_json::Value = tokio::fs::read_to_string(&path).await.ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        let field = |name: &str| document.get(name).and_then(|value| value.as_str()).map(str::to_string);
+        documents.push(StoredDocumentSummary { id, title: field("title"), timestamp: field("timestamp") });
+    }
+    documents.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then_with(|| a.id.cmp(&b.id)));
+    let total = documents.len();
+    let documents = documents.into_iter().skip(offset).take(limit).collect();
+    Ok(DocumentListResponse { documents, total, limit, offset })
+}
+
+/// Delete a stored document; `false` when there is no document with that id
+async fn delete_stored_document(dir: &std::path::Path, document_id: &str) -> Result<bool, std::io::Error> {
+    // Only UUIDs name stored documents, which also keeps the path inside `dir`
+    if Uuid::parse_str(document_id).is_err() {
+        return Ok(false);
+    }
+    match tokio::fs::remove_file(dir.join(format!("{}.json", document_id))).await {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// A stored document's JSON; `None` when there is no document with that id
+async fn load_stored_document(dir: &std::path::Path, document_id: &str) -> Result<Option<serde_json::Value>, std::io::Error> {
+    if Uuid::parse_str(document_id).is_err() {
+        return Ok(None);
+    }
+    match tokio::fs::read_to_string(dir.join(format!("{}.json", document_id))).await {
+        Ok(content) => Ok(Some(serde_json::from_str(&content)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Write an edited document over its stored copy, keeping the stored fields that are not
+/// part of the document model (version, metadata); a document deleted meanwhile is not recreated
+async fn store_edited_document(dir: &std::path::Path, document_id: &str, document: &Document) -> Result<(), std::io::Error> {
+    let Some(mut stored) = load_stored_document(dir, document_id).await? else {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "document not found"));
+    };
+    match (&mut stored, serde_json::to_value(document)?) {
+        (serde_json::Value::Object(fields), serde_json::Value::Object(edited)) => fields.extend(edited),
+        (stored, edited) => *stored = edited,
+    }
+    tokio::fs::write(dir.join(format!("{}.json", document_id)), serde_json::to_string_pretty(&stored)?).await
+}
+
 async fn load_document(documentUUID: &str) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
     let doc_path = get_document_path(documentUUID);
     let content = tokio::fs::read_to_string(&doc_path).await?;
@@ -196,6 +326,9 @@ struct MusicXmlImportRequest { xml: String, #[serde(default)] prefer_minor: bool
 #[derive(Debug, Serialize)]
 struct MusicXmlImportResponse { document: crate::models::core::Document }
 
+#[derive(Debug, Deserialize)]
+struct LilyPondImportRequest { source: String }
+
 use crate::parse::Document;
 use crate::parse::actions::{TransformRequest, apply_octave_transform, apply_slur_transform};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
@@ -308,13 +441,70 @@ pub struct SplitLineResponse {
 }
 
 use crate::document::edit::structural::Clipboard;
+use crate::document::edit::history::EditHistory;
 
 // App state for managing shared resources like the clipboard
 struct AppState {
     clipboard: Arc<Mutex<Option<Clipboard>>>,
+    /// Undo histories of the stored documents edited through `/api/document/edit`, by document UUID
+    edit_histories: Arc<Mutex<HashMap<String, EditHistory>>>,
+    parse_cache: Arc<Mutex<ParseCache>>,
 }
 
-pub async fn start_server() -> Result<(), Box<dyn std::error::Error>> {
+/// Number of `/api/parse` responses kept, so retyping recent input is not parsed again
+const PARSE_CACHE_CAPACITY: usize = 64;
+
+/// Least recently used `/api/parse` responses, keyed by a hash of the input and system
+struct ParseCache {
+    entries: HashMap<u64, (ParseRequest, ParseResponse)>,
+    /// Keys from least to most recently used
+    order: VecDeque<u64>,
+    capacity: usize,
+    hits: u64,
+}
+
+impl ParseCache {
+    fn new(capacity: usize) -> Self {
+        Self { entries: HashMap::new(), order: VecDeque::new(), capacity, hits: 0 }
+    }
+
+    fn key(request: &ParseRequest) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        (&request.input, &request.system).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Cached response for the same input and system, counted as a hit
+    fn get(&mut self, request: &ParseRequest) -> Option<ParseResponse> {
+        let key = Self::key(request);
+        let (cached_request, response) = self.entries.get(&key)?;
+        if cached_request != request {
+            return None;
+        }
+        let response = response.clone();
+        self.hits += 1;
+        self.order.retain(|k| *k != key);
+        self.order.push_back(key);
+        Some(response)
+    }
+
+    fn insert(&mut self, request: ParseRequest, response: ParseResponse) {
+        let key = Self::key(&request);
+        self.order.retain(|k| *k != key);
+        self.order.push_back(key);
+        self.entries.insert(key, (request, response));
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Default directory served at `/` when no `--static-dir` override is given
+pub const DEFAULT_STATIC_DIR: &str = "webapp/public";
+
+pub async fn start_server(addr: SocketAddr, static_dir: &str, debug: bool) -> Result<(), Box<dyn std::error::Error>> {
     // Preload CSS file on server startup
     match std::fs::read_to_string("assets/svg-styles.css") {
         Ok(_css_content) => {
@@ -325,36 +515,64 @@ pub async fn start_server() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    let app = router(static_dir, debug);
+
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+            return Err(format!("Cannot start server: {} is already in use (try --port or --bind)", addr).into());
+        }
+        Err(e) => return Err(format!("Cannot bind to {}: {}", addr, e).into()),
+    };
+    
+    println!("🎵 Music-Text Parser Web UI running on http://{}", addr);
+    println!("📝 Open your browser and start typing notation!");
+    
+    axum::serve(listener, app).await.unwrap();
+    
+    Ok(())
+}
+
+/// All routes of the web UI and its API, serving `static_dir` at `/`
+fn router(static_dir: &str, debug: bool) -> Router {
     // Shared state for the application
     let shared_state = Arc::new(AppState {
         clipboard: Arc::new(Mutex::new(None)),
+        edit_histories: Arc::new(Mutex::new(HashMap::new())),
+        parse_cache: Arc::new(Mutex::new(ParseCache::new(PARSE_CACHE_CAPACITY))),
     });
 
-    let app = Router::new()
+    let mut api = Router::new();
+    if debug {
+        api = api.route("/api/debug/tokens", get(debug_tokens_handler));
+    }
+
+    api
         .route("/api/import/musicxml", post(import_musicxml_handler))
+        .route("/api/import/lilypond", post(import_lilypond_handler))
+        .route("/api/parse", post(parse_handler))
+        .route("/api/parse/batch", post(parse_batch_handler))
+        .route("/api/overlay", get(overlay_handler))
+        .route("/api/audio", post(audio_handler))
+        .route("/api/incipit", get(incipit_handler))
+        .route("/render", get(render_query_handler))
+        .route("/api/png", post(png_handler))
         // RESTful Document API endpoints
-        .route("/api/documents", post(create_document_handler))
-        .route("/api/documents/:documentUUID", get(get_document_by_id_handler))
+        .route("/api/documents", post(create_document_handler).get(list_documents_handler))
+        .route("/api/documents/:documentUUID", get(get_document_by_id_handler).delete(delete_document_handler))
         // Rails-style document processing endpoints
         .route("/api/documents/render", post(render_document_handler))
         .route("/api/documents/transform", post(transform_document_handler))
         .route("/api/documents/export", post(export_document_handler))
+        .route("/api/document/edit", post(document_edit_handler))
+        .route("/api/document/undo", post(document_undo_handler))
+        .route("/api/document/redo", post(document_redo_handler))
+        .route("/api/health", get(health_endpoint))
         .route("/health", get(health_endpoint))
         .nest_service("/assets", ServeDir::new("assets"))
-        .nest_service("/", ServeDir::new("webapp/public"))
+        .nest_service("/", ServeDir::new(static_dir))
         .layer(CorsLayer::permissive())
-        .with_state(shared_state);
-
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
-        .await
-        .unwrap();
-    
-    println!("🎵 Music-Text Parser Web UI running on http://127.0.0.1:3000");
-    println!("📝 Open your browser and start typing notation!");
-    
-    axum::serve(listener, app).await.unwrap();
-    
-    Ok(())
+        .with_state(shared_state)
 }
 
 async fn render_from_model(Json(request): Json<RenderFromModelRequest>) -> impl IntoResponse {
@@ -426,6 +644,16 @@ async fn import_musicxml_handler(Json(payload): Json<MusicXmlImportRequest>) ->
     }
 }
 
+async fn import_lilypond_handler(Json(payload): Json<LilyPondImportRequest>) -> impl IntoResponse {
+    match import_lilypond(&payload.source) {
+        Ok(document) => {
+            let resp = MusicXmlImportResponse { document };
+            (StatusCode::OK, Json(resp)).into_response()
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": format!("{}", e)}))).into_response(),
+    }
+}
+
 // Duplicate removed; defined earlier
 
 
@@ -457,6 +685,8 @@ async fn create_document_handler(
                     column: 0,
                     index_in_doc: 0,
                     index_in_line: 0,
+                    clef: None,
+                    grand_staff: None,
                     lines: vec![
                         crate::models::StaveLine::ContentLine(crate::models::ContentLine {
                             id: Uuid::new_v4(),
@@ -488,6 +718,8 @@ async fn create_document_handler(
                     column: 1,
                     index_in_doc: 0,
                     index_in_line: 0,
+                    clef: None,
+                    grand_staff: None,
                     lines: vec![
                         crate::models::StaveLine::ContentLine(crate::models::ContentLine {
                             id: Uuid::new_v4(),
@@ -620,6 +852,7 @@ async fn transform_document_handler(Json(request): Json<TransformDocumentRequest
                         document: request.document,
                         updated_elements: vec![],
                         message: Some(format!("Failed to deserialize document for text editing: {}", e)),
+                        code: None,
                         svg: None,
                         formats: None,
                     });
@@ -670,6 +903,7 @@ async fn transform_document_handler(Json(request): Json<TransformDocumentRequest
                                 document: request.document,
                                 updated_elements: vec![],
                                 message: Some(format!("Failed to delete selection: {}", e)),
+                                code: None,
                                 svg: None,
                         formats: None,
                             });
@@ -693,6 +927,7 @@ async fn transform_document_handler(Json(request): Json<TransformDocumentRequest
                             document: request.document,
                             updated_elements: vec![],
                             message: Some(format!("Failed to parse input text '{}': {}", text, e)),
+                            code: Some(e.kind.code().to_string()),
                             svg: None,
                         formats: None,
                         });
@@ -718,6 +953,7 @@ async fn transform_document_handler(Json(request): Json<TransformDocumentRequest
                             document: request.document,
                             updated_elements: vec![],
                             message: Some(format!("Character insertion failed: {}", e)),
+                            code: None,
                             svg: None,
                         formats: None,
                         }),
@@ -761,6 +997,7 @@ async fn transform_document_handler(Json(request): Json<TransformDocumentRequest
                         document: request.document,
                         updated_elements: vec![],
                         message: Some(format!("Failed to insert text: {}", e)),
+                        code: None,
                         svg: None,
                         formats: None,
                     });
@@ -777,6 +1014,7 @@ async fn transform_document_handler(Json(request): Json<TransformDocumentRequest
                         document: request.document,
                         updated_elements: vec![],
                         message: Some(format!("Failed to deserialize document for text deletion: {}", e)),
+                        code: None,
                         svg: None,
                         formats: None,
                     });
@@ -861,6 +1099,7 @@ async fn transform_document_handler(Json(request): Json<TransformDocumentRequest
                         document: request.document,
                         updated_elements: vec![],
                         message: Some(format!("Failed to delete text: {}", e)),
+                        code: None,
                         svg: None,
                         formats: None,
                     });
@@ -966,6 +1205,7 @@ async fn transform_document_handler(Json(request): Json<TransformDocumentRequest
                     document: request.document,
                     updated_elements: vec![],
                     message: Some(format!("No notes found with the provided UUIDs. Searched for {} UUIDs in JSON document.", request.target_uuids.len())),
+                    code: None,
                     svg: None,
                         formats: None,
                 });
@@ -996,6 +1236,7 @@ async fn transform_document_handler(Json(request): Json<TransformDocumentRequest
                 document: request.document,
                 updated_elements: vec![],
                 message: Some(format!("Unknown command type: {}", request.command_type)),
+                code: None,
                 svg: None,
                         formats: None,
             });
@@ -1031,6 +1272,7 @@ async fn transform_document_handler(Json(request): Json<TransformDocumentRequest
         document: updated_document,
         updated_elements,
         message: Some(format!("Applied {} to {} elements", request.command_type, request.target_uuids.len())),
+        code: None,
         svg: svg_content,
         formats,
     })
@@ -1047,6 +1289,7 @@ pub struct RenderDocumentResponse {
     pub success: bool,
     pub document: serde_json::Value,
     pub formats: DocumentFormats,
+    pub directives: crate::pipeline::DirectiveReport,
     pub message: Option<String>,
 }
 
@@ -1060,6 +1303,7 @@ async fn render_document_handler(Json(request): Json<RenderDocumentRequest>) ->
                 success: false,
                 document: request.document,
                 formats: DocumentFormats { vexflow_svg: None, editor_svg: None, lilypond_svg: None, lilypond_src: None, midi: None },
+                directives: Default::default(),
                 message: Some(format!("Failed to deserialize document: {}", e)),
             }).into_response();
         }
@@ -1083,6 +1327,9 @@ async fn render_document_handler(Json(request): Json<RenderDocumentRequest>) ->
         midi: None,
     };
 
+    // Report which directives took effect
+    let directives = crate::pipeline::check_directives(doc.directives.keys().map(|k| k.as_str()));
+
     // Convert updated document back to JSON
     let updated_document = serde_json::to_value(&doc).unwrap_or(request.document);
 
@@ -1090,6 +1337,7 @@ async fn render_document_handler(Json(request): Json<RenderDocumentRequest>) ->
         success: true,
         document: updated_document,
         formats,
+        directives,
         message: Some("Document rendered successfully".to_string()),
     }).into_response()
 }
@@ -1176,6 +1424,76 @@ async fn export_document_handler(Json(request): Json<ExportDocumentRequest>) ->
                 }
             }
         }
+        "abc" => {
+            let updated_document = serde_json::to_value(&doc).unwrap_or(request.document);
+            Json(ExportDocumentResponse {
+                success: true,
+                document: updated_document,
+                format: request.format,
+                content: crate::renderers::abc::render_abc(&doc),
+                message: Some("ABC notation generated successfully".to_string()),
+            }).into_response()
+        }
+        "musicxml" => {
+            let updated_document = serde_json::to_value(&doc).unwrap_or(request.document);
+            Json(ExportDocumentResponse {
+                success: true,
+                document: updated_document,
+                format: request.format,
+                content: crate::renderers::musicxml::render_musicxml(&doc),
+                message: Some("MusicXML generated successfully".to_string()),
+            }).into_response()
+        }
+        "staff-svg" => {
+            let updated_document = serde_json::to_value(&doc).unwrap_or(request.document);
+            Json(ExportDocumentResponse {
+                success: true,
+                document: updated_document,
+                format: request.format,
+                content: crate::renderers::svg::render_staff_svg(&doc),
+                message: Some("Staff notation SVG generated successfully".to_string()),
+            }).into_response()
+        }
+        "jianpu-html" => {
+            let updated_document = serde_json::to_value(&doc).unwrap_or(request.document);
+            Json(ExportDocumentResponse {
+                success: true,
+                document: updated_document,
+                format: request.format,
+                content: crate::renderers::jianpu::render_jianpu_html(&doc),
+                message: Some("Jianpu HTML generated successfully".to_string()),
+            }).into_response()
+        }
+        "interactive-html" => {
+            // Self-contained page: SVG + timed events + embedded MIDI with playback highlighting
+            let tempo = request.options
+                .as_ref()
+                .and_then(|o| o.get("tempo"))
+                .and_then(|v| v.as_u64())
+                .map(|v| v as u32)
+                .unwrap_or(crate::renderers::midi::DEFAULT_TEMPO_BPM);
+            match crate::renderers::html::render_interactive_html(&doc, tempo) {
+                Ok(html) => {
+                    let updated_document = serde_json::to_value(&doc).unwrap_or(request.document);
+                    Json(ExportDocumentResponse {
+                        success: true,
+                        document: updated_document,
+                        format: request.format,
+                        content: html,
+                        message: Some("Interactive HTML generated successfully".to_string()),
+                    }).into_response()
+                }
+                Err(e) => {
+                    Json(ExportDocumentResponse {
+                        success: false,
+                        document: request.document,
+                        format: request.format,
+                        content: String::new(),
+                        message: Some(format!("Failed to generate interactive HTML: {}", e)),
+                    }).into_response()
+                }
+            }
+        }
         _ => {
             let format_str = request.format.clone();
             Json(ExportDocumentResponse {
@@ -1213,8 +1531,409 @@ async fn get_document_by_id_handler(
 
 
 
-async fn health_endpoint() -> impl IntoResponse {
-    Json(serde_json::json!({"status": "ok"}))
+/// Stored documents with their id, title and timestamp, paged by `limit` and `offset`
+async fn list_documents_handler(Query(query): Query<DocumentListQuery>) -> impl IntoResponse {
+    match list_stored_documents(&get_documents_dir(), &query).await {
+        Ok(list) => Json(list).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": format!("Failed to list documents: {}", e)}))).into_response(),
+    }
+}
+
+async fn delete_document_handler(State(state): State<Arc<AppState>>, Path(document_id): Path<String>) -> impl IntoResponse {
+    delete_document_response(&state.edit_histories, &get_documents_dir(), &document_id).await
+}
+
+/// Delete a stored document along with its edit history
+async fn delete_document_response(histories: &Mutex<HashMap<String, EditHistory>>, dir: &std::path::Path, document_id: &str) -> Response {
+    match delete_stored_document(dir, document_id).await {
+        Ok(true) => {
+            histories.lock().unwrap_or_else(|e| e.into_inner()).remove(document_id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Document not found", "documentUUID": document_id}))).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": format!("Failed to delete document: {}", e)}))).into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DocumentEditRequest {
+    /// UUID of the stored document to edit
+    pub document_uuid: String,
+    pub command_type: String,
+    pub target_uuids: Vec<String>,
+    pub parameters: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DocumentHistoryRequest {
+    pub document_uuid: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DocumentEditResponse {
+    pub success: bool,
+    pub document: Option<Document>,
+    pub message: Option<String>,
+}
+
+impl DocumentEditResponse {
+    fn failure(message: String) -> Self {
+        Self { success: false, document: None, message: Some(message) }
+    }
+}
+
+/// Apply an edit to a stored document, recording it in that document's history so it can
+/// be undone, and save the edited document back to the store
+async fn edit_stored_document(
+    histories: &Mutex<HashMap<String, EditHistory>>,
+    dir: &std::path::Path,
+    request: DocumentEditRequest,
+) -> DocumentEditResponse {
+    let document_id = request.document_uuid;
+    let has_history = histories.lock().unwrap_or_else(|e| e.into_inner()).contains_key(&document_id);
+    if !has_history {
+        let document = match load_stored_document(dir, &document_id).await {
+            Ok(Some(stored)) => match serde_json::from_value::<Document>(stored) {
+                Ok(document) => document,
+                Err(e) => return DocumentEditResponse::failure(format!("Stored document {} is not readable: {}", document_id, e)),
+            },
+            Ok(None) => return DocumentEditResponse::failure(format!("Document not found: {}", document_id)),
+            Err(e) => return DocumentEditResponse::failure(format!("Failed to load document {}: {}", document_id, e)),
+        };
+        histories.lock().unwrap_or_else(|e| e.into_inner())
+            .entry(document_id.clone())
+            .or_insert_with(|| EditHistory::new(document));
+    }
+
+    let parameters = request.parameters.unwrap_or_else(|| serde_json::json!({}));
+    let (document, outcome) = {
+        let mut histories = histories.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(history) = histories.get_mut(&document_id) else {
+            return DocumentEditResponse::failure(format!("Document not found: {}", document_id));
+        };
+        let outcome = history.apply(&request.command_type, &request.target_uuids, &parameters);
+        (history.document().clone(), outcome)
+    };
+    save_history_change(dir, &document_id, document, outcome).await
+}
+
+/// Undo (or redo) the last edit of a stored document and save the result
+async fn step_stored_document_history(
+    histories: &Mutex<HashMap<String, EditHistory>>,
+    dir: &std::path::Path,
+    document_id: &str,
+    redo: bool,
+) -> DocumentEditResponse {
+    let (document, outcome) = {
+        let mut histories = histories.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(history) = histories.get_mut(document_id) else {
+            return DocumentEditResponse::failure(format!("Document {} has not been edited", document_id));
+        };
+        let outcome = match redo {
+            false if history.undo() => Ok(()),
+            false => Err("Nothing to undo".to_string()),
+            true if history.redo() => Ok(()),
+            true => Err("Nothing to redo".to_string()),
+        };
+        (history.document().clone(), outcome)
+    };
+    save_history_change(dir, document_id, document, outcome).await
+}
+
+/// Save a document whose history changed and report it; a failed change leaves the store alone
+async fn save_history_change(dir: &std::path::Path, document_id: &str, document: Document, outcome: Result<(), String>) -> DocumentEditResponse {
+    let outcome = match outcome {
+        Ok(()) => store_edited_document(dir, document_id, &document).await
+            .map_err(|e| format!("Failed to save document {}: {}", document_id, e)),
+        Err(error) => Err(error),
+    };
+    DocumentEditResponse { success: outcome.is_ok(), document: Some(document), message: outcome.err() }
+}
+
+async fn document_edit_handler(State(state): State<Arc<AppState>>, Json(request): Json<DocumentEditRequest>) -> Json<DocumentEditResponse> {
+    Json(edit_stored_document(&state.edit_histories, &get_documents_dir(), request).await)
+}
+
+async fn document_undo_handler(State(state): State<Arc<AppState>>, Json(request): Json<DocumentHistoryRequest>) -> Json<DocumentEditResponse> {
+    Json(step_stored_document_history(&state.edit_histories, &get_documents_dir(), &request.document_uuid, false).await)
+}
+
+async fn document_redo_handler(State(state): State<Arc<AppState>>, Json(request): Json<DocumentHistoryRequest>) -> Json<DocumentEditResponse> {
+    Json(step_stored_document_history(&state.edit_histories, &get_documents_dir(), &request.document_uuid, true).await)
+}
+
+/// Liveness check for load balancers and integrators; parses nothing
+async fn health_endpoint(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let parse_cache_hits = state.parse_cache.lock().map(|cache| cache.hits).unwrap_or(0);
+    Json(serde_json::json!({"status": "ok", "version": API_VERSION, "parse_cache_hits": parse_cache_hits}))
+}
+
+/// Document and renderings of notation text, served from the cache when the same
+/// input and system were parsed recently
+async fn parse_handler(State(state): State<Arc<AppState>>, Json(request): Json<ParseRequest>) -> impl IntoResponse {
+    if let Some(response) = state.parse_cache.lock().ok().and_then(|mut cache| cache.get(&request)) {
+        return Json(response);
+    }
+    let response = parse_notation(&request);
+    if let Ok(mut cache) = state.parse_cache.lock() {
+        cache.insert(request, response.clone());
+    }
+    Json(response)
+}
+
+/// Parse notation text into a document with its LilyPond, VexFlow and editor renderings.
+/// A requested system is applied as a `System` directive before the input.
+fn parse_notation(request: &ParseRequest) -> ParseResponse {
+    let system = request.system.as_deref().and_then(crate::parse::line_classifier::notation_system_named);
+    let document = match crate::pipeline::document_from_notation_with_system(&request.input, system) {
+        Ok(document) => document,
+        Err(error) => return ParseResponse::failure(error),
+    };
+    let mut systems: Vec<String> = Vec::new();
+    for element in &document.elements {
+        if let crate::models::DocumentElement::Stave(stave) = element {
+            let name = format!("{:?}", stave.notation_system).to_lowercase();
+            if !systems.contains(&name) {
+                systems.push(name);
+            }
+        }
+    }
+    ParseResponse {
+        success: true,
+        plain_text: Some(document.to_plain_text()),
+        detected_notation_systems: Some(systems),
+        notation_system_scores: Some(crate::parse::line_classifier::notation_system_scores_in(document.value.as_deref().unwrap_or_default(), system)),
+        lilypond: crate::renderers::lilypond::renderer::convert_processed_document_to_lilypond_src(&document, None).ok(),
+        vexflow: Some(crate::renderers::vexflow::VexFlowRenderer::new().render_data_from_document(&document)),
+        editor_svg: crate::renderers::editor::svg::render_editor_svg(&document, None, None, None).ok(),
+        warnings: crate::pipeline::notation_warnings(&document),
+        document: Some(document),
+        error: None,
+        ..ParseResponse::failure(String::new())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchParseRequest {
+    items: Vec<BatchParseItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchParseItem {
+    id: String,
+    #[serde(flatten)]
+    request: ParseRequest,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchParseResult {
+    id: String,
+    #[serde(flatten)]
+    response: ParseResponse,
+}
+
+/// Snippets of a batch parsed at the same time
+const BATCH_PARSE_CONCURRENCY: usize = 8;
+
+/// Parse many snippets in one request, each exactly as `/api/parse` would. Results
+/// keep the order of the items, and a snippet that fails only fails its own result.
+async fn parse_batch_handler(Json(batch): Json<BatchParseRequest>) -> Json<Vec<BatchParseResult>> {
+    let permits = Arc::new(tokio::sync::Semaphore::new(BATCH_PARSE_CONCURRENCY));
+    let tasks: Vec<_> = batch.items.into_iter().map(|item| {
+        let permits = permits.clone();
+        let task = tokio::spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            tokio::task::spawn_blocking(move || parse_notation(&item.request)).await
+        });
+        (item.id, task)
+    }).collect();
+
+    let mut results = Vec::with_capacity(tasks.len());
+    for (id, task) in tasks {
+        let response = match task.await {
+            Ok(Ok(response)) => response,
+            Ok(Err(error)) | Err(error) => ParseResponse::failure(format!("Parse failed: {}", error)),
+        };
+        results.push(BatchParseResult { id, response });
+    }
+    Json(results)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DebugTokensQuery {
+    input: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DebugToken {
+    #[serde(rename = "type")]
+    token_type: String,
+    value: String,
+    column: usize,
+    length: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DebugLine {
+    line: usize,
+    role: &'static str,
+    text: String,
+    notation_system: Option<crate::models::NotationSystem>,  // System the line is parsed with
+    detected_notation_system: Option<crate::models::NotationSystem>,  // System the line looks like on its own
+    tokens: Vec<DebugToken>,
+}
+
+/// Classified role and tokens of every input line, for diagnosing misparses.
+/// Only routed when the server runs with `--debug`.
+async fn debug_tokens_handler(Query(query): Query<DebugTokensQuery>) -> impl IntoResponse {
+    Json(debug_tokens(&query.input))
+}
+
+fn debug_tokens(input: &str) -> Vec<DebugLine> {
+    use crate::document::line_parser::content_line_parser::{parse_content_line_with_mode, ParseMode};
+    use crate::parse::line_classifier::{classify_line_types, detect_line_notation_system, LineType};
+    use crate::parse::model::{ContentElement, HasPosition};
+
+    classify_line_types(input).into_iter().enumerate().map(|(idx, (line_type, text))| {
+        let line = idx + 1;
+        if text.trim().is_empty() {
+            return DebugLine { line, role: "blank", text: text.to_string(), notation_system: None, detected_notation_system: None, tokens: Vec::new() };
+        }
+
+        let (notation_system, tokens) = match line_type {
+            LineType::Content(system) => {
+                let elements = parse_content_line_with_mode(text, line, system, 0, ParseMode::Lossless)
+                    .map(|content_line| content_line.elements)
+                    .unwrap_or_default();
+                // Beats are listed element by element so each note shows its own position
+                let tokens = elements.iter().flat_map(|element| -> Vec<&dyn HasPosition> {
+                    match element {
+                        ContentElement::Beat(beat) => beat.elements.iter().map(|e| e as &dyn HasPosition).collect(),
+                        other => vec![other],
+                    }
+                })
+                .map(|token| DebugToken {
+                    token_type: token.type_name().to_string(),
+                    value: token.value().cloned().unwrap_or_default(),
+                    column: token.source().map_or(0, |source| source.column),
+                    length: token.source().map_or(0, |source| source.length),
+                })
+                .collect();
+                (Some(system), tokens)
+            }
+            _ => {
+                let mut column = 0;
+                let tokens = text.split(' ').filter_map(|word| {
+                    let start = column;
+                    column += word.chars().count() + 1;
+                    (!word.is_empty()).then(|| DebugToken {
+                        token_type: "Word".to_string(),
+                        value: word.to_string(),
+                        column: start + 1,
+                        length: word.chars().count(),
+                    })
+                }).collect();
+                (None, tokens)
+            }
+        };
+
+        DebugLine {
+            line,
+            role: line_type.role(),
+            text: text.to_string(),
+            notation_system,
+            detected_notation_system: Some(detect_line_notation_system(text)),
+            tokens,
+        }
+    }).collect()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OverlayQuery {
+    input: String,
+}
+
+/// Slur and beat-group spans of the notation with their CSS classes and variables,
+/// for drawing overlays on top of any renderer's output
+async fn overlay_handler(Query(query): Query<OverlayQuery>) -> impl IntoResponse {
+    Json(crate::document::overlay::overlay_spans(&query.input))
+}
+
+/// Notation rendered as a sine-wave WAV at the document's `Tempo` (120 BPM by default)
+async fn audio_handler(Json(request): Json<ParseRequest>) -> Response {
+    match crate::pipeline::document_from_notation(&request.input) {
+        Ok(document) => {
+            let tempo = crate::renderers::midi::events::tempo_from_directives(&document.directives);
+            let wav = crate::renderers::audio::render_wav(&document, tempo);
+            ([(header::CONTENT_TYPE, "audio/wav")], wav).into_response()
+        }
+        Err(error) => (StatusCode::BAD_REQUEST, error).into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IncipitQuery {
+    input: String,
+    measures: Option<usize>,
+}
+
+/// Measures in an incipit when the query doesn't ask for a number
+const DEFAULT_INCIPIT_MEASURES: usize = 2;
+
+/// SVG thumbnail of the opening measures of the notation, for catalogs and search results
+async fn incipit_handler(Query(query): Query<IncipitQuery>) -> Response {
+    let measures = query.measures.unwrap_or(DEFAULT_INCIPIT_MEASURES);
+    let svg = crate::pipeline::document_from_notation(&query.input)
+        .and_then(|document| crate::renderers::incipit::render_incipit(&document, measures));
+    match svg {
+        Ok(svg) => ([(header::CONTENT_TYPE, "image/svg+xml")], svg).into_response(),
+        Err(error) => (StatusCode::BAD_REQUEST, error).into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RenderQuery {
+    input: String,
+    format: String,
+    /// Quarter notes per minute, the document's `Tempo` when left out
+    tempo: Option<u32>,
+}
+
+/// Notation rendered as a standalone file that can be linked to or downloaded directly
+async fn render_query_handler(Query(query): Query<RenderQuery>) -> Response {
+    let document = match crate::pipeline::document_from_notation(&query.input) {
+        Ok(document) => document,
+        Err(error) => return (StatusCode::BAD_REQUEST, error).into_response(),
+    };
+    match query.format.as_str() {
+        "interactive-html" => {
+            let tempo = query.tempo.unwrap_or_else(|| crate::renderers::midi::tempo_from_directives(&document.directives));
+            match crate::renderers::html::render_interactive_html(&document, tempo) {
+                Ok(html) => ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html).into_response(),
+                Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, error).into_response(),
+            }
+        }
+        format => (StatusCode::BAD_REQUEST, format!("Unsupported render format: {}", format)).into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PngRequest {
+    input: String,
+    /// Pixels per SVG unit, `renderers::png::DEFAULT_SCALE` when left out
+    scale: Option<f32>,
+}
+
+/// Staff notation rendered as a PNG image, for embedding in docs
+async fn png_handler(Json(request): Json<PngRequest>) -> Response {
+    let scale = request.scale.unwrap_or(crate::renderers::png::DEFAULT_SCALE);
+    match crate::pipeline::document_from_notation(&request.input) {
+        Ok(document) => match crate::renderers::png::render_png(&document, scale) {
+            Ok(png) => ([(header::CONTENT_TYPE, "image/png")], png).into_response(),
+            Err(error) => (StatusCode::BAD_REQUEST, error).into_response(),
+        },
+        Err(error) => (StatusCode::BAD_REQUEST, error).into_response(),
+    }
 }
 
 /// Insert parsed elements into the document at the specified UUID location
@@ -1349,3 +2068,274 @@ async fn semantic_command_handler(Json(request): Json<SemanticCommandRequest>) -
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    #[test]
+    fn test_debug_tokens_roles_and_positions() {
+        let lines = debug_tokens("|S R|\n\n@lyrics do re");
+        assert_eq!(lines.len(), 3);
+
+        assert_eq!(lines[0].role, "content");
+        assert_eq!(lines[0].notation_system, Some(crate::models::NotationSystem::Sargam));
+        let notes: Vec<(&str, usize)> = lines[0].tokens.iter()
+            .filter(|t| t.token_type == "Note")
+            .map(|t| (t.value.as_str(), t.column))
+            .collect();
+        assert_eq!(notes, vec![("S", 2), ("R", 4)]);
+
+        assert_eq!(lines[1].role, "blank");
+
+        assert_eq!(lines[2].role, "lyrics");
+        assert_eq!(lines[2].notation_system, None);
+        assert_eq!(lines[2].tokens.iter().map(|t| t.column).collect::<Vec<_>>(), vec![1, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_health_route_reports_version() {
+        use tower::ServiceExt;
+        let request = axum::http::Request::get("/api/health").body(Body::empty()).unwrap();
+        let response = router(DEFAULT_STATIC_DIR, false).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json, serde_json::json!({"status": "ok", "version": env!("CARGO_PKG_VERSION"), "parse_cache_hits": 0}));
+    }
+
+    #[tokio::test]
+    async fn test_repeated_parse_is_served_from_cache() {
+        use tower::ServiceExt;
+        let app = router(DEFAULT_STATIC_DIR, false);
+        let body_of = |response: Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+        let parse = || axum::http::Request::post("/api/parse")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"input": "|1 2 3 4|"}"#))
+            .unwrap();
+
+        let first = body_of(app.clone().oneshot(parse()).await.unwrap()).await;
+        let second = body_of(app.clone().oneshot(parse()).await.unwrap()).await;
+        assert_eq!(first["success"], true);
+        assert_eq!(first, second);
+
+        let health = axum::http::Request::get("/api/health").body(Body::empty()).unwrap();
+        assert_eq!(body_of(app.oneshot(health).await.unwrap()).await["parse_cache_hits"], 1);
+    }
+
+    #[test]
+    fn test_parse_reports_crossing_spans() {
+        let response = parse_notation(&ParseRequest { input: "  ___\n(  )\n1 2 3 4\n".to_string(), system: None });
+        assert!(response.success);
+        let messages: Vec<&str> = response.warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages, vec!["Slur crosses the end of a phrasing slur"]);
+    }
+
+    #[test]
+    fn test_parse_ranks_notation_systems() {
+        let response = parse_notation(&ParseRequest { input: "|S R G M|".to_string(), system: None });
+        let scores = response.notation_system_scores.unwrap();
+        assert_eq!(scores[0].0, crate::models::NotationSystem::Sargam);
+        assert!(scores.len() > 1);
+
+        let response = parse_notation(&ParseRequest { input: "|1 2 3|".to_string(), system: None });
+        assert_eq!(response.notation_system_scores.unwrap()[0].0, crate::models::NotationSystem::Number);
+
+        // The top score is the system the notes were read in
+        for (input, system) in [("| C D E |", "western"), ("| r 1 2 3 |", "number"), ("| S r G |", "sargam")] {
+            let response = parse_notation(&ParseRequest { input: input.to_string(), system: None });
+            let top = response.notation_system_scores.unwrap()[0].0;
+            assert_eq!(format!("{:?}", top).to_lowercase(), system, "{}", input);
+            assert_eq!(response.detected_notation_systems.unwrap(), vec![system.to_string()], "{}", input);
+        }
+        let response = parse_notation(&ParseRequest { input: "| C D E |".to_string(), system: Some("sargam".to_string()) });
+        assert_eq!(response.notation_system_scores.unwrap(), vec![(crate::models::NotationSystem::Sargam, 1.0)]);
+
+        // The requested system leaves the input as written: the title and line numbers stay put
+        let input = "My Song\n\n| S R G |\n";
+        let response = parse_notation(&ParseRequest { input: input.to_string(), system: Some("sargam".to_string()) });
+        assert_eq!(response.plain_text.as_deref(), Some(input));
+        let document = response.document.unwrap();
+        assert_eq!(document.title.as_deref(), Some("My Song"));
+        assert_eq!(document.notes().next().unwrap().source.unwrap().line, 3);
+    }
+
+    #[test]
+    fn test_parse_cache_evicts_least_recently_used() {
+        let request = |input: &str| ParseRequest { input: input.to_string(), system: None };
+        let mut cache = ParseCache::new(2);
+        cache.insert(request("1"), parse_notation(&request("1")));
+        cache.insert(request("2"), parse_notation(&request("2")));
+        assert!(cache.get(&request("1")).is_some());
+        cache.insert(request("3"), parse_notation(&request("3")));
+        assert!(cache.get(&request("2")).is_none());
+        assert!(cache.get(&request("1")).is_some());
+        assert!(cache.get(&ParseRequest { system: Some("sargam".to_string()), ..request("1") }).is_none());
+        assert_eq!(cache.hits, 2);
+    }
+
+    #[tokio::test]
+    async fn test_audio_endpoint_returns_wav() {
+        let request = ParseRequest { input: "Tempo: 240\n\n|1 2 3 4|".to_string(), system: None };
+        let response = audio_handler(Json(request)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "audio/wav");
+        let wav = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!((&wav[0..4], &wav[8..12]), (&b"RIFF"[..], &b"WAVE"[..]));
+        // Four quarter notes at 240 BPM last one second
+        let data_len = u32::from_le_bytes([wav[40], wav[41], wav[42], wav[43]]);
+        assert_eq!(data_len, crate::renderers::audio::SAMPLE_RATE * 2);
+
+        let request = ParseRequest { input: "|1 2 :3|".to_string(), system: None };
+        assert_eq!(audio_handler(Json(request)).await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_png_endpoint_returns_image() {
+        let request = PngRequest { input: "|1 2 3 4|".to_string(), scale: Some(1.5) };
+        let response = png_handler(Json(request)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        let png = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+        let request = PngRequest { input: "|1 2 :3|".to_string(), scale: None };
+        assert_eq!(png_handler(Json(request)).await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_incipit_route_returns_svg() {
+        use tower::ServiceExt;
+        let app = router(DEFAULT_STATIC_DIR, false);
+        let request = axum::http::Request::get("/api/incipit?input=Key%3A%20D%0A%0A%7C1%202%7C3%204%7C5%206%7C&measures=2")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/svg+xml");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8(body.to_vec()).unwrap().starts_with("<svg"));
+
+        let request = axum::http::Request::get("/api/incipit?input=Title").body(Body::empty()).unwrap();
+        assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_render_route_returns_interactive_html() {
+        use tower::ServiceExt;
+        let app = router(DEFAULT_STATIC_DIR, false);
+        let request = axum::http::Request::get("/render?format=interactive-html&input=%7C1%202%203%204%7C%0A")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/html; charset=utf-8");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains("<svg") && html.contains("data:audio/midi;base64,"), "{}", html);
+
+        let request = axum::http::Request::get("/render?format=pdf&input=%7C1%7C").body(Body::empty()).unwrap();
+        assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_batch_parse_keeps_going_past_a_failed_item() {
+        let batch: BatchParseRequest = serde_json::from_value(serde_json::json!({"items": [
+            {"id": "a", "input": "|1 2 3 4|"},
+            {"id": "b", "input": "|1 2 :3|"},
+            {"id": "c", "input": "|S R G M|", "system": "sargam"},
+        ]})).unwrap();
+        let Json(results) = parse_batch_handler(Json(batch)).await;
+        let outcomes: Vec<(&str, bool)> = results.iter().map(|result| (result.id.as_str(), result.response.success)).collect();
+        assert_eq!(outcomes, vec![("a", true), ("b", false), ("c", true)]);
+        assert!(results[1].response.error.is_some());
+        let json = serde_json::to_value(&results[0]).unwrap();
+        assert_eq!((&json["id"], &json["success"]), (&serde_json::json!("a"), &serde_json::json!(true)));
+    }
+
+    #[tokio::test]
+    async fn test_document_store_listing_and_deletion() {
+        let dir = tempfile::tempdir().unwrap();
+        let histories = Mutex::new(HashMap::new());
+        let ids: Vec<String> = (0..3).map(|_| Uuid::new_v4().to_string()).collect();
+        for (i, id) in ids.iter().enumerate() {
+            let document = serde_json::json!({"title": format!("Song {}", i), "timestamp": format!("2026-01-0{}T00:00:00Z", i + 1)});
+            std::fs::write(dir.path().join(format!("{}.json", id)), document.to_string()).unwrap();
+        }
+
+        let query = DocumentListQuery { limit: Some(2), offset: Some(1) };
+        let page = list_stored_documents(dir.path(), &query).await.unwrap();
+        assert_eq!(page.total, 3);
+        // Newest first: Song 2, then Song 1 and Song 0
+        assert_eq!(page.documents, vec![
+            StoredDocumentSummary { id: ids[1].clone(), title: Some("Song 1".to_string()), timestamp: Some("2026-01-02T00:00:00Z".to_string()) },
+            StoredDocumentSummary { id: ids[0].clone(), title: Some("Song 0".to_string()), timestamp: Some("2026-01-01T00:00:00Z".to_string()) },
+        ]);
+
+        assert_eq!(delete_document_response(&histories, dir.path(), &ids[1]).await.status(), StatusCode::NO_CONTENT);
+        assert!(!dir.path().join(format!("{}.json", ids[1])).exists());
+        assert_eq!(delete_document_response(&histories, dir.path(), &ids[1]).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(delete_document_response(&histories, dir.path(), "../Cargo").await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(list_stored_documents(dir.path(), &DocumentListQuery { limit: None, offset: None }).await.unwrap().total, 2);
+    }
+
+    #[tokio::test]
+    async fn test_stored_documents_keep_their_own_edit_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let histories = Mutex::new(HashMap::new());
+        let octaves = |response: &DocumentEditResponse| {
+            response.document.as_ref().unwrap().notes().map(|note| note.octave).collect::<Vec<i8>>()
+        };
+        let stored_octaves = |id: &str| {
+            let content = std::fs::read_to_string(dir.path().join(format!("{}.json", id))).unwrap();
+            let document: Document = serde_json::from_str(&content).unwrap();
+            document.notes().map(|note| note.octave).collect::<Vec<i8>>()
+        };
+
+        let mut first_notes = Vec::new();
+        let ids: Vec<String> = (0..2).map(|_| Uuid::new_v4().to_string()).collect();
+        for id in &ids {
+            let document = crate::pipeline::document_from_notation("| 1 2 |\n").unwrap();
+            first_notes.push(document.notes().next().unwrap().id.to_string());
+            let mut stored = serde_json::to_value(&document).unwrap();
+            stored["metadata"] = serde_json::json!({"owner": "test"});
+            std::fs::write(dir.path().join(format!("{}.json", id)), stored.to_string()).unwrap();
+        }
+        let edit = |index: usize| DocumentEditRequest {
+            document_uuid: ids[index].clone(),
+            command_type: "set_octave".to_string(),
+            target_uuids: vec![first_notes[index].clone()],
+            parameters: Some(serde_json::json!({"octave_type": "highest"})),
+        };
+
+        assert!(!step_stored_document_history(&histories, dir.path(), &ids[0], false).await.success);
+        assert_eq!(octaves(&edit_stored_document(&histories, dir.path(), edit(0)).await), vec![2, 0]);
+        assert_eq!(octaves(&edit_stored_document(&histories, dir.path(), edit(1)).await), vec![2, 0]);
+        assert_eq!(stored_octaves(&ids[0]), vec![2, 0]);
+
+        // Undoing one document leaves the other's edit in place
+        let undone = step_stored_document_history(&histories, dir.path(), &ids[0], false).await;
+        assert_eq!((undone.success, octaves(&undone)), (true, vec![0, 0]));
+        assert_eq!((stored_octaves(&ids[0]), stored_octaves(&ids[1])), (vec![0, 0], vec![2, 0]));
+        assert!(!step_stored_document_history(&histories, dir.path(), &ids[0], false).await.success);
+        assert_eq!(octaves(&step_stored_document_history(&histories, dir.path(), &ids[0], true).await), vec![2, 0]);
+
+        // Fields outside the document model survive the save
+        let content = std::fs::read_to_string(dir.path().join(format!("{}.json", ids[0]))).unwrap();
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&content).unwrap()["metadata"]["owner"], "test");
+
+        let missing = DocumentEditRequest { document_uuid: Uuid::new_v4().to_string(), ..edit(0) };
+        assert!(edit_stored_document(&histories, dir.path(), missing).await.message.unwrap().contains("not found"));
+
+        // Deleting a document drops its history, and an edit saved after it is gone is not written
+        assert_eq!(delete_document_response(&histories, dir.path(), &ids[0]).await.status(), StatusCode::NO_CONTENT);
+        assert!(!histories.lock().unwrap().contains_key(&ids[0]));
+        std::fs::remove_file(dir.path().join(format!("{}.json", ids[1]))).unwrap();
+        assert!(!edit_stored_document(&histories, dir.path(), edit(1)).await.success);
+        assert!(!dir.path().join(format!("{}.json", ids[1])).exists());
+    }
+}
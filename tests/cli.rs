@@ -0,0 +1,60 @@
+use std::process::Command;
+
+fn music_text(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_music-text"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn converts_file_to_lilypond_on_stdout() {
+    let output = music_text(&["--input", "tests/fixtures/scale.txt", "--to", "lilypond"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let lilypond = String::from_utf8(output.stdout).unwrap();
+    assert!(lilypond.contains("\\version"), "{}", lilypond);
+}
+
+#[test]
+fn writes_midi_to_output_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scale.mid");
+    let output = music_text(&["--input", "tests/fixtures/scale.txt", "--to", "midi", "--output", path.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(std::fs::read(&path).unwrap().starts_with(b"MThd"));
+}
+
+#[test]
+fn parse_error_reports_position_and_fails() {
+    let output = music_text(&["--input", "tests/fixtures/bad_barline.txt", "--to", "abc"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("--> line 1, column"), "{}", stderr);
+}
+
+#[test]
+fn validate_accepts_valid_file_without_rendering() {
+    let output = music_text(&["--validate", "tests/fixtures/scale.txt"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn validate_reports_error_kind_and_position() {
+    let output = music_text(&["--validate", "tests/fixtures/bad_barline.txt"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error[missing_barline]: tests/fixtures/bad_barline.txt:1:6: "), "{}", stderr);
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn flattened_view_shows_consumed_markers_on_request() {
+    let hidden = music_text(&["--input", "tests/fixtures/octave_dot.txt", "--to", "flattened"]);
+    assert!(hidden.status.success(), "{}", String::from_utf8_lossy(&hidden.stderr));
+    assert_eq!(String::from_utf8(hidden.stdout).unwrap(), "\n1 2 3\n");
+
+    let shown = music_text(&["--input", "tests/fixtures/octave_dot.txt", "--to", "flattened", "--show-consumed"]);
+    assert_eq!(String::from_utf8(shown.stdout).unwrap(), "\x1b[7m.\x1b[0m\n1 2 3\n");
+}
@@ -171,7 +171,7 @@ async function renderVexFlowNotation(vexflowData, containerId = 'vexflow-output'
             
             // Convert sophisticated VexFlow elements to renderable notes
             const renderingResult = processVexFlowElementsAdvanced(staveData.notes || [], context, stave);
-            const { notes, beams, tuplets, slurs, ties } = renderingResult;
+            const { notes, beams, tuplets, slurs, ties, multiMeasureRests } = renderingResult;
             
             if (notes.length > 0) {
                 // Create voice
@@ -213,6 +213,9 @@ async function renderVexFlowNotation(vexflowData, containerId = 'vexflow-output'
                 formatter.format([voice], formatterMinWidth);
                 voice.draw(context, stave);
                 
+                // Multi-measure rests fill the space held for them, with the measure count centered above
+                multiMeasureRests.forEach(rest => drawMultiMeasureRest(rest, notes, stave, context));
+                
                 // Draw advanced features
                 beams.forEach(beam => beam.draw());
                 tuplets.forEach(tuplet => tuplet.draw());
@@ -262,25 +265,11 @@ function processBarlines(stave, elements) {
 }
 
 /**
- * Map barline type strings to VexFlow barline types
+ * Map barline type names (VexFlow Barline.type constants, e.g. "REPEAT_BEGIN") to VexFlow barline types
  */
 function mapBarlineType(barType) {
     const { Barline } = Vex.Flow;
-    switch (barType) {
-        case 'repeat-begin':
-            return Barline.type.REPEAT_BEGIN;
-        case 'repeat-end':
-            return Barline.type.REPEAT_END;
-        case 'double':
-            return Barline.type.DOUBLE;
-        case 'final':
-            return Barline.type.END;
-        case 'double-repeat':
-            return Barline.type.REPEAT_BOTH;
-        case 'single':
-        default:
-            return Barline.type.SINGLE;
-    }
+    return Barline.type[barType] ?? Barline.type.SINGLE;
 }
 
 /**
@@ -294,6 +283,7 @@ function processVexFlowElementsAdvanced(elements, context, stave) {
     const tuplets = [];
     const slurs = [];
     const ties = [];
+    const multiMeasureRests = [];
     
     let slurStartNote = null;
     let pendingSlurStart = false;
@@ -426,6 +416,13 @@ function processVexFlowElementsAdvanced(elements, context, stave) {
                 slurStartNote = null;
                 break;
                 
+            case 'MultiMeasureRest':
+                // A ghost whole note holds the rest's place in the voice; the rest is drawn over it after formatting
+                const spacer = new Vex.Flow.GhostNote({ duration: 'w' });
+                notes.push(spacer);
+                multiMeasureRests.push({ spacer, measures: element.measures });
+                break;
+                
             case 'BarLine':
                 // Barlines are handled at the stave level
                 break;
@@ -439,7 +436,27 @@ function processVexFlowElementsAdvanced(elements, context, stave) {
     // Add beaming for consecutive beamable notes
     addBeamsFromFlags(elements, notes, beams, context);
     
-    return { notes, beams, tuplets, slurs, ties };
+    return { notes, beams, tuplets, slurs, ties, multiMeasureRests };
+}
+
+/**
+ * Draw a multi-measure rest between the notes on either side of its place in the voice
+ */
+function drawMultiMeasureRest({ spacer, measures }, notes, stave, context) {
+    const index = notes.indexOf(spacer);
+    const before = notes[index - 1];
+    const after = notes[index + 1];
+    const left = before ? before.getAbsoluteX() + before.getWidth() : stave.getNoteStartX();
+    const right = after ? after.getAbsoluteX() : stave.getNoteEndX();
+    new Vex.Flow.MultiMeasureRest(measures, {
+        show_number: true,
+        number_line: -0.5,
+        padding_left: left - stave.getX(),
+        padding_right: stave.getX() + stave.getWidth() - right
+    })
+        .setStave(stave)
+        .setContext(context)
+        .draw();
 }
 
 /**
@@ -455,7 +472,7 @@ function addBeamsFromFlags(elements, notes, beams, context) {
     for (let i = 0; i < elements.length; i++) {
         const element = elements[i];
         
-        if (element.type === 'Note' || element.type === 'Rest') {
+        if (element.type === 'Note' || element.type === 'Rest' || element.type === 'MultiMeasureRest') {
             if (element.type === 'Note') {
                 // Check for beam start
                 if (element.beam_start) {
@@ -573,8 +590,12 @@ function createAdvancedVexFlowNote(element) {
                 // Simple string format from our backend
                 note.addModifier(new Accidental(acc), 0);
             } else if (acc.accidental) {
-                // Object format (legacy or other sources)
-                note.addModifier(new Accidental(acc.accidental), acc.index || 0);
+                // Object format (legacy or other sources, cautionary accidentals)
+                const accidental = new Accidental(acc.accidental);
+                if (acc.cautionary) {
+                    accidental.setAsCautionary();
+                }
+                note.addModifier(accidental, acc.index || 0);
             }
         });
     }